@@ -0,0 +1,95 @@
+//! Instruction counts and cache events of byodb running the core workloads,
+//! measured by Callgrind. Unlike wall-clock times, they barely vary from run
+//! to run, so even small regressions in byodb-rust stand out. Needs
+//! Valgrind and a matching `iai-callgrind-runner`; run with
+//! `cargo bench --features iai --bench iai`.
+//!
+//! Every benchmark runs [`OPS`] operations of its workload on a single
+//! thread, so the counts per operation are the totals divided by that.
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use db_cmp::backend::Backend;
+use db_cmp::config::Config;
+use db_cmp::ops::Outcome;
+use db_cmp::workload::Workload;
+use db_cmp::{bench, db, ops, seed};
+use iai_callgrind::{
+    Callgrind, LibraryBenchmarkConfig, library_benchmark, library_benchmark_group, main,
+};
+use tempfile::TempDir;
+
+/// Number of items the DB is seeded with.
+const ITEMS: usize = 10_000;
+
+/// Number of operations of every benchmark, or full scans for the scan
+/// workload.
+const OPS: usize = 1000;
+
+/// A seeded DB to run `config` against. The seeding isn't measured.
+struct Fixture {
+    db: Arc<dyn Backend>,
+    temp_dir: TempDir,
+    config: Config,
+}
+
+fn setup(workload: Workload) -> Fixture {
+    let config = Config {
+        workload,
+        items: ITEMS,
+        threads: 1,
+        iters: match workload {
+            Workload::Scan => 10,
+            _ => OPS,
+        },
+        writer: false,
+        // Sample only at the start and end, so that the counts don't depend
+        // on how long the run takes.
+        sample_interval_ms: 3_600_000,
+        ..Config::default()
+    };
+    let (db, temp_dir) = db::open_temp("byodb", &config.backend_opts, None).unwrap();
+    seed::seed_db(seed::items(&config).unwrap(), &*db).unwrap();
+    Fixture {
+        db: Arc::from(db),
+        temp_dir,
+        config,
+    }
+}
+
+/// Deletes the DB of a benchmark, which isn't measured either.
+fn teardown((outcome, fixture): (Outcome, Fixture)) {
+    drop(outcome);
+    drop(fixture);
+}
+
+#[library_benchmark(setup = setup, teardown = teardown)]
+#[bench::scan(Workload::Scan)]
+#[bench::point_get(Workload::PointGet)]
+#[bench::ycsb_a(Workload::YcsbA)]
+#[bench::ycsb_c(Workload::YcsbC)]
+#[bench::ycsb_e(Workload::YcsbE)]
+#[bench::insert_only(Workload::InsertOnly)]
+fn byodb(fixture: Fixture) -> (Outcome, Fixture) {
+    let outcome = match fixture.config.spec() {
+        None => bench::bench_readers(&fixture.db, &fixture.config, None, None),
+        Some(spec) => ops::run(
+            &fixture.db,
+            &db::temp_path(&fixture.temp_dir),
+            &fixture.config,
+            &spec,
+            None,
+            None,
+            None,
+        ),
+    };
+    (black_box(outcome.unwrap()), fixture)
+}
+
+library_benchmark_group!(name = workloads; benchmarks = byodb);
+main!(
+    // Also simulate the caches, for their hits and misses.
+    config = LibraryBenchmarkConfig::default().tool(Callgrind::with_args(["--cache-sim=yes"]));
+    library_benchmark_groups = workloads
+);
@@ -0,0 +1,71 @@
+//! The core workloads as Criterion benchmarks, one group per workload with
+//! a benchmark per backend, each driven by the same runner as `db-cmp run`
+//! on a single thread. Run with `cargo bench`, optionally with the features
+//! of other backends.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use db_cmp::backend::{self, Backend};
+use db_cmp::config::Config;
+use db_cmp::workload::Workload;
+use db_cmp::{bench, db, ops, seed};
+
+/// Number of items every DB is seeded with.
+const ITEMS: usize = 10_000;
+
+const WORKLOADS: [Workload; 6] = [
+    Workload::Scan,
+    Workload::PointGet,
+    Workload::YcsbA,
+    Workload::YcsbC,
+    Workload::YcsbE,
+    Workload::InsertOnly,
+];
+
+fn workloads(c: &mut Criterion) {
+    // The null backend measures nothing, and byodb-go needs a server.
+    let backends: Vec<_> = backend::names()
+        .filter(|&name| name != backend::NULL && name != "byodb-go")
+        .collect();
+    for workload in WORKLOADS {
+        let mut group = c.benchmark_group(workload.to_string());
+        for &name in &backends {
+            let config = Config {
+                backends: vec![name.to_string()],
+                workload,
+                items: ITEMS,
+                threads: 1,
+                writer: false,
+                ..Config::default()
+            };
+            let (db, temp_dir) = db::open_temp(name, &config.backend_opts, None).unwrap();
+            seed::seed_db(seed::items(&config).unwrap(), &*db).unwrap();
+            let db: Arc<dyn Backend> = Arc::from(db);
+            let path = db::temp_path(&temp_dir);
+            let spec = config.spec();
+            group.bench_function(name, |b| {
+                b.iter_custom(|iters| {
+                    let config = Config {
+                        iters: iters as usize,
+                        ..config.clone()
+                    };
+                    let outcome = match &spec {
+                        None => bench::bench_readers(&db, &config, None, None),
+                        Some(spec) => ops::run(&db, &path, &config, spec, None, None, None),
+                    };
+                    outcome.unwrap().elapsed
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(3));
+    targets = workloads
+}
+criterion_main!(benches);
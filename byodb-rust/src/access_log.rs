@@ -0,0 +1,167 @@
+//! Access logs of real workloads, turned into [`Trace`]s to replay on any
+//! backend.
+//!
+//! An access log is a CSV file of `op,key,value_size,timestamp` lines, e.g.
+//! `update,user42,512,1718000000.25`. The op is one of `read`, `update`,
+//! `insert`, `delete`, `scan` and `rmw`. The value size is the size of the
+//! value written by updates and inserts, or the number of records read by
+//! scans, and may be empty otherwise. Timestamps are numbers in any unit,
+//! that only order the operations. A header line starting with `op`, blank
+//! lines and lines starting with `#` are skipped.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail, ensure};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::config::Config;
+use crate::distribution::Length;
+use crate::ops::{self, FIELD_SIZE, Mix, Op, VALUE_SIZE};
+use crate::trace::{Step, Trace, TracedOp};
+use crate::workload::Workload;
+
+/// An operation of the log.
+struct Access {
+    op: Op,
+    key: String,
+    value_size: Option<usize>,
+    timestamp: f64,
+}
+
+fn parse(line: &str) -> Result<Access> {
+    let fields: Vec<_> = line.split(',').map(str::trim).collect();
+    let [op, key, value_size, timestamp] = fields[..] else {
+        bail!("expected op,key,value_size,timestamp");
+    };
+    let Some(op) = Op::ALL.into_iter().find(|o| o.name() == op) else {
+        bail!("unknown operation {op}");
+    };
+    ensure!(!key.is_empty(), "empty key");
+    let value_size = match value_size {
+        "" => None,
+        size => Some(size.parse().context("invalid value size")?),
+    };
+    if matches!(op, Op::Update | Op::Insert | Op::Scan) {
+        ensure!(
+            value_size.is_some_and(|size| size > 0),
+            "{} needs a positive value size",
+            op.name()
+        );
+    }
+    Ok(Access {
+        op,
+        key: key.to_string(),
+        value_size,
+        timestamp: timestamp.parse().context("invalid timestamp")?,
+    })
+}
+
+fn read(path: &Path) -> Result<Vec<Access>> {
+    let file = File::open(path)?;
+    let mut accesses = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (i == 0 && line.starts_with("op")) {
+            continue;
+        }
+        accesses.push(parse(line).with_context(|| format!("line {}", i + 1))?);
+    }
+    ensure!(!accesses.is_empty(), "no operations");
+    Ok(accesses)
+}
+
+/// Loads the access log at `path` as a trace of `config.threads` threads,
+/// which perform its operations in timestamp order, each in its own
+/// transaction, dealt to them in turn. Values are random, of the logged
+/// sizes, from `config.seed`.
+///
+/// The DB is seeded with every key whose first access isn't an insert, with
+/// a value of the first size logged for it, or else `config.value_size`.
+pub fn load(path: &Path, config: &Config) -> Result<Trace> {
+    let mut accesses =
+        read(path).with_context(|| format!("failed to load access log {}", path.display()))?;
+    accesses.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    let default_size = config.value_size.unwrap_or(Length::Fixed(VALUE_SIZE));
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+
+    let mut first_sizes: HashMap<&str, Option<usize>> = HashMap::new();
+    let mut seeded = Vec::new();
+    for access in &accesses {
+        let size = access.value_size.filter(|_| access.op != Op::Scan);
+        match first_sizes.get_mut(access.key.as_str()) {
+            Some(first_size) => {
+                if first_size.is_none() {
+                    *first_size = size;
+                }
+            }
+            None => {
+                first_sizes.insert(&access.key, size);
+                if access.op != Op::Insert {
+                    seeded.push(access.key.as_str());
+                }
+            }
+        }
+    }
+    let items: Vec<_> = seeded
+        .into_iter()
+        .map(|key| {
+            let size = first_sizes[key].map_or(default_size, Length::Fixed);
+            (key.to_string(), ops::value(&mut rng, size))
+        })
+        .collect();
+
+    let mut counts = [0; Op::ALL.len()];
+    let mut threads = vec![Vec::new(); config.threads];
+    for (i, access) in accesses.iter().enumerate() {
+        counts[access.op as usize] += 1;
+        let key = access.key.clone().into_bytes();
+        let size = access.value_size.unwrap_or_default();
+        let op = match access.op {
+            Op::Read => TracedOp::Read(key),
+            Op::Update => TracedOp::Update(key, value(&mut rng, size)),
+            Op::Insert => TracedOp::Insert(key, value(&mut rng, size)),
+            Op::Delete => TracedOp::Delete(key),
+            Op::Scan => TracedOp::Scan(key, size as u64),
+            Op::Rmw => TracedOp::Rmw {
+                key,
+                pick: rng.random(),
+                field: value(&mut rng, FIELD_SIZE),
+            },
+        };
+        threads[i % config.threads].push(Step {
+            batch: false,
+            ops: vec![op],
+        });
+    }
+    let [read, update, insert, delete, scan, rmw] = counts.map(|n| n as f64);
+    let mix = Mix {
+        read,
+        update,
+        insert,
+        delete,
+        scan,
+        rmw,
+    };
+    Ok(Trace {
+        config: Config {
+            workload: Workload::Custom,
+            mix: Some(mix),
+            items: items.len(),
+            iters: accesses.len().div_ceil(config.threads),
+            duration_ms: None,
+            batch: 1,
+            ..config.clone()
+        },
+        threads,
+        items: Some(items),
+    })
+}
+
+fn value(rng: &mut ChaCha8Rng, size: usize) -> Vec<u8> {
+    ops::value(rng, Length::Fixed(size)).into_bytes()
+}
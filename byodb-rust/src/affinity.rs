@@ -0,0 +1,128 @@
+//! Pinning the benchmark threads to CPUs, and binding them to a NUMA node,
+//! so that the scheduler moving them around doesn't add to the variance
+//! between runs, and so that runs can compare sockets. Linux only.
+
+use std::fs;
+
+use anyhow::{Context, Result, ensure};
+
+use crate::config::Config;
+
+/// The CPUs the benchmark threads of `config` are pinned to, the `t`th
+/// thread to the `t % len`th one, or `None` if they aren't pinned: the CPUs
+/// of its NUMA node if it's bound to one, or else the ones the process may
+/// run on.
+pub fn worker_cpus(config: &Config) -> Result<Option<Vec<usize>>> {
+    if !config.pin_threads {
+        return Ok(None);
+    }
+    let cpus = match config.numa_node {
+        Some(node) => node_cpus(node)?,
+        None => allowed_cpus()?,
+    };
+    ensure!(!cpus.is_empty(), "there are no CPUs to pin threads to");
+    Ok(Some(cpus))
+}
+
+/// The CPUs of NUMA `node`, from sysfs.
+fn node_cpus(node: usize) -> Result<Vec<usize>> {
+    let path = format!("/sys/devices/system/node/node{node}/cpulist");
+    let list = fs::read_to_string(&path).with_context(|| format!("no NUMA node {node}"))?;
+    parse_cpu_list(list.trim()).with_context(|| format!("invalid CPU list in {path}"))
+}
+
+/// Parses a list of CPUs like `0-3,8,10-11`.
+fn parse_cpu_list(list: &str) -> Result<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.split(',').filter(|range| !range.is_empty()) {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        cpus.extend(first.parse::<usize>()?..=last.parse()?);
+    }
+    Ok(cpus)
+}
+
+/// Pins the calling thread to `cpu`.
+pub fn pin(cpu: usize) -> Result<()> {
+    set_affinity(&[cpu]).with_context(|| format!("failed to pin thread to CPU {cpu}"))
+}
+
+/// Binds the calling thread, and the threads it starts from then on, to the
+/// CPUs of NUMA `node`, and its memory allocations to the memory of `node`.
+pub fn bind_to_node(node: usize) -> Result<()> {
+    set_affinity(&node_cpus(node)?)
+        .and_then(|()| bind_memory(node))
+        .with_context(|| format!("failed to bind to NUMA node {node}"))
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cpus: &[usize]) -> Result<()> {
+    // SAFETY: an all-zero cpu_set_t is the empty set, and CPU_SET and
+    // sched_setaffinity only access the set they're given.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in cpus {
+            ensure!(
+                cpu < libc::CPU_SETSIZE as usize,
+                "CPU {cpu} is out of range"
+            );
+            libc::CPU_SET(cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn allowed_cpus() -> Result<Vec<usize>> {
+    // SAFETY: as in set_affinity; sched_getaffinity fills the set.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(std::io::Error::last_os_error())
+                .context("failed to get the CPUs the process may run on");
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn bind_memory(node: usize) -> Result<()> {
+    /// The mode of set_mempolicy that allocates from the given nodes only.
+    const MPOL_BIND: libc::c_int = 2;
+    let bits = libc::c_ulong::BITS as usize;
+    let mut mask = vec![0 as libc::c_ulong; node / bits + 1];
+    mask[node / bits] |= 1 << (node % bits);
+    // SAFETY: the kernel reads `maxnode` bits of `mask`, one less than
+    // given, which it holds.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_set_mempolicy,
+            MPOL_BIND,
+            mask.as_ptr(),
+            mask.len() * bits + 1,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cpus: &[usize]) -> Result<()> {
+    anyhow::bail!("pinning threads is only supported on Linux")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allowed_cpus() -> Result<Vec<usize>> {
+    anyhow::bail!("pinning threads is only supported on Linux")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bind_memory(_node: usize) -> Result<()> {
+    anyhow::bail!("binding to NUMA nodes is only supported on Linux")
+}
@@ -0,0 +1,217 @@
+//! Drives op-based workloads from the tasks of a multi-threaded tokio
+//! runtime, like an async service would, to show what the blocking calls
+//! of the backends cost the runtime.
+//!
+//! Every backend blocks its caller, so an async service either calls it
+//! right from its tasks, stalling the runtime worker that runs them, or
+//! hands each call to the blocking thread pool with `spawn_blocking`, paying
+//! for a thread hop instead. A ticker task sleeping for [`TICK`] at a time
+//! measures how late the runtime gets to it meanwhile.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, ensure};
+use tokio::runtime;
+use tokio::task;
+
+use crate::backend::Backend;
+use crate::cli::AsyncArgs;
+use crate::config::Config;
+use crate::histogram::Histogram;
+use crate::ops::{self, Client, Spec};
+use crate::{db, seed};
+
+/// How long the ticker task sleeps at a time.
+pub const TICK: Duration = Duration::from_millis(1);
+
+/// How the tasks call the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Right from the task, blocking the runtime worker running it.
+    Blocking,
+    /// On the blocking thread pool of the runtime, awaiting the result.
+    SpawnBlocking,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 2] = [Mode::Blocking, Mode::SpawnBlocking];
+
+    /// Runs `call` the way of this mode.
+    pub async fn call<T, F>(self, call: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        match self {
+            Mode::Blocking => call(),
+            Mode::SpawnBlocking => task::spawn_blocking(call)
+                .await
+                .context("a blocking call panicked")?,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Mode::Blocking => "blocking",
+            Mode::SpawnBlocking => "spawn-blocking",
+        })
+    }
+}
+
+/// What [`run`] measured.
+pub struct Outcome {
+    /// Number of operations completed by all tasks.
+    pub ops: u64,
+    /// Wall-clock time of all operations.
+    pub elapsed: Duration,
+    /// Latencies of the operations as awaited by the tasks, including any
+    /// wait for a thread to run them on.
+    pub latency: Histogram,
+    /// Latencies of the calls to the backend alone.
+    pub service: Histogram,
+    /// How much later than due the ticker woke up.
+    pub lateness: Histogram,
+}
+
+/// Performs `n_ops` operations of `spec` on `db`, which holds the initial
+/// records of `config`, from `in_flight` tasks on a runtime of `workers`
+/// threads, calling the backend the way of `mode`. Each task yields to the
+/// runtime between its operations, like one serving requests would.
+pub fn run(
+    db: &Arc<dyn Backend>,
+    config: &Config,
+    spec: &Spec,
+    mode: Mode,
+    workers: usize,
+    in_flight: usize,
+    n_ops: usize,
+) -> Result<Outcome> {
+    ensure!(workers > 0, "the runtime needs at least one worker");
+    ensure!(in_flight > 0, "at least one operation must be in flight");
+    let runtime = runtime::Builder::new_multi_thread()
+        .worker_threads(workers)
+        .enable_time()
+        .build()
+        .context("failed to build the tokio runtime")?;
+    let clients = ops::clients(db, config, spec, in_flight, n_ops)?;
+    let left = Arc::new(AtomicUsize::new(n_ops));
+    let done = Arc::new(AtomicBool::new(false));
+    runtime.block_on(async move {
+        let ticker = tokio::spawn(tick(done.clone()));
+        let start_time = Instant::now();
+        let tasks: Vec<_> = clients
+            .into_iter()
+            .map(|client| tokio::spawn(drive(client, mode, left.clone())))
+            .collect();
+        let mut ops = 0;
+        let mut latency = Histogram::default();
+        let mut service = Histogram::default();
+        for task in tasks {
+            let (client, task_ops, task_latency) = task.await.context("a task panicked")??;
+            ops += task_ops;
+            latency.merge(&task_latency);
+            service.merge(&client.latency());
+        }
+        let elapsed = start_time.elapsed();
+        done.store(true, Ordering::Relaxed);
+        let lateness = ticker.await.context("the ticker panicked")?;
+        Ok(Outcome {
+            ops,
+            elapsed,
+            latency,
+            service,
+            lateness,
+        })
+    })
+}
+
+/// Performs the operations of `client` until none are `left`. Returns it
+/// with the number of operations it performed and their latencies.
+async fn drive(
+    mut client: Client,
+    mode: Mode,
+    left: Arc<AtomicUsize>,
+) -> Result<(Client, u64, Histogram)> {
+    let mut ops = 0;
+    let mut latency = Histogram::default();
+    while left
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        .is_ok()
+    {
+        let start_time = Instant::now();
+        let n;
+        (client, n) = mode
+            .call(move || {
+                let n = client.step()?;
+                Ok((client, n))
+            })
+            .await?;
+        latency.record(start_time.elapsed());
+        ops += n as u64;
+        task::yield_now().await;
+    }
+    Ok((client, ops, latency))
+}
+
+/// Sleeps for [`TICK`] at a time until `done`, recording how late it woke
+/// up each time.
+async fn tick(done: Arc<AtomicBool>) -> Histogram {
+    let mut lateness = Histogram::default();
+    while !done.load(Ordering::Relaxed) {
+        let due = Instant::now() + TICK;
+        tokio::time::sleep_until(due.into()).await;
+        lateness.record(Instant::now().saturating_duration_since(due));
+    }
+    lateness
+}
+
+/// Runs `db-cmp async`: runs the workload of `args` on each backend from
+/// tokio tasks, in every [`Mode`].
+pub fn command(args: AsyncArgs) -> Result<()> {
+    let config = Config {
+        workload: args.workload,
+        items: args.items,
+        ..args.backend.into_config()
+    };
+    config.validate()?;
+    let spec = config
+        .spec()
+        .with_context(|| format!("async needs an op-based workload, not {}", config.workload))?;
+    println!(
+        "{:<10} {:<14} {:>10} {:>10} {:>10} {:>12} {:>12}",
+        "Backend", "Mode", "Ops/s", "p50 (us)", "p99 (us)", "Call p99", "Tick p99"
+    );
+    for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        for mode in Mode::ALL {
+            // Every mode starts from the same records.
+            let (db, _temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+            seed::seed_db(seed::items(&config)?, &*db)?;
+            let db = Arc::from(db);
+            let outcome = run(
+                &db,
+                &config,
+                &spec,
+                mode,
+                args.workers,
+                args.in_flight,
+                args.ops,
+            )
+            .with_context(|| format!("failed to run {backend} {mode}"))?;
+            println!(
+                "{backend:<10} {mode:<14} {:>10.0} {:>10.1} {:>10.1} {:>12.1} {:>12.1}",
+                outcome.ops as f64 / outcome.elapsed.as_secs_f64(),
+                outcome.latency.quantile_us(0.5),
+                outcome.latency.quantile_us(0.99),
+                outcome.service.quantile_us(0.99),
+                outcome.lateness.quantile_us(0.99),
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, RwLock};
+
+use anyhow::{Result, bail};
+
+use super::write_set::WriteSet;
+use super::{Backend, Opts, ReadTxn, WriteTxn};
+
+/// The versions of a key, oldest first, with the version of the commit that
+/// wrote each. `None` marks a delete.
+type Versions = Vec<(u64, Option<Vec<u8>>)>;
+
+/// Nothing is stored at `path`: the store lives in memory and is lost when
+/// dropped.
+pub fn open(_path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    if let Some(key) = opts.keys().next() {
+        bail!("unknown btreemap option {key}");
+    }
+    Ok(Box::new(BTreeMapBackend {
+        store: RwLock::new(Store::default()),
+        snapshots: Mutex::new(BTreeMap::new()),
+        writer: Mutex::new(()),
+    }))
+}
+
+/// An in-memory [`BTreeMap`], as an upper bound on what a persistent store
+/// can achieve, with the same snapshot isolation as the others: a single
+/// writer at a time, and readers that see the store as of when they began.
+///
+/// Every key keeps the versions that an open snapshot may still read. The
+/// map is only locked for the duration of each operation, so readers never
+/// block commits, however long their transactions.
+struct BTreeMapBackend {
+    store: RwLock<Store>,
+    /// The number of open read-only transactions at each version. Locked
+    /// before `store` by whoever locks both.
+    snapshots: Mutex<BTreeMap<u64, usize>>,
+    /// Held by the read-write transaction in progress.
+    writer: Mutex<()>,
+}
+
+#[derive(Default)]
+struct Store {
+    map: BTreeMap<Vec<u8>, Versions>,
+    /// The version of the last commit.
+    version: u64,
+    /// The keys with versions that only open snapshots may read, by the
+    /// version that made them so, oldest first.
+    stale: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl Store {
+    /// The value of `key` as of `version`.
+    fn get(&self, key: &[u8], version: u64) -> Option<&[u8]> {
+        visible(self.map.get(key)?, version)
+    }
+
+    /// The key-value pairs from `start` as of `version`, in key order.
+    fn range<'a>(
+        &'a self,
+        start: Bound<&[u8]>,
+        version: u64,
+    ) -> impl Iterator<Item = (&'a [u8], &'a [u8])> {
+        self.map
+            .range::<[u8], _>((start, Bound::Unbounded))
+            .filter_map(move |(k, versions)| Some((k.as_slice(), visible(versions, version)?)))
+    }
+
+    /// Drops the versions of `key` that no snapshot at `oldest` or later
+    /// can read, and returns whether any of those left will need dropping
+    /// once the older snapshots end.
+    fn prune(&mut self, key: &[u8], oldest: u64) -> bool {
+        let Some(versions) = self.map.get_mut(key) else {
+            return false;
+        };
+        // Keep the newest version the oldest snapshot can read, and every
+        // one after it.
+        let keep = versions
+            .iter()
+            .rposition(|(v, _)| *v <= oldest)
+            .unwrap_or(0);
+        versions.drain(..keep);
+        match versions[..] {
+            [(v, None)] if v <= oldest => {
+                self.map.remove(key);
+                false
+            }
+            [(_, Some(_))] => false,
+            _ => true,
+        }
+    }
+}
+
+/// The value among `versions` as of `version`.
+fn visible(versions: &Versions, version: u64) -> Option<&[u8]> {
+    let (_, value) = versions.iter().rev().find(|(v, _)| *v <= version)?;
+    value.as_deref()
+}
+
+impl Backend for BTreeMapBackend {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let version = self.store.read().unwrap().version;
+        *snapshots.entry(version).or_default() += 1;
+        Ok(Box::new(BTreeMapRTxn {
+            backend: self,
+            version,
+        }))
+    }
+
+    /// Waits for the read-write transaction in progress, if any, to end.
+    /// Writes are buffered and applied on commit.
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        let writer = self.writer.lock().unwrap();
+        let version = self.store.read().unwrap().version;
+        Ok(Box::new(BTreeMapRWTxn {
+            backend: self,
+            _writer: writer,
+            version,
+            writes: WriteSet::default(),
+        }))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+}
+
+struct BTreeMapRTxn<'d> {
+    backend: &'d BTreeMapBackend,
+    version: u64,
+}
+
+impl Drop for BTreeMapRTxn<'_> {
+    fn drop(&mut self) {
+        let mut snapshots = self.backend.snapshots.lock().unwrap();
+        let readers = snapshots.get_mut(&self.version).unwrap();
+        *readers -= 1;
+        if *readers == 0 {
+            snapshots.remove(&self.version);
+        }
+    }
+}
+
+impl ReadTxn for BTreeMapRTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        let store = self.backend.store.read().unwrap();
+        Ok(store.get(key, self.version).map(|v| Cow::Owned(v.to_vec())))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        let store = self.backend.store.read().unwrap();
+        for (k, v) in store.range(start, self.version) {
+            if !visit(k, v) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        let store = self.backend.store.read().unwrap();
+        let range = store
+            .map
+            .range::<[u8], _>((Bound::Unbounded, end))
+            .rev()
+            .filter_map(|(k, versions)| Some((k, visible(versions, self.version)?)));
+        for (k, v) in range {
+            if !visit(k, v) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct BTreeMapRWTxn<'d> {
+    backend: &'d BTreeMapBackend,
+    _writer: MutexGuard<'d, ()>,
+    /// The last version committed, which no other transaction can change
+    /// before this one ends.
+    version: u64,
+    writes: WriteSet,
+}
+
+impl ReadTxn for BTreeMapRWTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        match self.writes.get(key) {
+            Some(v) => Ok(v.map(Cow::Borrowed)),
+            None => {
+                let store = self.backend.store.read().unwrap();
+                Ok(store.get(key, self.version).map(|v| Cow::Owned(v.to_vec())))
+            }
+        }
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        let store = self.backend.store.read().unwrap();
+        let base = store.range(start, self.version).map(Ok);
+        self.writes.scan(start, base, visit)
+    }
+}
+
+impl WriteTxn for BTreeMapRWTxn<'_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.writes.put(key, val);
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        let existed = match self.writes.get(key) {
+            Some(v) => v.is_some(),
+            None => {
+                let store = self.backend.store.read().unwrap();
+                store.get(key, self.version).is_some()
+            }
+        };
+        self.writes.delete(key);
+        Ok(existed)
+    }
+
+    /// Adds the writes as the next version, and drops the versions they
+    /// overwrite that no open snapshot can read anymore.
+    fn commit(self: Box<Self>) -> Result<()> {
+        let snapshots = self.backend.snapshots.lock().unwrap();
+        let mut store = self.backend.store.write().unwrap();
+        let version = self.version + 1;
+        let oldest = snapshots.keys().next().copied().unwrap_or(version);
+        while let Some((_, k)) = store.stale.pop_front_if(|(v, _)| *v <= oldest) {
+            store.prune(&k, oldest);
+        }
+        for (k, v) in self.writes.into_writes() {
+            store.map.entry(k.clone()).or_default().push((version, v));
+            if store.prune(&k, oldest) {
+                store.stale.push_back((version, k));
+            }
+        }
+        store.version = version;
+        Ok(())
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
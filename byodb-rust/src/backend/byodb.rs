@@ -0,0 +1,132 @@
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+
+use byodb_rust::{
+    DB, DBBuilder, RTxn, RWTxn,
+    error::{TreeError, TxnError},
+};
+
+use super::{Backend, Opts, ReadTxn, Stats, WriteTxn, named_stats, parse_opt};
+use crate::layout::{self, PAGE_SIZE};
+
+/// Supports the options `free_batch_size` and `min_file_growth_size` of
+/// [`DBBuilder`].
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut builder = DBBuilder::new(path);
+    for (key, val) in opts {
+        builder = match key.as_str() {
+            "free_batch_size" => builder.free_batch_size(parse_opt(key, val)?),
+            "min_file_growth_size" => builder.min_file_growth_size(parse_opt(key, val)?),
+            _ => bail!("unknown byodb option {key}"),
+        };
+    }
+    Ok(Box::new(Byodb {
+        db: builder.build()?,
+        path: path.to_path_buf(),
+    }))
+}
+
+struct Byodb {
+    db: DB,
+    path: PathBuf,
+}
+
+impl Backend for Byodb {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(self.db.r_txn()))
+    }
+
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(self.db.rw_txn()))
+    }
+
+    /// Reads the layout of the file as of the last commit.
+    fn stats(&self) -> Result<Option<Stats>> {
+        let layout = layout::read(&self.path)?;
+        let leaves = layout.leaves();
+        let mut stats = named_stats([
+            ("page size", PAGE_SIZE.to_string()),
+            ("tree height", layout.levels.len().to_string()),
+            ("keys", leaves.keys.to_string()),
+            ("allocated pages", layout.pages.to_string()),
+            ("tree pages", layout.tree_pages().to_string()),
+            ("free pages", layout.free_pages.to_string()),
+            ("free list pages", layout.free_list_pages.to_string()),
+            ("unreclaimed pages", layout.unreachable_pages().to_string()),
+            ("overflow pages", "0".to_string()),
+            ("leaf fill", format!("{:.1}%", leaves.fill() * 100.0)),
+        ]);
+        for (i, level) in layout.levels.iter().enumerate() {
+            stats.push((
+                format!("level {i} nodes"),
+                format!(
+                    "{} ({} keys, {:.1}% full)",
+                    level.nodes,
+                    level.keys,
+                    level.fill() * 100.0
+                ),
+            ));
+        }
+        Ok(Some(stats))
+    }
+}
+
+impl ReadTxn for RTxn<'_, '_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(RTxn::get(self, key)?.map(Cow::Borrowed))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        for (k, v) in self.in_order_range_iter(&(start, Bound::Unbounded)) {
+            if !visit(k, v) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ReadTxn for RWTxn<'_, '_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(RWTxn::get(self, key)?.map(Cow::Borrowed))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        for (k, v) in self.in_order_range_iter(&(start, Bound::Unbounded)) {
+            if !visit(k, v) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WriteTxn for RWTxn<'_, '_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        match self.update(key, val) {
+            Err(TxnError::Tree(TreeError::KeyNotFound)) => Ok(self.insert(key, val)?),
+            result => Ok(result?),
+        }
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        match RWTxn::delete(self, key) {
+            Ok(()) => Ok(true),
+            Err(TxnError::Tree(TreeError::KeyNotFound)) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        RWTxn::commit(*self);
+        Ok(())
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        RWTxn::abort(*self);
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//! Drives the original Go implementation of byodb in a subprocess.
+//!
+//! The Go side isn't part of this repository: `byodb-go.command` must name an
+//! executable wrapping byodb-go that is started as `<command> <db path>` and
+//! serves requests on stdin/stdout until stdin is closed.
+//!
+//! All integers are little-endian. A request is an opcode byte, a `u32`
+//! argument count, and that many `u32`-length-prefixed byte strings. A
+//! response is a status byte (0 for success, 1 for failure), a `u32` count,
+//! and that many length-prefixed byte strings; a failure carries a single
+//! error message. Transactions are identified by the 8-byte `u64` id
+//! returned when they begin.
+//!
+//! Opcode | Arguments | Response
+//! --- | --- | ---
+//! `R` (begin read-only) | | txn id
+//! `W` (begin read-write) | | txn id
+//! `G` (get) | txn id, key | value, or nothing if absent
+//! `S` (scan) | txn id, bound (`U`, `I` or `E`), start key, `u32` limit | up to limit keys and values, interleaved
+//! `P` (put) | txn id, key, value |
+//! `D` (delete) | txn id, key | one byte, 1 if the key existed
+//! `C` (commit) | txn id |
+//! `A` (abort) | txn id |
+
+use std::borrow::Cow;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::ops::Bound;
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use super::{Backend, Opts, ReadTxn, WriteTxn};
+
+/// Number of items fetched per scan request.
+const SCAN_BATCH: u32 = 1024;
+
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut command = None;
+    for (key, val) in opts {
+        match key.as_str() {
+            "command" => command = Some(val),
+            _ => bail!("unknown byodb-go option {key}"),
+        }
+    }
+    let command =
+        command.context("byodb-go requires --backend-opt byodb-go.command=<executable>")?;
+    let mut child = Command::new(command)
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start {command}"))?;
+    let conn = Conn {
+        stdin: Some(BufWriter::new(child.stdin.take().unwrap())),
+        stdout: BufReader::new(child.stdout.take().unwrap()),
+    };
+    Ok(Box::new(ByodbGo {
+        child,
+        conn: Mutex::new(conn),
+    }))
+}
+
+/// The subprocess serves one request at a time, so concurrent transactions
+/// take turns on the pipe.
+struct ByodbGo {
+    child: Child,
+    conn: Mutex<Conn>,
+}
+
+impl ByodbGo {
+    fn call(&self, op: u8, args: &[&[u8]]) -> Result<Vec<Vec<u8>>> {
+        self.conn
+            .lock()
+            .unwrap()
+            .call(op, args)
+            .with_context(|| format!("byodb-go request {} failed", op as char))
+    }
+
+    fn begin(&self, op: u8) -> Result<ByodbGoTxn<'_>> {
+        let id = match self.call(op, &[])?.as_slice() {
+            [id] => id.as_slice().try_into()?,
+            _ => bail!("malformed byodb-go response"),
+        };
+        Ok(ByodbGoTxn {
+            db: self,
+            id,
+            done: false,
+        })
+    }
+}
+
+impl Backend for ByodbGo {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(self.begin(b'R')?))
+    }
+
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(self.begin(b'W')?))
+    }
+}
+
+impl Drop for ByodbGo {
+    fn drop(&mut self) {
+        // Closing stdin asks the subprocess to exit.
+        self.conn.get_mut().unwrap().stdin.take();
+        let _ = self.child.wait();
+    }
+}
+
+struct Conn {
+    stdin: Option<BufWriter<ChildStdin>>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Conn {
+    fn call(&mut self, op: u8, args: &[&[u8]]) -> Result<Vec<Vec<u8>>> {
+        let stdin = self.stdin.as_mut().unwrap();
+        stdin.write_all(&[op])?;
+        stdin.write_all(&(args.len() as u32).to_le_bytes())?;
+        for arg in args {
+            stdin.write_all(&(arg.len() as u32).to_le_bytes())?;
+            stdin.write_all(arg)?;
+        }
+        stdin.flush()?;
+
+        let mut status = [0u8];
+        self.stdout.read_exact(&mut status)?;
+        let n = self.read_u32()?;
+        let mut strings = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            let mut s = vec![0u8; self.read_u32()? as usize];
+            self.stdout.read_exact(&mut s)?;
+            strings.push(s);
+        }
+        match status[0] {
+            0 => Ok(strings),
+            _ => Err(anyhow!(
+                "{}",
+                String::from_utf8_lossy(strings.first().map_or(&[][..], |s| s))
+            )),
+        }
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        self.stdout.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+}
+
+struct ByodbGoTxn<'d> {
+    db: &'d ByodbGo,
+    id: [u8; 8],
+    /// Whether the transaction was committed or aborted.
+    done: bool,
+}
+
+impl ByodbGoTxn<'_> {
+    fn finish(mut self, op: u8) -> Result<()> {
+        self.done = true;
+        self.db.call(op, &[&self.id])?;
+        Ok(())
+    }
+}
+
+impl ReadTxn for ByodbGoTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        let mut resp = self.db.call(b'G', &[&self.id, key])?;
+        Ok(resp.pop().map(Cow::Owned))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        let (mut bound, mut key) = match start {
+            Bound::Unbounded => (b'U', Vec::new()),
+            Bound::Included(k) => (b'I', k.to_vec()),
+            Bound::Excluded(k) => (b'E', k.to_vec()),
+        };
+        loop {
+            let resp = self
+                .db
+                .call(b'S', &[&self.id, &[bound], &key, &SCAN_BATCH.to_le_bytes()])?;
+            if resp.len() % 2 != 0 {
+                bail!("malformed byodb-go response");
+            }
+            for kv in resp.chunks_exact(2) {
+                if !visit(&kv[0], &kv[1]) {
+                    return Ok(());
+                }
+            }
+            if resp.len() / 2 < SCAN_BATCH as usize {
+                return Ok(());
+            }
+            bound = b'E';
+            key = resp[resp.len() - 2].clone();
+        }
+    }
+}
+
+impl WriteTxn for ByodbGoTxn<'_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.db.call(b'P', &[&self.id, key, val])?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        let resp = self.db.call(b'D', &[&self.id, key])?;
+        Ok(resp.first().is_some_and(|existed| existed == &[1]))
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        (*self).finish(b'C')
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        (*self).finish(b'A')
+    }
+}
+
+impl Drop for ByodbGoTxn<'_> {
+    fn drop(&mut self) {
+        // Read-only transactions are never finished explicitly.
+        if !self.done {
+            let _ = self.db.call(b'A', &[&self.id]);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+use std::io;
+use std::ops::Bound;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result};
+
+use super::{Backend, ReadTxn, Stats, WriteTxn};
+
+/// Random I/O faults: each one fails a call with probability `rate`.
+pub struct Faults {
+    /// `rate` scaled to the range of `u64`, or 0 to never fail.
+    threshold: u64,
+    /// State of the RNG that picks the calls that fail.
+    rng: AtomicU64,
+    injected: AtomicU64,
+}
+
+impl Faults {
+    /// Faults with probability `rate`, picked by an RNG seeded from `seed`.
+    pub fn new(rate: f64, seed: u64) -> Faults {
+        Faults {
+            threshold: (rate.clamp(0.0, 1.0) * u64::MAX as f64) as u64,
+            rng: AtomicU64::new(seed),
+            injected: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of faults injected so far.
+    pub fn injected(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    /// A random number, from SplitMix64, whose state is a counter that can
+    /// be shared by threads.
+    fn next(&self) -> u64 {
+        let mut z = self
+            .rng
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Fails with `EIO` if a fault is injected into the current call.
+    fn inject(&self, call: &str) -> Result<()> {
+        if self.threshold == 0 || self.next() >= self.threshold {
+            return Ok(());
+        }
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        Err(io::Error::from_raw_os_error(libc::EIO)).context(format!("injected fault in {call}"))
+    }
+}
+
+/// A backend whose read-write transactions fail at random, as if the I/O
+/// beneath them did: every put, delete and commit fails with `EIO` as
+/// [`Faults`] pick. A commit that fails does so before the commit of the
+/// wrapped backend, or after it, like a sync that failed once its writes
+/// were made, so it may or may not be committed, but never partially.
+/// Read-only transactions never fail.
+pub struct Faulty {
+    inner: Box<dyn Backend>,
+    faults: Arc<Faults>,
+}
+
+impl Faulty {
+    pub fn new(inner: Box<dyn Backend>, faults: Arc<Faults>) -> Faulty {
+        Faulty { inner, faults }
+    }
+}
+
+impl Backend for Faulty {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        self.inner.begin_ro()
+    }
+
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(FaultyTxn {
+            inner: self.inner.begin_rw()?,
+            faults: &self.faults,
+        }))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        self.inner.reverse_scans()
+    }
+
+    fn readers_block_writers(&self) -> bool {
+        self.inner.readers_block_writers()
+    }
+
+    fn stats(&self) -> Result<Option<Stats>> {
+        self.inner.stats()
+    }
+}
+
+struct FaultyTxn<'d> {
+    inner: Box<dyn WriteTxn + 'd>,
+    faults: &'d Faults,
+}
+
+impl ReadTxn for FaultyTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        self.inner.get(key)
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        self.inner.scan(start, visit)
+    }
+}
+
+impl WriteTxn for FaultyTxn<'_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.faults.inject("put")?;
+        self.inner.put(key, val)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        self.faults.inject("delete")?;
+        self.inner.delete(key)
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        let before = self.faults.next().is_multiple_of(2);
+        if before {
+            self.faults.inject("commit")?;
+        }
+        self.inner.commit()?;
+        if !before {
+            self.faults.inject("commit")?;
+        }
+        Ok(())
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        self.inner.abort()
+    }
+}
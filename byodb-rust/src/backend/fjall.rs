@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use fjall::{
+    Guard, KeyspaceCreateOptions, PersistMode, Readable, SingleWriterTxDatabase,
+    SingleWriterTxKeyspace, SingleWriterWriteTx,
+};
+
+use super::{Backend, Durability, Opts, ReadTxn, Stats, WriteTxn, named_stats, parse_opt};
+
+/// Flushes the journal to the OS on every commit (`persist = buffer`), or
+/// also syncs it (`sync_data`). fjall has no periodic sync.
+pub fn durability(durability: Durability) -> Option<Opts> {
+    let persist = match durability {
+        Durability::None => "buffer",
+        Durability::Periodic => return None,
+        Durability::Commit => "sync_data",
+    };
+    Some(Opts::from([("persist".to_string(), persist.to_string())]))
+}
+
+/// Supports the options `cache_size` (in bytes) and `persist`, how every
+/// commit persists the journal: `buffer`, `sync_data` or `sync_all`
+/// (default: not at all, left to fjall).
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut builder = SingleWriterTxDatabase::builder(path);
+    let mut persist = None;
+    for (key, val) in opts {
+        builder = match key.as_str() {
+            "cache_size" => builder.cache_size(parse_opt(key, val)?),
+            "persist" => {
+                persist = Some(match val.as_str() {
+                    "buffer" => PersistMode::Buffer,
+                    "sync_data" => PersistMode::SyncData,
+                    "sync_all" => PersistMode::SyncAll,
+                    _ => bail!("invalid value {val} for backend option {key}"),
+                });
+                builder
+            }
+            _ => bail!("unknown fjall option {key}"),
+        };
+    }
+    let db = builder.open()?;
+    let kv = db.keyspace("kv", KeyspaceCreateOptions::default)?;
+    Ok(Box::new(Fjall { db, kv, persist }))
+}
+
+struct Fjall {
+    db: SingleWriterTxDatabase,
+    kv: SingleWriterTxKeyspace,
+    persist: Option<PersistMode>,
+}
+
+impl Backend for Fjall {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(FjallTxn {
+            txn: self.db.read_tx(),
+            kv: &self.kv,
+        }))
+    }
+
+    /// fjall's transactional keyspaces have a single writer, so this blocks
+    /// while another read-write transaction is open.
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(FjallTxn {
+            txn: self.db.write_tx().durability(self.persist),
+            kv: &self.kv,
+        }))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+
+    /// Ingests the items, which writes them straight into tables, bypassing
+    /// the journal and the memtable.
+    fn bulk_load(&self, items: &[(String, String)]) -> Result<()> {
+        let mut ingestion = self.kv.inner().start_ingestion()?;
+        for (k, v) in items {
+            ingestion.write(k.as_bytes(), v.as_bytes())?;
+        }
+        ingestion.finish()?;
+        Ok(())
+    }
+
+    /// The shape of the LSM tree of the `kv` keyspace.
+    fn stats(&self) -> Result<Option<Stats>> {
+        let kv = self.kv.inner();
+        Ok(Some(named_stats([
+            ("approximate keys", self.kv.approximate_len().to_string()),
+            ("disk bytes", self.db.disk_space()?.to_string()),
+            ("tables", kv.table_count().to_string()),
+            ("level 0 tables", kv.l0_table_count().to_string()),
+            ("blob files", kv.blob_file_count().to_string()),
+            ("journals", self.db.journal_count().to_string()),
+        ])))
+    }
+}
+
+/// A read-only ([`fjall::Snapshot`]) or read-write ([`SingleWriterWriteTx`])
+/// transaction over the `kv` keyspace.
+struct FjallTxn<'d, T> {
+    txn: T,
+    kv: &'d SingleWriterTxKeyspace,
+}
+
+fn scan(
+    iter: impl Iterator<Item = Guard>,
+    visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+) -> Result<()> {
+    for guard in iter {
+        let (k, v) = guard.into_inner()?;
+        if !visit(&k, &v) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl<T: Readable> ReadTxn for FjallTxn<'_, T> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(self.txn.get(self.kv, key)?.map(|v| Cow::Owned(v.to_vec())))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        scan(
+            self.txn
+                .range::<&[u8], _>(self.kv, (start, Bound::Unbounded)),
+            visit,
+        )
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        scan(
+            self.txn
+                .range::<&[u8], _>(self.kv, (Bound::Unbounded, end))
+                .rev(),
+            visit,
+        )
+    }
+}
+
+impl WriteTxn for FjallTxn<'_, SingleWriterWriteTx<'_>> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.txn.insert(self.kv, key, val);
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        let existed = self.txn.contains_key(self.kv, key)?;
+        self.txn.remove(self.kv, key);
+        Ok(existed)
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        Ok(self.txn.commit()?)
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        self.txn.rollback();
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+use std::fs;
+use std::ops::Bound;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use heed::types::Bytes;
+use heed::{Database, Env, EnvFlags, EnvOpenOptions, PutFlags, RoTxn, RwTxn, WithoutTls};
+
+use super::{Backend, Durability, Opts, ReadTxn, Stats, WriteTxn, named_stats, parse_opt};
+
+/// The default maximum size of the memory map. It only reserves address
+/// space, so it can be much larger than the DB.
+const DEFAULT_MAP_SIZE: usize = 64 << 30;
+
+/// Syncs on every commit, or never with `sync = false` (`MDB_NOSYNC`). LMDB
+/// has no periodic sync.
+pub fn durability(durability: Durability) -> Option<Opts> {
+    let sync = match durability {
+        Durability::None => "false",
+        Durability::Periodic => return None,
+        Durability::Commit => "true",
+    };
+    Some(Opts::from([("sync".to_string(), sync.to_string())]))
+}
+
+/// Supports the options `map_size` (in bytes), `max_readers` and `sync`
+/// (default: true), whether commits are synced.
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    // Read transactions don't use thread-local storage, so that a thread can
+    // hold several of them at once (e.g. a long-lived reader and a fresh one).
+    let mut options = EnvOpenOptions::new().read_txn_without_tls();
+    options.map_size(DEFAULT_MAP_SIZE);
+    for (key, val) in opts {
+        match key.as_str() {
+            "map_size" => options.map_size(parse_opt(key, val)?),
+            "max_readers" => options.max_readers(parse_opt(key, val)?),
+            "sync" if parse_opt::<bool>(key, val)? => &mut options,
+            // Safety: the store only has to survive crashes of the process.
+            "sync" => unsafe { options.flags(EnvFlags::NO_SYNC) },
+            _ => bail!("unknown lmdb option {key}"),
+        };
+    }
+    fs::create_dir_all(path)?;
+    // Safety: the environment is opened at most once per process, and the
+    // file isn't modified by anything other than LMDB.
+    let env = unsafe { options.open(path)? };
+    let mut t = env.write_txn()?;
+    let db = env.create_database(&mut t, None)?;
+    t.commit()?;
+    Ok(Box::new(Lmdb { env, db }))
+}
+
+struct Lmdb {
+    env: Env<WithoutTls>,
+    db: Database<Bytes, Bytes>,
+}
+
+impl Backend for Lmdb {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(LmdbRTxn {
+            txn: self.env.read_txn()?,
+            db: self.db,
+        }))
+    }
+
+    /// LMDB has a single writer, so this blocks while another read-write
+    /// transaction is open.
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(LmdbRWTxn {
+            txn: self.env.write_txn()?,
+            db: self.db,
+        }))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+
+    /// Appends the items to the end of the tree, which skips the search for
+    /// where they go and fills the pages.
+    fn bulk_load(&self, items: &[(String, String)]) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        for (k, v) in items {
+            self.db
+                .put_with_flags(&mut txn, PutFlags::APPEND, k.as_bytes(), v.as_bytes())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<Option<Stats>> {
+        let txn = self.env.read_txn()?;
+        let stats = self.db.stat(&txn)?;
+        Ok(Some(named_stats([
+            ("page size", stats.page_size.to_string()),
+            ("tree height", stats.depth.to_string()),
+            ("keys", stats.entries.to_string()),
+            (
+                "allocated pages",
+                (self.env.info().last_page_number + 1).to_string(),
+            ),
+            ("leaf pages", stats.leaf_pages.to_string()),
+            ("branch pages", stats.branch_pages.to_string()),
+            ("overflow pages", stats.overflow_pages.to_string()),
+        ])))
+    }
+}
+
+fn scan(
+    db: Database<Bytes, Bytes>,
+    txn: &RoTxn,
+    start: Bound<&[u8]>,
+    visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+) -> Result<()> {
+    for kv in db.range(txn, &(start, Bound::Unbounded))? {
+        let (k, v) = kv?;
+        if !visit(k, v) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct LmdbRTxn<'e> {
+    txn: RoTxn<'e, WithoutTls>,
+    db: Database<Bytes, Bytes>,
+}
+
+impl ReadTxn for LmdbRTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(self.db.get(&self.txn, key)?.map(Cow::Borrowed))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        scan(self.db, &self.txn, start, visit)
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        for kv in self.db.rev_range(&self.txn, &(Bound::Unbounded, end))? {
+            let (k, v) = kv?;
+            if !visit(k, v) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct LmdbRWTxn<'e> {
+    txn: RwTxn<'e>,
+    db: Database<Bytes, Bytes>,
+}
+
+impl ReadTxn for LmdbRWTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(self.db.get(&self.txn, key)?.map(Cow::Borrowed))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        scan(self.db, &self.txn, start, visit)
+    }
+}
+
+impl WriteTxn for LmdbRWTxn<'_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        Ok(self.db.put(&mut self.txn, key, val)?)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        Ok(self.db.delete(&mut self.txn, key)?)
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        Ok(self.txn.commit()?)
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        self.txn.abort();
+        Ok(())
+    }
+}
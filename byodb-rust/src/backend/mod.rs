@@ -0,0 +1,470 @@
+//! The key-value stores that can be benchmarked, behind a common interface.
+//!
+//! Each store implements [`Backend`] and is listed in [`REGISTRY`] under the
+//! name selected by `--backend`. Stores other than byodb are behind cargo
+//! features of the same name.
+//!
+//! The in-memory [`BASELINE`] backend is benchmarked alongside the others by
+//! default, as an upper bound that shows the overhead of persistence, and
+//! the [`NULL`] backend measures the overhead of the harness itself.
+//!
+//! Backend-specific tuning is passed through as `<backend>.<option>=<value>`
+//! pairs (see [`BackendOpts`]), which each backend interprets itself. The
+//! common [`Durability`] levels are mapped to such options by each backend.
+//!
+//! [`Faulty`] wraps any of them to inject I/O faults into its writes.
+
+mod btreemap;
+mod byodb;
+mod byodb_go;
+mod faults;
+#[cfg(feature = "fjall")]
+mod fjall;
+#[cfg(feature = "lmdb")]
+mod lmdb;
+mod null;
+#[cfg(feature = "redb")]
+mod redb;
+mod remote;
+#[cfg(feature = "rocksdb")]
+mod rocksdb;
+#[cfg(feature = "sled")]
+mod sled;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod write_set;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::ops::Bound;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+pub use faults::{Faults, Faulty};
+
+/// An embedded key-value store.
+pub trait Backend: Send + Sync {
+    /// Begins a read-only transaction.
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>>;
+    /// Begins a read-write transaction.
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>>;
+    /// Whether the read-only transactions of the store support
+    /// [`ReadTxn::scan_rev`].
+    fn reverse_scans(&self) -> bool {
+        false
+    }
+    /// Whether an open read-only transaction makes commits wait until it
+    /// ends, rather than keeping the versions it may read.
+    fn readers_block_writers(&self) -> bool {
+        false
+    }
+    /// Loads `items`, sorted by key and without duplicates, into the empty
+    /// store, through its fastest path for bulk loads. By default, they're
+    /// inserted in order in a single transaction.
+    fn bulk_load(&self, items: &[(String, String)]) -> Result<()> {
+        let mut t = self.begin_rw()?;
+        for (k, v) in items {
+            t.put(k.as_bytes(), v.as_bytes())?;
+        }
+        t.commit()
+    }
+    /// Structural statistics of the store, like the height of its tree or
+    /// how many pages it uses, if it reports any.
+    fn stats(&self) -> Result<Option<Stats>> {
+        Ok(None)
+    }
+}
+
+/// Named statistics of a store, in the order they're reported. What they
+/// are depends on the store.
+pub type Stats = Vec<(String, String)>;
+
+/// [`Stats`] with the given names.
+fn named_stats<const N: usize>(stats: [(&str, String); N]) -> Stats {
+    stats
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+}
+
+/// A transaction with a consistent view of the store.
+pub trait ReadTxn {
+    /// Retrieves the value associated with `key`, if any.
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>>;
+    /// Visits the key-value pairs starting from `start`, in key order,
+    /// until `visit` returns `false`.
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()>;
+    /// Visits the key-value pairs up to `end`, in descending key order,
+    /// until `visit` returns `false`. Only supported by the read-only
+    /// transactions of stores whose [`Backend::reverse_scans`] says so.
+    fn scan_rev(
+        &self,
+        _end: Bound<&[u8]>,
+        _visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        bail!("reverse scans aren't supported")
+    }
+}
+
+/// A transaction that can also modify the store. Changes are discarded
+/// unless [`WriteTxn::commit`] is called.
+pub trait WriteTxn: ReadTxn {
+    /// Inserts `key` or updates its value if it already exists.
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()>;
+    /// Deletes `key`, returning whether it existed.
+    fn delete(&mut self, key: &[u8]) -> Result<bool>;
+    /// Makes the changes durable and visible to subsequent transactions.
+    fn commit(self: Box<Self>) -> Result<()>;
+    /// Discards the changes.
+    fn abort(self: Box<Self>) -> Result<()>;
+}
+
+/// The error of a read-write transaction that failed because of a
+/// concurrent one, like an optimistic commit that lost a race, and may
+/// succeed if retried from the start. Any of its methods may fail with it.
+#[derive(Debug)]
+pub struct Conflict;
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the transaction conflicted with a concurrent one")
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+/// Whether `err` is, or was caused by, a [`Conflict`].
+pub fn is_conflict(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<Conflict>())
+}
+
+/// Backend-specific options of all backends, keyed by `<backend>.<option>`.
+pub type BackendOpts = BTreeMap<String, String>;
+
+/// The options of a single backend, keyed without the `<backend>.` prefix.
+pub type Opts = BTreeMap<String, String>;
+
+/// When commits are synced to disk, from the least to the most durable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Durability {
+    /// Never: commits survive a crash of the process, but not of the OS.
+    None,
+    /// Every so often in the background, so an OS crash loses the latest
+    /// commits.
+    Periodic,
+    /// On every commit, before it returns.
+    Commit,
+}
+
+impl Durability {
+    /// All levels, from the least to the most durable.
+    pub const ALL: [Durability; 3] = [Durability::None, Durability::Periodic, Durability::Commit];
+}
+
+impl fmt::Display for Durability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no durability is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+/// A backend that can be selected by name.
+pub struct Registration {
+    pub name: &'static str,
+    /// Opens (or creates) the store at the given path. The path is either
+    /// a file or a directory, depending on the backend.
+    pub open: fn(&Path, &Opts) -> Result<Box<dyn Backend>>,
+    /// The options that give the store a durability level, or `None` if it
+    /// doesn't support it.
+    pub durability: fn(Durability) -> Option<Opts>,
+    /// Whether several processes can open the store at once, and share its
+    /// writes.
+    pub multi_process: bool,
+}
+
+/// The in-memory backend that every run is compared against.
+pub const BASELINE: &str = "btreemap";
+
+/// The backend that does nothing, run to calibrate the harness overhead.
+pub const NULL: &str = "null";
+
+/// All backends compiled into this binary.
+pub const REGISTRY: &[Registration] = &[
+    Registration {
+        name: "byodb",
+        open: byodb::open,
+        durability: sync_on_commit,
+        multi_process: false,
+    },
+    Registration {
+        name: "byodb-go",
+        open: byodb_go::open,
+        durability: sync_on_commit,
+        multi_process: false,
+    },
+    Registration {
+        name: BASELINE,
+        open: btreemap::open,
+        durability: in_memory,
+        multi_process: false,
+    },
+    Registration {
+        name: NULL,
+        open: null::open,
+        durability: in_memory,
+        multi_process: false,
+    },
+    Registration {
+        name: "remote",
+        open: remote::open,
+        durability: remote::durability,
+        multi_process: true,
+    },
+    #[cfg(feature = "sled")]
+    Registration {
+        name: "sled",
+        open: sled::open,
+        durability: sled::durability,
+        multi_process: false,
+    },
+    #[cfg(feature = "redb")]
+    Registration {
+        name: "redb",
+        open: redb::open,
+        durability: redb::durability,
+        multi_process: false,
+    },
+    #[cfg(feature = "rocksdb")]
+    Registration {
+        name: "rocksdb",
+        open: rocksdb::open,
+        durability: rocksdb::durability,
+        multi_process: false,
+    },
+    #[cfg(feature = "lmdb")]
+    Registration {
+        name: "lmdb",
+        open: lmdb::open,
+        durability: lmdb::durability,
+        multi_process: true,
+    },
+    #[cfg(feature = "sqlite")]
+    Registration {
+        name: "sqlite",
+        open: sqlite::open,
+        durability: sqlite::durability,
+        multi_process: true,
+    },
+    #[cfg(feature = "fjall")]
+    Registration {
+        name: "fjall",
+        open: fjall::open,
+        durability: fjall::durability,
+        multi_process: false,
+    },
+];
+
+/// The names of all backends in [`REGISTRY`].
+pub fn names() -> impl Iterator<Item = &'static str> {
+    REGISTRY.iter().map(|r| r.name)
+}
+
+fn registration(name: &str) -> Result<&'static Registration> {
+    REGISTRY
+        .iter()
+        .find(|r| r.name == name)
+        .ok_or_else(|| anyhow!("unknown backend {name}"))
+}
+
+/// Whether the backend named `name` supports `durability`.
+pub fn supports(name: &str, durability: Durability) -> Result<bool> {
+    Ok((registration(name)?.durability)(durability).is_some())
+}
+
+/// Whether several processes can open the store of the backend named
+/// `name` at once.
+pub fn multi_process(name: &str) -> Result<bool> {
+    Ok(registration(name)?.multi_process)
+}
+
+/// `opts` with the options that give the backend named `name` the
+/// `durability` level added, unless they're already set.
+pub fn with_durability(
+    name: &str,
+    durability: Durability,
+    opts: &BackendOpts,
+) -> Result<BackendOpts> {
+    let Some(own_opts) = (registration(name)?.durability)(durability) else {
+        bail!("{name} doesn't support the {durability} durability level");
+    };
+    let mut opts = opts.clone();
+    for (option, val) in own_opts {
+        opts.entry(format!("{name}.{option}")).or_insert(val);
+    }
+    Ok(opts)
+}
+
+/// Opens (or creates) the store at `path` with the backend named `name`,
+/// configured with its options from `opts`.
+pub fn open(name: &str, path: &Path, opts: &BackendOpts) -> Result<Box<dyn Backend>> {
+    let registration = registration(name)?;
+    let mut own_opts = Opts::new();
+    for (key, val) in opts {
+        let (backend, option) = key
+            .split_once('.')
+            .ok_or_else(|| anyhow!("backend option {key} is not of the form <backend>.<option>"))?;
+        if !names().any(|n| n == backend) {
+            bail!("backend option {key} is for unknown backend {backend}");
+        }
+        if backend == name {
+            own_opts.insert(option.to_string(), val.clone());
+        }
+    }
+    (registration.open)(path, &own_opts)
+}
+
+/// The durability of in-memory stores, which have nothing to sync.
+fn in_memory(_durability: Durability) -> Option<Opts> {
+    Some(Opts::new())
+}
+
+/// The durability of stores that always sync on commit.
+fn sync_on_commit(durability: Durability) -> Option<Opts> {
+    (durability == Durability::Commit).then(Opts::new)
+}
+
+/// Parses the value of a backend option.
+fn parse_opt<T>(key: &str, val: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    val.parse()
+        .map_err(|err| anyhow!("{err}"))
+        .with_context(|| format!("invalid value {val} for backend option {key}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::seed::count_items;
+
+    /// The backends that keep their store in memory, which every test can
+    /// open without a file. Every one must pass the tests below, whatever
+    /// it does with the values.
+    const IN_MEMORY: [&str; 2] = [BASELINE, NULL];
+
+    fn open_in_memory(name: &str) -> Box<dyn Backend> {
+        open(name, Path::new(""), &BackendOpts::new()).unwrap()
+    }
+
+    /// Puts `keys` in a transaction of their own, and commits it.
+    fn commit(db: &dyn Backend, keys: impl IntoIterator<Item = String>) {
+        let mut t = db.begin_rw().unwrap();
+        for k in keys {
+            t.put(k.as_bytes(), b"value").unwrap();
+        }
+        t.commit().unwrap();
+    }
+
+    fn keys(range: std::ops::Range<usize>) -> impl Iterator<Item = String> {
+        range.map(|i| format!("key-{i:04}"))
+    }
+
+    /// Counts the records `t` scans forward, and backward if it can.
+    fn count(t: &dyn ReadTxn, reverse: bool) -> usize {
+        let mut n = 0;
+        t.scan(Bound::Unbounded, &mut |_, _| {
+            n += 1;
+            true
+        })
+        .unwrap();
+        if reverse {
+            let mut n_rev = 0;
+            t.scan_rev(Bound::Unbounded, &mut |_, _| {
+                n_rev += 1;
+                true
+            })
+            .unwrap();
+            assert_eq!(n_rev, n);
+        }
+        n
+    }
+
+    #[test]
+    fn commits_are_visible() {
+        for name in IN_MEMORY {
+            let db = open_in_memory(name);
+            commit(&*db, keys(0..10));
+            commit(&*db, keys(10..15));
+            let reverse = db.reverse_scans();
+            assert_eq!(count(&*db.begin_ro().unwrap(), reverse), 15, "{name}");
+        }
+    }
+
+    #[test]
+    fn writes_are_visible_to_their_transaction_only() {
+        for name in IN_MEMORY {
+            let db = open_in_memory(name);
+            let mut t = db.begin_rw().unwrap();
+            for k in keys(0..10) {
+                t.put(k.as_bytes(), b"value").unwrap();
+            }
+            assert_eq!(count(&*t, false), 10, "{name}");
+            t.abort().unwrap();
+            assert_eq!(count_items(&*db).unwrap(), 0, "{name}");
+        }
+    }
+
+    #[test]
+    fn snapshots_are_isolated() {
+        for name in IN_MEMORY {
+            let db = open_in_memory(name);
+            commit(&*db, keys(0..5));
+            let snapshot = db.begin_ro().unwrap();
+            commit(&*db, keys(5..10));
+            let reverse = db.reverse_scans();
+            assert_eq!(count(&*snapshot, reverse), 5, "{name}");
+            drop(snapshot);
+            assert_eq!(count_items(&*db).unwrap(), 10, "{name}");
+        }
+    }
+
+    #[test]
+    fn concurrent_commits_are_kept() {
+        const THREADS: usize = 4;
+        const TXNS: usize = 100;
+        const PUTS: usize = 10;
+        for name in IN_MEMORY {
+            let db = open_in_memory(name);
+            thread::scope(|s| {
+                for t in 0..THREADS {
+                    let db = &*db;
+                    s.spawn(move || {
+                        for i in 0..TXNS {
+                            // Yield between the puts, so that the
+                            // transactions of the threads overlap if the
+                            // backend lets them.
+                            let mut txn = db.begin_rw().unwrap();
+                            for p in 0..PUTS {
+                                let key = format!("thread-{t}-{i:04}-{p:02}");
+                                txn.put(key.as_bytes(), b"value").unwrap();
+                                thread::yield_now();
+                            }
+                            txn.commit().unwrap();
+                        }
+                    });
+                }
+            });
+            assert_eq!(count_items(&*db).unwrap(), THREADS * TXNS * PUTS, "{name}");
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Result, bail};
+
+use super::{Backend, Opts, ReadTxn, WriteTxn};
+
+/// Nothing is stored at `path`, nor anywhere else.
+pub fn open(_path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    if let Some(key) = opts.keys().next() {
+        bail!("unknown null option {key}");
+    }
+    Ok(Box::new(Null {
+        len: AtomicUsize::new(0),
+    }))
+}
+
+/// A store that discards everything, to measure the harness's own overhead.
+///
+/// Only the number of committed puts is remembered, so that scans visit as
+/// many (empty) items as the other backends do. Gets never find anything.
+struct Null {
+    len: AtomicUsize,
+}
+
+impl Backend for Null {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(NullTxn {
+            null: self,
+            len: self.len.load(Ordering::Acquire),
+            puts: 0,
+        }))
+    }
+
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(NullTxn {
+            null: self,
+            len: self.len.load(Ordering::Acquire),
+            puts: 0,
+        }))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+}
+
+struct NullTxn<'d> {
+    null: &'d Null,
+    /// The number of items as of when the transaction began, plus its puts.
+    len: usize,
+    /// The puts of the transaction, which commits add to those of the
+    /// others.
+    puts: usize,
+}
+
+impl ReadTxn for NullTxn<'_> {
+    fn get(&self, _key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(None)
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        if let Bound::Unbounded = start {
+            for _ in 0..self.len {
+                if !visit(&[], &[]) {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        self.scan(end, visit)
+    }
+}
+
+impl WriteTxn for NullTxn<'_> {
+    fn put(&mut self, _key: &[u8], _val: &[u8]) -> Result<()> {
+        self.len += 1;
+        self.puts += 1;
+        Ok(())
+    }
+
+    fn delete(&mut self, _key: &[u8]) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        self.null.len.fetch_add(self.puts, Ordering::Release);
+        Ok(())
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
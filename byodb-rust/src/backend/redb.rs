@@ -0,0 +1,167 @@
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use redb::{
+    Database, ReadOnlyTable, ReadTransaction, ReadableDatabase, ReadableTable, TableDefinition,
+    WriteTransaction,
+};
+
+use super::{Backend, Durability, Opts, ReadTxn, Stats, WriteTxn, named_stats};
+
+const TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("kv");
+
+/// Commits with [`redb::Durability::Immediate`] or [`redb::Durability::None`].
+/// redb has no periodic sync.
+pub fn durability(durability: Durability) -> Option<Opts> {
+    let durability = match durability {
+        Durability::None => "none",
+        Durability::Periodic => return None,
+        Durability::Commit => "immediate",
+    };
+    Some(Opts::from([(
+        "durability".to_string(),
+        durability.to_string(),
+    )]))
+}
+
+/// Supports the option `durability` of write transactions, `immediate`
+/// (default) or `none`.
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut durability = redb::Durability::Immediate;
+    for (key, val) in opts {
+        durability = match (key.as_str(), val.as_str()) {
+            ("durability", "immediate") => redb::Durability::Immediate,
+            ("durability", "none") => redb::Durability::None,
+            ("durability", _) => bail!("invalid value {val} for backend option {key}"),
+            _ => bail!("unknown redb option {key}"),
+        };
+    }
+    let db = Database::create(path)?;
+    // Create the table up front, since read transactions can't.
+    let t = db.begin_write()?;
+    t.open_table(TABLE)?;
+    t.commit()?;
+    Ok(Box::new(Redb { db, durability }))
+}
+
+struct Redb {
+    db: Database,
+    durability: redb::Durability,
+}
+
+impl Backend for Redb {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(TABLE)?;
+        Ok(Box::new(RedbRTxn { _txn: txn, table }))
+    }
+
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        let mut txn = self.db.begin_write()?;
+        txn.set_durability(self.durability)?;
+        Ok(Box::new(RedbRWTxn { txn }))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+
+    /// Takes the write lock to read them, so it waits for writers.
+    fn stats(&self) -> Result<Option<Stats>> {
+        let txn = self.db.begin_write()?;
+        let stats = txn.stats()?;
+        txn.abort()?;
+        Ok(Some(named_stats([
+            ("page size", stats.page_size().to_string()),
+            ("tree height", stats.tree_height().to_string()),
+            ("allocated pages", stats.allocated_pages().to_string()),
+            ("leaf pages", stats.leaf_pages().to_string()),
+            ("branch pages", stats.branch_pages().to_string()),
+            ("stored bytes", stats.stored_bytes().to_string()),
+            ("metadata bytes", stats.metadata_bytes().to_string()),
+            ("fragmented bytes", stats.fragmented_bytes().to_string()),
+        ])))
+    }
+}
+
+struct RedbRTxn {
+    _txn: ReadTransaction,
+    table: ReadOnlyTable<&'static [u8], &'static [u8]>,
+}
+
+impl ReadTxn for RedbRTxn {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(self.table.get(key)?.map(|v| Cow::Owned(v.value().to_vec())))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        for kv in self.table.range::<&[u8]>((start, Bound::Unbounded))? {
+            let (k, v) = kv?;
+            if !visit(k.value(), v.value()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        for kv in self.table.range::<&[u8]>((Bound::Unbounded, end))?.rev() {
+            let (k, v) = kv?;
+            if !visit(k.value(), v.value()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The table is reopened for every operation, since it borrows the
+/// transaction.
+struct RedbRWTxn {
+    txn: WriteTransaction,
+}
+
+impl ReadTxn for RedbRWTxn {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        let table = self.txn.open_table(TABLE)?;
+        Ok(table.get(key)?.map(|v| Cow::Owned(v.value().to_vec())))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        let table = self.txn.open_table(TABLE)?;
+        for kv in table.range::<&[u8]>((start, Bound::Unbounded))? {
+            let (k, v) = kv?;
+            if !visit(k.value(), v.value()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WriteTxn for RedbRWTxn {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.txn.open_table(TABLE)?.insert(key, val)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        Ok(self.txn.open_table(TABLE)?.remove(key)?.is_some())
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        self.txn.commit()?;
+        Ok(())
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        self.txn.abort()?;
+        Ok(())
+    }
+}
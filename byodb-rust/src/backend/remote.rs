@@ -0,0 +1,212 @@
+use std::borrow::Cow;
+use std::cell::{RefCell, RefMut};
+use std::net::TcpStream;
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result, bail, ensure};
+
+use super::{Backend, Durability, Opts, ReadTxn, WriteTxn, parse_opt};
+use crate::net::{self, Conn, Frame};
+
+/// Address of the server, unless set by the `addr` option.
+const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+
+/// Number of records a scan asks the server for at a time.
+const SCAN_PAGE: u32 = 256;
+
+/// The durability of the store is up to the server, so no option sets it.
+pub fn durability(_durability: Durability) -> Option<Opts> {
+    Some(Opts::new())
+}
+
+/// Connects to a `db-cmp-server` at the option `addr` (default:
+/// `127.0.0.1:7878`), which serves a store of its own, so `path` isn't
+/// used. Whatever the server already holds is visible to transactions.
+pub fn open(_path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    for (key, val) in opts {
+        match key.as_str() {
+            "addr" => addr = parse_opt(key, val)?,
+            _ => bail!("unknown remote option {key}"),
+        }
+    }
+    let remote = Remote {
+        addr,
+        reverse_scans: false,
+        pool: Mutex::new(Vec::new()),
+    };
+    let (conn, reverse_scans) = remote.connect()?;
+    remote.pool.lock().unwrap().push(conn);
+    Ok(Box::new(Remote {
+        reverse_scans,
+        ..remote
+    }))
+}
+
+/// A [`Conn`] has a single transaction open at a time, so each transaction
+/// takes one from a pool, and returns it when done.
+struct Remote {
+    addr: String,
+    reverse_scans: bool,
+    pool: Mutex<Vec<Conn>>,
+}
+
+impl Remote {
+    /// Connects to the server. Returns the connection, and whether the store
+    /// supports reverse scans.
+    fn connect(&self) -> Result<(Conn, bool)> {
+        let stream = TcpStream::connect(&self.addr)
+            .with_context(|| format!("failed to connect to {}", self.addr))?;
+        let mut conn = Conn::new(stream)?;
+        let mut hello = conn
+            .receive()?
+            .context("the server closed the connection")?;
+        ensure!(
+            hello.u8()? == net::HELLO,
+            "expected a hello from the server"
+        );
+        let reverse_scans = hello.u8()? != 0;
+        Ok((conn, reverse_scans))
+    }
+
+    fn begin(&self, op: u8) -> Result<RemoteTxn<'_>> {
+        let conn = match self.pool.lock().unwrap().pop() {
+            Some(conn) => conn,
+            None => self.connect()?.0,
+        };
+        let mut t = RemoteTxn {
+            conn: Some(RefCell::new(conn)),
+            open: false,
+            pool: &self.pool,
+        };
+        t.conn().call(Frame::new(op))?;
+        t.open = true;
+        Ok(t)
+    }
+}
+
+impl Backend for Remote {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(self.begin(net::BEGIN_RO)?))
+    }
+
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(self.begin(net::BEGIN_RW)?))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        self.reverse_scans
+    }
+}
+
+struct RemoteTxn<'d> {
+    /// Always `Some` until dropped.
+    conn: Option<RefCell<Conn>>,
+    /// Whether the server has the transaction open.
+    open: bool,
+    pool: &'d Mutex<Vec<Conn>>,
+}
+
+impl RemoteTxn<'_> {
+    fn conn(&self) -> RefMut<'_, Conn> {
+        self.conn.as_ref().unwrap().borrow_mut()
+    }
+
+    /// Ends the transaction on the server with `op`.
+    fn end(&mut self, op: u8) -> Result<()> {
+        self.open = false;
+        self.conn().call(Frame::new(op))?;
+        Ok(())
+    }
+
+    /// Visits the records from `bound` a page at a time, in reverse if
+    /// `reverse`.
+    fn scan_pages(
+        &self,
+        bound: Bound<&[u8]>,
+        reverse: bool,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        let mut conn = self.conn();
+        let mut bound = bound.map(<[u8]>::to_vec);
+        let mut last = Vec::new();
+        loop {
+            let request = Frame::new(net::SCAN)
+                .bound(bound.as_ref().map(Vec::as_slice))
+                .u8(reverse as u8)
+                .u32(SCAN_PAGE);
+            let mut page = conn.call(request)?;
+            let n = page.u32()?;
+            for _ in 0..n {
+                let (k, v) = (page.bytes()?, page.bytes()?);
+                if !visit(k, v) {
+                    return Ok(());
+                }
+                last.clear();
+                last.extend_from_slice(k);
+            }
+            if n < SCAN_PAGE {
+                return Ok(());
+            }
+            bound = Bound::Excluded(last.clone());
+        }
+    }
+}
+
+impl Drop for RemoteTxn<'_> {
+    fn drop(&mut self) {
+        // Neither committed nor aborted, e.g. due to an error.
+        if self.open && self.end(net::ABORT).is_err() {
+            return;
+        }
+        let conn = self.conn.take().unwrap().into_inner();
+        self.pool.lock().unwrap().push(conn);
+    }
+}
+
+impl ReadTxn for RemoteTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        let mut conn = self.conn();
+        let mut response = conn.call(Frame::new(net::GET).bytes(key))?;
+        Ok(match response.u8()? {
+            0 => None,
+            _ => Some(Cow::Owned(response.bytes()?.to_vec())),
+        })
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        self.scan_pages(start, false, visit)
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        self.scan_pages(end, true, visit)
+    }
+}
+
+impl WriteTxn for RemoteTxn<'_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.conn()
+            .call(Frame::new(net::PUT).bytes(key).bytes(val))?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        let mut conn = self.conn();
+        let mut response = conn.call(Frame::new(net::DELETE).bytes(key))?;
+        Ok(response.u8()? != 0)
+    }
+
+    fn commit(mut self: Box<Self>) -> Result<()> {
+        self.end(net::COMMIT)
+    }
+
+    fn abort(mut self: Box<Self>) -> Result<()> {
+        self.end(net::ABORT)
+    }
+}
@@ -0,0 +1,206 @@
+use std::borrow::Cow;
+use std::fs;
+use std::ops::Bound;
+use std::path::Path;
+
+use anyhow::Result;
+use rocksdb::{
+    DBIteratorWithThreadMode, Direction, ErrorKind, IteratorMode, OptimisticTransactionDB,
+    OptimisticTransactionOptions, Options, SnapshotWithThreadMode, SstFileWriter, Transaction,
+    WriteOptions,
+};
+
+use super::{
+    Backend, Conflict, Durability, Opts, ReadTxn, Stats, WriteTxn, named_stats, parse_opt,
+};
+
+/// Syncs the WAL on every commit (`sync`), or leaves it to the OS. RocksDB
+/// has no periodic sync.
+pub fn durability(durability: Durability) -> Option<Opts> {
+    let sync = match durability {
+        Durability::None => "false",
+        Durability::Periodic => return None,
+        Durability::Commit => "true",
+    };
+    Some(Opts::from([("sync".to_string(), sync.to_string())]))
+}
+
+/// Supports the option `sync` (default: false), whether every commit syncs
+/// the WAL. All others are passed through to RocksDB's options string parser
+/// (e.g. `write_buffer_size`, `max_background_jobs`, `compression`), see
+/// <https://github.com/facebook/rocksdb/wiki/Option-String-and-Option-Map>.
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut write_options = WriteOptions::default();
+    let mut opts_str = Vec::new();
+    for (key, val) in opts {
+        match key.as_str() {
+            "sync" => write_options.set_sync(parse_opt(key, val)?),
+            _ => opts_str.push(format!("{key}={val}")),
+        }
+    }
+    let mut options = Options::default().get_options_from_string(opts_str.join(";"))?;
+    options.create_if_missing(true);
+    Ok(Box::new(RocksDb {
+        db: OptimisticTransactionDB::open(&options, path)?,
+        write_options,
+    }))
+}
+
+/// Read-write transactions are optimistic: conflicting commits fail
+/// instead of blocking each other.
+struct RocksDb {
+    db: OptimisticTransactionDB,
+    write_options: WriteOptions,
+}
+
+impl Backend for RocksDb {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(self.db.snapshot()))
+    }
+
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(self.db.transaction_opt(
+            &self.write_options,
+            &OptimisticTransactionOptions::default(),
+        )))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+
+    /// Writes the items to an SST file, which is then ingested, bypassing
+    /// the WAL and the memtable.
+    fn bulk_load(&self, items: &[(String, String)]) -> Result<()> {
+        let path = self.db.path().join("bulk_load.sst");
+        let options = Options::default();
+        let mut writer = SstFileWriter::create(&options);
+        writer.open(&path)?;
+        for (k, v) in items {
+            writer.put(k, v)?;
+        }
+        writer.finish()?;
+        self.db.ingest_external_file(vec![&path])?;
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    /// The shape of the LSM tree, from RocksDB's properties.
+    fn stats(&self) -> Result<Option<Stats>> {
+        let property = |name: &str| -> Result<String> {
+            let value = self.db.property_int_value(name)?;
+            Ok(value.map_or_else(|| "unknown".to_string(), |value| value.to_string()))
+        };
+        let mut stats = named_stats([
+            ("approximate keys", property("rocksdb.estimate-num-keys")?),
+            (
+                "live data bytes",
+                property("rocksdb.estimate-live-data-size")?,
+            ),
+            ("SST file bytes", property("rocksdb.total-sst-files-size")?),
+            (
+                "memtable bytes",
+                property("rocksdb.cur-size-all-mem-tables")?,
+            ),
+        ]);
+        // RocksDB's default number of levels.
+        for level in 0..7 {
+            let files = self
+                .db
+                .property_value(&format!("rocksdb.num-files-at-level{level}"))?;
+            stats.push((
+                format!("level {level} files"),
+                files.unwrap_or_else(|| "unknown".to_string()),
+            ));
+        }
+        Ok(Some(stats))
+    }
+}
+
+/// Visits the entries of `iter`, which was positioned at `start`, skipping
+/// it if excluded.
+fn scan<D: rocksdb::DBAccess>(
+    start: Bound<&[u8]>,
+    iter: DBIteratorWithThreadMode<'_, D>,
+    visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+) -> Result<()> {
+    for kv in iter {
+        let (k, v) = kv?;
+        if matches!(start, Bound::Excluded(start) if *k == *start) {
+            continue;
+        }
+        if !visit(&k, &v) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn iterator_mode(start: Bound<&[u8]>) -> IteratorMode<'_> {
+    match start {
+        Bound::Included(k) | Bound::Excluded(k) => IteratorMode::From(k, Direction::Forward),
+        Bound::Unbounded => IteratorMode::Start,
+    }
+}
+
+fn reverse_iterator_mode(end: Bound<&[u8]>) -> IteratorMode<'_> {
+    match end {
+        Bound::Included(k) | Bound::Excluded(k) => IteratorMode::From(k, Direction::Reverse),
+        Bound::Unbounded => IteratorMode::End,
+    }
+}
+
+impl ReadTxn for SnapshotWithThreadMode<'_, OptimisticTransactionDB> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(SnapshotWithThreadMode::get(self, key)?.map(Cow::Owned))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        scan(start, self.iterator(iterator_mode(start)), visit)
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        scan(end, self.iterator(reverse_iterator_mode(end)), visit)
+    }
+}
+
+impl ReadTxn for Transaction<'_, OptimisticTransactionDB> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(Transaction::get(self, key)?.map(Cow::Owned))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        scan(start, self.iterator(iterator_mode(start)), visit)
+    }
+}
+
+impl WriteTxn for Transaction<'_, OptimisticTransactionDB> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        Ok(Transaction::put(self, key, val)?)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        let existed = self.get_pinned_for_update(key, true)?.is_some();
+        Transaction::delete(self, key)?;
+        Ok(existed)
+    }
+
+    /// Fails with a [`Conflict`] if a concurrent transaction wrote a key
+    /// this one accessed since it began.
+    fn commit(self: Box<Self>) -> Result<()> {
+        match Transaction::commit(*self) {
+            Err(err) if matches!(err.kind(), ErrorKind::Busy | ErrorKind::TryAgain) => {
+                Err(anyhow::Error::new(Conflict).context(err))
+            }
+            result => Ok(result?),
+        }
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        Ok(self.rollback()?)
+    }
+}
@@ -0,0 +1,182 @@
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+
+use super::write_set::WriteSet;
+use super::{Backend, Durability, Opts, ReadTxn, Stats, WriteTxn, named_stats, parse_opt};
+
+/// How often sled syncs in the background by default.
+const DEFAULT_FLUSH_EVERY_MS: u64 = 500;
+
+/// Syncs on every commit (`flush_on_commit`), every
+/// [`DEFAULT_FLUSH_EVERY_MS`], or never (`flush_every_ms = 0`).
+pub fn durability(durability: Durability) -> Option<Opts> {
+    let (key, val) = match durability {
+        Durability::None => ("flush_every_ms", "0".to_string()),
+        Durability::Periodic => ("flush_every_ms", DEFAULT_FLUSH_EVERY_MS.to_string()),
+        Durability::Commit => ("flush_on_commit", "true".to_string()),
+    };
+    Some(Opts::from([(key.to_string(), val)]))
+}
+
+/// Supports the options `flush_every_ms` (default: 500, 0 for never), how
+/// often sled syncs in the background, and `flush_on_commit` (default:
+/// false), whether every commit is synced too.
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut config = sled::Config::new().path(path);
+    let mut flush_on_commit = false;
+    for (key, val) in opts {
+        match key.as_str() {
+            "flush_every_ms" => {
+                let every_ms: u64 = parse_opt(key, val)?;
+                config = config.flush_every_ms((every_ms > 0).then_some(every_ms));
+            }
+            "flush_on_commit" => flush_on_commit = parse_opt(key, val)?,
+            _ => bail!("unknown sled option {key}"),
+        }
+    }
+    Ok(Box::new(Sled {
+        db: config.open()?,
+        flush_on_commit,
+    }))
+}
+
+struct Sled {
+    db: sled::Db,
+    flush_on_commit: bool,
+}
+
+impl Backend for Sled {
+    /// sled has no snapshots, so reads observe concurrently committed writes.
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(SledRTxn { db: &self.db }))
+    }
+
+    /// Writes are buffered and applied atomically as a single batch on
+    /// commit.
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(SledRWTxn {
+            db: &self.db,
+            flush_on_commit: self.flush_on_commit,
+            writes: WriteSet::default(),
+        }))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+
+    /// Applies a single batch.
+    fn bulk_load(&self, items: &[(String, String)]) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (k, v) in items {
+            batch.insert(k.as_bytes(), v.as_bytes());
+        }
+        self.db.apply_batch(batch)?;
+        if self.flush_on_commit {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<Option<Stats>> {
+        Ok(Some(named_stats([
+            ("keys", self.db.len().to_string()),
+            ("disk bytes", self.db.size_on_disk()?.to_string()),
+        ])))
+    }
+}
+
+struct SledRTxn<'d> {
+    db: &'d sled::Db,
+}
+
+impl ReadTxn for SledRTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        Ok(self.db.get(key)?.map(|v| Cow::Owned(v.to_vec())))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        for kv in self.db.range::<&[u8], _>((start, Bound::Unbounded)) {
+            let (k, v) = kv?;
+            if !visit(&k, &v) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        for kv in self.db.range::<&[u8], _>((Bound::Unbounded, end)).rev() {
+            let (k, v) = kv?;
+            if !visit(&k, &v) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct SledRWTxn<'d> {
+    db: &'d sled::Db,
+    flush_on_commit: bool,
+    writes: WriteSet,
+}
+
+impl ReadTxn for SledRWTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        match self.writes.get(key) {
+            Some(v) => Ok(v.map(Cow::Borrowed)),
+            None => Ok(self.db.get(key)?.map(|v| Cow::Owned(v.to_vec()))),
+        }
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        let base = self
+            .db
+            .range::<&[u8], _>((start, Bound::Unbounded))
+            .map(|kv| Ok(kv?));
+        self.writes.scan(start, base, visit)
+    }
+}
+
+impl WriteTxn for SledRWTxn<'_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.writes.put(key, val);
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        let existed = match self.writes.get(key) {
+            Some(v) => v.is_some(),
+            None => self.db.contains_key(key)?,
+        };
+        self.writes.delete(key);
+        Ok(existed)
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (k, v) in self.writes.into_writes() {
+            match v {
+                Some(v) => batch.insert(k, v),
+                None => batch.remove(k),
+            }
+        }
+        self.db.apply_batch(batch)?;
+        if self.flush_on_commit {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
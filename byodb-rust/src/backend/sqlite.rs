@@ -0,0 +1,278 @@
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use rusqlite::{Connection, ErrorCode, OptionalExtension, Rows, params};
+
+use super::{Backend, Conflict, Durability, Opts, ReadTxn, Stats, WriteTxn, named_stats};
+
+/// How long a transaction waits for another one to release its lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Syncs the WAL on every commit (`synchronous = full`), only when it's
+/// checkpointed (`normal`), or never (`off`).
+pub fn durability(durability: Durability) -> Option<Opts> {
+    let synchronous = match durability {
+        Durability::None => "off",
+        Durability::Periodic => "normal",
+        Durability::Commit => "full",
+    };
+    Some(Opts::from([(
+        "synchronous".to_string(),
+        synchronous.to_string(),
+    )]))
+}
+
+/// Supports the options `journal_mode` (default: `wal`) and `synchronous`
+/// (default: SQLite's), which are set as the PRAGMAs of the same name, and
+/// `begin`, how read-write transactions begin: `immediate` (the default) or
+/// `deferred`.
+pub fn open(path: &Path, opts: &Opts) -> Result<Box<dyn Backend>> {
+    let mut pragmas = vec![("journal_mode".to_string(), "wal".to_string())];
+    let mut begin_rw = "BEGIN IMMEDIATE";
+    for (key, val) in opts {
+        match key.as_str() {
+            "journal_mode" => pragmas[0].1 = val.clone(),
+            "synchronous" => pragmas.push((key.clone(), val.clone())),
+            "begin" => {
+                begin_rw = match val.as_str() {
+                    "immediate" => "BEGIN IMMEDIATE",
+                    "deferred" => "BEGIN DEFERRED",
+                    _ => bail!("invalid value {val} for backend option {key}"),
+                }
+            }
+            _ => bail!("unknown sqlite option {key}"),
+        }
+    }
+    let db = Sqlite {
+        path: path.to_path_buf(),
+        pragmas,
+        begin_rw,
+        pool: Mutex::new(Vec::new()),
+    };
+    db.connect()?.execute_batch(
+        "CREATE TABLE IF NOT EXISTS kv (key BLOB PRIMARY KEY, value BLOB) WITHOUT ROWID",
+    )?;
+    Ok(Box::new(db))
+}
+
+/// A [`Connection`] can't be shared between threads, so each transaction
+/// takes one from a pool, and returns it when done.
+struct Sqlite {
+    path: PathBuf,
+    pragmas: Vec<(String, String)>,
+    /// The statement beginning read-write transactions.
+    begin_rw: &'static str,
+    pool: Mutex<Vec<Connection>>,
+}
+
+impl Sqlite {
+    fn connect(&self) -> Result<Connection> {
+        let conn = Connection::open(&self.path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        for (key, val) in &self.pragmas {
+            // journal_mode returns a row, so use query_row instead of execute.
+            conn.query_row(&format!("PRAGMA {key} = {val}"), [], |_| Ok(()))
+                .optional()?;
+        }
+        Ok(conn)
+    }
+
+    fn begin(&self, sql: &str) -> Result<SqliteTxn<'_>> {
+        let conn = match self.pool.lock().unwrap().pop() {
+            Some(conn) => conn,
+            None => self.connect()?,
+        };
+        conn.execute_batch(sql)?;
+        Ok(SqliteTxn {
+            conn: Some(conn),
+            pool: &self.pool,
+        })
+    }
+}
+
+impl Backend for Sqlite {
+    fn begin_ro(&self) -> Result<Box<dyn ReadTxn + '_>> {
+        Ok(Box::new(self.begin("BEGIN DEFERRED")?))
+    }
+
+    /// Takes the write lock up front by default, so SQLite has a single
+    /// writer. Deferred transactions only take it once they first write,
+    /// which fails with a [`Conflict`] if another one committed since they
+    /// read.
+    fn begin_rw(&self) -> Result<Box<dyn WriteTxn + '_>> {
+        Ok(Box::new(self.begin(self.begin_rw)?))
+    }
+
+    fn reverse_scans(&self) -> bool {
+        true
+    }
+
+    /// Outside of WAL mode, a reader holds a shared lock on the file, which
+    /// commits have to wait for.
+    fn readers_block_writers(&self) -> bool {
+        !self.pragmas[0].1.eq_ignore_ascii_case("wal")
+    }
+
+    /// The bundled SQLite has no `dbstat` table, so only the page counts of
+    /// the whole file are known.
+    fn stats(&self) -> Result<Option<Stats>> {
+        let t = self.begin("BEGIN DEFERRED")?;
+        let pragma = |name: &str| -> Result<String> {
+            let value: i64 = t
+                .conn()
+                .query_row(&format!("PRAGMA {name}"), [], |row| row.get(0))?;
+            Ok(value.to_string())
+        };
+        let keys: i64 = t
+            .conn()
+            .query_row("SELECT count(*) FROM kv", [], |row| row.get(0))?;
+        Ok(Some(named_stats([
+            ("page size", pragma("page_size")?),
+            ("keys", keys.to_string()),
+            ("allocated pages", pragma("page_count")?),
+            ("free pages", pragma("freelist_count")?),
+        ])))
+    }
+}
+
+struct SqliteTxn<'d> {
+    /// Always `Some` until dropped.
+    conn: Option<Connection>,
+    pool: &'d Mutex<Vec<Connection>>,
+}
+
+impl SqliteTxn<'_> {
+    fn conn(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl Drop for SqliteTxn<'_> {
+    fn drop(&mut self) {
+        let conn = self.conn.take().unwrap();
+        if !conn.is_autocommit() {
+            // Neither committed nor aborted, e.g. due to an error.
+            if conn.execute_batch("ROLLBACK").is_err() {
+                return;
+            }
+        }
+        self.pool.lock().unwrap().push(conn);
+    }
+}
+
+impl ReadTxn for SqliteTxn<'_> {
+    fn get(&self, key: &[u8]) -> Result<Option<Cow<'_, [u8]>>> {
+        let val = self
+            .conn()
+            .prepare_cached("SELECT value FROM kv WHERE key = ?1")?
+            .query_row(params![key], |row| row.get::<_, Vec<u8>>(0))
+            .optional()?;
+        Ok(val.map(Cow::Owned))
+    }
+
+    fn scan(&self, start: Bound<&[u8]>, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+        let conn = self.conn();
+        let mut stmt;
+        let rows = match start {
+            Bound::Included(k) => {
+                stmt =
+                    conn.prepare_cached("SELECT key, value FROM kv WHERE key >= ?1 ORDER BY key")?;
+                stmt.query(params![k])?
+            }
+            Bound::Excluded(k) => {
+                stmt =
+                    conn.prepare_cached("SELECT key, value FROM kv WHERE key > ?1 ORDER BY key")?;
+                stmt.query(params![k])?
+            }
+            Bound::Unbounded => {
+                stmt = conn.prepare_cached("SELECT key, value FROM kv ORDER BY key")?;
+                stmt.query([])?
+            }
+        };
+        visit_rows(rows, visit)
+    }
+
+    fn scan_rev(
+        &self,
+        end: Bound<&[u8]>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()> {
+        let conn = self.conn();
+        let mut stmt;
+        let rows = match end {
+            Bound::Included(k) => {
+                stmt = conn.prepare_cached(
+                    "SELECT key, value FROM kv WHERE key <= ?1 ORDER BY key DESC",
+                )?;
+                stmt.query(params![k])?
+            }
+            Bound::Excluded(k) => {
+                stmt = conn
+                    .prepare_cached("SELECT key, value FROM kv WHERE key < ?1 ORDER BY key DESC")?;
+                stmt.query(params![k])?
+            }
+            Bound::Unbounded => {
+                stmt = conn.prepare_cached("SELECT key, value FROM kv ORDER BY key DESC")?;
+                stmt.query([])?
+            }
+        };
+        visit_rows(rows, visit)
+    }
+}
+
+/// Turns `SQLITE_BUSY`, which a deferred transaction fails with if its
+/// snapshot is stale by the time it writes, into a [`Conflict`].
+fn conflict(err: rusqlite::Error) -> anyhow::Error {
+    match err.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy) => anyhow::Error::new(Conflict).context(err),
+        _ => err.into(),
+    }
+}
+
+/// Passes the key-value pairs of `rows` to `visit` until it returns `false`.
+fn visit_rows(mut rows: Rows, visit: &mut dyn FnMut(&[u8], &[u8]) -> bool) -> Result<()> {
+    while let Some(row) = rows.next()? {
+        let k = row.get_ref(0)?.as_blob()?;
+        let v = row.get_ref(1)?.as_blob()?;
+        if !visit(k, v) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl WriteTxn for SqliteTxn<'_> {
+    fn put(&mut self, key: &[u8], val: &[u8]) -> Result<()> {
+        self.conn()
+            .prepare_cached(
+                "INSERT INTO kv (key, value) VALUES (?1, ?2) \
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            )?
+            .execute(params![key, val])
+            .map_err(conflict)?;
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        let n = self
+            .conn()
+            .prepare_cached("DELETE FROM kv WHERE key = ?1")?
+            .execute(params![key])
+            .map_err(conflict)?;
+        Ok(n > 0)
+    }
+
+    fn commit(self: Box<Self>) -> Result<()> {
+        self.conn().execute_batch("COMMIT").map_err(conflict)?;
+        Ok(())
+    }
+
+    fn abort(self: Box<Self>) -> Result<()> {
+        self.conn().execute_batch("ROLLBACK")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use anyhow::Result;
+
+/// The uncommitted writes of a read-write transaction, for backends that
+/// can only apply writes atomically in a batch. Reads within the
+/// transaction consult the write set before the underlying store.
+#[derive(Default)]
+pub struct WriteSet {
+    /// `None` marks a deleted key.
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl WriteSet {
+    /// Returns `Some(None)` if `key` was deleted, or `None` if it wasn't
+    /// written at all.
+    pub fn get(&self, key: &[u8]) -> Option<Option<&[u8]>> {
+        self.writes.get(key).map(|v| v.as_deref())
+    }
+
+    pub fn put(&mut self, key: &[u8], val: &[u8]) {
+        self.writes.insert(key.to_vec(), Some(val.to_vec()));
+    }
+
+    pub fn delete(&mut self, key: &[u8]) {
+        self.writes.insert(key.to_vec(), None);
+    }
+
+    /// Consumes the write set, yielding the writes in key order.
+    pub fn into_writes(self) -> impl Iterator<Item = (Vec<u8>, Option<Vec<u8>>)> {
+        self.writes.into_iter()
+    }
+
+    /// Visits the key-value pairs of `base` (which must start from `start`,
+    /// in key order) with the write set applied on top, until `visit`
+    /// returns `false`.
+    pub fn scan<K, V>(
+        &self,
+        start: Bound<&[u8]>,
+        base: impl Iterator<Item = Result<(K, V)>>,
+        visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+    ) -> Result<()>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut base = base.peekable();
+        let mut writes = self
+            .writes
+            .range::<[u8], _>((start, Bound::Unbounded))
+            .peekable();
+        loop {
+            // Take from whichever side has the smaller key; on a tie, the
+            // write shadows the base.
+            let take_write = match (base.peek(), writes.peek()) {
+                (None, None) => return Ok(()),
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some(Err(_)), Some(_)) => false,
+                (Some(Ok((bk, _))), Some((wk, _))) => wk.as_slice() <= bk.as_ref(),
+            };
+            let keep_going = if take_write {
+                let (wk, wv) = writes.next().unwrap();
+                if base
+                    .peek()
+                    .is_some_and(|b| b.as_ref().is_ok_and(|(bk, _)| bk.as_ref() == wk))
+                {
+                    base.next();
+                }
+                match wv {
+                    Some(wv) => visit(wk, wv),
+                    None => true,
+                }
+            } else {
+                let (bk, bv) = base.next().unwrap()?;
+                visit(bk.as_ref(), bv.as_ref())
+            };
+            if !keep_going {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::ops::Bound;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+
+use anyhow::{Result, ensure};
+
+use crate::affinity;
+use crate::backend::{Backend, ReadTxn};
+use crate::config::Config;
+use crate::histogram::{Histogram, Latencies};
+use crate::ops::{Outcome, ThreadOutcome};
+use crate::perf::Counters;
+use crate::process::{self, CpuTime, Io};
+use crate::profile::Profiler;
+use crate::shadow::Shadow;
+use crate::timeline::{self, Counter, Monitor, Sample};
+use crate::workload::Workload;
+use crate::writers::{Retries, Writers};
+
+/// Visits every record of `t`, in descending key order if `reverse`.
+fn full_scan(
+    t: &dyn ReadTxn,
+    reverse: bool,
+    visit: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+) -> Result<()> {
+    if reverse {
+        t.scan_rev(Bound::Unbounded, visit)
+    } else {
+        t.scan(Bound::Unbounded, visit)
+    }
+}
+
+/// Runs `config.threads` readers that each iterate over the whole DB
+/// `config.iters` times, or for `config.duration_ms`, in descending key order
+/// for [`Workload::ReverseScan`]. The commits of the
+/// writers are applied to `shadow`, if any. Every sample of the run is also
+/// passed to `monitor`, if any.
+pub fn bench_readers(
+    db: &Arc<dyn Backend>,
+    config: &Config,
+    shadow: Option<&Arc<Shadow>>,
+    mut monitor: Option<&mut dyn Monitor>,
+) -> Result<Outcome> {
+    let &Config {
+        threads: n_threads,
+        writer: bkgd_writer,
+        ..
+    } = config;
+    let budget = config.budget();
+    let reverse = config.workload == Workload::ReverseScan;
+    ensure!(
+        !reverse || db.reverse_scans(),
+        "the backend doesn't support reverse scans"
+    );
+    ensure!(
+        config.target_ops.is_none() && config.rate_per_thread.is_none(),
+        "a target rate only applies to op-based workloads"
+    );
+    ensure!(
+        config.batch == 1,
+        "batches of operations only apply to op-based workloads"
+    );
+
+    let cpus = affinity::worker_cpus(config)?;
+    let cpu_of = |t: usize| cpus.as_ref().map(|cpus| cpus[t % cpus.len()]);
+
+    // Optionally start background writer.
+    let (sender, receiver): (Sender<()>, Receiver<()>) = mpsc::channel();
+    let background_thread = if bkgd_writer && config.writers == 0 {
+        Some(thread::spawn({
+            let db = db.clone();
+            move || {
+                let mut t = db.begin_rw().unwrap();
+                // Get one key.
+                let mut k = Vec::new();
+                t.scan(Bound::Unbounded, &mut |key, _| {
+                    k.extend_from_slice(key);
+                    false
+                })
+                .unwrap();
+                let dummy_val = [1u8; 100];
+                // Mindlessly do some busy work until termination.
+                while receiver.try_recv().is_err() {
+                    t.put(&k, &dummy_val).unwrap();
+                }
+                t.abort().unwrap();
+            }
+        }))
+    } else {
+        None
+    };
+
+    if let Some(warmup) = config.warmup {
+        let threads: Vec<_> = (0..n_threads)
+            .map(|t| {
+                let db = db.clone();
+                let cpu = cpu_of(t);
+                thread::spawn(move || {
+                    if let Some(cpu) = cpu {
+                        affinity::pin(cpu).unwrap();
+                    }
+                    let start_time = Instant::now();
+                    let mut iters = 0;
+                    while !warmup.is_spent(iters, start_time) {
+                        let t = db.begin_ro().unwrap();
+                        full_scan(&*t, reverse, &mut |_k, _v| true).unwrap();
+                        iters += 1;
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    // Run benchmark load.
+    let counters: Arc<Vec<Counter>> =
+        Arc::new((0..n_threads).map(|_| Counter::default()).collect());
+    let io = Io::now();
+    let perf = config.perf_counters.then(Counters::start).transpose()?;
+    let profiler = config.profile.map(Profiler::start).transpose()?;
+    let writers = (config.writers > 0).then(|| Writers::start(db, config.writers, shadow));
+    let start_time = Instant::now();
+    let sample = || -> Result<Sample> {
+        let thread_ops: Vec<_> = counters.iter().map(Counter::get).collect();
+        let sample = Sample {
+            elapsed_ms: start_time.elapsed().as_millis() as u64,
+            ops: thread_ops.iter().sum(),
+            thread_ops,
+            n_keys: 0,
+            disk_bytes: 0,
+            live_bytes: 0,
+            rss_bytes: process::rss_bytes().unwrap_or(0),
+            mapped_bytes: process::mapped_bytes().unwrap_or(0),
+        };
+        if let Some(monitor) = &mut monitor {
+            // Full scans take too long for recent latencies to be of any use.
+            monitor.update(&sample, None)?;
+        }
+        Ok(sample)
+    };
+    let ((elapsed, cpu, thread_results), timeline) =
+        timeline::sampled(config.sample_interval(), sample, || {
+            // Like in op-based workloads, the wall-clock and CPU time of the
+            // workers alone, over the same interval.
+            let measured_time = Instant::now();
+            let cpu = CpuTime::now();
+            let mut threads = Vec::new();
+            for t in 0..n_threads {
+                let db = db.clone();
+                let counters = counters.clone();
+                let cpu = cpu_of(t);
+                threads.push(thread::spawn(move || {
+                    if let Some(cpu) = cpu {
+                        affinity::pin(cpu).unwrap();
+                    }
+                    let counter = &counters[t];
+                    let mut latency = Histogram::default();
+                    let thread_start_time = Instant::now();
+                    let mut iters = 0;
+                    while !budget.is_spent(iters, thread_start_time) {
+                        iters += 1;
+                        let start_time = Instant::now();
+                        let t = db.begin_ro().unwrap();
+                        full_scan(&*t, reverse, &mut |_k, _v| {
+                            counter.incr();
+                            true
+                        })
+                        .unwrap();
+                        latency.record(start_time.elapsed());
+                    }
+                    (latency, thread_start_time.elapsed())
+                }));
+            }
+            let thread_results: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+            (
+                measured_time.elapsed(),
+                CpuTime::now().since(&cpu),
+                thread_results,
+            )
+        });
+    let writers = writers.map(Writers::stop).transpose()?;
+    let io = Option::zip(Io::now(), io).map(|(now, before)| now.since(&before));
+    let perf = perf.map(Counters::stop).transpose()?;
+    let flamegraph = profiler.map(Profiler::finish).transpose()?;
+    // Only the writers commit what they put, unlike the background writer.
+    let written_bytes = writers.as_ref().map_or(0, |w| w.written_bytes);
+    let retries = writers
+        .as_ref()
+        .map_or_else(Retries::default, |w| w.retries);
+    if let Some(background_thread) = background_thread {
+        sender.send(()).unwrap();
+        background_thread.join().unwrap();
+    }
+    let mut latency = Histogram::default();
+    let mut thread_outcomes = Vec::new();
+    for ((thread_latency, thread_elapsed), counter) in
+        thread_results.into_iter().zip(counters.iter())
+    {
+        latency.merge(&thread_latency);
+        thread_outcomes.push(ThreadOutcome {
+            ops: counter.get(),
+            elapsed: thread_elapsed,
+            latency: thread_latency,
+        });
+    }
+    let mut latencies = Latencies::new();
+    if latency.count() > 0 {
+        latencies.insert("full-scan".to_string(), latency);
+    }
+    Ok(Outcome {
+        ops: counters.iter().map(Counter::get).sum(),
+        elapsed,
+        latencies,
+        timeline: timeline?,
+        threads: thread_outcomes,
+        writers,
+        // The readers never commit.
+        commit_latency: Histogram::default(),
+        written_bytes,
+        retries,
+        io,
+        cpu,
+        perf,
+        flamegraph,
+        trace: None,
+    })
+}
@@ -0,0 +1,50 @@
+//! Serves a store over the TCP protocol of `db_cmp::net`, so that `db-cmp`
+//! can benchmark it as a networked service with `--backend remote`.
+
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Parser, builder::PossibleValuesParser};
+
+use db_cmp::backend;
+use db_cmp::cli::parse_key_val;
+use db_cmp::net;
+
+/// Serves the store at `--db-path` to the `remote` backend of `db-cmp`.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Backend of the store.
+    #[arg(
+        long,
+        default_value = "byodb",
+        value_parser = PossibleValuesParser::new(backend::names())
+    )]
+    backend: String,
+    /// Backend-specific option, e.g. `rocksdb.max_background_jobs=4`. Can be repeated.
+    #[arg(long = "backend-opt", value_name = "BACKEND.OPTION=VALUE", value_parser = parse_key_val)]
+    backend_opts: Vec<(String, String)>,
+    /// Path of the store, created if it doesn't exist.
+    #[arg(long)]
+    db_path: PathBuf,
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    addr: SocketAddr,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let opts = args.backend_opts.into_iter().collect();
+    let db = backend::open(&args.backend, &args.db_path, &opts)
+        .with_context(|| format!("failed to open DB {}", args.db_path.display()))?;
+    let listener = TcpListener::bind(args.addr)
+        .with_context(|| format!("failed to listen on {}", args.addr))?;
+    eprintln!(
+        "Serving {} {} on {}",
+        args.backend,
+        args.db_path.display(),
+        args.addr
+    );
+    net::serve(&*db, listener)
+}
@@ -0,0 +1,122 @@
+//! Bulk loads: most backends have a faster path to fill an empty store with
+//! a lot of items than inserting them one transaction at a time, from
+//! writing them in order to building their files directly.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, ensure};
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+
+use crate::backend::Backend;
+use crate::cli::BulkLoadArgs;
+use crate::config::Config;
+use crate::{db, seed};
+
+/// How items are loaded into an empty DB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// In random order, `batch` items per transaction.
+    Random { batch: usize, seed: u64 },
+    /// Through [`Backend::bulk_load`].
+    Bulk,
+}
+
+impl Method {
+    /// Loads `items`, sorted by key and without duplicates, into `db`, and
+    /// returns how long it took, including the commits.
+    pub fn load(self, db: &dyn Backend, items: &[(String, String)]) -> Result<Duration> {
+        match self {
+            Method::Random { batch, seed } => {
+                let mut shuffled: Vec<_> = items.iter().collect();
+                shuffled.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+                let start = Instant::now();
+                for chunk in shuffled.chunks(batch) {
+                    let mut t = db.begin_rw()?;
+                    for (k, v) in chunk {
+                        t.put(k.as_bytes(), v.as_bytes())?;
+                    }
+                    t.commit()?;
+                }
+                Ok(start.elapsed())
+            }
+            Method::Bulk => {
+                let start = Instant::now();
+                db.bulk_load(items)?;
+                Ok(start.elapsed())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Random { .. } => f.pad("random"),
+            Method::Bulk => f.pad("bulk"),
+        }
+    }
+}
+
+/// Runs `db-cmp bulk-load`: loads each backend of `args` with random
+/// inserts and through its bulk-load path, and compares them.
+pub fn command(args: BulkLoadArgs) -> Result<()> {
+    ensure!(
+        args.batch > 0,
+        "bulk-load needs a batch of at least one item"
+    );
+    let config = Config {
+        items: args.items,
+        ..args.backend.into_config()
+    };
+    // Sorted and without duplicates, the last value of a key winning like
+    // it does when seeding.
+    let items: Vec<_> = seed::items(&config)?
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .collect();
+    let methods = [
+        Method::Random {
+            batch: args.batch,
+            seed: config.seed,
+        },
+        Method::Bulk,
+    ];
+    let mib = |bytes: u64| bytes as f64 / (1 << 20) as f64;
+    println!(
+        "{:<10} {:<7} {:>10} {:>12} {:>14}",
+        "Backend", "Method", "Time (s)", "Items/s", "DB size (MiB)"
+    );
+    for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        let mut loads = Vec::new();
+        for method in methods {
+            let (db, temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+            let elapsed = method
+                .load(&*db, &items)
+                .with_context(|| format!("failed to load {backend} with {method} inserts"))?;
+            // Closed first, so that what it writes on close is included.
+            drop(db);
+            let size = db::disk_usage(&db::temp_path(&temp_dir))?;
+            println!(
+                "{backend:<10} {method:<7} {:>10.3} {:>12.0} {:>14.2}",
+                elapsed.as_secs_f64(),
+                items.len() as f64 / elapsed.as_secs_f64(),
+                mib(size),
+            );
+            loads.push((elapsed, size));
+        }
+        let [(random_time, random_size), (bulk_time, bulk_size)] = loads[..] else {
+            unreachable!();
+        };
+        println!(
+            "{backend:<10} the bulk load was {:.2}x as fast as random inserts, and changed the size of the DB by {:+.2}MiB",
+            random_time.as_secs_f64() / bulk_time.as_secs_f64(),
+            mib(bulk_size) - mib(random_size),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,946 @@
+//! The command line of the `db-cmp` binary, whose subcommands the modules
+//! they drive run, e.g. [`crate::runner::command`] for `run`.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use clap::{Args, Parser, Subcommand, builder::PossibleValuesParser};
+
+use crate::backend::{self, Durability};
+use crate::config::{Budget, Config, parse_duration, parse_rate};
+use crate::crash::Corruption;
+use crate::distribution::{Distribution, Hotspot, Length};
+use crate::gate::{Condition, Target};
+use crate::ops::{KeyType, Mix};
+use crate::profile::Profile;
+use crate::results::Output;
+use crate::workload::Workload;
+
+/// Benchmarks concurrent readers of embedded key-value stores.
+///
+/// Options given on the command line override those in `--config`.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Hides the progress bars of the seed and measured phases.
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Creates a DB file at `--db-path` populated with seeded items.
+    Seed(DataArgs),
+    /// Runs the benchmark against the DB at `--db-path`, or against a
+    /// freshly seeded temporary DB if unset.
+    Run(Box<RunArgs>),
+    /// Checks that the DB at `--db-path` contains exactly the seeded items.
+    Verify(DataArgs),
+    /// Reports the structure of the DB at `--db-path`, like the height of
+    /// its tree and how its pages are used.
+    Stats(DataArgs),
+    /// Kills a process committing transactions to each backend with SIGKILL
+    /// at random points, and checks what the DB recovers.
+    Crash(CrashArgs),
+    /// The process killed by `crash`, which reports every transaction it
+    /// commits or aborts on stdout.
+    #[command(hide = true)]
+    CrashChild(CrashChildArgs),
+    /// Applies the same seeded stream of transactions to several backends,
+    /// and checks that they return the same and hold the same records as the
+    /// first one.
+    Difftest(DifftestArgs),
+    /// Runs concurrent writers and readers on each backend, and checks the
+    /// history of what they read and wrote for anomalies of snapshot
+    /// isolation.
+    Isolation(IsolationArgs),
+    /// Runs writers on each backend with and without a read transaction
+    /// open the whole time, and compares how much the DB and the memory grow
+    /// and how fast the writers commit.
+    LongReader(LongReaderArgs),
+    /// Measures the latency of seeking to a random key of each backend apart
+    /// from that of moving on to each next record, at several DB sizes.
+    Seek(SeekArgs),
+    /// Measures how long each backend takes to open an existing DB, with its
+    /// files in the page cache and not, at several DB sizes.
+    Open(OpenArgs),
+    /// Loads each backend with sorted items through its bulk-load path and
+    /// with random inserts, and compares how long it took and how big the DB
+    /// ended up.
+    BulkLoad(BulkLoadArgs),
+    /// Runs an op-based workload on each backend from several processes at
+    /// once, sharing a DB if the backend supports it and each with its own
+    /// otherwise, and compares how throughput scales with the processes.
+    Processes(ProcessesArgs),
+    /// A process run by `processes`, which reports what it measured on
+    /// stdout.
+    #[command(hide = true)]
+    ProcessChild(ProcessChildArgs),
+    /// Runs an op-based workload on each backend from the tasks of a tokio
+    /// runtime, calling it right from the tasks and through
+    /// `spawn_blocking`, and compares the latencies and how much the
+    /// runtime lags behind.
+    #[cfg(feature = "async")]
+    Async(AsyncArgs),
+    /// Renders results saved by `run --out-file`.
+    Report(ReportArgs),
+    /// Shows the trend of the results of a backend and workload over the
+    /// runs recorded by `run --history`.
+    #[cfg(feature = "history")]
+    History(HistoryArgs),
+}
+
+/// Options describing the seeded data.
+#[derive(Args, Debug)]
+pub struct DataArgs {
+    /// TOML file describing the benchmark scenario.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Comma-separated backends to benchmark, one after the other [default: byodb].
+    #[arg(
+        long = "backend",
+        value_delimiter = ',',
+        value_parser = PossibleValuesParser::new(backend::names())
+    )]
+    pub backends: Option<Vec<String>>,
+    /// Backend-specific option, e.g. `rocksdb.max_background_jobs=4`. Can be repeated.
+    #[arg(long = "backend-opt", value_name = "BACKEND.OPTION=VALUE", value_parser = parse_key_val)]
+    pub backend_opts: Vec<(String, String)>,
+    /// What the benchmark threads do, which also determines the seeded
+    /// items [default: scan].
+    #[arg(long)]
+    pub workload: Option<Workload>,
+    /// Number of items to seed the DB with [default: 1000].
+    #[arg(long)]
+    pub items: Option<usize>,
+    /// Size of the seeded keys of the scan workload: `<n>`, `<min>..=<max>`,
+    /// `lognormal(<median>,<sigma>)` or `pareto(<min>,<alpha>)`, clamped to
+    /// the sizes byodb supports [default: 1..=1000].
+    #[arg(long, value_name = "LENGTH")]
+    pub key_size: Option<Length>,
+    /// What the keys look like: `random`, `u64-seq` for record numbers in
+    /// order, as big-endian integers, or `uuid-v4` (or `uuid`) and `uuid-v7`
+    /// for random and time-ordered UUIDs [default: random].
+    #[arg(long)]
+    pub key_type: Option<KeyType>,
+    /// Size of the values, like `--key-size` [default: 1..=1000 for the scan
+    /// workload, 1000 otherwise].
+    #[arg(long, value_name = "LENGTH")]
+    pub value_size: Option<Length>,
+    /// Number of threads inserting the seeded items, which are dealt to them
+    /// by the hash of their key [default: 1].
+    #[arg(long, value_name = "N")]
+    pub seed_threads: Option<usize>,
+    /// Number of seeded items inserted by each transaction of a seeding
+    /// thread [default: all of its items].
+    #[arg(long, value_name = "N")]
+    pub seed_batch: Option<usize>,
+    /// Seeds the DB with the `key<TAB>value` lines of this dump instead of
+    /// generated items, for the scan workload.
+    #[arg(long = "from", value_name = "DUMP")]
+    pub seed_from: Option<PathBuf>,
+    /// Seed of the RNG used to generate the seeded items [default: 1].
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Path of the DB file (or directory, depending on the backend). `run`
+    /// runs on it as it is, without a load phase, unless `--load-only`.
+    #[arg(long)]
+    pub db_path: Option<PathBuf>,
+}
+
+impl DataArgs {
+    /// Resolves the effective [`Config`]: the `--config` file (or the
+    /// defaults), overridden by any flags given on the command line.
+    pub fn into_config(self) -> Result<Config> {
+        let mut config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        if let Some(backends) = self.backends {
+            config.backends = backends;
+        }
+        config.backend_opts.extend(self.backend_opts);
+        if let Some(workload) = self.workload {
+            config.workload = workload;
+        }
+        if let Some(items) = self.items {
+            config.items = items;
+        }
+        if let Some(key_type) = self.key_type {
+            config.key_type = key_type;
+        }
+        if self.key_size.is_some() {
+            config.key_size = self.key_size;
+        }
+        if self.value_size.is_some() {
+            config.value_size = self.value_size;
+        }
+        if let Some(seed_threads) = self.seed_threads {
+            config.seed_threads = seed_threads;
+        }
+        if self.seed_batch.is_some() {
+            config.seed_batch = self.seed_batch;
+        }
+        if self.seed_from.is_some() {
+            config.seed_from = self.seed_from;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+        if self.db_path.is_some() {
+            config.db_path = self.db_path;
+        }
+        Ok(config)
+    }
+}
+
+/// Options of the `run` subcommand.
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub data: DataArgs,
+    /// Whether to also benchmark the in-memory `btreemap` backend first, as an
+    /// upper bound for the others [default: true].
+    #[arg(long)]
+    pub baseline: Option<bool>,
+    /// Whether to first run the no-op `null` backend, to measure the overhead
+    /// of the harness itself [default: true].
+    #[arg(long)]
+    pub calibrate: Option<bool>,
+    /// Number of times to benchmark each backend, each on a freshly seeded
+    /// DB, to report the mean, standard deviation and 95% confidence
+    /// interval of every metric [default: 1].
+    #[arg(long, value_name = "N")]
+    pub repeat: Option<usize>,
+    /// Number of parallel benchmark threads [default: 1].
+    #[arg(long)]
+    pub threads: Option<usize>,
+    /// Runs every backend with 1, 2, 4 and so on threads in turn, up to this
+    /// many, and reports the speedup and parallel efficiency of each
+    /// relative to a single thread.
+    #[arg(long, value_name = "MAX", conflicts_with = "threads")]
+    pub threads_sweep: Option<usize>,
+    /// Whether to pin every benchmark thread to a CPU of its own, in turn,
+    /// to reduce the variance between runs. Linux only [default: false].
+    #[arg(long)]
+    pub pin_threads: Option<bool>,
+    /// Runs on this NUMA node only: on its CPUs, allocating from its memory,
+    /// e.g. to compare sockets. Linux only.
+    #[arg(long, value_name = "N")]
+    pub numa_node: Option<usize>,
+    /// Number of iterations each thread performs: full in-order scans for the
+    /// scan workload, or single operations otherwise [default: 1000].
+    #[arg(long)]
+    pub iters: Option<usize>,
+    /// Runs every thread for this long, e.g. `60s`, of at least 1ms, instead
+    /// of `--iters` iterations.
+    #[arg(long, value_parser = parse_duration, conflicts_with = "iters")]
+    pub duration: Option<Duration>,
+    /// Weighted operations of the op-based workload, e.g.
+    /// `read=70,update=20,insert=5,delete=3,scan=2` (ops: read, update,
+    /// insert, delete, scan, rmw). Selects the custom workload instead of the
+    /// scan one.
+    #[arg(long)]
+    pub mix: Option<Mix>,
+    /// Draws the operations of the op-based workload from the generator
+    /// registered under this name instead of its own, e.g. `ycsb`,
+    /// `prefix-scan`, `multi-tenant`, `churn`, or one registered by a
+    /// program embedding the harness. The workload still seeds the records.
+    #[arg(long, value_name = "NAME")]
+    pub generator: Option<String>,
+    /// Number of items each scan operation reads: `<n>`, or `<min>..=<max>`
+    /// for a uniformly random number [default: 1..=100].
+    #[arg(long, value_name = "LENGTH")]
+    pub scan_len: Option<Length>,
+    /// Number of records under each key prefix of the prefix-scan workload
+    /// [default: 10].
+    #[arg(long, value_name = "N")]
+    pub prefix_size: Option<usize>,
+    /// Number of tenants of the multi-tenant workload [default: 100].
+    #[arg(long, value_name = "N")]
+    pub tenants: Option<usize>,
+    /// Fraction of the operations of the rmw workload that are
+    /// read-modify-writes rather than reads [default: 0.5].
+    #[arg(long, value_name = "FRACTION")]
+    pub rmw_fraction: Option<f64>,
+    /// Fraction of the reads of the op-based workload that look up keys no
+    /// record has, whose latencies are reported as `read-miss` [default: 0].
+    #[arg(long, value_name = "FRACTION")]
+    pub miss_fraction: Option<f64>,
+    /// Overrides the key distribution of the op-based workload.
+    #[arg(long)]
+    pub distribution: Option<Distribution>,
+    /// Skew of the Zipfian key distributions, between 0 and 1 exclusive
+    /// [default: 0.99].
+    #[arg(long)]
+    pub theta: Option<f64>,
+    /// Percentage of the operations that access a percentage of the keys,
+    /// with the hotspot distribution [default: 90/10].
+    #[arg(long, value_name = "OPS/KEYS")]
+    pub hotspot: Option<Hotspot>,
+    /// Whether to run a background writer that spins during the scan workload
+    /// [default: true].
+    #[arg(long)]
+    pub writer: Option<bool>,
+    /// Number of writer threads that each commit single-key transactions as
+    /// fast as they can while the workload runs, to measure write contention.
+    /// Replaces the background writer [default: 0].
+    #[arg(long, value_name = "N")]
+    pub writers: Option<usize>,
+    /// Whether to keep an in-memory model of what the workload and the
+    /// writers commit, and fail the run if the DB doesn't hold exactly the
+    /// same records at the end. Serializes the commits, so throughputs
+    /// aren't comparable [default: false].
+    #[arg(long)]
+    pub shadow: Option<bool>,
+    /// Saves every operation each thread of the op-based workload performs,
+    /// with its keys and values, to this file, to `--replay` it later.
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+    /// Performs exactly the operations of a trace saved by `--record`,
+    /// instead of drawing them, with the workload, items and threads it was
+    /// recorded with.
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+    /// Replays a CSV log of `op,key,value_size,timestamp` lines of a real
+    /// workload, on a DB seeded with the keys it accesses before inserting
+    /// them, with its operations dealt to the threads in timestamp order.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["record", "replay"])]
+    pub access_log: Option<PathBuf>,
+    /// Whether to close the DB, evict its files from the OS page cache and
+    /// reopen it before the measured phase, to measure cold reads
+    /// [default: false].
+    #[arg(long)]
+    pub cold: Option<bool>,
+    /// Only runs the load phase, and reports what it took, into a new DB at
+    /// `--db-path` if set, which later runs with `--db-path` run on without
+    /// loading it again.
+    #[arg(long)]
+    pub load_only: bool,
+    /// Runs the workload for this many iterations of every thread, or this
+    /// long (e.g. `10s`), before the measured phase.
+    #[arg(long, value_name = "ITERS|DURATION")]
+    pub warmup: Option<Budget>,
+    /// Runs an open loop that starts this many operations per second across
+    /// all threads, however long they take, and measures their latencies
+    /// from when they should have started.
+    #[arg(long, value_name = "OPS_PER_SEC")]
+    pub target_ops: Option<f64>,
+    /// Throttles every thread to this many operations per second, still
+    /// each one after the previous one, to measure latencies at a given load
+    /// below saturation.
+    #[arg(long, value_name = "OPS_PER_SEC", conflicts_with = "target_ops")]
+    pub rate_per_thread: Option<f64>,
+    /// Number of operations of the op-based workload in every transaction,
+    /// which all run in read-write transactions if more than one
+    /// [default: 1].
+    #[arg(long, value_name = "OPS")]
+    pub batch: Option<usize>,
+    /// Whether to run every backend with 1, 10, 100, 1000 and 10000
+    /// operations per transaction in turn, and report the throughput and
+    /// commit latency of each [default: false].
+    #[arg(long, conflicts_with = "batch")]
+    pub batch_sweep: Option<bool>,
+    /// Whether to run every backend on DBs of 10k, 100k, 1M and 10M seeded
+    /// items in turn, and report how the latencies of its reads and writes
+    /// scale with the size of the DB [default: false].
+    #[arg(long, conflicts_with = "items")]
+    pub items_sweep: Option<bool>,
+    /// Whether to run every backend with values of 64, 128, 256, 512 and
+    /// 1000 bytes in turn, and report the throughput and space amplification
+    /// at each size [default: false].
+    #[arg(long, conflicts_with = "value_size")]
+    pub value_size_sweep: Option<bool>,
+    /// When commits are synced to disk: never, periodically in the
+    /// background, or on every commit. Mapped to the options of each backend,
+    /// which keep their own default if unset.
+    #[arg(long, value_enum)]
+    pub durability: Option<Durability>,
+    /// Whether to run every backend with each durability level it supports
+    /// in turn, and report the throughput and commit latency of each
+    /// [default: false].
+    #[arg(long, conflicts_with = "durability")]
+    pub durability_sweep: Option<bool>,
+    /// Creates the temporary directories of the DBs in this directory,
+    /// instead of the system's (often a tmpfs, in memory), e.g. to run on a
+    /// given device.
+    #[arg(long, value_name = "DIR", conflicts_with = "db_path")]
+    pub db_dir: Option<PathBuf>,
+    /// Caches a copy of every seeded DB in this directory, and copies the
+    /// cached one instead of seeding when the backend and the seeded items
+    /// are the same.
+    #[arg(long, value_name = "DIR", conflicts_with = "db_path")]
+    pub seed_cache: Option<PathBuf>,
+    /// How often the progress of the run is sampled, in milliseconds
+    /// [default: 100].
+    #[arg(long, value_name = "MS")]
+    pub sample_interval: Option<u64>,
+    /// Whether to count the cycles, instructions, last-level cache misses
+    /// and branch misses of the measured phase, with the hardware
+    /// performance counters of Linux [default: false].
+    #[arg(long)]
+    pub perf_counters: Option<bool>,
+    /// Profiles the measured phase of every run, and saves the profile in
+    /// `--profile-dir`. Needs the `flamegraph` feature.
+    #[arg(long, value_enum)]
+    pub profile: Option<Profile>,
+    #[command(flatten)]
+    pub outputs: OutputArgs,
+}
+
+impl RunArgs {
+    /// Resolves the effective [`Config`], like [`DataArgs::into_config`].
+    pub fn into_config(self) -> Result<Config> {
+        // An explicit number of items replaces a sweep of them, like
+        // explicit batches and durabilities do.
+        let items_given = self.data.items.is_some();
+        let value_size_given = self.data.value_size.is_some();
+        let mut config = self.data.into_config()?;
+        if let Some(baseline) = self.baseline {
+            config.baseline = baseline;
+        }
+        if let Some(calibrate) = self.calibrate {
+            config.calibrate = calibrate;
+        }
+        if let Some(repeat) = self.repeat {
+            config.repeat = repeat;
+        }
+        if let Some(threads) = self.threads {
+            config.threads = threads;
+            config.threads_sweep = None;
+        }
+        if self.threads_sweep.is_some() {
+            config.threads_sweep = self.threads_sweep;
+        }
+        if let Some(pin_threads) = self.pin_threads {
+            config.pin_threads = pin_threads;
+        }
+        if self.numa_node.is_some() {
+            config.numa_node = self.numa_node;
+        }
+        if let Some(iters) = self.iters {
+            config.iters = iters;
+        }
+        if let Some(duration) = self.duration {
+            config.duration_ms = Some(duration.as_millis() as u64);
+        }
+        if self.iters.is_some() {
+            config.duration_ms = None;
+        }
+        if let Some(mix) = self.mix {
+            config.mix = Some(mix);
+            if config.workload == Workload::Scan {
+                config.workload = Workload::Custom;
+            }
+        }
+        if self.generator.is_some() {
+            config.generator = self.generator;
+        }
+        if self.scan_len.is_some() {
+            config.scan_len = self.scan_len;
+        }
+        if let Some(prefix_size) = self.prefix_size {
+            config.prefix_size = prefix_size;
+        }
+        if let Some(tenants) = self.tenants {
+            config.tenants = tenants;
+        }
+        if let Some(rmw_fraction) = self.rmw_fraction {
+            config.rmw_fraction = rmw_fraction;
+        }
+        if let Some(miss_fraction) = self.miss_fraction {
+            config.miss_fraction = miss_fraction;
+        }
+        if self.distribution.is_some() {
+            config.distribution = self.distribution;
+        }
+        if let Some(theta) = self.theta {
+            config.theta = theta;
+        }
+        if let Some(hotspot) = self.hotspot {
+            config.hotspot = hotspot;
+        }
+        if self.db_dir.is_some() {
+            config.db_dir = self.db_dir;
+        }
+        if self.seed_cache.is_some() {
+            config.seed_cache = self.seed_cache;
+        }
+        if let Some(cold) = self.cold {
+            config.cold = cold;
+        }
+        if self.load_only {
+            config.load_only = true;
+        }
+        if self.warmup.is_some() {
+            config.warmup = self.warmup;
+        }
+        if self.target_ops.is_some() {
+            config.target_ops = self.target_ops;
+        }
+        if self.rate_per_thread.is_some() {
+            config.rate_per_thread = self.rate_per_thread;
+        }
+        if let Some(sample_interval) = self.sample_interval {
+            config.sample_interval_ms = sample_interval;
+        }
+        if let Some(perf_counters) = self.perf_counters {
+            config.perf_counters = perf_counters;
+        }
+        if self.profile.is_some() {
+            config.profile = self.profile;
+        }
+        if let Some(writer) = self.writer {
+            config.writer = writer;
+        }
+        if let Some(writers) = self.writers {
+            config.writers = writers;
+        }
+        if let Some(shadow) = self.shadow {
+            config.shadow = shadow;
+        }
+        if self.record.is_some() {
+            config.record = self.record;
+        }
+        if self.replay.is_some() {
+            config.replay = self.replay;
+        }
+        if self.access_log.is_some() {
+            config.access_log = self.access_log;
+        }
+        if items_given {
+            config.items_sweep = false;
+        }
+        if let Some(items_sweep) = self.items_sweep {
+            config.items_sweep = items_sweep;
+        }
+        if value_size_given {
+            config.value_size_sweep = false;
+        }
+        if let Some(value_size_sweep) = self.value_size_sweep {
+            config.value_size_sweep = value_size_sweep;
+        }
+        if let Some(batch) = self.batch {
+            config.batch = batch;
+            config.batch_sweep = false;
+        }
+        if let Some(batch_sweep) = self.batch_sweep {
+            config.batch_sweep = batch_sweep;
+        }
+        if self.durability.is_some() {
+            config.durability = self.durability;
+            config.durability_sweep = false;
+        }
+        if let Some(durability_sweep) = self.durability_sweep {
+            config.durability_sweep = durability_sweep;
+        }
+        Ok(config)
+    }
+}
+
+/// What becomes of the results of `run`, besides the summaries printed to
+/// stdout.
+#[derive(Args, Debug, Default)]
+pub struct OutputArgs {
+    /// How to output the results: human-readable summaries, or a document
+    /// saved to `--out-file`, or printed instead of the summaries if unset
+    /// [default: text, or with `--out-file`, csv or html if it ends in `.csv`
+    /// or `.html`, and json otherwise].
+    #[arg(long, value_enum)]
+    pub output: Option<Output>,
+    /// Saves the results to this file, as `--output` says. JSON results can
+    /// be rendered by `report`.
+    #[arg(long)]
+    pub out_file: Option<PathBuf>,
+    /// Saves the throughput of every thread over time to this file, as CSV
+    /// if it ends in `.csv`, or JSON otherwise.
+    #[arg(long)]
+    pub timeline_file: Option<PathBuf>,
+    /// Saves gnuplot data files and scripts plotting the latency CDFs and
+    /// throughput by number of threads to this directory.
+    #[arg(long)]
+    pub plot_dir: Option<PathBuf>,
+    /// Saves the profiles of `--profile` to this directory, as
+    /// `<backend>-<workload>.svg` [default: the current directory].
+    #[arg(long)]
+    pub profile_dir: Option<PathBuf>,
+    /// Compares the latencies of every type of operation to those of the
+    /// same scenario in this results file, saved by `run --out-file`, and
+    /// tells which differences across the repetitions are statistically
+    /// significant.
+    #[arg(long, value_name = "FILE")]
+    pub baseline_file: Option<PathBuf>,
+    /// Saves a manifest of the effective config, including the seed, to
+    /// this file, or prints it first if `-`. Passed back as `--config`, it
+    /// runs the same scenario again.
+    #[arg(long, value_name = "FILE")]
+    pub manifest: Option<PathBuf>,
+    /// Writes a line to this file for every operation and transaction of
+    /// the op-based workloads once done, with how long it took and its key,
+    /// so that slow outliers can be traced back to them. Slows the run down.
+    #[arg(long, value_name = "FILE")]
+    pub trace_output: Option<PathBuf>,
+    /// Fails the run if this condition on how the results compare to those
+    /// of `--baseline-file` holds for any backend, e.g.
+    /// `throughput < baseline*0.95 || p99 > baseline*1.10`. The results are
+    /// saved and printed first.
+    #[arg(long, value_name = "CONDITION", requires = "baseline_file")]
+    pub fail_if: Option<Condition>,
+    /// Fails the run if the p99 latency of all operations of any backend
+    /// isn't under this budget, e.g. `500us`. The results are saved and
+    /// printed first.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub assert_p99_under: Option<Duration>,
+    /// Fails the run if the throughput of any backend isn't over this many
+    /// operations per second, e.g. `100k`. The results are saved and
+    /// printed first.
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    pub assert_throughput_over: Option<f64>,
+    /// Serves Prometheus metrics of the progress of the run, such as the
+    /// operations of every thread, their latencies and the memory usage, at
+    /// `http://<ADDR>/metrics` while it lasts.
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+    /// Appends the results to this SQLite history database, for `history`
+    /// to show trends.
+    #[cfg(feature = "history")]
+    #[arg(long)]
+    pub history: Option<PathBuf>,
+    /// Shows a live dashboard of the throughput of every thread, the p99
+    /// latency, the memory usage and the remaining time while measuring.
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub tui: bool,
+}
+
+impl OutputArgs {
+    /// Resolves the effective output format.
+    pub fn output(&self) -> Output {
+        let extension = self.out_file.as_ref().and_then(|path| path.extension());
+        self.output.unwrap_or(match extension {
+            _ if self.out_file.is_none() => Output::Text,
+            Some(ext) if ext == "csv" => Output::Csv,
+            Some(ext) if ext == "html" => Output::Html,
+            _ => Output::Json,
+        })
+    }
+
+    /// The absolute targets of the `--assert-*` options.
+    pub fn targets(&self) -> Vec<Target> {
+        let p99 = self.assert_p99_under.map(Target::P99Under);
+        let throughput = self.assert_throughput_over.map(Target::ThroughputOver);
+        p99.into_iter().chain(throughput).collect()
+    }
+}
+
+/// Options of the `report` subcommand.
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Results files saved by `run --out-file`.
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// How to output the results of all files: a summary per file, or all
+    /// together, e.g. in a single Markdown table [default: text].
+    #[arg(long, value_enum)]
+    pub output: Option<Output>,
+    /// Saves gnuplot data files and scripts plotting the results of all
+    /// files to this directory, like `run --plot-dir`. Comparing results of
+    /// different numbers of threads plots the scaling curves.
+    #[arg(long)]
+    pub plot_dir: Option<PathBuf>,
+}
+
+/// Options of the subcommands that run on backends of their own, rather
+/// than on the [`Config`] of `--config`.
+#[derive(Args, Debug)]
+pub struct BackendArgs {
+    /// Comma-separated backends, one after the other.
+    #[arg(
+        long = "backend",
+        value_delimiter = ',',
+        default_value = "byodb",
+        value_parser = PossibleValuesParser::new(backend::names())
+    )]
+    pub backends: Vec<String>,
+    /// Backend-specific option, e.g. `rocksdb.max_background_jobs=4`. Can be repeated.
+    #[arg(long = "backend-opt", value_name = "BACKEND.OPTION=VALUE", value_parser = parse_key_val)]
+    pub backend_opts: Vec<(String, String)>,
+    /// Creates the temporary directories of the DBs in this directory,
+    /// instead of the system's.
+    #[arg(long, value_name = "DIR")]
+    pub db_dir: Option<PathBuf>,
+    /// Seed of the RNG used to generate the seeded items and the operations,
+    /// and to pick whatever else is random.
+    #[arg(long, default_value_t = 1)]
+    pub seed: u64,
+}
+
+impl BackendArgs {
+    /// The [`Config`] of the backends: the defaults, with the backends, the
+    /// DB directory and the seed given.
+    pub fn into_config(self) -> Config {
+        Config {
+            backends: self.backends,
+            backend_opts: self.backend_opts.into_iter().collect(),
+            db_dir: self.db_dir,
+            seed: self.seed,
+            ..Config::default()
+        }
+    }
+}
+
+/// Options of the `crash` subcommand.
+#[derive(Args, Debug)]
+pub struct CrashArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// When commits are synced to disk, like for `run`.
+    #[arg(long, value_enum)]
+    pub durability: Option<Durability>,
+    /// Number of times to kill the process and recover the DB of every
+    /// backend.
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub crashes: usize,
+    /// Kills the process at a uniformly random time within this long of it
+    /// opening the DB, e.g. `500ms`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "1s")]
+    pub kill_within: Duration,
+    /// Damages the tail of the DB file written last after every crash, like
+    /// a write torn by a power loss, to check whether backends detect it.
+    #[arg(long, value_enum)]
+    pub corrupt: Option<Corruption>,
+    /// Probability that every put, delete and commit of the process fails
+    /// with EIO, to check that a transaction that failed is either committed
+    /// in full or not at all.
+    #[arg(long, value_name = "PROBABILITY", default_value_t = 0.0)]
+    pub fault_rate: f64,
+}
+
+/// Options of the hidden `crash-child` subcommand.
+#[derive(Args, Debug)]
+pub struct CrashChildArgs {
+    #[arg(long)]
+    pub backend: String,
+    #[arg(long = "backend-opt", value_parser = parse_key_val)]
+    pub backend_opts: Vec<(String, String)>,
+    #[arg(long)]
+    pub db_path: PathBuf,
+    /// Id of the first transaction to run.
+    #[arg(long)]
+    pub first_txn: u64,
+    #[arg(long)]
+    pub fault_rate: f64,
+    #[arg(long)]
+    pub fault_seed: u64,
+}
+
+/// Options of the `difftest` subcommand.
+#[derive(Args, Debug)]
+#[command(mut_arg("backends", |arg| arg
+    .default_value("btreemap,byodb")
+    .help("Comma-separated backends to compare, at least two. The first one is the reference the others are compared to")))]
+pub struct DifftestArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// Number of operations to apply.
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    pub ops: u64,
+    /// Compares the records of all backends at the end of the transaction
+    /// of every this many operations.
+    #[arg(long, value_name = "OPS", default_value_t = 1000)]
+    pub check_every: u64,
+    /// Number of distinct keys, few enough for records to be overwritten,
+    /// deleted and recreated often.
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pub keys: u64,
+    /// Maximum number of operations per transaction.
+    #[arg(long, value_name = "OPS", default_value_t = 8)]
+    pub batch: usize,
+    /// Size of the values put, like for `run`.
+    #[arg(long, value_name = "LENGTH", default_value = "1..=200")]
+    pub value_size: Length,
+}
+
+/// Options of the `isolation` subcommand.
+#[derive(Args, Debug)]
+pub struct IsolationArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// Number of writer threads, which each read-modify-write a few keys
+    /// per transaction.
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    pub writers: usize,
+    /// Number of reader threads, which read every key in a snapshot until
+    /// the writers are done.
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    pub readers: usize,
+    /// Number of keys, few enough for the writers to contend on them.
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    pub keys: usize,
+    /// Number of transactions of every writer.
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    pub txns: u64,
+}
+
+/// Options of the `long-reader` subcommand.
+#[derive(Args, Debug)]
+pub struct LongReaderArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// Number of writer threads, which each overwrite a thousand keys of
+    /// their own in turn, one per transaction.
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    pub writers: usize,
+    /// How long the writers run, with and without the reader.
+    #[arg(long, value_parser = parse_duration, default_value = "10s")]
+    pub duration: Duration,
+    /// Number of seeded items, which the reader's snapshot holds on to.
+    #[arg(long, default_value_t = 10000)]
+    pub items: usize,
+}
+
+/// Options of the `seek` subcommand.
+#[derive(Args, Debug)]
+pub struct SeekArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// Comma-separated numbers of seeded items, each measured on a DB of its
+    /// own.
+    #[arg(long, value_delimiter = ',', default_value = "1000,100000")]
+    pub items: Vec<usize>,
+    /// Number of scans, each from a random key.
+    #[arg(long, default_value_t = 10000)]
+    pub iters: usize,
+    /// Number of records each scan moves on to after the first one.
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    pub nexts: usize,
+}
+
+/// Options of the `open` subcommand.
+#[derive(Args, Debug)]
+pub struct OpenArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// Measures the existing DB at this path, of a single backend, instead
+    /// of seeding DBs of `--items` items.
+    #[arg(long, conflicts_with = "items")]
+    pub db_path: Option<PathBuf>,
+    /// Comma-separated numbers of seeded items, each measured on a DB of its
+    /// own.
+    #[arg(long, value_delimiter = ',', default_value = "1000,100000")]
+    pub items: Vec<usize>,
+    /// Number of cold opens, and of warm ones.
+    #[arg(long, default_value_t = 10)]
+    pub iters: usize,
+}
+
+/// Options of the `bulk-load` subcommand.
+#[derive(Args, Debug)]
+pub struct BulkLoadArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// Number of items to load, generated like the seeded items of the scan
+    /// workload.
+    #[arg(long, default_value_t = 100000)]
+    pub items: usize,
+    /// Number of items per transaction of the random inserts.
+    #[arg(long, default_value_t = 1000)]
+    pub batch: usize,
+}
+
+/// Options of the `processes` subcommand.
+#[derive(Args, Debug)]
+pub struct ProcessesArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// The op-based workload to run.
+    #[arg(long, default_value = "ycsb-a")]
+    pub workload: Workload,
+    /// Number of seeded items.
+    #[arg(long, default_value_t = 10000)]
+    pub items: usize,
+    /// Number of operations of each process.
+    #[arg(long, default_value_t = 10000)]
+    pub iters: usize,
+    /// Comma-separated numbers of processes, each measured in a run of its
+    /// own.
+    #[arg(long, value_delimiter = ',', default_value = "1,2,4")]
+    pub processes: Vec<usize>,
+}
+
+/// Options of the `process-child` subcommand.
+#[derive(Args, Debug)]
+pub struct ProcessChildArgs {
+    #[arg(long)]
+    pub backend: String,
+    #[arg(long = "backend-opt", value_parser = parse_key_val)]
+    pub backend_opts: Vec<(String, String)>,
+    #[arg(long)]
+    pub db_path: PathBuf,
+    #[arg(long)]
+    pub workload: Workload,
+    /// Number of seeded items of the DB.
+    #[arg(long)]
+    pub items: usize,
+    #[arg(long)]
+    pub iters: usize,
+    /// Seed of the RNG used to generate the operations.
+    #[arg(long)]
+    pub seed: u64,
+}
+
+/// Options of the `async` subcommand.
+#[cfg(feature = "async")]
+#[derive(Args, Debug)]
+pub struct AsyncArgs {
+    #[command(flatten)]
+    pub backend: BackendArgs,
+    /// The op-based workload to run.
+    #[arg(long, default_value = "ycsb-c")]
+    pub workload: Workload,
+    /// Number of seeded items.
+    #[arg(long, default_value_t = 10000)]
+    pub items: usize,
+    /// Number of operations of all tasks together.
+    #[arg(long, default_value_t = 100000)]
+    pub ops: usize,
+    /// Number of worker threads of the runtime.
+    #[arg(long, default_value_t = 4)]
+    pub workers: usize,
+    /// Number of tasks, each with an operation in flight at a time.
+    #[arg(long, default_value_t = 64)]
+    pub in_flight: usize,
+}
+
+/// Options of the `history` subcommand.
+#[cfg(feature = "history")]
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// History database written by `run --history`.
+    pub file: PathBuf,
+    /// Backend whose runs to show.
+    #[arg(long, default_value = "byodb")]
+    pub backend: String,
+    /// Workload whose runs to show.
+    #[arg(long, default_value = "scan")]
+    pub workload: Workload,
+    /// Shows the latencies of this type of operation only, e.g. `read`,
+    /// instead of all of them together.
+    #[arg(long)]
+    pub op: Option<String>,
+    /// Number of most recent runs to show.
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+/// Parses a `KEY=VALUE` pair.
+pub fn parse_key_val(s: &str) -> Result<(String, String)> {
+    let (key, val) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("{s} is not of the form KEY=VALUE"))?;
+    Ok((key.to_string(), val.to_string()))
+}
@@ -0,0 +1,741 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Error, Result, anyhow, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{self, BackendOpts, Durability};
+use crate::distribution::{Distribution, Hotspot, Length};
+use crate::generator;
+use crate::interrupt;
+use crate::ops::{KeyType, Mix, Spec};
+use crate::profile::Profile;
+use crate::workload::Workload;
+
+/// The batch sizes of `batch_sweep`.
+pub const BATCH_SWEEP: [usize; 5] = [1, 10, 100, 1000, 10000];
+
+/// The numbers of seeded items of `items_sweep`.
+pub const ITEMS_SWEEP: [usize; 4] = [10_000, 100_000, 1_000_000, 10_000_000];
+
+/// The value sizes of `value_size_sweep`, up to the largest byodb supports.
+pub const VALUE_SIZE_SWEEP: [usize; 5] = [64, 128, 256, 512, 1000];
+
+/// The fields that don't change what a single run measures, only which
+/// runs there are and where their DBs are, left out of its
+/// [`Config::scenario`].
+const NOT_SCENARIO: [&str; 13] = [
+    "backends",
+    "baseline",
+    "calibrate",
+    "repeat",
+    "sweep",
+    "threads_sweep",
+    "items_sweep",
+    "value_size_sweep",
+    "batch_sweep",
+    "durability_sweep",
+    "seed_cache",
+    "db_path",
+    "db_dir",
+];
+
+/// The numbers of threads of a thread sweep up to `max`: the powers of two
+/// below it, and itself.
+pub fn thread_counts(max: usize) -> Vec<usize> {
+    let mut threads: Vec<_> = (0..)
+        .map(|i| 1 << i)
+        .take_while(|&threads| threads < max)
+        .collect();
+    threads.push(max);
+    threads
+}
+
+/// Values of the fields of a [`Config`] to run every combination of, in the
+/// `[sweep]` table of a TOML file, e.g. `threads = [1, 2, 4]`.
+pub type Sweep = toml::Table;
+
+/// A full benchmark scenario.
+///
+/// Every field can be set from a TOML file (see [`Config::load`]) and then
+/// overridden by the corresponding command line flag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Names of the backends to benchmark, one after the other.
+    pub backends: Vec<String>,
+    /// Whether to also benchmark the in-memory baseline backend, first.
+    pub baseline: bool,
+    /// Whether to first run the no-op backend to measure the harness overhead.
+    pub calibrate: bool,
+    /// Number of times each backend is benchmarked, each time on a freshly
+    /// seeded DB unless `db_path` is set.
+    pub repeat: usize,
+    /// Backend-specific options, keyed by `<backend>.<option>`.
+    pub backend_opts: BackendOpts,
+    /// What the benchmark threads do.
+    pub workload: Workload,
+    /// Overrides the operation mix of the op-based workload.
+    pub mix: Option<Mix>,
+    /// The name of a generator registered with
+    /// [`crate::generator::register`], built in or by a program embedding
+    /// the harness, drawing the operations instead of the one of the
+    /// workload. The op-based workload still seeds the records.
+    pub generator: Option<String>,
+    /// Overrides the number of items read by each scan operation.
+    pub scan_len: Option<Length>,
+    /// Number of records under each key prefix of the prefix-scan workload.
+    pub prefix_size: usize,
+    /// Number of tenants of the multi-tenant workload.
+    pub tenants: usize,
+    /// Fraction of the operations of the rmw workload that are
+    /// read-modify-writes rather than reads.
+    pub rmw_fraction: f64,
+    /// Fraction of the reads of the op-based workload that look up keys no
+    /// record has.
+    pub miss_fraction: f64,
+    /// Overrides the key distribution of the op-based workload.
+    pub distribution: Option<Distribution>,
+    /// Skew of the Zipfian key distributions, between 0 and 1 exclusive.
+    pub theta: f64,
+    /// Parameters of the hotspot key distribution.
+    pub hotspot: Hotspot,
+    /// Number of items to seed the DB with.
+    pub items: usize,
+    /// Whether to run every backend on DBs of each of the [`ITEMS_SWEEP`]
+    /// numbers of items in turn, instead of `items`.
+    pub items_sweep: bool,
+    /// Number of threads inserting the seeded items.
+    pub seed_threads: usize,
+    /// Number of seeded items inserted by each transaction of a seeding
+    /// thread, instead of all of its items.
+    pub seed_batch: Option<usize>,
+    /// Dump of the items to seed the DB with instead of generated ones, in
+    /// the format described in [`crate::dump`].
+    pub seed_from: Option<PathBuf>,
+    /// Directory of copies of seeded DBs, which runs seeding the same items
+    /// into the same backend copy instead of seeding their own.
+    pub seed_cache: Option<PathBuf>,
+    /// What the keys look like.
+    pub key_type: KeyType,
+    /// Size of the seeded keys of the scan workload, instead of uniformly
+    /// random up to the largest size byodb supports.
+    pub key_size: Option<Length>,
+    /// Size of the values, instead of uniformly random up to the largest
+    /// size byodb supports for the scan workload, or 1000 bytes otherwise.
+    pub value_size: Option<Length>,
+    /// Whether to run every backend with values of each of the
+    /// [`VALUE_SIZE_SWEEP`] sizes in turn, instead of `value_size`.
+    pub value_size_sweep: bool,
+    /// Number of parallel benchmark threads.
+    pub threads: usize,
+    /// Runs every backend with 1, 2, 4 and so on threads in turn, up to
+    /// this many, instead of `threads`.
+    pub threads_sweep: Option<usize>,
+    /// Whether to pin every benchmark thread to a CPU of its own, in turn,
+    /// among the ones of `numa_node` if set. Linux only.
+    pub pin_threads: bool,
+    /// NUMA node to run on: the process is bound to its CPUs and allocates
+    /// from its memory, from before the DB is opened. Linux only.
+    pub numa_node: Option<usize>,
+    /// Number of iterations each thread performs: full in-order scans for
+    /// the scan workload, or single operations otherwise.
+    pub iters: usize,
+    /// Wall-clock time each thread runs for, in milliseconds, instead of
+    /// `iters` iterations.
+    pub duration_ms: Option<u64>,
+    /// Number of operations of the op-based workload in every transaction.
+    /// Batches of more than one all run in read-write transactions.
+    pub batch: usize,
+    /// Whether to run every backend with each of the [`BATCH_SWEEP`] batch
+    /// sizes in turn, instead of `batch`.
+    pub batch_sweep: bool,
+    /// When commits are synced to disk, mapped to backend-specific options.
+    /// Each backend keeps its own default if unset.
+    pub durability: Option<Durability>,
+    /// Whether to run every backend with each durability level it supports
+    /// in turn, instead of `durability`.
+    pub durability_sweep: bool,
+    /// Whether to evict the DB files from the OS page cache before the
+    /// measured phase, to measure cold reads.
+    pub cold: bool,
+    /// Whether to only run the load phase, into a new DB at `db_path` if
+    /// set, and skip the run phase.
+    pub load_only: bool,
+    /// How long every thread runs the workload, without measuring it, before
+    /// the measured phase.
+    pub warmup: Option<Budget>,
+    /// Operations per second of an open-loop run, across all threads. The
+    /// threads run closed loops, each operation right after the previous
+    /// one, if unset.
+    pub target_ops: Option<f64>,
+    /// Operations per second each thread is throttled to, with a token
+    /// bucket, in a closed loop. Unthrottled if unset.
+    pub rate_per_thread: Option<f64>,
+    /// How often the progress of the run is sampled, in milliseconds.
+    pub sample_interval_ms: u64,
+    /// Whether to count hardware events, like cycles and cache misses, in
+    /// the measured phase. Linux only.
+    pub perf_counters: bool,
+    /// What to profile the measured phase with, if anything.
+    pub profile: Option<Profile>,
+    /// Whether to run a background writer that spins during the scan
+    /// workload, unless there are `writers`.
+    pub writer: bool,
+    /// Number of writer threads that commit independent transactions during
+    /// the measured phase, besides the `threads` running the workload.
+    pub writers: usize,
+    /// Whether to apply every commit of the measured phase to an in-memory
+    /// model of the DB, and fail the run unless the DB holds exactly the
+    /// same records at the end. Serializes the commits.
+    pub shadow: bool,
+    /// File to save the trace of the op-based workload to: every operation
+    /// each thread performs, with its keys and values.
+    pub record: Option<PathBuf>,
+    /// Trace to perform the operations of, instead of drawing them, with the
+    /// workload, items and threads it was recorded with.
+    pub replay: Option<PathBuf>,
+    /// Access log of a real workload to replay, instead of drawing the
+    /// operations, in the format described in [`crate::access_log`].
+    pub access_log: Option<PathBuf>,
+    /// Seed of the RNG used to generate the seeded items.
+    pub seed: u64,
+    /// Path of the DB file (or directory, depending on the backend), which
+    /// runs use as it is, without a load phase, unless `load_only`. A
+    /// temporary directory is used if unset.
+    pub db_path: Option<PathBuf>,
+    /// Directory to create the temporary directories of the DBs in, instead
+    /// of the system's, e.g. to run on a given device.
+    pub db_dir: Option<PathBuf>,
+    /// Fields to run every combination of the values of, overriding the
+    /// ones above.
+    pub sweep: Sweep,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            backends: vec!["byodb".to_string()],
+            baseline: true,
+            calibrate: true,
+            repeat: 1,
+            backend_opts: BackendOpts::new(),
+            workload: Workload::Scan,
+            mix: None,
+            generator: None,
+            scan_len: None,
+            prefix_size: 10,
+            tenants: 100,
+            rmw_fraction: 0.5,
+            miss_fraction: 0.0,
+            distribution: None,
+            theta: 0.99,
+            hotspot: Hotspot {
+                ops: 90.0,
+                keys: 10.0,
+            },
+            items: 1000,
+            items_sweep: false,
+            seed_threads: 1,
+            seed_batch: None,
+            seed_from: None,
+            seed_cache: None,
+            key_type: KeyType::Random,
+            key_size: None,
+            value_size: None,
+            value_size_sweep: false,
+            threads: 1,
+            threads_sweep: None,
+            pin_threads: false,
+            numa_node: None,
+            iters: 1000,
+            duration_ms: None,
+            batch: 1,
+            batch_sweep: false,
+            durability: None,
+            durability_sweep: false,
+            cold: false,
+            load_only: false,
+            warmup: None,
+            target_ops: None,
+            rate_per_thread: None,
+            sample_interval_ms: 100,
+            perf_counters: false,
+            profile: None,
+            writer: true,
+            writers: 0,
+            shadow: false,
+            record: None,
+            replay: None,
+            access_log: None,
+            seed: 1,
+            db_path: None,
+            db_dir: None,
+            sweep: Sweep::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads a scenario from the TOML file at `path`.
+    /// Fields missing from the file keep their default values.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("failed to parse config {}", path.display()))
+    }
+
+    /// Formats the config as a manifest of the run: TOML that
+    /// [`Config::load`] reads back, to run the same scenario with the same
+    /// seed, after a comment naming the build that ran it.
+    pub fn manifest(&self) -> Result<String> {
+        let mut manifest = format!(
+            "# Reproduce with `db-cmp run --config <this file>`. Run by db-cmp {}",
+            env!("CARGO_PKG_VERSION")
+        );
+        if !env!("DB_CMP_GIT_COMMIT").is_empty() {
+            manifest += &format!(" at commit {}", env!("DB_CMP_GIT_COMMIT"));
+        }
+        manifest += ".\n";
+        manifest += &toml::to_string(self).context("failed to serialize the config")?;
+        Ok(manifest)
+    }
+
+    /// The operations of the op-based workload, with the overrides of this
+    /// config applied, or `None` for the scan workload.
+    pub fn spec(&self) -> Option<Spec> {
+        let mut spec = self.workload.spec(self)?;
+        if let Some(mix) = self.mix {
+            spec.mix = mix;
+        }
+        if let Some(scan_len) = self.scan_len {
+            spec.scan_len = scan_len;
+        }
+        if let Some(distribution) = self.distribution {
+            spec.distribution = distribution;
+        }
+        if let Some(value_size) = self.value_size {
+            spec.value_size = value_size;
+        }
+        Some(spec)
+    }
+
+    /// How long every thread runs the measured phase.
+    pub fn budget(&self) -> Budget {
+        match self.duration_ms {
+            Some(ms) => Budget::Time(Duration::from_millis(ms)),
+            None => Budget::Iters(self.iters),
+        }
+    }
+
+    pub fn sample_interval(&self) -> Duration {
+        Duration::from_millis(self.sample_interval_ms)
+    }
+
+    /// The scenarios every backend runs, one after the other: just this one,
+    /// or one per combination of the values of its sweeps, varying the last
+    /// swept field the fastest.
+    pub fn scenarios(&self) -> Result<Vec<Config>> {
+        let mut scenarios = vec![Config {
+            sweep: Sweep::new(),
+            ..self.clone()
+        }];
+        for (field, values) in &self.sweep {
+            if matches!(field.as_str(), "backends" | "sweep") {
+                bail!("sweep.{field} can't be swept");
+            }
+            let Some(values) = values.as_array().filter(|values| !values.is_empty()) else {
+                bail!("sweep.{field} must be a non-empty array of values");
+            };
+            scenarios = scenarios
+                .iter()
+                .flat_map(|config| values.iter().map(|value| config.with(field, value)))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(max) = self.threads_sweep {
+            if max == 0 {
+                bail!("the thread sweep needs at least one thread");
+            }
+            let threads = thread_counts(max);
+            scenarios = scenarios
+                .iter()
+                .flat_map(|config| {
+                    threads.iter().map(|&threads| Config {
+                        threads,
+                        ..config.clone()
+                    })
+                })
+                .collect();
+        }
+        if self.items_sweep {
+            scenarios = scenarios
+                .iter()
+                .flat_map(|config| {
+                    ITEMS_SWEEP.map(|items| Config {
+                        items,
+                        ..config.clone()
+                    })
+                })
+                .collect();
+        }
+        if self.value_size_sweep {
+            scenarios = scenarios
+                .iter()
+                .flat_map(|config| {
+                    VALUE_SIZE_SWEEP.map(|size| Config {
+                        value_size: Some(Length::Fixed(size)),
+                        ..config.clone()
+                    })
+                })
+                .collect();
+        }
+        if self.batch_sweep {
+            scenarios = scenarios
+                .iter()
+                .flat_map(|config| {
+                    BATCH_SWEEP.map(|batch| Config {
+                        batch,
+                        ..config.clone()
+                    })
+                })
+                .collect();
+        }
+        if self.durability_sweep {
+            scenarios = scenarios
+                .iter()
+                .flat_map(|config| {
+                    Durability::ALL.map(|durability| Config {
+                        durability: Some(durability),
+                        ..config.clone()
+                    })
+                })
+                .collect();
+        }
+        Ok(scenarios)
+    }
+
+    /// The fields that vary between the [`scenarios`](Config::scenarios).
+    pub fn swept_fields(&self) -> Vec<String> {
+        let mut fields: Vec<_> = self.sweep.keys().cloned().collect();
+        if self.threads_sweep.is_some() {
+            fields.push("threads".to_string());
+        }
+        if self.items_sweep {
+            fields.push("items".to_string());
+        }
+        if self.value_size_sweep {
+            fields.push("value_size".to_string());
+        }
+        if self.batch_sweep {
+            fields.push("batch".to_string());
+        }
+        if self.durability_sweep {
+            fields.push("durability".to_string());
+        }
+        fields
+    }
+
+    /// The options of `backend`, including the ones of `durability`.
+    pub fn backend_opts_for(&self, backend: &str) -> Result<BackendOpts> {
+        match self.durability {
+            Some(durability) => backend::with_durability(backend, durability, &self.backend_opts),
+            None => Ok(self.backend_opts.clone()),
+        }
+    }
+
+    /// Checks that the options of the config make sense together, before
+    /// anything runs, including that its sweeps make valid scenarios.
+    pub fn validate(&self) -> Result<()> {
+        if self.db_path.is_some() {
+            self.single_backend()?;
+        }
+        if self.record.is_some() {
+            ensure!(
+                self.spec().is_some(),
+                "only op-based workloads can be recorded"
+            );
+            ensure!(
+                self.sweep.is_empty() && !self.batch_sweep && !self.durability_sweep,
+                "sweeps can't be recorded"
+            );
+            ensure!(self.repeat == 1, "repetitions can't be recorded");
+            ensure!(self.warmup.is_none(), "warmups can't be recorded");
+        }
+        ensure!(
+            self.replay.is_none() || self.access_log.is_none(),
+            "either a trace or an access log can be replayed, not both"
+        );
+        ensure!(
+            self.record.is_none() || (self.replay.is_none() && self.access_log.is_none()),
+            "replays can't be recorded"
+        );
+        ensure!(
+            self.generator.is_none() || self.spec().is_some(),
+            "generators draw the operations of op-based workloads, not of {}",
+            self.workload
+        );
+        if let Some(name) = &self.generator {
+            generator::factory(name)?;
+        }
+        ensure!(
+            self.repeat > 0,
+            "the number of repetitions must be positive"
+        );
+        for scenario in self.scenarios()? {
+            scenario.validate_scenario()?;
+        }
+        Ok(())
+    }
+
+    /// Checks the options of a single scenario, as swept.
+    fn validate_scenario(&self) -> Result<()> {
+        ensure!(self.prefix_size > 0, "the prefix size must be positive");
+        // Durations are kept in milliseconds, so shorter ones would be 0.
+        ensure!(
+            self.duration_ms != Some(0),
+            "the duration must be at least 1ms"
+        );
+        ensure!(self.tenants > 0, "the number of tenants must be positive");
+        ensure!(
+            self.theta > 0.0 && self.theta < 1.0,
+            "theta must be between 0 and 1 exclusive"
+        );
+        ensure!(
+            (0.0..=1.0).contains(&self.rmw_fraction),
+            "rmw fraction must be between 0 and 1"
+        );
+        ensure!(
+            (0.0..=1.0).contains(&self.miss_fraction),
+            "miss fraction must be between 0 and 1"
+        );
+        ensure!(
+            self.target_ops.is_none_or(|target_ops| target_ops > 0.0),
+            "the target rate must be positive"
+        );
+        ensure!(
+            self.rate_per_thread.is_none_or(|rate| rate > 0.0),
+            "the rate per thread must be positive"
+        );
+        ensure!(self.batch > 0, "the batch size must be positive");
+        ensure!(
+            self.batch == 1 || self.target_ops.is_none(),
+            "batches of operations can't follow a target rate"
+        );
+        Ok(())
+    }
+
+    /// The value of `field`, as in a TOML file, or `None` if unset.
+    pub fn field(&self, field: &str) -> Option<toml::Value> {
+        toml::Table::try_from(self).ok()?.remove(field)
+    }
+
+    /// The fields of this config that make up the scenario a run of it
+    /// measures, as in a TOML file: all of them but the ones that don't
+    /// change what it measures, such as the backends run or the repetitions.
+    /// Runs of different sweeps or invocations, like those of a baseline,
+    /// measured the same scenario if these are equal.
+    pub fn scenario(&self) -> Result<toml::Table> {
+        let mut table = toml::Table::try_from(self)?;
+        for field in NOT_SCENARIO {
+            table.remove(field);
+        }
+        Ok(table)
+    }
+
+    /// This config with `field` set to `value`.
+    fn with(&self, field: &str, value: &toml::Value) -> Result<Config> {
+        let mut table = toml::Table::try_from(self)?;
+        table.insert(field.to_string(), value.clone());
+        table
+            .try_into()
+            .with_context(|| format!("invalid value {value} of sweep.{field}"))
+    }
+
+    /// Returns the only backend, for operations on a single DB at `db_path`.
+    pub fn single_backend(&self) -> Result<&str> {
+        match self.backends.as_slice() {
+            [backend] => Ok(backend),
+            _ => bail!("exactly one backend must be given with --db-path"),
+        }
+    }
+}
+
+/// How long a phase of the run lasts: a number of iterations of every
+/// thread, or a wall-clock duration like `500ms`, `10s` or `2m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Budget {
+    Iters(usize),
+    Time(Duration),
+}
+
+impl Budget {
+    /// Whether a thread that started at `start_time` and has done `iters`
+    /// iterations is done, or the run was interrupted.
+    pub fn is_spent(&self, iters: usize, start_time: Instant) -> bool {
+        if interrupt::is_interrupted() {
+            return true;
+        }
+        match *self {
+            Budget::Iters(n) => iters >= n,
+            Budget::Time(duration) => start_time.elapsed() >= duration,
+        }
+    }
+}
+
+impl FromStr for Budget {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(iters) = s.parse() {
+            return Ok(Budget::Iters(iters));
+        }
+        Ok(Budget::Time(parse_duration(s)?))
+    }
+}
+
+impl TryFrom<String> for Budget {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Budget> for String {
+    fn from(budget: Budget) -> Self {
+        budget.to_string()
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Budget::Iters(n) => write!(f, "{n}"),
+            // In the largest unit that loses nothing, so that it parses back
+            // to the same duration.
+            Budget::Time(duration) => {
+                let nanos = duration.as_nanos();
+                match nanos {
+                    _ if nanos % 1_000_000_000 == 0 => write!(f, "{}s", nanos / 1_000_000_000),
+                    _ if nanos % 1_000_000 == 0 => write!(f, "{}ms", nanos / 1_000_000),
+                    _ => write!(f, "{}us", nanos as f64 / 1e3),
+                }
+            }
+        }
+    }
+}
+
+/// Parses a duration with a unit, like `500us`, `500ms`, `10s` or `2m`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .ok_or_else(|| anyhow!("duration {s} needs a unit: us, ms, s or m"))?;
+    let (n, unit) = s.split_at(split);
+    let n: f64 = n.parse().with_context(|| format!("invalid duration {s}"))?;
+    let unit_nanos = match unit {
+        "us" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        "m" => 60e9,
+        _ => bail!("unknown unit {unit} of duration {s}, expected us, ms, s or m"),
+    };
+    // Rounded to the nanosecond, so that `0.5ms` is exactly 500us.
+    Ok(Duration::from_nanos((n * unit_nanos).round() as u64))
+}
+
+/// Parses a rate of operations per second, optionally with a `k` or `M`
+/// suffix, like `5000`, `100k` or `1.5M`.
+pub fn parse_rate(s: &str) -> Result<f64> {
+    let (n, scale) = match s.strip_suffix('k') {
+        Some(n) => (n, 1e3),
+        None => match s.strip_suffix('M') {
+            Some(n) => (n, 1e6),
+            None => (s, 1.0),
+        },
+    };
+    let n: f64 = n.parse().with_context(|| format!("invalid rate {s}"))?;
+    ensure!(n.is_finite() && n >= 0.0, "invalid rate {s}");
+    Ok(n * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("500us").unwrap(), Duration::from_micros(500));
+        assert_eq!(parse_duration("0.5ms").unwrap(), Duration::from_micros(500));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("1.5m").unwrap(), Duration::from_secs(90));
+        // Not 0.299999...s.
+        assert_eq!(parse_duration("0.3s").unwrap(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn parse_malformed_durations() {
+        for malformed in ["", "10", "ms", "10h", "1.2.3s", "-1s", "1 s"] {
+            assert!(parse_duration(malformed).is_err(), "{malformed:?} parsed");
+        }
+    }
+
+    #[test]
+    fn validate_durations() {
+        let config = |duration_ms| Config {
+            duration_ms,
+            ..Config::default()
+        };
+        assert!(config(None).validate().is_ok());
+        assert!(config(Some(1)).validate().is_ok());
+        assert!(config(Some(0)).validate().is_err());
+    }
+
+    #[test]
+    fn validate_generators() {
+        let config = |generator: &str| Config {
+            workload: Workload::YcsbA,
+            generator: Some(generator.to_string()),
+            ..Config::default()
+        };
+        assert!(config("churn").validate().is_ok());
+        assert!(config("unregistered").validate().is_err());
+    }
+
+    #[test]
+    fn validate_swept_scenarios() {
+        let config = |thetas: &str| Config {
+            sweep: toml::from_str(&format!("theta = {thetas}")).unwrap(),
+            ..Config::default()
+        };
+        assert!(config("[0.5, 0.99]").validate().is_ok());
+        assert!(config("[0.5, 1.5]").validate().is_err());
+        assert!(
+            Config {
+                rmw_fraction: 2.0,
+                ..Config::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_rates() {
+        assert_eq!(parse_rate("5000").unwrap(), 5000.0);
+        assert_eq!(parse_rate("100k").unwrap(), 100_000.0);
+        assert_eq!(parse_rate("1.5M").unwrap(), 1_500_000.0);
+        assert_eq!(parse_rate("0").unwrap(), 0.0);
+    }
+
+    #[test]
+    fn parse_malformed_rates() {
+        for malformed in ["", "k", "abc", "-1", "1.5G", "inf", "NaN"] {
+            assert!(parse_rate(malformed).is_err(), "{malformed:?} parsed");
+        }
+    }
+}
@@ -0,0 +1,764 @@
+//! Crash-recovery checks: a child process commits transactions to a DB until
+//! it's killed with SIGKILL, then the DB is reopened and checked against the
+//! op log of the transactions that the child reported as done.
+//!
+//! The child reports [`OPENED`] once it has opened the DB, and then a line
+//! per transaction, `committed <txn>`, `aborted <txn>` or `failed <txn>
+//! <error>`, once it's done. With faults injected (see [`Faulty`]), it also
+//! reports `faults <n>` whenever their number grows.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::Bound;
+use std::os::unix::fs::FileExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result, bail, ensure};
+use clap::ValueEnum;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::backend::{Backend, BackendOpts, Faults, Faulty};
+use crate::cli::{CrashArgs, CrashChildArgs};
+use crate::config::Config;
+use crate::db;
+
+/// What the child reports once it has opened the DB.
+pub const OPENED: &str = "opened";
+
+/// How long the child has to open the DB.
+const CRASH_OPEN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Number of keys every transaction puts.
+const TXN_KEYS: usize = 8;
+
+/// Size of the values put.
+const VALUE_SIZE: usize = 100;
+
+/// Every transaction whose id is a multiple of this is aborted instead of
+/// committed, to check that none of its writes leak.
+const ABORT_EVERY: u64 = 7;
+
+/// Prefix of the keys put by the transactions.
+const TXN_PREFIX: &str = "crash-txn-";
+
+/// Size of the tail of a file that [`corrupt`] damages, about a page.
+const TAIL_SIZE: u64 = 4096;
+
+/// Number of bytes of the tail flipped by [`Corruption::Flip`].
+const FLIPPED_BYTES: usize = 8;
+
+/// Key that every transaction also overwrites with its id, so that it holds
+/// the id of the last one committed.
+const LAST_KEY: &[u8] = b"crash-last";
+
+fn key(txn: u64, i: usize) -> String {
+    format!("{TXN_PREFIX}{txn:010}-{i}")
+}
+
+fn value(txn: u64, i: usize) -> Vec<u8> {
+    format!("{txn}-{i}.")
+        .into_bytes()
+        .into_iter()
+        .cycle()
+        .take(VALUE_SIZE)
+        .collect()
+}
+
+/// Runs transactions numbered from `first_txn` on `db` until killed,
+/// reporting each one to `log` once it's committed, aborted or failed, and
+/// the number of `faults` injected into `db` whenever it grows.
+pub fn write_until_killed(
+    db: &dyn Backend,
+    faults: &Faults,
+    first_txn: u64,
+    log: &mut dyn Write,
+) -> Result<()> {
+    let mut injected = 0;
+    for txn in first_txn.. {
+        match run_txn(db, txn) {
+            Ok(true) => writeln!(log, "committed {txn}")?,
+            Ok(false) => writeln!(log, "aborted {txn}")?,
+            Err(err) => writeln!(
+                log,
+                "failed {txn} {}",
+                format!("{err:#}").replace('\n', " ")
+            )?,
+        }
+        if faults.injected() != injected {
+            injected = faults.injected();
+            writeln!(log, "faults {injected}")?;
+        }
+        log.flush()?;
+    }
+    Ok(())
+}
+
+/// Runs transaction `txn`, returning whether it was committed rather than
+/// aborted.
+fn run_txn(db: &dyn Backend, txn: u64) -> Result<bool> {
+    let mut t = db.begin_rw()?;
+    for i in 0..TXN_KEYS {
+        t.put(key(txn, i).as_bytes(), &value(txn, i))?;
+    }
+    t.put(LAST_KEY, txn.to_string().as_bytes())?;
+    if txn.is_multiple_of(ABORT_EVERY) {
+        t.abort()?;
+        return Ok(false);
+    }
+    t.commit()?;
+    Ok(true)
+}
+
+/// The transactions that the child reported, over all the times it was
+/// killed.
+#[derive(Debug, Default)]
+pub struct OpLog {
+    pub committed: BTreeSet<u64>,
+    pub aborted: BTreeSet<u64>,
+    /// The transactions that failed with the error reported, which may or
+    /// may not have been committed, but not partially.
+    pub failed: BTreeMap<u64, String>,
+    /// The transactions that were running when the child was killed, which
+    /// may or may not have been committed, but not partially.
+    pub in_flight: BTreeSet<u64>,
+    /// Number of faults injected into the child before the last time it was
+    /// killed.
+    faults: u64,
+    /// Number of faults injected since then.
+    new_faults: u64,
+    next_txn: u64,
+}
+
+impl OpLog {
+    /// Records a line reported by the child.
+    pub fn record(&mut self, line: &str) -> Result<()> {
+        let parse = |txn: &str| -> Result<u64> {
+            txn.parse()
+                .with_context(|| format!("malformed op log line {line}"))
+        };
+        let txn = match line.split_once(' ') {
+            Some(("faults", faults)) => {
+                self.new_faults = parse(faults)?;
+                return Ok(());
+            }
+            Some(("failed", rest)) => {
+                let (txn, error) = rest.split_once(' ').unwrap_or((rest, ""));
+                let txn = parse(txn)?;
+                self.failed.insert(txn, error.to_string());
+                txn
+            }
+            Some(("committed", txn)) => {
+                let txn = parse(txn)?;
+                self.committed.insert(txn);
+                txn
+            }
+            Some(("aborted", txn)) => {
+                let txn = parse(txn)?;
+                self.aborted.insert(txn);
+                txn
+            }
+            _ => bail!("malformed op log line {line}"),
+        };
+        self.next_txn = txn + 1;
+        Ok(())
+    }
+
+    /// Records that the child was killed, while running the next transaction.
+    pub fn crashed(&mut self) {
+        self.in_flight.insert(self.next_txn);
+        self.next_txn += 1;
+        self.faults += self.new_faults;
+        self.new_faults = 0;
+    }
+
+    /// Number of faults injected into the child so far.
+    pub fn faults(&self) -> u64 {
+        self.faults + self.new_faults
+    }
+
+    /// Records that the `lost` committed transactions were rolled back, so
+    /// that they no longer count as committed.
+    pub fn roll_back(&mut self, lost: &[u64]) {
+        for txn in lost {
+            self.committed.remove(txn);
+        }
+    }
+
+    /// The id of the next transaction the child should run, after all those
+    /// it may have started.
+    pub fn next_txn(&self) -> u64 {
+        self.next_txn
+    }
+}
+
+/// What [`check`] found wrong with a DB.
+#[derive(Debug, Default)]
+pub struct Findings {
+    /// Committed transactions missing after the last one present, as if the
+    /// DB was rolled back to an earlier commit.
+    pub rolled_back: Vec<u64>,
+    /// Anything else, which no rollback explains.
+    pub problems: Vec<String>,
+}
+
+/// Checks that `db` has all the transactions committed according to `log`,
+/// and none of the aborted ones. Transactions in flight or that failed must
+/// have been committed entirely or not at all.
+pub fn check(db: &dyn Backend, log: &OpLog) -> Result<Findings> {
+    let t = db.begin_ro()?;
+    let mut found: BTreeMap<u64, Vec<(usize, bool)>> = BTreeMap::new();
+    let mut malformed = Vec::new();
+    t.scan(Bound::Included(TXN_PREFIX.as_bytes()), &mut |k, v| {
+        let Some(rest) = k.strip_prefix(TXN_PREFIX.as_bytes()) else {
+            return false;
+        };
+        let parsed = str::from_utf8(rest).ok().and_then(|rest| {
+            let (txn, i) = rest.split_once('-')?;
+            Some((txn.parse().ok()?, i.parse().ok()?))
+        });
+        match parsed {
+            Some((txn, i)) => found
+                .entry(txn)
+                .or_default()
+                .push((i, v == value(txn, i).as_slice())),
+            None => malformed.push(String::from_utf8_lossy(k).into_owned()),
+        }
+        true
+    })?;
+
+    let mut findings = Findings {
+        rolled_back: Vec::new(),
+        problems: malformed
+            .into_iter()
+            .map(|k| format!("unexpected key {k}"))
+            .collect(),
+    };
+    let complete = |txn: &u64| {
+        found.get(txn).is_some_and(|keys| {
+            keys.len() == TXN_KEYS && keys.iter().all(|&(i, ok)| i < TXN_KEYS && ok)
+        })
+    };
+    // The last transaction that was committed and still is, if any.
+    let mut last_present = None;
+    for txn in found.keys() {
+        if log.committed.contains(txn) {
+            if complete(txn) {
+                last_present = Some(*txn);
+            } else {
+                findings.problems.push(format!(
+                    "committed transaction {txn} is partially lost or corrupt"
+                ));
+            }
+        } else if log.aborted.contains(txn) || txn.is_multiple_of(ABORT_EVERY) {
+            findings
+                .problems
+                .push(format!("aborted transaction {txn} leaked"));
+        } else if !log.in_flight.contains(txn) && !log.failed.contains_key(txn) {
+            findings
+                .problems
+                .push(format!("transaction {txn} leaked before it started"));
+        } else if complete(txn) {
+            last_present = Some(*txn);
+        } else {
+            findings
+                .problems
+                .push(format!("transaction {txn} was partially committed"));
+        }
+    }
+    for txn in &log.committed {
+        if found.contains_key(txn) {
+            continue;
+        }
+        if last_present.is_none_or(|last| *txn > last) {
+            findings.rolled_back.push(*txn);
+        } else {
+            findings
+                .problems
+                .push(format!("committed transaction {txn} is lost"));
+        }
+    }
+
+    let last = t.get(LAST_KEY)?;
+    let last = last.as_deref().map(|last| String::from_utf8_lossy(last));
+    match (last_present, last) {
+        (None, None) => {}
+        (Some(want), Some(got)) if got == want.to_string() => {}
+        (want, got) => findings.problems.push(format!(
+            "the last committed transaction is {}, not {}",
+            got.as_deref().unwrap_or("missing"),
+            want.map_or("none".to_string(), |want| want.to_string())
+        )),
+    }
+    Ok(findings)
+}
+
+/// How to damage the DB after a crash, like a write torn by a power loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Corruption {
+    /// Truncates a random number of bytes off the end of the file.
+    Truncate,
+    /// Flips random bits of random bytes near the end of the file.
+    Flip,
+}
+
+/// Damages the tail of the file of the DB at `path` most recently modified,
+/// i.e. the one its last commit wrote to, e.g. a log. That includes files
+/// next to a DB file named after it, like SQLite's WAL. Returns what it did.
+pub fn corrupt(path: &Path, corruption: Corruption, rng: &mut impl Rng) -> Result<String> {
+    let mut last = last_modified(path)?;
+    if let (Some(dir), Some(name)) = (path.parent(), path.file_name())
+        && path.is_file()
+    {
+        for entry in fs::read_dir(dir)? {
+            let sibling = entry?.path();
+            let related = sibling
+                .file_name()
+                .is_some_and(|s| s.as_encoded_bytes().starts_with(name.as_encoded_bytes()));
+            if related && sibling != path {
+                last = last.max(last_modified(&sibling)?);
+            }
+        }
+    }
+    let (_, file) = last.with_context(|| format!("{} has no files", path.display()))?;
+    let f = OpenOptions::new().read(true).write(true).open(&file)?;
+    let len = f.metadata()?.len();
+    let tail = len.min(TAIL_SIZE);
+    if tail == 0 {
+        return Ok(format!("left the empty {} alone", file.display()));
+    }
+    match corruption {
+        Corruption::Truncate => {
+            let cut = rng.random_range(1..=tail);
+            f.set_len(len - cut)?;
+            Ok(format!("truncated {cut} bytes off {}", file.display()))
+        }
+        Corruption::Flip => {
+            for _ in 0..FLIPPED_BYTES {
+                let offset = len - rng.random_range(1..=tail);
+                let mut byte = [0];
+                f.read_exact_at(&mut byte, offset)?;
+                byte[0] ^= 1 << rng.random_range(0..8);
+                f.write_all_at(&byte, offset)?;
+            }
+            Ok(format!(
+                "flipped {FLIPPED_BYTES} bits in the last {tail} bytes of {}",
+                file.display()
+            ))
+        }
+    }
+}
+
+/// The regular file at or under `path` modified last, and when, if any.
+fn last_modified(path: &Path) -> Result<Option<(SystemTime, PathBuf)>> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+        return Ok(Some((metadata.modified()?, path.to_path_buf())));
+    }
+    let mut last = None;
+    for entry in fs::read_dir(path)? {
+        last = last.max(last_modified(&entry?.path())?);
+    }
+    Ok(last)
+}
+
+/// What reopening the DB after the crashes found.
+pub enum Outcome {
+    /// Every check passed.
+    Consistent,
+    /// The latest committed transactions were lost, but the DB is as it was
+    /// after an earlier commit.
+    RolledBack(Vec<u64>),
+    /// The problems found by [`check`], which the backend didn't detect.
+    Corrupt(Vec<String>),
+    /// The DB failed to open or to be read, with this error.
+    Unrecoverable(String),
+}
+
+/// How a backend fared over all the times it was crashed.
+pub struct Verdict {
+    pub backend: String,
+    pub crashes: usize,
+    pub log: OpLog,
+    /// Number of times the child died of an error rather than being killed.
+    pub deaths: usize,
+    /// Longest time it took to reopen the DB, if it ever did.
+    pub max_recovery: Option<Duration>,
+    pub outcome: Outcome,
+}
+
+/// Prints a table comparing how every backend fared.
+pub fn print_verdicts(verdicts: &[Verdict]) {
+    println!(
+        "{:<10} {:>8} {:>10} {:>8} {:>8} {:>16}  Outcome",
+        "Backend", "Crashes", "Committed", "Faults", "Errors", "Max recovery"
+    );
+    for verdict in verdicts {
+        let outcome = match &verdict.outcome {
+            Outcome::Consistent => "consistent".to_string(),
+            Outcome::RolledBack(lost) => format!("rolled back ({} commits lost)", lost.len()),
+            Outcome::Corrupt(problems) => {
+                format!("silently corrupt ({} problems)", problems.len())
+            }
+            Outcome::Unrecoverable(_) => "unrecoverable".to_string(),
+        };
+        println!(
+            "{:<10} {:>8} {:>10} {:>8} {:>8} {:>16}  {outcome}",
+            verdict.backend,
+            verdict.crashes,
+            verdict.log.committed.len(),
+            verdict.log.faults(),
+            verdict.log.failed.len() + verdict.deaths,
+            verdict
+                .max_recovery
+                .map_or("-".to_string(), |recovery| format!("{recovery:.1?}")),
+        );
+    }
+}
+
+/// Runs `db-cmp crash`: crashes a process committing to each backend of
+/// `args` at random points, re-running the current executable as its
+/// `crash-child`, and checks what every DB recovers.
+pub fn command(args: CrashArgs) -> Result<()> {
+    let config = Config {
+        durability: args.durability,
+        ..args.backend.into_config()
+    };
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let mut verdicts = Vec::new();
+    for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        // The DB is created here, so that the child only ever reopens it.
+        let (db, temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+        drop(db);
+        let path = db::temp_path(&temp_dir);
+        if !path.exists() {
+            println!("{backend}: skipped, since it doesn't persist anything");
+            continue;
+        }
+        let mut verdict = Verdict {
+            backend: backend.clone(),
+            crashes: 0,
+            log: OpLog::default(),
+            deaths: 0,
+            max_recovery: None,
+            outcome: Outcome::Consistent,
+        };
+        for n in 1..=args.crashes {
+            let kill_after = args.kill_within.mul_f64(rng.random());
+            let child = Child {
+                backend,
+                opts: &opts,
+                path: &path,
+                fault_rate: args.fault_rate,
+                fault_seed: rng.random(),
+            };
+            let killed = child
+                .crash_after(kill_after, &mut verdict.log)
+                .with_context(|| format!("failed to crash {backend}"))?;
+            verdict.crashes += 1;
+            if let Err(status) = killed {
+                // Errors are expected of the faults injected, but not otherwise.
+                ensure!(
+                    args.fault_rate > 0.0,
+                    "the child of {backend} exited before it was killed: {status}"
+                );
+                verdict.deaths += 1;
+            }
+            let corrupted = match args.corrupt {
+                Some(corruption) => format!(", {}", corrupt(&path, corruption, &mut rng)?),
+                None => String::new(),
+            };
+            let start_time = Instant::now();
+            let db = match db::open_existing(backend, &path, &opts) {
+                Ok(db) => db,
+                Err(err) => {
+                    verdict.outcome = Outcome::Unrecoverable(format!("{err:#}"));
+                    break;
+                }
+            };
+            let recovery = start_time.elapsed();
+            verdict.max_recovery = verdict.max_recovery.max(Some(recovery));
+            // Errors reading the DB mean that the backend detected corruption.
+            let findings = match check(&*db, &verdict.log) {
+                Ok(findings) => findings,
+                Err(err) => {
+                    verdict.outcome = Outcome::Unrecoverable(format!("{err:#}"));
+                    break;
+                }
+            };
+            let status = match (findings.problems.len(), findings.rolled_back.len()) {
+                (0, 0) => "OK".to_string(),
+                (0, lost) => format!("rolled back, {lost} commits lost"),
+                _ => "FAILED".to_string(),
+            };
+            println!(
+                "{backend}: crash {n}/{}: killed after {kill_after:.1?}{corrupted}, recovered in {recovery:.1?}, {} committed and {} aborted transactions so far: {status}",
+                args.crashes,
+                verdict.log.committed.len(),
+                verdict.log.aborted.len(),
+            );
+            if !findings.problems.is_empty() {
+                verdict.outcome = Outcome::Corrupt(findings.problems);
+                break;
+            }
+            if !findings.rolled_back.is_empty() {
+                verdict.log.roll_back(&findings.rolled_back);
+                match &mut verdict.outcome {
+                    Outcome::RolledBack(lost) => lost.extend(findings.rolled_back),
+                    outcome => *outcome = Outcome::RolledBack(findings.rolled_back),
+                }
+            }
+        }
+        match &verdict.outcome {
+            Outcome::Consistent | Outcome::RolledBack(_) => {}
+            Outcome::Corrupt(problems) => {
+                for problem in problems {
+                    println!("  {problem}");
+                }
+            }
+            Outcome::Unrecoverable(err) => println!("  failed to recover the DB: {err}"),
+        }
+        if let Some((txn, error)) = verdict.log.failed.first_key_value() {
+            println!("  transaction {txn} failed first: {error}");
+        }
+        verdicts.push(verdict);
+    }
+    print_verdicts(&verdicts);
+    // Corrupting a DB can lose commits, which the backend may then detect,
+    // but it shouldn't return bad data.
+    let failed: Vec<_> = verdicts
+        .iter()
+        .filter(|v| match v.outcome {
+            Outcome::Consistent => false,
+            Outcome::Corrupt(_) => true,
+            Outcome::RolledBack(_) | Outcome::Unrecoverable(_) => args.corrupt.is_none(),
+        })
+        .map(|v| v.backend.as_str())
+        .collect();
+    if !failed.is_empty() {
+        bail!("{} didn't recover consistently", failed.join(", "));
+    }
+    Ok(())
+}
+
+/// The `crash-child` process of a backend.
+struct Child<'a> {
+    backend: &'a str,
+    opts: &'a BackendOpts,
+    path: &'a Path,
+    fault_rate: f64,
+    fault_seed: u64,
+}
+
+impl Child<'_> {
+    /// Runs the child on the DB, kills it `kill_after` it opened the DB, and
+    /// records what it reported in `log`. Returns the exit status of the
+    /// child if it wasn't killed, but exited first.
+    fn crash_after(&self, kill_after: Duration, log: &mut OpLog) -> Result<Result<(), ExitStatus>> {
+        let mut command = process::Command::new(env::current_exe()?);
+        command
+            .arg("crash-child")
+            .arg("--backend")
+            .arg(self.backend)
+            .arg("--db-path")
+            .arg(self.path)
+            .arg("--first-txn")
+            .arg(log.next_txn().to_string())
+            .arg("--fault-rate")
+            .arg(self.fault_rate.to_string())
+            .arg("--fault-seed")
+            .arg(self.fault_seed.to_string());
+        for (key, val) in self.opts {
+            command.arg("--backend-opt").arg(format!("{key}={val}"));
+        }
+        let mut child = command.stdout(Stdio::piped()).spawn()?;
+        // Read the op log as it's written, so that the child never blocks on
+        // a full pipe.
+        let stdout = child.stdout.take().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        match receiver.recv_timeout(CRASH_OPEN_TIMEOUT) {
+            Ok(line) => {
+                ensure!(line? == OPENED, "the child didn't open the DB first");
+                thread::sleep(kill_after);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                child.kill()?;
+                bail!("the child didn't open the DB in time");
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                bail!("the child failed to open the DB: {}", child.wait()?);
+            }
+        }
+        child.kill()?;
+        let status = child.wait()?;
+        for line in receiver {
+            log.record(&line?)?;
+        }
+        log.crashed();
+        if status.signal() != Some(libc::SIGKILL) {
+            return Ok(Err(status));
+        }
+        Ok(Ok(()))
+    }
+}
+
+/// Runs `db-cmp crash-child`, the process that `crash` kills.
+pub fn child_command(args: CrashChildArgs) -> Result<()> {
+    let opts = args.backend_opts.into_iter().collect();
+    let db = db::open_existing(&args.backend, &args.db_path, &opts)?;
+    let faults = Arc::new(Faults::new(args.fault_rate, args.fault_seed));
+    let db = Faulty::new(db, faults.clone());
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", OPENED)?;
+    stdout.flush()?;
+    write_until_killed(&db, &faults, args.first_txn, &mut stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend;
+
+    fn log(lines: &[&str]) -> OpLog {
+        let mut log = OpLog::default();
+        for line in lines {
+            log.record(line).unwrap();
+        }
+        log
+    }
+
+    #[test]
+    fn op_log_records_lines() {
+        let mut log = log(&[
+            "committed 1",
+            "failed 2 injected fault in commit: Input/output error",
+            "faults 1",
+            "aborted 3",
+        ]);
+        assert_eq!(log.committed, BTreeSet::from([1]));
+        assert_eq!(log.aborted, BTreeSet::from([3]));
+        assert_eq!(
+            log.failed,
+            BTreeMap::from([(
+                2,
+                "injected fault in commit: Input/output error".to_string()
+            )])
+        );
+        assert_eq!(log.next_txn(), 4);
+        assert_eq!(log.faults(), 1);
+
+        log.crashed();
+        assert_eq!(log.in_flight, BTreeSet::from([4]));
+        assert_eq!(log.next_txn(), 5);
+        log.record("committed 5").unwrap();
+        log.record("faults 2").unwrap();
+        assert_eq!(log.faults(), 3);
+
+        log.roll_back(&[1, 5]);
+        assert!(log.committed.is_empty());
+    }
+
+    #[test]
+    fn op_log_rejects_malformed_lines() {
+        for line in ["", "committed", "committed x", "faults -1", "started 1"] {
+            assert!(OpLog::default().record(line).is_err(), "{line:?}");
+        }
+    }
+
+    /// Commits transaction `txn` in full, even if it's one to abort.
+    fn commit(db: &dyn Backend, txn: u64) {
+        let mut t = db.begin_rw().unwrap();
+        for i in 0..TXN_KEYS {
+            t.put(key(txn, i).as_bytes(), &value(txn, i)).unwrap();
+        }
+        t.put(LAST_KEY, txn.to_string().as_bytes()).unwrap();
+        t.commit().unwrap();
+    }
+
+    fn open() -> Box<dyn Backend> {
+        backend::open(backend::BASELINE, Path::new(""), &BackendOpts::new()).unwrap()
+    }
+
+    #[test]
+    fn check_accepts_what_was_committed() {
+        let db = open();
+        for txn in 1..=8 {
+            run_txn(&*db, txn).unwrap();
+        }
+        let lines: Vec<_> = (1..=8)
+            .map(|txn| match txn % ABORT_EVERY {
+                0 => format!("aborted {txn}"),
+                _ => format!("committed {txn}"),
+            })
+            .collect();
+        let mut log = log(&lines.iter().map(String::as_str).collect::<Vec<_>>());
+        let findings = check(&*db, &log).unwrap();
+        assert!(findings.problems.is_empty(), "{:?}", findings.problems);
+        assert!(findings.rolled_back.is_empty());
+
+        // The transaction in flight may or may not have been committed.
+        log.crashed();
+        commit(&*db, 9);
+        let findings = check(&*db, &log).unwrap();
+        assert!(findings.problems.is_empty(), "{:?}", findings.problems);
+    }
+
+    #[test]
+    fn check_finds_rollbacks() {
+        let db = open();
+        commit(&*db, 1);
+        let log = log(&["committed 1", "committed 2", "committed 3"]);
+        let findings = check(&*db, &log).unwrap();
+        assert_eq!(findings.rolled_back, [2, 3]);
+        assert!(findings.problems.is_empty(), "{:?}", findings.problems);
+    }
+
+    #[test]
+    fn check_finds_lost_and_leaked_transactions() {
+        let db = open();
+        commit(&*db, 1);
+        commit(&*db, 3);
+        let findings = check(&*db, &log(&["committed 1", "committed 2", "committed 3"])).unwrap();
+        assert_eq!(findings.problems, ["committed transaction 2 is lost"]);
+
+        let db = open();
+        commit(&*db, 7);
+        let findings = check(&*db, &log(&["aborted 7"])).unwrap();
+        assert!(findings.problems[0].contains("aborted transaction 7 leaked"));
+
+        let db = open();
+        commit(&*db, 1);
+        let findings = check(&*db, &OpLog::default()).unwrap();
+        assert!(findings.problems[0].contains("leaked before it started"));
+    }
+
+    #[test]
+    fn check_finds_partial_commits() {
+        let db = open();
+        let mut t = db.begin_rw().unwrap();
+        t.put(key(1, 0).as_bytes(), &value(1, 0)).unwrap();
+        t.put(LAST_KEY, b"1").unwrap();
+        t.commit().unwrap();
+        let findings = check(&*db, &log(&["failed 1 injected fault in put"])).unwrap();
+        assert!(findings.problems[0].contains("transaction 1 was partially committed"));
+    }
+}
@@ -0,0 +1,244 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use tempfile::TempDir;
+
+use crate::backend::{self, Backend, BackendOpts};
+use crate::config::Config;
+
+/// Opens the store at `path` with `backend`, failing if it doesn't exist yet.
+pub fn open_existing(backend: &str, path: &Path, opts: &BackendOpts) -> Result<Box<dyn Backend>> {
+    if !path.exists() {
+        bail!(
+            "DB {} does not exist; create it with `db-cmp seed` first",
+            path.display()
+        );
+    }
+    backend::open(backend, path, opts)
+        .with_context(|| format!("failed to open DB {}", path.display()))
+}
+
+/// Creates a store with `backend` inside a new temporary directory, in `dir`
+/// or else the system's. The temporary directory is deleted when dropped.
+pub fn open_temp(
+    backend: &str,
+    opts: &BackendOpts,
+    dir: Option<&Path>,
+) -> Result<(Box<dyn Backend>, TempDir)> {
+    let temp_dir = temp_dir(dir)?;
+    let db = backend::open(backend, &temp_path(&temp_dir), opts)
+        .context("failed to create temporary DB")?;
+    Ok((db, temp_dir))
+}
+
+/// Creates a new temporary directory for a DB, in `dir` or else the
+/// system's. It's deleted when dropped.
+pub fn temp_dir(dir: Option<&Path>) -> Result<TempDir> {
+    match dir {
+        Some(dir) => TempDir::new_in(dir),
+        None => TempDir::new(),
+    }
+    .context("failed to create temporary DB directory")
+}
+
+/// Where [`open_temp`] stores the DB inside `temp_dir`.
+pub fn temp_path(temp_dir: &TempDir) -> PathBuf {
+    temp_dir.path().join("db")
+}
+
+/// Total size of the files at `path`, which is either a file or a directory.
+pub fn disk_usage(path: &Path) -> Result<u64> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        // Backends that don't persist anything don't create `path`.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err).with_context(|| format!("failed to stat {}", path.display())),
+    };
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in fs::read_dir(path).with_context(|| format!("failed to list {}", path.display()))? {
+        size += disk_usage(&entry?.path())?;
+    }
+    Ok(size)
+}
+
+/// Copies the files at `from`, which is either a file or a directory, to
+/// `to`.
+pub fn copy(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        fs::copy(from, to)
+            .with_context(|| format!("failed to copy {} to {}", from.display(), to.display()))?;
+        return Ok(());
+    }
+    fs::create_dir(to).with_context(|| format!("failed to create {}", to.display()))?;
+    for entry in fs::read_dir(from).with_context(|| format!("failed to list {}", from.display()))? {
+        let entry = entry?;
+        copy(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Deletes the files at `path`, which is either a file or a directory.
+pub fn remove(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .with_context(|| format!("failed to delete {}", path.display()))
+}
+
+/// Evicts the files at `path`, which is either a file or a directory, from
+/// the OS page cache, after writing back their dirty pages. Pages still
+/// mapped by a process stay cached, so the DB must be closed first.
+pub fn evict_from_cache(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        for entry in
+            fs::read_dir(path).with_context(|| format!("failed to list {}", path.display()))?
+        {
+            evict_from_cache(&entry?.path())?;
+        }
+        return Ok(());
+    }
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    file.sync_data()
+        .with_context(|| format!("failed to sync {}", path.display()))?;
+    advise_dont_need(&file).with_context(|| format!("failed to evict {}", path.display()))
+}
+
+#[cfg(target_os = "linux")]
+fn advise_dont_need(file: &File) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: posix_fadvise only reads its arguments.
+    let err = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    if err != 0 {
+        return Err(std::io::Error::from_raw_os_error(err).into());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn advise_dont_need(_file: &File) -> Result<()> {
+    bail!("evicting files from the page cache is only supported on Linux")
+}
+
+/// The type of the filesystem holding `path`, e.g. `ext4` or `tmpfs`, if
+/// known.
+#[cfg(target_os = "linux")]
+pub fn filesystem(path: &Path) -> Option<String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // The DB files of backends that don't persist anything don't exist.
+    let path = path.ancestors().find(|path| path.exists())?;
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statfs only writes to `stat`, which is zeroable, and reads
+    // the NUL-terminated path.
+    let stat = unsafe {
+        let mut stat: libc::statfs = std::mem::zeroed();
+        if libc::statfs(c_path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        stat
+    };
+    // From statfs(2).
+    let name = match stat.f_type as u32 {
+        0xef53 => "ext4",
+        0x58465342 => "xfs",
+        0x9123683e => "btrfs",
+        0x2fc12fc1 => "zfs",
+        0xf2f52010 => "f2fs",
+        0x01021994 => "tmpfs",
+        0x858458f6 => "ramfs",
+        0x794c7630 => "overlayfs",
+        0x6969 => "nfs",
+        0x65735546 => "fuse",
+        0x4d44 => "vfat",
+        other => return Some(format!("unknown ({other:#x})")),
+    };
+    Some(name.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn filesystem(_path: &Path) -> Option<String> {
+    None
+}
+
+/// The options the filesystem holding `path` is mounted with, e.g.
+/// `rw,noatime`, followed by those of the filesystem itself that differ,
+/// like `data=ordered`, if known.
+#[cfg(target_os = "linux")]
+pub fn mount_options(path: &Path) -> Option<String> {
+    let path = path
+        .ancestors()
+        .find(|path| path.exists())?
+        .canonicalize()
+        .ok()?;
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+    // <id> <parent> <dev> <root> <mount point> <options> [<tag>...] - <type>
+    // <source> <superblock options>, with spaces in paths escaped as \040.
+    // The deepest mount point holding the path wins, and the latest of the
+    // mounts on the same point.
+    let mut deepest: Option<(usize, String)> = None;
+    for line in mountinfo.lines() {
+        let (mount, fs) = line.split_once(" - ")?;
+        let fields: Vec<_> = mount.split(' ').collect();
+        let (mount_point, options) = (unescape_mount_path(fields.get(4)?), fields.get(5)?);
+        if !path.starts_with(&mount_point) {
+            continue;
+        }
+        let depth = Path::new(&mount_point).components().count();
+        if deepest.as_ref().is_some_and(|(d, _)| *d > depth) {
+            continue;
+        }
+        let mut all: Vec<_> = options.split(',').collect();
+        for option in fs.split(' ').nth(2).unwrap_or_default().split(',') {
+            if !all.contains(&option) {
+                all.push(option);
+            }
+        }
+        deepest = Some((depth, all.join(",")));
+    }
+    deepest.map(|(_, options)| options)
+}
+
+#[cfg(target_os = "linux")]
+fn unescape_mount_path(path: &str) -> String {
+    path.replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn mount_options(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Whether `filesystem` keeps its files in memory, which makes I/O free.
+pub fn is_in_memory(filesystem: &str) -> bool {
+    matches!(filesystem, "tmpfs" | "ramfs")
+}
+
+/// Runs `db-cmp stats`: prints the statistics that the backend reports of
+/// the DB at `config.db_path`.
+pub fn print_stats(config: &Config) -> Result<()> {
+    let path = config
+        .db_path
+        .as_deref()
+        .context("stats requires --db-path")?;
+    let backend = config.single_backend()?;
+    let db = open_existing(backend, path, &config.backend_opts_for(backend)?)?;
+    let Some(stats) = db.stats()? else {
+        bail!("{backend} doesn't report any statistics");
+    };
+    let width = stats.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, value) in stats {
+        println!("{name:<width$}  {value}");
+    }
+    Ok(())
+}
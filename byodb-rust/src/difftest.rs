@@ -0,0 +1,342 @@
+//! Differential testing: the same seeded stream of transactions is applied to
+//! several backends, and whatever they return, as well as their contents
+//! every so often, must be identical to what the first one does.
+
+use std::fmt;
+use std::ops::Bound;
+use std::panic::{self, AssertUnwindSafe};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::backend::{Backend, WriteTxn};
+use crate::cli::DifftestArgs;
+use crate::distribution::Length;
+use crate::{db, ops};
+
+/// Maximum number of records visited by a scan.
+const SCAN_LEN: usize = 10;
+
+/// Fraction of the transactions aborted rather than committed.
+const ABORT_FRACTION: f64 = 0.1;
+
+/// An operation of the stream, on the key numbered as given.
+#[derive(Debug, Clone)]
+pub enum Op {
+    Put(u64, String),
+    Delete(u64),
+    Get(u64),
+    /// Visits up to [`SCAN_LEN`] records from the key.
+    Scan(u64),
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Put(n, v) => write!(f, "put {} ({} bytes)", key(*n), v.len()),
+            Op::Delete(n) => write!(f, "delete {}", key(*n)),
+            Op::Get(n) => write!(f, "get {}", key(*n)),
+            Op::Scan(n) => write!(f, "scan from {}", key(*n)),
+        }
+    }
+}
+
+fn key(n: u64) -> String {
+    ops::key(n, true)
+}
+
+/// A transaction of the stream, committed or aborted after its operations.
+pub struct Txn {
+    pub ops: Vec<Op>,
+    pub commit: bool,
+}
+
+/// Deterministically generates transactions of 1 to `max_ops` random
+/// operations on `keys` keys: half puts, and a fifth each of deletes and
+/// gets, so that records are overwritten, deleted and recreated, and a tenth
+/// scans.
+pub struct Stream {
+    rng: ChaCha8Rng,
+    keys: u64,
+    max_ops: usize,
+    value_size: Length,
+}
+
+impl Stream {
+    pub fn new(seed: u64, keys: u64, max_ops: usize, value_size: Length) -> Self {
+        Stream {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            keys,
+            max_ops,
+            value_size,
+        }
+    }
+
+    fn op(&mut self) -> Op {
+        let n = self.rng.random_range(0..self.keys);
+        match self.rng.random_range(0..10) {
+            0..5 => Op::Put(n, ops::value(&mut self.rng, self.value_size)),
+            5..7 => Op::Delete(n),
+            7..9 => Op::Get(n),
+            _ => Op::Scan(n),
+        }
+    }
+}
+
+impl Iterator for Stream {
+    type Item = Txn;
+
+    fn next(&mut self) -> Option<Txn> {
+        let n_ops = self.rng.random_range(1..=self.max_ops);
+        Some(Txn {
+            ops: (0..n_ops).map(|_| self.op()).collect(),
+            commit: !self.rng.random_bool(ABORT_FRACTION),
+        })
+    }
+}
+
+type Records = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// What an operation returned.
+#[derive(PartialEq)]
+enum Seen {
+    Nothing,
+    Existed(bool),
+    Value(Option<Vec<u8>>),
+    Records(Records),
+}
+
+impl fmt::Display for Seen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Seen::Nothing => f.write_str("nothing"),
+            Seen::Existed(true) => f.write_str("an existing record"),
+            Seen::Existed(false) => f.write_str("no record"),
+            Seen::Value(Some(v)) => write!(f, "{}", String::from_utf8_lossy(v)),
+            Seen::Value(None) => f.write_str("no value"),
+            Seen::Records(records) => write!(f, "{} records", records.len()),
+        }
+    }
+}
+
+fn apply(t: &mut dyn WriteTxn, op: &Op) -> Result<Seen> {
+    Ok(match op {
+        Op::Put(n, v) => {
+            t.put(key(*n).as_bytes(), v.as_bytes())?;
+            Seen::Nothing
+        }
+        Op::Delete(n) => Seen::Existed(t.delete(key(*n).as_bytes())?),
+        Op::Get(n) => Seen::Value(t.get(key(*n).as_bytes())?.map(|v| v.into_owned())),
+        Op::Scan(n) => {
+            let mut records = Vec::new();
+            t.scan(Bound::Included(key(*n).as_bytes()), &mut |k, v| {
+                records.push((k.to_vec(), v.to_vec()));
+                records.len() < SCAN_LEN
+            })?;
+            Seen::Records(records)
+        }
+    })
+}
+
+/// Runs `f`, turning a panic into an error, since backends may panic on
+/// what they find inconsistent.
+fn unpanicked<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = (panic.downcast_ref::<String>().map(String::as_str))
+            .or_else(|| panic.downcast_ref::<&str>().copied())
+            .unwrap_or("unknown panic");
+        Err(anyhow!("panicked: {message}"))
+    })
+}
+
+/// All the records of `db`, in order.
+fn contents(db: &dyn Backend) -> Result<Records> {
+    let mut records = Vec::new();
+    db.begin_ro()?.scan(Bound::Unbounded, &mut |k, v| {
+        records.push((k.to_vec(), v.to_vec()));
+        true
+    })?;
+    Ok(records)
+}
+
+/// The first record that differs between `a` and `b`, which differ.
+fn first_difference((a_name, a): (&str, &Records), (b_name, b): (&str, &Records)) -> String {
+    let lossy = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+    let same = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    match (a.get(same), b.get(same)) {
+        (Some((a_k, a_v)), Some((b_k, b_v))) if a_k == b_k => format!(
+            "{} is {} in {a_name}, but {} in {b_name}",
+            lossy(a_k),
+            lossy(a_v),
+            lossy(b_v)
+        ),
+        (Some((a_k, _)), Some((b_k, _))) if a_k > b_k => {
+            format!("{} is in {b_name}, but not in {a_name}", lossy(b_k))
+        }
+        (Some((a_k, _)), _) => format!("{} is in {a_name}, but not in {b_name}", lossy(a_k)),
+        (None, Some((b_k, _))) => format!("{} is in {b_name}, but not in {a_name}", lossy(b_k)),
+        (None, None) => unreachable!("the records don't differ"),
+    }
+}
+
+/// Where the backends first diverged.
+pub struct Divergence {
+    /// Number of the operation, from 1, or of the last one before the
+    /// contents were compared.
+    pub op: u64,
+    /// Number of the transaction of the operation, from 1.
+    pub txn: u64,
+    pub what: String,
+}
+
+/// What a run found.
+pub struct Report {
+    pub ops: u64,
+    pub txns: u64,
+    /// Number of times the contents of the backends were compared.
+    pub checks: u64,
+    pub divergence: Option<Divergence>,
+}
+
+/// Applies transactions of `stream` to every backend of `dbs` until `n_ops`
+/// operations are done, or they diverge from the first one. Their contents
+/// are compared at the end of the transaction of every `check_every`
+/// operations, and at the end.
+pub fn run(
+    dbs: &[(&str, &dyn Backend)],
+    stream: Stream,
+    n_ops: u64,
+    check_every: u64,
+) -> Result<Report> {
+    let mut report = Report {
+        ops: 0,
+        txns: 0,
+        checks: 0,
+        divergence: None,
+    };
+    let mut last_check = 0;
+    for txn in stream {
+        if report.ops >= n_ops {
+            break;
+        }
+        report.txns += 1;
+        let mut ts = dbs
+            .iter()
+            .map(|(name, db)| {
+                unpanicked(|| db.begin_rw())
+                    .with_context(|| format!("{name} failed to begin transaction {}", report.txns))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for op in &txn.ops {
+            report.ops += 1;
+            let seen = dbs
+                .iter()
+                .zip(&mut ts)
+                .map(|((name, _), t)| {
+                    unpanicked(|| apply(&mut **t, op)).with_context(|| {
+                        format!(
+                            "{name} failed to {op}, operation {} of transaction {}",
+                            report.ops, report.txns
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let (ref_name, _) = dbs[0];
+            let Some(((name, _), other)) = dbs
+                .iter()
+                .zip(&seen)
+                .skip(1)
+                .find(|(_, other)| **other != seen[0])
+            else {
+                continue;
+            };
+            let what = match (&seen[0], other) {
+                (Seen::Records(a), Seen::Records(b)) => first_difference((ref_name, a), (name, b)),
+                (a, b) => format!("{ref_name} saw {a}, but {name} saw {b}"),
+            };
+            report.divergence = Some(Divergence {
+                op: report.ops,
+                txn: report.txns,
+                what: format!("{op}: {what}"),
+            });
+            return Ok(report);
+        }
+        for ((name, _), t) in dbs.iter().zip(ts) {
+            unpanicked(|| if txn.commit { t.commit() } else { t.abort() })
+                .with_context(|| format!("{name} failed to end transaction {}", report.txns))?;
+        }
+        if report.ops - last_check >= check_every || report.ops >= n_ops {
+            last_check = report.ops;
+            report.checks += 1;
+            if let Some(what) = compare(dbs)? {
+                report.divergence = Some(Divergence {
+                    op: report.ops,
+                    txn: report.txns,
+                    what,
+                });
+                return Ok(report);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// How the contents of a backend of `dbs` differ from the first one's, if
+/// they do.
+fn compare(dbs: &[(&str, &dyn Backend)]) -> Result<Option<String>> {
+    let (ref_name, ref_db) = dbs[0];
+    let expected =
+        unpanicked(|| contents(ref_db)).with_context(|| format!("{ref_name} failed to scan"))?;
+    for &(name, db) in &dbs[1..] {
+        let records =
+            unpanicked(|| contents(db)).with_context(|| format!("{name} failed to scan"))?;
+        if records != expected {
+            return Ok(Some(first_difference(
+                (ref_name, &expected),
+                (name, &records),
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// Runs `db-cmp difftest`: applies the same stream of transactions to the
+/// backends of `args`, and fails if they diverge.
+pub fn command(args: DifftestArgs) -> Result<()> {
+    ensure!(
+        args.backend.backends.len() >= 2,
+        "difftest needs at least two backends to compare"
+    );
+    ensure!(args.keys > 0, "difftest needs keys");
+    ensure!(args.batch > 0, "the batch size must be positive");
+    let config = args.backend.into_config();
+    let mut dbs = Vec::new();
+    for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        dbs.push(db::open_temp(backend, &opts, config.db_dir.as_deref())?);
+    }
+    let named: Vec<_> = config
+        .backends
+        .iter()
+        .zip(&dbs)
+        .map(|(backend, (db, _))| (backend.as_str(), &**db))
+        .collect();
+    let stream = Stream::new(config.seed, args.keys, args.batch, args.value_size);
+    let report = run(&named, stream, args.ops, args.check_every)?;
+    let backends = config.backends.join(", ");
+    if let Some(divergence) = report.divergence {
+        bail!(
+            "{backends} diverged at operation {} of transaction {} (seed {}): {}",
+            divergence.op,
+            divergence.txn,
+            config.seed,
+            divergence.what
+        );
+    }
+    println!(
+        "{backends}: identical over {} operations in {} transactions, compared {} times",
+        report.ops, report.txns, report.checks
+    );
+    Ok(())
+}
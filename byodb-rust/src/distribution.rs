@@ -0,0 +1,411 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Context, Error, Result, anyhow, bail, ensure};
+use clap::ValueEnum;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// How op-based workloads pick the keys they access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Distribution {
+    /// Every existing key is equally likely.
+    Uniform,
+    /// A few keys, scattered over the keyspace, are accessed most.
+    Zipfian,
+    /// A fixed fraction of the operations access a fixed fraction of the
+    /// keys, uniformly (see `--hotspot`). The hot keys are the lowest key
+    /// numbers, so they're scattered over the keyspace with random keys,
+    /// but they're the lowest keys with ordered, `u64-seq` or `uuid-v7`
+    /// ones.
+    Hotspot,
+    /// The most recently inserted keys are accessed most, following a
+    /// Zipfian distribution counting back from the newest.
+    Latest,
+}
+
+/// Picks key numbers according to a [`Distribution`].
+pub enum KeyChooser {
+    Uniform,
+    /// Zipfian over a fixed `space` of key numbers, hashed so that the
+    /// popular keys aren't clustered together. Numbers beyond the existing
+    /// keys are rejected and resampled.
+    ScrambledZipfian {
+        zipfian: Zipfian,
+        space: u64,
+    },
+    /// Zipfian over the existing keys, counting back from the newest.
+    Latest(Zipfian),
+    Hotspot(Hotspot),
+}
+
+impl KeyChooser {
+    /// The parameters of `distribution` are taken from `config`. `space` is
+    /// the number of keys that are expected to exist by the end of the run,
+    /// including inserted ones.
+    pub fn new(distribution: Distribution, config: &Config, space: u64) -> Self {
+        match distribution {
+            Distribution::Uniform => KeyChooser::Uniform,
+            Distribution::Zipfian => KeyChooser::ScrambledZipfian {
+                zipfian: Zipfian::new(space, config.theta),
+                space,
+            },
+            Distribution::Hotspot => KeyChooser::Hotspot(config.hotspot),
+            Distribution::Latest => KeyChooser::Latest(Zipfian::new(space, config.theta)),
+        }
+    }
+
+    /// Picks one of the key numbers `0..n_keys`.
+    pub fn next(&mut self, rng: &mut impl Rng, n_keys: u64) -> u64 {
+        match self {
+            KeyChooser::Uniform => rng.random_range(0..n_keys),
+            KeyChooser::ScrambledZipfian { zipfian, space } => loop {
+                let k = fnv1a(zipfian.sample(rng, *space)) % *space;
+                if k < n_keys {
+                    return k;
+                }
+            },
+            KeyChooser::Latest(zipfian) => n_keys - 1 - zipfian.sample(rng, n_keys),
+            KeyChooser::Hotspot(hotspot) => {
+                // The hot keys are the lowest numbers: only the key type
+                // decides whether they're scattered over the keyspace.
+                let n_hot = ((n_keys as f64 * hotspot.keys / 100.0) as u64).clamp(1, n_keys);
+                if n_hot == n_keys || rng.random::<f64>() * 100.0 < hotspot.ops {
+                    rng.random_range(0..n_hot)
+                } else {
+                    rng.random_range(n_hot..n_keys)
+                }
+            }
+        }
+    }
+}
+
+/// `ops`% of the operations access `keys`% of the keys, written as
+/// `<ops>/<keys>`, e.g. `90/10`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hotspot {
+    pub ops: f64,
+    pub keys: f64,
+}
+
+impl FromStr for Hotspot {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (ops, keys) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow!("hotspot {s} is not of the form <ops>/<keys>"))?;
+        let hotspot = Hotspot {
+            ops: ops.parse().context("invalid hotspot ops percentage")?,
+            keys: keys.parse().context("invalid hotspot keys percentage")?,
+        };
+        ensure!(
+            (0.0..=100.0).contains(&hotspot.ops) && hotspot.keys > 0.0 && hotspot.keys <= 100.0,
+            "hotspot percentages must be between 0 and 100"
+        );
+        Ok(hotspot)
+    }
+}
+
+impl TryFrom<String> for Hotspot {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Hotspot> for String {
+    fn from(hotspot: Hotspot) -> Self {
+        hotspot.to_string()
+    }
+}
+
+impl fmt::Display for Hotspot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.ops, self.keys)
+    }
+}
+
+/// Samples item numbers following a Zipfian distribution with skew
+/// `0 < theta < 1`, where item 0 is the most popular, as described in
+/// "Quickly Generating Billion-Record Synthetic Databases" by Gray et al.
+pub struct Zipfian {
+    theta: f64,
+    alpha: f64,
+    zeta2: f64,
+    /// Number of items `zetan` and `eta` were computed for.
+    n: u64,
+    zetan: f64,
+    eta: f64,
+}
+
+impl Zipfian {
+    pub fn new(n: u64, theta: f64) -> Self {
+        let mut zipfian = Zipfian {
+            theta,
+            alpha: 1.0 / (1.0 - theta),
+            zeta2: zeta(0, 2, theta, 0.0),
+            n: 0,
+            zetan: 0.0,
+            eta: 0.0,
+        };
+        zipfian.resize(n);
+        zipfian
+    }
+
+    /// Recomputes the constants for `n` items. Only the terms added or
+    /// removed are summed, so that a slowly growing or shrinking keyspace
+    /// stays cheap.
+    fn resize(&mut self, n: u64) {
+        self.zetan = if n >= self.n {
+            zeta(self.n, n, self.theta, self.zetan)
+        } else {
+            self.zetan - zeta(n, self.n, self.theta, 0.0)
+        };
+        self.n = n;
+        self.eta =
+            (1.0 - (2.0 / n as f64).powf(1.0 - self.theta)) / (1.0 - self.zeta2 / self.zetan);
+    }
+
+    /// Samples one of the items `0..n`.
+    pub fn sample(&mut self, rng: &mut impl Rng, n: u64) -> u64 {
+        if n != self.n {
+            self.resize(n);
+        }
+        let u: f64 = rng.random();
+        let uz = u * self.zetan;
+        if uz < 1.0 {
+            return 0;
+        }
+        if uz < 1.0 + 0.5f64.powf(self.theta) {
+            return 1;
+        }
+        let item = n as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha);
+        (item as u64).min(n - 1)
+    }
+}
+
+/// Adds the terms `from..to` of the generalized harmonic series to `sum`.
+fn zeta(from: u64, to: u64, theta: f64, sum: f64) -> f64 {
+    (from..to).fold(sum, |sum, i| sum + 1.0 / ((i + 1) as f64).powf(theta))
+}
+
+/// The 64-bit FNV-1a hash of `n`, as used by YCSB to scatter key numbers.
+pub fn fnv1a(mut n: u64) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for _ in 0..8 {
+        hash ^= n & 0xff;
+        hash = hash.wrapping_mul(0x100000001b3);
+        n >>= 8;
+    }
+    hash
+}
+
+/// A distribution of lengths, written as `<n>` for a fixed length,
+/// `<min>..=<max>` for a uniformly random one, `lognormal(<median>,<sigma>)`
+/// or `pareto(<min>,<alpha>)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Length {
+    Fixed(usize),
+    Uniform {
+        min: usize,
+        max: usize,
+    },
+    /// The logarithm of the length is normally distributed, with standard
+    /// deviation `sigma`.
+    LogNormal {
+        median: f64,
+        sigma: f64,
+    },
+    /// Heavy-tailed, with a smaller shape `alpha` for a heavier tail.
+    Pareto {
+        min: f64,
+        alpha: f64,
+    },
+}
+
+impl Length {
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        match *self {
+            Length::Fixed(n) => n,
+            Length::Uniform { min, max } => rng.random_range(min..=max),
+            Length::LogNormal { median, sigma } => {
+                // A standard normal sample, by the Box-Muller transform.
+                let (u1, u2) = (1.0 - rng.random::<f64>(), rng.random::<f64>());
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (median * (sigma * z).exp()).round() as usize
+            }
+            Length::Pareto { min, alpha } => {
+                let u = 1.0 - rng.random::<f64>();
+                (min / u.powf(1.0 / alpha)) as usize
+            }
+        }
+    }
+}
+
+impl FromStr for Length {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |n: &str| {
+            n.parse::<usize>()
+                .with_context(|| format!("invalid length {n}"))
+        };
+        if let Some((name, params)) = s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            let (a, b) = params
+                .split_once(',')
+                .ok_or_else(|| anyhow!("{name} distribution {s} needs two parameters"))?;
+            let parse = |p: &str| {
+                p.trim()
+                    .parse::<f64>()
+                    .with_context(|| format!("invalid {name} parameter {p}"))
+            };
+            let (a, b) = (parse(a)?, parse(b)?);
+            return match name {
+                "lognormal" => {
+                    ensure!(a > 0.0 && b >= 0.0, "invalid lognormal distribution {s}");
+                    Ok(Length::LogNormal {
+                        median: a,
+                        sigma: b,
+                    })
+                }
+                "pareto" => {
+                    ensure!(a > 0.0 && b > 0.0, "invalid pareto distribution {s}");
+                    Ok(Length::Pareto { min: a, alpha: b })
+                }
+                _ => bail!("unknown length distribution {name}, expected lognormal or pareto"),
+            };
+        }
+        match s.split_once("..=") {
+            None => Ok(Length::Fixed(parse(s)?)),
+            Some((min, max)) => {
+                let (min, max) = (parse(min)?, parse(max)?);
+                ensure!(min <= max, "empty length range {s}");
+                Ok(Length::Uniform { min, max })
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Length {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Length> for String {
+    fn from(length: Length) -> Self {
+        length.to_string()
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Fixed(n) => write!(f, "{n}"),
+            Length::Uniform { min, max } => write!(f, "{min}..={max}"),
+            Length::LogNormal { median, sigma } => write!(f, "lognormal({median},{sigma})"),
+            Length::Pareto { min, alpha } => write!(f, "pareto({min},{alpha})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn parse_lengths() {
+        assert_eq!("100".parse::<Length>().unwrap(), Length::Fixed(100));
+        assert_eq!(
+            "10..=20".parse::<Length>().unwrap(),
+            Length::Uniform { min: 10, max: 20 }
+        );
+        assert_eq!(
+            "lognormal(100, 0.5)".parse::<Length>().unwrap(),
+            Length::LogNormal {
+                median: 100.0,
+                sigma: 0.5
+            }
+        );
+        assert_eq!(
+            "pareto(10,1.5)".parse::<Length>().unwrap(),
+            Length::Pareto {
+                min: 10.0,
+                alpha: 1.5
+            }
+        );
+    }
+
+    #[test]
+    fn lengths_round_trip() {
+        for s in ["100", "10..=20", "lognormal(100,0.5)", "pareto(10,1.5)"] {
+            let length: Length = s.parse().unwrap();
+            assert_eq!(length.to_string().parse::<Length>().unwrap(), length);
+        }
+    }
+
+    #[test]
+    fn parse_malformed_lengths() {
+        for malformed in [
+            "",
+            "abc",
+            "-1",
+            "20..=10",
+            "10..20",
+            "lognormal(100)",
+            "lognormal(-1,0.5)",
+            "pareto(0,1)",
+            "pareto(10,x)",
+            "gauss(1,2)",
+        ] {
+            assert!(malformed.parse::<Length>().is_err(), "{malformed:?} parsed");
+        }
+    }
+
+    #[test]
+    fn zipfian_skew() {
+        let (n, theta) = (1000, 0.99);
+        let mut zipfian = Zipfian::new(n, theta);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut counts = vec![0u32; n as usize];
+        let samples = 100_000;
+        for _ in 0..samples {
+            counts[zipfian.sample(&mut rng, n) as usize] += 1;
+        }
+        // Item i is drawn with probability 1 / ((i + 1)^theta * zeta(n)).
+        let zetan = zeta(0, n, theta, 0.0);
+        for i in [0, 1, 9] {
+            let expected = samples as f64 / ((i + 1) as f64).powf(theta) / zetan;
+            let actual = counts[i] as f64;
+            assert!(
+                (actual / expected - 1.0).abs() < 0.1,
+                "item {i} drawn {actual} times, expected {expected}"
+            );
+        }
+        assert!(counts[0] > counts[1] && counts[1] > counts[9]);
+    }
+
+    #[test]
+    fn zipfian_resizes_both_ways() {
+        let mut zipfian = Zipfian::new(1000, 0.99);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for n in [2000, 500] {
+            assert!(zipfian.sample(&mut rng, n) < n);
+            let fresh = Zipfian::new(n, 0.99);
+            assert!((zipfian.zetan - fresh.zetan).abs() < 1e-9);
+            assert!((zipfian.eta - fresh.eta).abs() < 1e-9);
+        }
+    }
+}
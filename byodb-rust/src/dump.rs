@@ -0,0 +1,55 @@
+//! Dumps of key-value pairs to seed DBs with, instead of generated ones, so
+//! that benchmarks can run against real datasets.
+//!
+//! A dump is a UTF-8 text file with a `key<TAB>value` line per pair. Tabs,
+//! newlines and backslashes in keys and values are escaped as `\t`, `\n`
+//! and `\\`. Blank lines are skipped.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail, ensure};
+
+/// Loads the pairs of the dump at `path`, in order.
+pub fn load(path: &Path) -> Result<Vec<(String, String)>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read dump {}", path.display()))?;
+    let mut items = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let item = parse(line)
+            .with_context(|| format!("invalid line {} of dump {}", i + 1, path.display()))?;
+        items.push(item);
+    }
+    ensure!(!items.is_empty(), "dump {} is empty", path.display());
+    Ok(items)
+}
+
+fn parse(line: &str) -> Result<(String, String)> {
+    let Some((key, value)) = line.split_once('\t').filter(|(_, v)| !v.contains('\t')) else {
+        bail!("expected key<TAB>value");
+    };
+    ensure!(!key.is_empty(), "empty key");
+    Ok((unescape(key)?, unescape(value)?))
+}
+
+fn unescape(s: &str) -> Result<String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next() {
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('\\') => '\\',
+            Some(other) => bail!("unknown escape \\{other}"),
+            None => bail!("trailing backslash"),
+        });
+    }
+    Ok(unescaped)
+}
@@ -0,0 +1,641 @@
+//! Conditions on how results compare to a baseline, like
+//! `throughput < baseline*0.95 || p99 > baseline*1.10`, which fail the run
+//! when they hold, so that automation can gate merges on benchmarks.
+//!
+//! A condition is comparisons (`<`, `<=`, `>`, `>=`) joined by `&&` and
+//! `||`, which bind as usual, and grouped by parentheses. Each comparison
+//! compares a metric to an arithmetic expression of numbers and `baseline`,
+//! the value of the same metric in the baseline, with `*` and `/`. Metrics
+//! are `throughput` in operations per second, and `mean`, `p50`, `p90`,
+//! `p99`, `p999` and `max` latencies in microseconds, of all operations
+//! together, or of a single type when prefixed with it, like `read.p99`.
+//!
+//! [`Target`]s are absolute instead, like a fixed latency budget, and fail
+//! the run when a result misses them.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Error, Result, anyhow, bail, ensure};
+
+use crate::histogram;
+use crate::results::{self, RunResult};
+
+/// A parsed condition, with its source.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    expr: Expr,
+    source: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare {
+        metric: Metric,
+        left: Value,
+        cmp: Cmp,
+        right: Value,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A side of a comparison.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    /// The metric of the comparison in the results.
+    Metric,
+    /// The metric of the comparison in the baseline.
+    Baseline,
+    Mul(Box<Value>, Box<Value>),
+    Div(Box<Value>, Box<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Metric {
+    /// The type of operation, or `None` for all of them.
+    op: Option<String>,
+    /// One of [`STATS`].
+    stat: &'static str,
+}
+
+/// The statistics that metrics can be.
+const STATS: [&str; 7] = ["throughput", "mean", "p50", "p90", "p99", "p999", "max"];
+
+/// A result that met the condition, with the values of its metrics.
+pub struct Violation {
+    pub backend: String,
+    pub workload: String,
+    /// Every metric of the condition, with its value in the result and in
+    /// the baseline.
+    pub metrics: Vec<(String, f64, f64)>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics: Vec<_> = self
+            .metrics
+            .iter()
+            .map(|(name, value, base)| format!("{name} {value:.3} (baseline {base:.3})"))
+            .collect();
+        write!(
+            f,
+            "{} {}: {}",
+            self.backend,
+            self.workload,
+            metrics.join(", ")
+        )
+    }
+}
+
+impl Condition {
+    /// Checks every result of `results` that has a counterpart of the same
+    /// backend and scenario in `baseline`, as told by
+    /// [`results::counterpart`], rather than of the same workload alone, so
+    /// that every scenario of a sweep is held to its own. Returns the ones
+    /// that met the condition.
+    pub fn check(&self, results: &[RunResult], baseline: &[RunResult]) -> Result<Vec<Violation>> {
+        let mut violations = Vec::new();
+        for result in results {
+            let Some(base) = results::counterpart(result, baseline) else {
+                continue;
+            };
+            if !self.expr.holds(result, base)? {
+                continue;
+            }
+            let mut metrics = Vec::new();
+            self.expr.metrics(&mut |metric| {
+                let name = metric.to_string();
+                if !metrics.iter().any(|(n, _, _)| *n == name) {
+                    metrics.push((name, metric.of(result)?, metric.of(base)?));
+                }
+                Ok(())
+            })?;
+            violations.push(Violation {
+                backend: result.backend.clone(),
+                workload: result.config.workload.to_string(),
+                metrics,
+            });
+        }
+        Ok(violations)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Expr {
+    fn holds(&self, result: &RunResult, base: &RunResult) -> Result<bool> {
+        Ok(match self {
+            Expr::Or(a, b) => a.holds(result, base)? || b.holds(result, base)?,
+            Expr::And(a, b) => a.holds(result, base)? && b.holds(result, base)?,
+            Expr::Compare {
+                metric,
+                left,
+                cmp,
+                right,
+            } => {
+                let (value, base_value) = (metric.of(result)?, metric.of(base)?);
+                let left = left.eval(value, base_value);
+                let right = right.eval(value, base_value);
+                match cmp {
+                    Cmp::Lt => left < right,
+                    Cmp::Le => left <= right,
+                    Cmp::Gt => left > right,
+                    Cmp::Ge => left >= right,
+                }
+            }
+        })
+    }
+
+    /// Calls `visit` with the metric of every comparison, in order.
+    fn metrics(&self, visit: &mut dyn FnMut(&Metric) -> Result<()>) -> Result<()> {
+        match self {
+            Expr::Or(a, b) | Expr::And(a, b) => {
+                a.metrics(visit)?;
+                b.metrics(visit)
+            }
+            Expr::Compare { metric, .. } => visit(metric),
+        }
+    }
+}
+
+impl Value {
+    fn eval(&self, metric: f64, baseline: f64) -> f64 {
+        match self {
+            Value::Number(n) => *n,
+            Value::Metric => metric,
+            Value::Baseline => baseline,
+            Value::Mul(a, b) => a.eval(metric, baseline) * b.eval(metric, baseline),
+            Value::Div(a, b) => a.eval(metric, baseline) / b.eval(metric, baseline),
+        }
+    }
+}
+
+impl Metric {
+    /// The value of the metric in `result`.
+    fn of(&self, result: &RunResult) -> Result<f64> {
+        let latency = match &self.op {
+            None => result.total_latency(),
+            Some(op) => result.latencies.get(op).cloned().ok_or_else(|| {
+                anyhow!(
+                    "{} {} has no {op} operations",
+                    result.backend,
+                    result.config.workload
+                )
+            })?,
+        };
+        Ok(match self.stat {
+            "throughput" if self.op.is_none() => result.throughput(),
+            "throughput" => latency.count() as f64 / (result.elapsed_us as f64 / 1e6),
+            "mean" => latency.mean_us(),
+            "p50" => latency.quantile_us(0.5),
+            "p90" => latency.quantile_us(0.9),
+            "p99" => latency.quantile_us(0.99),
+            "p999" => latency.quantile_us(0.999),
+            _ => latency.max_us(),
+        })
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(op) = &self.op {
+            write!(f, "{op}.")?;
+        }
+        f.write_str(self.stat)
+    }
+}
+
+impl FromStr for Metric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (op, stat) = match s.rsplit_once('.') {
+            Some((op, stat)) => (Some(op.to_string()), stat),
+            None => (None, s),
+        };
+        let Some(stat) = STATS.into_iter().find(|&known| known == stat) else {
+            bail!(
+                "unknown metric {s}, expected one of {}, optionally prefixed with a type of operation, like read.p99",
+                STATS.join(", ")
+            );
+        };
+        Ok(Metric { op, stat })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Cmp(&'static str),
+    And,
+    Or,
+    Mul,
+    Div,
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let symbols = [
+            ("<=", Token::Cmp("<=")),
+            (">=", Token::Cmp(">=")),
+            ("<", Token::Cmp("<")),
+            (">", Token::Cmp(">")),
+            ("&&", Token::And),
+            ("||", Token::Or),
+            ("*", Token::Mul),
+            ("/", Token::Div),
+            ("(", Token::Open),
+            (")", Token::Close),
+        ];
+        let len =
+            if let Some((symbol, token)) = symbols.into_iter().find(|(s, _)| rest.starts_with(s)) {
+                tokens.push(token);
+                symbol.len()
+            } else if c.is_ascii_digit() || c == '.' {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(rest.len());
+                let number = &rest[..len];
+                tokens.push(Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| anyhow!("invalid number {number}"))?,
+                ));
+                len
+            } else if c.is_ascii_alphabetic() {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Name(rest[..len].to_string()));
+                len
+            } else {
+                bail!("unexpected {c}");
+            };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser of the tokens of a condition.
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let eaten = self.peek() == Some(token);
+        self.next += eaten as usize;
+        eaten
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.comparison()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        // A parenthesized condition, unless it's an arithmetic expression.
+        if self.peek() == Some(&Token::Open) {
+            let start = self.next;
+            self.next += 1;
+            if let Ok(expr) = self.or()
+                && self.eat(&Token::Close)
+            {
+                return Ok(expr);
+            }
+            self.next = start;
+        }
+        let mut metric = None;
+        let left = self.product(&mut metric)?;
+        let cmp = match self.peek() {
+            Some(Token::Cmp(cmp)) => match *cmp {
+                "<" => Cmp::Lt,
+                "<=" => Cmp::Le,
+                ">" => Cmp::Gt,
+                _ => Cmp::Ge,
+            },
+            _ => bail!("expected a comparison"),
+        };
+        self.next += 1;
+        let right = self.product(&mut metric)?;
+        let metric = metric.ok_or_else(|| anyhow!("a comparison has no metric"))?;
+        Ok(Expr::Compare {
+            metric,
+            left,
+            cmp,
+            right,
+        })
+    }
+
+    /// Parses a product of values, naming at most one `metric`.
+    fn product(&mut self, metric: &mut Option<Metric>) -> Result<Value> {
+        let mut value = self.value(metric)?;
+        loop {
+            if self.eat(&Token::Mul) {
+                value = Value::Mul(Box::new(value), Box::new(self.value(metric)?));
+            } else if self.eat(&Token::Div) {
+                value = Value::Div(Box::new(value), Box::new(self.value(metric)?));
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn value(&mut self, metric: &mut Option<Metric>) -> Result<Value> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| anyhow!("unexpected end"))?;
+        self.next += 1;
+        Ok(match token {
+            Token::Number(n) => Value::Number(n),
+            Token::Name(name) if name == "baseline" => Value::Baseline,
+            Token::Name(name) => {
+                let named: Metric = name.parse()?;
+                match metric {
+                    Some(other) if *other != named => {
+                        bail!("a comparison can't compare {other} to {named}")
+                    }
+                    _ => *metric = Some(named),
+                }
+                Value::Metric
+            }
+            Token::Open => {
+                let value = self.product(metric)?;
+                ensure!(self.eat(&Token::Close), "expected )");
+                value
+            }
+            other => bail!("unexpected {other:?}"),
+        })
+    }
+}
+
+impl FromStr for Condition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            next: 0,
+        };
+        let expr = parser.or()?;
+        ensure!(
+            parser.next == parser.tokens.len(),
+            "unexpected {:?}",
+            parser.tokens[parser.next]
+        );
+        Ok(Condition {
+            expr,
+            source: s.to_string(),
+        })
+    }
+}
+
+/// An absolute target that every result must meet, regardless of any
+/// baseline.
+#[derive(Debug, Clone, Copy)]
+pub enum Target {
+    /// The p99 latency of all operations is under this.
+    P99Under(Duration),
+    /// The throughput is over this many operations per second.
+    ThroughputOver(f64),
+}
+
+impl Target {
+    /// Checks `result`, returning how it missed the target, if it did.
+    pub fn check(self, result: &RunResult) -> Option<Miss> {
+        let actual = match self {
+            Target::P99Under(budget) => {
+                let p99 = result.total_latency().quantile_us(0.99);
+                (p99 >= budget.as_secs_f64() * 1e6).then_some(p99)
+            }
+            Target::ThroughputOver(min) => {
+                let throughput = result.throughput();
+                (throughput <= min).then_some(throughput)
+            }
+        }?;
+        Some(Miss {
+            backend: result.backend.clone(),
+            workload: result.config.workload.to_string(),
+            target: self,
+            actual,
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Target::P99Under(budget) => write!(
+                f,
+                "p99 < {}",
+                histogram::format_ns(budget.as_nanos() as u64)
+            ),
+            Target::ThroughputOver(min) => write!(f, "throughput > {min:.0} ops/s"),
+        }
+    }
+}
+
+/// A result that missed a [`Target`], with its value of the metric.
+pub struct Miss {
+    pub backend: String,
+    pub workload: String,
+    pub target: Target,
+    /// The p99 latency in microseconds, or the throughput.
+    pub actual: f64,
+}
+
+impl fmt::Display for Miss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: ", self.backend, self.workload)?;
+        match self.target {
+            Target::P99Under(budget) => {
+                let budget_us = budget.as_secs_f64() * 1e6;
+                let us = |us: f64| histogram::format_ns((us * 1e3) as u64);
+                write!(
+                    f,
+                    "p99 {} is over the budget of {} by {} ({:+.1}%)",
+                    us(self.actual),
+                    us(budget_us),
+                    us(self.actual - budget_us),
+                    (self.actual / budget_us - 1.0) * 100.0
+                )
+            }
+            Target::ThroughputOver(min) => write!(
+                f,
+                "throughput {:.0} ops/s is under the target of {min:.0} ops/s by {:.0} ops/s ({:+.1}%)",
+                self.actual,
+                min - self.actual,
+                (self.actual / min - 1.0) * 100.0
+            ),
+        }
+    }
+}
+
+/// Checks every result of `results` against every target of `targets`.
+/// Returns how they missed them.
+pub fn missed_targets<'r>(
+    targets: &[Target],
+    results: impl IntoIterator<Item = &'r RunResult>,
+) -> Vec<Miss> {
+    results
+        .into_iter()
+        .flat_map(|result| targets.iter().filter_map(|target| target.check(result)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Expr {
+        s.parse::<Condition>().unwrap().expr
+    }
+
+    fn compare(metric: &str, cmp: Cmp, right: Value) -> Expr {
+        Expr::Compare {
+            metric: metric.parse().unwrap(),
+            left: Value::Metric,
+            cmp,
+            right,
+        }
+    }
+
+    fn boxed(a: Expr, b: Expr) -> (Box<Expr>, Box<Expr>) {
+        (Box::new(a), Box::new(b))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let (a, b) = boxed(
+            compare("p50", Cmp::Gt, Value::Number(2.0)),
+            compare("max", Cmp::Ge, Value::Number(3.0)),
+        );
+        let (a, b) = boxed(compare("p99", Cmp::Lt, Value::Number(1.0)), Expr::And(a, b));
+        assert_eq!(parse("p99 < 1 || p50 > 2 && max >= 3"), Expr::Or(a, b));
+    }
+
+    #[test]
+    fn parentheses_group_conditions() {
+        let (a, b) = boxed(
+            compare("p99", Cmp::Lt, Value::Number(1.0)),
+            compare("p50", Cmp::Gt, Value::Number(2.0)),
+        );
+        let (a, b) = boxed(Expr::Or(a, b), compare("max", Cmp::Le, Value::Number(3.0)));
+        assert_eq!(parse("(p99 < 1 || p50 > 2) && max <= 3"), Expr::And(a, b));
+    }
+
+    #[test]
+    fn baseline_times_factor() {
+        let right = Value::Mul(Box::new(Value::Baseline), Box::new(Value::Number(0.95)));
+        assert_eq!(
+            parse("throughput < baseline*0.95"),
+            compare("throughput", Cmp::Lt, right)
+        );
+        let right = Value::Div(Box::new(Value::Baseline), Box::new(Value::Number(2.0)));
+        assert_eq!(
+            parse("p99 > (baseline / 2)"),
+            compare("p99", Cmp::Gt, right)
+        );
+    }
+
+    #[test]
+    fn metric_on_either_side() {
+        let expr = parse("baseline * 1.1 < p99 / 2");
+        let Expr::Compare {
+            metric,
+            left,
+            cmp,
+            right,
+        } = expr
+        else {
+            panic!("not a comparison: {expr:?}");
+        };
+        assert_eq!(metric, "p99".parse().unwrap());
+        assert_eq!(
+            left,
+            Value::Mul(Box::new(Value::Baseline), Box::new(Value::Number(1.1)))
+        );
+        assert_eq!(cmp, Cmp::Lt);
+        assert_eq!(
+            right,
+            Value::Div(Box::new(Value::Metric), Box::new(Value::Number(2.0)))
+        );
+    }
+
+    #[test]
+    fn dotted_metric_names() {
+        let metric: Metric = "read.p99".parse().unwrap();
+        assert_eq!(metric.op.as_deref(), Some("read"));
+        assert_eq!(metric.stat, "p99");
+        let metric: Metric = "read-miss.throughput".parse().unwrap();
+        assert_eq!(metric.op.as_deref(), Some("read-miss"));
+        assert_eq!(metric.stat, "throughput");
+        assert_eq!(
+            parse("read.p99 > baseline"),
+            compare("read.p99", Cmp::Gt, Value::Baseline)
+        );
+        assert!("read.p42".parse::<Metric>().is_err());
+    }
+
+    #[test]
+    fn malformed_conditions() {
+        for malformed in [
+            "",
+            "p99",
+            "p99 >",
+            "p99 > 1 &&",
+            "|| p99 > 1",
+            "p42 > 1",
+            "(p99 > 1",
+            "p99 > 1)",
+            "p99 > (baseline * 2",
+            "1 < 2",
+            "p99 > p50",
+            "p99 > 1.2.3",
+            "p99 = 1",
+            "p99 > 1 & p50 > 1",
+        ] {
+            assert!(
+                malformed.parse::<Condition>().is_err(),
+                "{malformed:?} parsed"
+            );
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! What draws the operations of op-based workloads: a [`Generator`] per
+//! thread, made by the factory registered under a name with [`register`].
+//! The built-in workloads draw theirs from the generators registered under
+//! `ycsb`, `prefix-scan`, `multi-tenant` and `churn`, and a program
+//! embedding the harness registers its own for an access pattern of its
+//! own, run like any other with `config.generator` (or `--generator`) set
+//! to its name.
+//!
+//! Every thread of a run has a generator of its own, made from the
+//! [`Keys`] shared by all of them, which it asks for its next operation with
+//! an RNG of that operation's own.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use anyhow::{Result, anyhow};
+use rand::distr::{Alphanumeric, SampleString};
+use rand::{Rng, RngCore};
+
+use crate::config::Config;
+use crate::distribution::KeyChooser;
+use crate::ops::{FIELD_SIZE, MISS_SUFFIX, Op, Spec, value};
+use crate::trace::TracedOp;
+
+/// Draws the operations of a thread.
+pub trait Generator: Send {
+    /// Draws the next operation with `rng`, or `None` to skip it, e.g. a
+    /// delete with no record left to delete.
+    fn next_op(&mut self, rng: &mut dyn RngCore) -> Option<TracedOp>;
+}
+
+/// Makes the generator of the thread with the given index, for a run of
+/// the config.
+pub type Factory = dyn Fn(&Config, &Keys, usize) -> Box<dyn Generator> + Send + Sync;
+
+/// Factories by name.
+type Registry = Vec<(String, Arc<Factory>)>;
+
+/// The generators registered by name, starting with the built-in ones.
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(|| {
+    let mut registry = Vec::new();
+    add(&mut registry, "ycsb", |config, keys, _| {
+        Box::new(Ycsb::new(config, keys))
+    });
+    add(&mut registry, "prefix-scan", |config, keys, _| {
+        Box::new(PrefixScan(Ycsb::new(config, keys)))
+    });
+    add(&mut registry, "multi-tenant", |config, keys, _| {
+        Box::new(MultiTenant(Ycsb::new(config, keys)))
+    });
+    add(&mut registry, "churn", |config, keys, _| {
+        Box::new(Churn(Ycsb::new(config, keys)))
+    });
+    RwLock::new(registry)
+});
+
+/// Registers `factory` under `name`, replacing any registered before, even
+/// a built-in one, so that runs with `config.generator` set to `name` draw
+/// their operations from the generators it makes.
+pub fn register(
+    name: &str,
+    factory: impl Fn(&Config, &Keys, usize) -> Box<dyn Generator> + Send + Sync + 'static,
+) {
+    add(&mut REGISTRY.write().unwrap(), name, factory);
+}
+
+/// Adds `factory` to `registry` under `name`, replacing any added before.
+fn add(
+    registry: &mut Registry,
+    name: &str,
+    factory: impl Fn(&Config, &Keys, usize) -> Box<dyn Generator> + Send + Sync + 'static,
+) {
+    registry.retain(|(registered, _)| registered != name);
+    registry.push((name.to_string(), Arc::new(factory)));
+}
+
+/// The records of a run, shared by the generators of all its threads.
+#[derive(Clone)]
+pub struct Keys {
+    /// The workload of the run, numbering the keys of the records.
+    pub spec: Spec,
+    /// Number of records, including the ones inserted during the run.
+    pub n_keys: Arc<AtomicU64>,
+    /// Number of records removed by deletes of the oldest record.
+    pub n_deleted: Arc<AtomicU64>,
+    /// Number of keys chosen from, including room for the inserts.
+    pub space: u64,
+}
+
+impl Keys {
+    /// The key of a new record.
+    pub fn insert_key(&self) -> String {
+        self.spec.key(self.n_keys.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// The generator of thread `t`: the one registered as `config.generator`,
+/// if set, or else the built-in one of the workload.
+pub fn generator(config: &Config, keys: &Keys, t: usize) -> Result<Box<dyn Generator>> {
+    let name = match &config.generator {
+        Some(name) => name.as_str(),
+        None => builtin(&keys.spec),
+    };
+    Ok(factory(name)?(config, keys, t))
+}
+
+/// The factory registered under `name`.
+pub fn factory(name: &str) -> Result<Arc<Factory>> {
+    let registry = REGISTRY.read().unwrap();
+    let (_, factory) = registry
+        .iter()
+        .find(|(registered, _)| registered == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = registry.iter().map(|(name, _)| name.as_str()).collect();
+            anyhow!(
+                "no generator {name} is registered, only {}",
+                names.join(", ")
+            )
+        })?;
+    Ok(factory.clone())
+}
+
+/// The name of the built-in generator of the workload of `spec`.
+fn builtin(spec: &Spec) -> &'static str {
+    if spec.prefix_size.is_some() {
+        "prefix-scan"
+    } else if spec.tenants.is_some() {
+        "multi-tenant"
+    } else if spec.delete_oldest {
+        "churn"
+    } else {
+        "ycsb"
+    }
+}
+
+/// YCSB's core workload: the mix of the spec, on keys chosen from its
+/// distribution, with scans of a number of records from the chosen key.
+/// The generator of the YCSB workloads, and of the others whose operations
+/// are drawn the same way, like `point-get` or `custom`.
+pub struct Ycsb {
+    keys: Keys,
+    chooser: KeyChooser,
+}
+
+impl Ycsb {
+    pub fn new(config: &Config, keys: &Keys) -> Self {
+        Ycsb {
+            chooser: KeyChooser::new(keys.spec.distribution, config, keys.space),
+            keys: keys.clone(),
+        }
+    }
+
+    /// The number of an existing record, chosen from the distribution.
+    fn choose(&mut self, rng: &mut impl Rng) -> u64 {
+        let n_keys = self.keys.n_keys.load(Ordering::Relaxed);
+        self.chooser.next(rng, n_keys)
+    }
+
+    fn choose_key(&mut self, rng: &mut impl Rng) -> String {
+        let n = self.choose(rng);
+        self.keys.spec.key(n)
+    }
+
+    /// Draws an operation of type `op`. Skips empty scans.
+    fn draw(&mut self, op: Op, rng: &mut impl Rng) -> Option<TracedOp> {
+        let spec = self.keys.spec;
+        Some(match op {
+            Op::Read => {
+                let mut k = self.choose_key(rng);
+                // Drawn only if there are misses, so as not to change the
+                // operations drawn otherwise.
+                if spec.miss_fraction > 0.0 && rng.random::<f64>() < spec.miss_fraction {
+                    k.push_str(MISS_SUFFIX);
+                }
+                TracedOp::Read(k.into_bytes())
+            }
+            Op::Update => {
+                let k = self.choose_key(rng);
+                let v = value(rng, spec.value_size);
+                TracedOp::Update(k.into_bytes(), v.into_bytes())
+            }
+            Op::Insert => {
+                let k = self.keys.insert_key();
+                let v = value(rng, spec.value_size);
+                TracedOp::Insert(k.into_bytes(), v.into_bytes())
+            }
+            Op::Delete => TracedOp::Delete(self.choose_key(rng).into_bytes()),
+            Op::Scan => {
+                let k = self.choose_key(rng);
+                let len = spec.scan_len.sample(rng);
+                if len == 0 {
+                    return None;
+                }
+                TracedOp::Scan(k.into_bytes(), len as u64)
+            }
+            Op::Rmw => TracedOp::Rmw {
+                key: self.choose_key(rng).into_bytes(),
+                pick: rng.random(),
+                field: Alphanumeric.sample_string(rng, FIELD_SIZE).into_bytes(),
+            },
+        })
+    }
+}
+
+impl Generator for Ycsb {
+    fn next_op(&mut self, mut rng: &mut dyn RngCore) -> Option<TracedOp> {
+        let op = self.keys.spec.mix.choose(&mut rng);
+        self.draw(op, &mut rng)
+    }
+}
+
+/// The prefix-scan workload: like [`Ycsb`], but scans read every record
+/// under the prefix of the chosen one.
+pub struct PrefixScan(pub Ycsb);
+
+impl Generator for PrefixScan {
+    fn next_op(&mut self, mut rng: &mut dyn RngCore) -> Option<TracedOp> {
+        let ycsb = &mut self.0;
+        match ycsb.keys.spec.mix.choose(&mut rng) {
+            Op::Scan => {
+                let n = ycsb.choose(&mut rng);
+                Some(TracedOp::PrefixScan(ycsb.keys.spec.prefix(n).into_bytes()))
+            }
+            op => ycsb.draw(op, &mut rng),
+        }
+    }
+}
+
+/// The multi-tenant workload: like [`Ycsb`], but scans stop at the end of
+/// the tenant of the chosen record.
+pub struct MultiTenant(pub Ycsb);
+
+impl Generator for MultiTenant {
+    /// Skips empty scans.
+    fn next_op(&mut self, mut rng: &mut dyn RngCore) -> Option<TracedOp> {
+        let ycsb = &mut self.0;
+        match ycsb.keys.spec.mix.choose(&mut rng) {
+            Op::Scan => {
+                let spec = ycsb.keys.spec;
+                let n = ycsb.choose(&mut rng);
+                let len = spec.scan_len.sample(&mut rng);
+                if len == 0 {
+                    return None;
+                }
+                Some(TracedOp::ScanWithin {
+                    prefix: spec.prefix(n).into_bytes(),
+                    from: spec.key(n).into_bytes(),
+                    len: len as u64,
+                })
+            }
+            op => ycsb.draw(op, &mut rng),
+        }
+    }
+}
+
+/// The churn workload: like [`Ycsb`], but deletes remove the oldest record
+/// left, so that the records form a sliding window.
+pub struct Churn(pub Ycsb);
+
+impl Generator for Churn {
+    /// Skips deletes with no record left to delete.
+    fn next_op(&mut self, mut rng: &mut dyn RngCore) -> Option<TracedOp> {
+        let keys = &self.0.keys;
+        match keys.spec.mix.choose(&mut rng) {
+            Op::Delete => {
+                // Leave the newest record, so the DB never runs empty.
+                let n = keys
+                    .n_deleted
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                        (n + 1 < keys.n_keys.load(Ordering::Relaxed)).then_some(n + 1)
+                    })
+                    .ok()?;
+                Some(TracedOp::Delete(keys.spec.key(n).into_bytes()))
+            }
+            op => self.0.draw(op, &mut rng),
+        }
+    }
+}
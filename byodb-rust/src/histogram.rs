@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::time::Duration;
+
+use anyhow::{Error, Result, anyhow};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use hdrhistogram::serialization::{Deserializer, Serializer, V2DeflateSerializer};
+use serde::{Deserialize, Serialize};
+
+/// Number of significant decimal digits of the recorded latencies.
+const SIGFIG: u8 = 3;
+
+/// Width of the longest bar printed by [`Histogram::print`].
+const BAR_WIDTH: u64 = 40;
+
+/// Latencies of each type of operation, by name.
+pub type Latencies = BTreeMap<String, Histogram>;
+
+/// A histogram of latencies in nanoseconds, which grows to fit whatever it
+/// records.
+///
+/// It's saved in the standard compressed HdrHistogram format, encoded as
+/// base64, so that saved results can also be read by other HdrHistogram
+/// tools.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Histogram(hdrhistogram::Histogram<u64>);
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram(hdrhistogram::Histogram::new(SIGFIG).unwrap())
+    }
+}
+
+impl Histogram {
+    pub fn record(&mut self, latency: Duration) {
+        self.0
+            .record(latency.as_nanos().max(1) as u64)
+            .expect("auto-resizing histograms can record any value");
+    }
+
+    /// Forgets everything recorded so far.
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    /// Adds the counts of `other`, e.g. recorded by another thread.
+    pub fn merge(&mut self, other: &Histogram) {
+        self.0
+            .add(&other.0)
+            .expect("auto-resizing histograms can always be added");
+    }
+
+    pub fn count(&self) -> u64 {
+        self.0.len()
+    }
+
+    pub fn mean_us(&self) -> f64 {
+        self.0.mean() / 1e3
+    }
+
+    /// The latency that a fraction `quantile` of the latencies are at most.
+    pub fn quantile_us(&self, quantile: f64) -> f64 {
+        self.0.value_at_quantile(quantile) as f64 / 1e3
+    }
+
+    pub fn max_us(&self) -> f64 {
+        self.0.max() as f64 / 1e3
+    }
+
+    /// Number of latencies of at most `latency`, give or take the precision
+    /// of the histogram.
+    pub fn count_at_most(&self, latency: Duration) -> u64 {
+        self.0.count_between(0, latency.as_nanos() as u64)
+    }
+
+    /// The recorded latencies in nanoseconds, each rounded to the highest
+    /// equivalent value of its bucket, with their counts, from the lowest.
+    pub fn counts(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.0
+            .iter_recorded()
+            .map(|v| (v.value_iterated_to(), v.count_at_value()))
+    }
+
+    /// Samples the latency percentile curve, for plotting: the latency at
+    /// `1 - 10^-x` for `x` from 0 to `nines` in `steps_per_nine` steps each,
+    /// as `(x, latency in us)` pairs.
+    pub fn percentile_curve(&self, nines: u32, steps_per_nine: u32) -> Vec<(f64, f64)> {
+        (0..=nines * steps_per_nine)
+            .map(|i| {
+                let x = i as f64 / steps_per_nine as f64;
+                (x, self.quantile_us(1.0 - 10f64.powf(-x)))
+            })
+            .collect()
+    }
+
+    /// Prints the latencies as a bar chart of power-of-two buckets, one per
+    /// line, from the lowest to the highest non-empty one.
+    pub fn print(&self) {
+        if self.0.is_empty() {
+            return;
+        }
+        let buckets: Vec<_> = (self.0.min().max(1).ilog2()..=self.0.max().ilog2())
+            .map(|i| (i, self.0.count_between(1 << i, (1 << (i + 1)) - 1)))
+            .collect();
+        let total = self.count();
+        let max = buckets.iter().map(|&(_, c)| c).max().unwrap_or(0).max(1);
+        for (i, count) in buckets {
+            let line = format!(
+                "  {:>7} .. {:<7} {count:>10} {:>6.2}% {}",
+                format_ns(1 << i),
+                format_ns(1 << (i + 1)),
+                count as f64 * 100.0 / total as f64,
+                "#".repeat((count * BAR_WIDTH).div_ceil(max) as usize)
+            );
+            println!("{}", line.trim_end());
+        }
+    }
+}
+
+impl TryFrom<String> for Histogram {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        let bytes = BASE64_STANDARD.decode(s)?;
+        let histogram = Deserializer::new()
+            .deserialize(&mut Cursor::new(bytes))
+            .map_err(|err| anyhow!("invalid histogram: {err:?}"))?;
+        Ok(Histogram(histogram))
+    }
+}
+
+impl From<Histogram> for String {
+    fn from(histogram: Histogram) -> Self {
+        let mut bytes = Vec::new();
+        V2DeflateSerializer::new()
+            .serialize(&histogram.0, &mut bytes)
+            .expect("serializing into memory can't fail");
+        BASE64_STANDARD.encode(bytes)
+    }
+}
+
+/// Formats a duration in nanoseconds with the largest unit under it, like
+/// `612.3us`.
+pub fn format_ns(ns: u64) -> String {
+    match ns {
+        0..1_000 => format!("{ns}ns"),
+        1_000..1_000_000 => format!("{:.1}us", ns as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1}ms", ns as f64 / 1e6),
+        _ => format!("{:.1}s", ns as f64 / 1e9),
+    }
+}
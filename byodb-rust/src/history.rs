@@ -0,0 +1,182 @@
+//! A local SQLite database of the results of every run, to follow the
+//! performance of a backend over time.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+
+use crate::results::Document;
+use crate::workload::Workload;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    version TEXT NOT NULL,
+    os TEXT NOT NULL,
+    arch TEXT NOT NULL,
+    cpus INTEGER NOT NULL,
+    debug INTEGER NOT NULL,
+    backend TEXT NOT NULL,
+    workload TEXT NOT NULL,
+    threads INTEGER NOT NULL,
+    n_items INTEGER NOT NULL,
+    ops INTEGER NOT NULL,
+    elapsed_us INTEGER NOT NULL,
+    ops_per_sec REAL NOT NULL,
+    avg_latency_us REAL,
+    p50_us REAL,
+    p99_us REAL,
+    -- The whole result as JSON, including the config and histograms.
+    result TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS runs_by_scenario ON runs (backend, workload, timestamp);
+CREATE TABLE IF NOT EXISTS op_metrics (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    op TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    ops_per_sec REAL NOT NULL,
+    mean_us REAL NOT NULL,
+    p50_us REAL NOT NULL,
+    p90_us REAL NOT NULL,
+    p99_us REAL NOT NULL,
+    p999_us REAL NOT NULL,
+    max_us REAL NOT NULL,
+    PRIMARY KEY (run_id, op)
+);
+";
+
+fn open(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)
+        .with_context(|| format!("failed to open history {}", path.display()))?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+/// Appends the results of every backend in `document` to the history at
+/// `path`, creating it if needed.
+pub fn record(path: &Path, document: &Document) -> Result<()> {
+    let mut conn = open(path)?;
+    let txn = conn.transaction()?;
+    let env = &document.environment;
+    for result in &document.results {
+        let latency = result.total_latency();
+        let [p50_us, p99_us] =
+            [0.5, 0.99].map(|q| (latency.count() > 0).then(|| latency.quantile_us(q)));
+        txn.execute(
+            "INSERT INTO runs (timestamp, version, os, arch, cpus, debug, backend, workload,
+                threads, n_items, ops, elapsed_us, ops_per_sec, avg_latency_us, p50_us, p99_us,
+                result)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            params![
+                env.timestamp as i64,
+                env.version,
+                env.os,
+                env.arch,
+                env.cpus as i64,
+                env.debug,
+                result.backend,
+                result.config.workload.to_string(),
+                result.config.threads as i64,
+                result.n_items as i64,
+                result.ops as i64,
+                result.elapsed_us as i64,
+                result.throughput(),
+                result.avg_latency_us(),
+                p50_us,
+                p99_us,
+                serde_json::to_string(result)?,
+            ],
+        )?;
+        let run_id = txn.last_insert_rowid();
+        for (op, m) in &result.metrics {
+            txn.execute(
+                "INSERT INTO op_metrics (run_id, op, count, ops_per_sec, mean_us, p50_us, p90_us,
+                    p99_us, p999_us, max_us)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    run_id,
+                    op,
+                    m.count as i64,
+                    m.ops_per_sec,
+                    m.mean_us,
+                    m.p50_us,
+                    m.p90_us,
+                    m.p99_us,
+                    m.p999_us,
+                    m.max_us,
+                ],
+            )?;
+        }
+    }
+    txn.commit()?;
+    Ok(())
+}
+
+/// Prints the trend of the throughput and latencies of `backend` running
+/// `workload`, oldest first, over the last `limit` runs in the history at
+/// `path`. The latencies are of `op` if given, or of all operations.
+pub fn print_trend(
+    path: &Path,
+    backend: &str,
+    workload: Workload,
+    op: Option<&str>,
+    limit: usize,
+) -> Result<()> {
+    let conn = open(path)?;
+    let sql = match op {
+        None => {
+            "SELECT datetime(timestamp, 'unixepoch'), version, threads, ops_per_sec, p50_us, p99_us
+             FROM runs
+             WHERE backend = ?1 AND workload = ?2 AND ?3 IS NULL
+             ORDER BY timestamp DESC, id DESC LIMIT ?4"
+        }
+        Some(_) => {
+            "SELECT datetime(r.timestamp, 'unixepoch'), r.version, r.threads, m.ops_per_sec,
+                 m.p50_us, m.p99_us
+             FROM runs r JOIN op_metrics m ON m.run_id = r.id
+             WHERE r.backend = ?1 AND r.workload = ?2 AND m.op = ?3
+             ORDER BY r.timestamp DESC, r.id DESC LIMIT ?4"
+        }
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt
+        .query_map(
+            params![backend, workload.to_string(), op, limit as i64],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, f64>(3)?,
+                    row.get::<_, Option<f64>>(4)?,
+                    row.get::<_, Option<f64>>(5)?,
+                ))
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    rows.reverse();
+    if rows.is_empty() {
+        println!("No runs of {backend} with the {workload} workload in the history");
+        return Ok(());
+    }
+    println!(
+        "{:<20} {:>8} {:>8} {:>12} {:>8} {:>10} {:>10}",
+        "Time (UTC)", "Version", "Threads", "Ops/s", "Change", "p50(us)", "p99(us)"
+    );
+    let mut prev_ops_per_sec = None;
+    for (time, version, threads, ops_per_sec, p50_us, p99_us) in rows {
+        let change = match prev_ops_per_sec {
+            Some(prev) => format!("{:+.1}%", (ops_per_sec / prev - 1.0) * 100.0),
+            None => String::new(),
+        };
+        let [p50_us, p99_us] =
+            [p50_us, p99_us].map(|us| us.map_or("-".to_string(), |us| format!("{us:.3}")));
+        println!(
+            "{time:<20} {version:>8} {threads:>8} {ops_per_sec:>12.0} {change:>8} {p50_us:>10} {p99_us:>10}"
+        );
+        prev_ops_per_sec = Some(ops_per_sec);
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! What identifies the machine results are measured on, beyond its OS,
+//! architecture and number of CPUs.
+
+use std::ffi::CStr;
+use std::fs;
+
+/// The name of the machine, if known.
+pub fn hostname() -> Option<String> {
+    let mut name = [0; 256];
+    // SAFETY: gethostname writes at most `name.len()` bytes to `name`.
+    if unsafe { libc::gethostname(name.as_mut_ptr(), name.len()) } != 0 {
+        return None;
+    }
+    // Truncated names may not be NUL-terminated.
+    *name.last_mut().unwrap() = 0;
+    // SAFETY: `name` is NUL-terminated.
+    let name = unsafe { CStr::from_ptr(name.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// The release of the running kernel, e.g. `6.8.0-45-generic`, if known.
+pub fn kernel() -> Option<String> {
+    // SAFETY: uname only writes to `uts`, which is zeroable.
+    let uts = unsafe {
+        let mut uts: libc::utsname = std::mem::zeroed();
+        if libc::uname(&mut uts) != 0 {
+            return None;
+        }
+        uts
+    };
+    // SAFETY: uname NUL-terminates the fields it fills in.
+    let release = unsafe { CStr::from_ptr(uts.release.as_ptr()) };
+    Some(release.to_string_lossy().into_owned())
+}
+
+/// The model name of the CPUs, from `/proc/cpuinfo` on Linux, if known.
+pub fn cpu_model() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    // x86 calls it `model name`, some ARM kernels `Processor`.
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        matches!(key.trim(), "model name" | "Processor" | "cpu model")
+            .then(|| value.trim().to_string())
+            .filter(|model| !model.is_empty())
+    })
+}
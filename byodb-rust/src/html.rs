@@ -0,0 +1,296 @@
+use std::fmt::Write;
+
+use crate::results::{Document, RunResult};
+
+/// Size of the charts, in pixels.
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 320.0;
+/// Room around the plot area for the axes and their labels.
+const MARGIN: f64 = 56.0;
+
+/// Colors of the lines of a chart, in order.
+const COLORS: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f",
+];
+
+/// The highest latency percentile charted, as a number of nines: 99.99%.
+const NINES: u32 = 4;
+
+/// Renders `document` as a self-contained HTML page, with a summary table and,
+/// for every result, SVG charts of the latency percentiles of each type of
+/// operation and of the throughput over time.
+pub fn render(document: &Document) -> String {
+    let mut html = String::new();
+    html += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n";
+    html += "<title>db-cmp results</title>\n";
+    html += "<style>\n\
+        body { font-family: sans-serif; margin: 2em; }\n\
+        table { border-collapse: collapse; }\n\
+        th, td { border: 1px solid #ccc; padding: 0.25em 0.75em; text-align: right; }\n\
+        th:first-child, td:first-child, th:nth-child(2), td:nth-child(2) { text-align: left; }\n\
+        svg { margin: 0.5em 1em 0.5em 0; }\n\
+        svg text { font-size: 12px; }\n\
+        </style>\n</head>\n<body>\n";
+    html += "<h1>db-cmp results</h1>\n";
+    let env = &document.environment;
+    if !env.version.is_empty() {
+        writeln!(
+            html,
+            "<p>db-cmp {}, {} {}, {} CPUs{}</p>",
+            escape(&env.version),
+            escape(&env.os),
+            escape(&env.arch),
+            env.cpus,
+            if env.debug { ", debug build" } else { "" }
+        )
+        .unwrap();
+        let details: Vec<_> = [
+            env.hostname.as_ref().map(|host| format!("host {host}")),
+            env.cpu_model.clone(),
+            env.kernel.as_ref().map(|kernel| format!("kernel {kernel}")),
+            env.git_commit
+                .as_ref()
+                .map(|commit| format!("commit {commit}")),
+            env.rustc.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !details.is_empty() {
+            writeln!(html, "<p>{}</p>", escape(&details.join(", "))).unwrap();
+        }
+    }
+
+    html += "<table>\n<tr><th>Backend</th><th>Workload</th><th>Op</th><th>Throughput</th>\
+        <th>p50 (us)</th><th>p99 (us)</th></tr>\n";
+    for result in &document.results {
+        for (op, throughput, latency) in result.breakdown() {
+            let [p50_us, p99_us] = [0.5, 0.99].map(|q| match latency.count() {
+                0 => "-".to_string(),
+                _ => format!("{:.3}", latency.quantile_us(q)),
+            });
+            let unit = match op.as_str() {
+                "all" => result.config.workload.unit(),
+                _ => "op",
+            };
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{throughput:.0} {unit}s/s</td>\
+                <td>{p50_us}</td><td>{p99_us}</td></tr>",
+                escape(&result.backend),
+                result.config.workload,
+                escape(&op)
+            )
+            .unwrap();
+        }
+    }
+    for skipped in &document.skipped {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>all</td><td title=\"{}\">N/A</td><td>-</td><td>-</td></tr>",
+            escape(&skipped.backend),
+            skipped.workload,
+            escape(&skipped.reason)
+        )
+        .unwrap();
+    }
+    html += "</table>\n";
+
+    for result in &document.results {
+        writeln!(
+            html,
+            "<h2>{} &mdash; {}</h2>",
+            escape(&result.backend),
+            result.config.workload
+        )
+        .unwrap();
+        html += &percentile_chart(result);
+        html += &timeline_chart(result);
+        html += "\n";
+    }
+    html += "</body>\n</html>\n";
+    html
+}
+
+/// Charts the latency of each type of operation of `result`, and of the
+/// commits of their transactions, by percentile, on a scale that stretches
+/// the tail.
+fn percentile_chart(result: &RunResult) -> String {
+    let commit = result
+        .commit_latency
+        .as_ref()
+        .map(|latency| ("commit".to_string(), latency));
+    let series: Vec<_> = result
+        .latencies
+        .iter()
+        .map(|(op, latency)| (op.clone(), latency))
+        .chain(commit)
+        .filter(|(_, latency)| latency.count() > 0)
+        .map(|(op, latency)| (op, latency.percentile_curve(NINES, 20)))
+        .collect();
+    let x_ticks: Vec<_> = [0.0, 50.0, 90.0, 99.0, 99.9, 99.99]
+        .into_iter()
+        .map(|p: f64| (-(1.0 - p / 100.0).log10(), format!("{p}%")))
+        .collect();
+    line_chart("Latency by percentile", "latency (us)", &series, &x_ticks)
+}
+
+/// Charts the throughput of all threads of `result` between consecutive
+/// samples of its timeline.
+fn timeline_chart(result: &RunResult) -> String {
+    if result.timeline.len() < 2 {
+        return String::new();
+    }
+    let points: Vec<_> = result
+        .timeline
+        .windows(2)
+        .map(|pair| {
+            let (from, to) = (&pair[0], &pair[1]);
+            let secs = (to.elapsed_ms - from.elapsed_ms).max(1) as f64 / 1e3;
+            (
+                to.elapsed_ms as f64 / 1e3,
+                (to.ops - from.ops) as f64 / secs,
+            )
+        })
+        .collect();
+    let end = points.last().unwrap().0;
+    let x_ticks: Vec<_> = (0..=4)
+        .map(|i| {
+            let x = end * i as f64 / 4.0;
+            (x, format!("{x:.1}s"))
+        })
+        .collect();
+    let unit = result.config.workload.unit();
+    line_chart(
+        "Throughput over time",
+        &format!("{unit}s/s"),
+        &[("all threads".to_string(), points)],
+        &x_ticks,
+    )
+}
+
+/// Draws `series` of points as an SVG line chart, with a legend. The y axis
+/// starts at 0, and the x axis spans the `x_ticks`.
+fn line_chart(
+    title: &str,
+    y_label: &str,
+    series: &[(String, Vec<(f64, f64)>)],
+    x_ticks: &[(f64, String)],
+) -> String {
+    let x_min = x_ticks.first().map_or(0.0, |t| t.0);
+    let x_max = x_ticks
+        .last()
+        .map_or(1.0, |t| t.0)
+        .max(x_min + f64::EPSILON);
+    let y_max = series
+        .iter()
+        .flat_map(|(_, points)| points.iter().map(|p| p.1))
+        .fold(0.0, f64::max)
+        .max(f64::EPSILON);
+    let (plot_w, plot_h) = (WIDTH - 2.0 * MARGIN, HEIGHT - 2.0 * MARGIN);
+    let x_pos = |x: f64| MARGIN + (x - x_min) / (x_max - x_min) * plot_w;
+    let y_pos = |y: f64| HEIGHT - MARGIN - y / y_max * plot_h;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\">"
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<text x=\"{}\" y=\"20\" text-anchor=\"middle\" font-weight=\"bold\">{}</text>",
+        WIDTH / 2.0,
+        escape(title)
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<text x=\"14\" y=\"{}\" text-anchor=\"middle\" transform=\"rotate(-90 14 {})\">{}</text>",
+        HEIGHT / 2.0,
+        HEIGHT / 2.0,
+        escape(y_label)
+    )
+    .unwrap();
+    // Axes, with the ticks of the x axis and 5 of the y axis.
+    writeln!(
+        svg,
+        "<path d=\"M{MARGIN} {MARGIN} V{} H{}\" fill=\"none\" stroke=\"#333\"/>",
+        HEIGHT - MARGIN,
+        WIDTH - MARGIN
+    )
+    .unwrap();
+    for (x, label) in x_ticks {
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            x_pos(*x),
+            HEIGHT - MARGIN + 16.0,
+            escape(label)
+        )
+        .unwrap();
+    }
+    for i in 0..=5 {
+        let y = y_max * i as f64 / 5.0;
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            MARGIN - 4.0,
+            y_pos(y) + 4.0,
+            format_tick(y)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<path d=\"M{MARGIN} {:.1} H{}\" stroke=\"#eee\"/>",
+            y_pos(y),
+            WIDTH - MARGIN
+        )
+        .unwrap();
+    }
+    for (i, (name, points)) in series.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let d: Vec<_> = points
+            .iter()
+            .enumerate()
+            .map(|(j, &(x, y))| {
+                let cmd = if j == 0 { 'M' } else { 'L' };
+                format!("{cmd}{:.1} {:.1}", x_pos(x), y_pos(y))
+            })
+            .collect();
+        writeln!(
+            svg,
+            "<path d=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"/>",
+            d.join(" ")
+        )
+        .unwrap();
+        let legend_y = MARGIN + 16.0 * i as f64;
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{legend_y}\" fill=\"{color}\">{}</text>",
+            MARGIN + 8.0,
+            escape(name)
+        )
+        .unwrap();
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+/// Formats a tick of the y axis compactly, e.g. `1.5k` for 1500.
+fn format_tick(y: f64) -> String {
+    match y {
+        y if y >= 1e6 => format!("{:.1}M", y / 1e6),
+        y if y >= 1e3 => format!("{:.1}k", y / 1e3),
+        y if y >= 10.0 => format!("{y:.0}"),
+        y => format!("{y:.2}"),
+    }
+}
+
+/// Escapes `s` for use in HTML text or attributes.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,32 @@
+//! Stopping a run early, e.g. on Ctrl-C, while keeping its results so far.
+
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks every thread of the run to stop after its current iteration, as if
+/// its [`Budget`](crate::config::Budget) was spent. A second interrupt
+/// exits right away.
+pub fn interrupt() {
+    if INTERRUPTED.swap(true, Ordering::Relaxed) {
+        process::exit(130);
+    }
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Interrupts the run on Ctrl-C (SIGINT) or SIGTERM.
+pub fn install_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if !is_interrupted() {
+            eprintln!("Interrupted, stopping the run (interrupt again to exit right away)");
+        }
+        interrupt();
+    })
+    .context("failed to install the Ctrl-C handler")
+}
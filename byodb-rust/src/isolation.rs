@@ -0,0 +1,688 @@
+//! Checks of snapshot isolation, in the spirit of Elle: writers
+//! read-modify-write a few keys per transaction, each version naming the
+//! transaction that wrote it and the version it overwrote, while readers read
+//! every key in a snapshot. The history of what each transaction read and
+//! wrote, and when, is then checked for anomalies.
+//!
+//! Versions are stored as `<txn>:<prev>`, where `txn` is the id of the
+//! transaction that wrote it, from 1, and `prev` the id of the version it
+//! overwrote, 0 being the seeded one. Following the `prev` of every key gives
+//! the order of its versions.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Instant;
+
+use anyhow::{Context, Result, anyhow, ensure};
+use rand::Rng;
+use rand::seq::index;
+
+use crate::backend::Backend;
+use crate::cli::IsolationArgs;
+use crate::{db, ops};
+
+/// Maximum number of keys a writer modifies per transaction.
+const TXN_KEYS: usize = 3;
+
+/// Fraction of the writers' transactions aborted rather than committed.
+const ABORT_FRACTION: f64 = 0.15;
+
+fn key(k: usize) -> String {
+    format!("isolation-{k:04}")
+}
+
+/// What a transaction read from a key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Read {
+    Version { txn: u64, prev: u64 },
+    Missing,
+    Garbage,
+}
+
+impl Read {
+    fn parse(value: Option<&[u8]>) -> Self {
+        let Some(value) = value else {
+            return Read::Missing;
+        };
+        let version = str::from_utf8(value).ok().and_then(|value| {
+            let (txn, prev) = value.split_once(':')?;
+            Some(Read::Version {
+                txn: txn.parse().ok()?,
+                prev: prev.parse().ok()?,
+            })
+        });
+        version.unwrap_or(Read::Garbage)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Committed,
+    Aborted,
+    /// The commit failed, e.g. on a conflict, so its writes may or may not
+    /// be visible.
+    Failed,
+}
+
+/// A transaction of the history, with the times it began, started to commit
+/// and ended, in nanoseconds since the history began.
+#[derive(Debug)]
+struct Txn {
+    /// Id of a writer's transaction, or 0 for a reader's.
+    id: u64,
+    begin: u64,
+    /// When a writer called commit, or `u64::MAX` if it didn't.
+    commit: u64,
+    end: u64,
+    status: Status,
+    /// The keys read, and what was read from them, in order.
+    reads: Vec<(usize, Read)>,
+}
+
+/// What every transaction of a run did.
+pub struct History {
+    keys: usize,
+    writers: Vec<Txn>,
+    readers: Vec<Txn>,
+}
+
+impl History {
+    pub fn transactions(&self) -> usize {
+        self.writers.len() + self.readers.len()
+    }
+}
+
+/// Seeds `keys` keys in `db`, then runs `writers` threads that each run
+/// `txns` transactions, and `readers` threads that read every key in a
+/// snapshot until the writers are done. Readers only take a snapshot once
+/// a writer's transaction ended since their last one, so that the history
+/// grows with the writes.
+pub fn record(
+    db: &dyn Backend,
+    keys: usize,
+    writers: usize,
+    readers: usize,
+    txns: u64,
+    seed: u64,
+) -> Result<History> {
+    let mut t = db.begin_rw()?;
+    for k in 0..keys {
+        t.put(key(k).as_bytes(), b"0:0")?;
+    }
+    t.commit()?;
+
+    let start_time = Instant::now();
+    let now = || start_time.elapsed().as_nanos() as u64;
+    let done = AtomicBool::new(false);
+    let ended = AtomicU64::new(0);
+    let ended = &ended;
+    thread::scope(|s| {
+        let reader_threads: Vec<_> = (0..readers)
+            .map(|_| {
+                s.spawn(|| -> Result<Vec<Txn>> {
+                    let mut history = Vec::new();
+                    let mut last_ended = None;
+                    while !done.load(Ordering::Relaxed) {
+                        if last_ended == Some(ended.load(Ordering::Relaxed)) {
+                            thread::yield_now();
+                            continue;
+                        }
+                        last_ended = Some(ended.load(Ordering::Relaxed));
+                        let begin = now();
+                        let t = db.begin_ro()?;
+                        let mut reads = Vec::with_capacity(keys);
+                        for k in 0..keys {
+                            reads.push((k, Read::parse(t.get(key(k).as_bytes())?.as_deref())));
+                        }
+                        drop(t);
+                        history.push(Txn {
+                            id: 0,
+                            begin,
+                            commit: u64::MAX,
+                            end: now(),
+                            status: Status::Committed,
+                            reads,
+                        });
+                    }
+                    Ok(history)
+                })
+            })
+            .collect();
+        let writer_threads: Vec<_> = (0..writers as u64)
+            .map(|w| {
+                s.spawn(move || -> Result<Vec<Txn>> {
+                    let mut rng = ops::stream(seed, w);
+                    let mut history = Vec::new();
+                    for i in 0..txns {
+                        let id = w * txns + i + 1;
+                        let n_keys = rng.random_range(1..=TXN_KEYS.min(keys));
+                        let begin = now();
+                        let mut t = db.begin_rw()?;
+                        let mut reads = Vec::with_capacity(n_keys);
+                        for k in index::sample(&mut rng, keys, n_keys) {
+                            let read = Read::parse(t.get(key(k).as_bytes())?.as_deref());
+                            let prev = match read {
+                                Read::Version { txn, .. } => txn,
+                                _ => 0,
+                            };
+                            t.put(key(k).as_bytes(), format!("{id}:{prev}").as_bytes())?;
+                            reads.push((k, read));
+                        }
+                        let (commit, status) = if rng.random_bool(ABORT_FRACTION) {
+                            t.abort()?;
+                            (u64::MAX, Status::Aborted)
+                        } else {
+                            let commit = now();
+                            match t.commit() {
+                                Ok(()) => (commit, Status::Committed),
+                                Err(_) => (commit, Status::Failed),
+                            }
+                        };
+                        history.push(Txn {
+                            id,
+                            begin,
+                            commit,
+                            end: now(),
+                            status,
+                            reads,
+                        });
+                        ended.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Ok(history)
+                })
+            })
+            .collect();
+        // The readers only stop once the writers are done, even if one fails.
+        let writer_results: Vec<_> = writer_threads.into_iter().map(|t| t.join()).collect();
+        done.store(true, Ordering::Relaxed);
+        let reader_results: Vec<_> = reader_threads.into_iter().map(|t| t.join()).collect();
+        let mut history = History {
+            keys,
+            writers: Vec::new(),
+            readers: Vec::new(),
+        };
+        for txns in writer_results {
+            let txns = txns.map_err(|_| anyhow!("a writer panicked"))?;
+            history.writers.extend(txns.context("a writer failed")?);
+        }
+        for txns in reader_results {
+            let txns = txns.map_err(|_| anyhow!("a reader panicked"))?;
+            history.readers.extend(txns.context("a reader failed")?);
+        }
+        Ok(history)
+    })
+}
+
+/// A kind of anomaly, i.e. of what snapshot isolation rules out, except for
+/// stale reads, which it allows unless it's strong.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anomaly {
+    /// A read of a version written by an aborted transaction (G1a).
+    AbortedRead,
+    /// A read of a version whose transaction hadn't started to commit by the
+    /// time the read ended (G1b).
+    UncommittedRead,
+    /// A snapshot with some of the writes of a transaction, but older
+    /// versions of other keys it wrote.
+    FracturedRead,
+    /// Two committed transactions that overwrote the same version.
+    LostUpdate,
+    /// A snapshot that misses a transaction that committed before it began,
+    /// which only strong snapshot isolation, or linearizability, rules out.
+    StaleRead,
+    /// A read of a missing key, or of a value that no transaction wrote.
+    GarbageRead,
+}
+
+impl Anomaly {
+    pub const ALL: [Anomaly; 6] = [
+        Anomaly::AbortedRead,
+        Anomaly::UncommittedRead,
+        Anomaly::FracturedRead,
+        Anomaly::LostUpdate,
+        Anomaly::StaleRead,
+        Anomaly::GarbageRead,
+    ];
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Anomaly::AbortedRead => "aborted read",
+            Anomaly::UncommittedRead => "uncommitted read",
+            Anomaly::FracturedRead => "fractured read",
+            Anomaly::LostUpdate => "lost update",
+            Anomaly::StaleRead => "stale read",
+            Anomaly::GarbageRead => "garbage read",
+        })
+    }
+}
+
+/// The anomalies found in a history: the number of transactions with each
+/// kind, indexed like [`Anomaly::ALL`], and the first of them.
+#[derive(Default)]
+pub struct Anomalies {
+    pub counts: [u64; 6],
+    pub examples: [Option<String>; 6],
+}
+
+impl Anomalies {
+    fn record(&mut self, anomaly: Anomaly, example: impl FnOnce() -> String) {
+        let i = anomaly as usize;
+        self.counts[i] += 1;
+        if self.examples[i].is_none() {
+            self.examples[i] = Some(example());
+        }
+    }
+
+    pub fn count(&self, anomaly: Anomaly) -> u64 {
+        self.counts[anomaly as usize]
+    }
+
+    /// Whether the history is consistent with snapshot isolation.
+    pub fn is_snapshot_isolated(&self) -> bool {
+        Anomaly::ALL
+            .iter()
+            .all(|&anomaly| anomaly == Anomaly::StaleRead || self.count(anomaly) == 0)
+    }
+}
+
+/// The position of every version of a key in its order, from 0 for the
+/// seeded one, following the `prev` of each version.
+fn depths(versions: &HashMap<u64, u64>) -> HashMap<u64, u64> {
+    let mut depths = HashMap::from([(0, 0)]);
+    for &txn in versions.keys() {
+        // Walk back to a version of known depth, then number the way back.
+        let mut path = Vec::new();
+        let mut at = txn;
+        while !depths.contains_key(&at) {
+            path.push(at);
+            match versions.get(&at) {
+                // Cycles, and versions overwriting unknown ones, are garbage
+                // anyway.
+                Some(&prev) if !path.contains(&prev) => at = prev,
+                _ => break,
+            }
+        }
+        let mut depth = depths.get(&at).copied().unwrap_or(0);
+        for txn in path.into_iter().rev() {
+            depth += 1;
+            depths.insert(txn, depth);
+        }
+    }
+    depths
+}
+
+/// Checks `history` for anomalies.
+pub fn check(history: &History) -> Anomalies {
+    let mut anomalies = Anomalies::default();
+    let writers: HashMap<u64, &Txn> = history.writers.iter().map(|t| (t.id, t)).collect();
+    // The versions of every key, by the transaction that wrote them, and the
+    // version they overwrote, of the transactions that may have committed.
+    let mut versions = vec![HashMap::new(); history.keys];
+    let mut overwritten = vec![HashMap::new(); history.keys];
+    for t in &history.writers {
+        if t.status == Status::Aborted {
+            continue;
+        }
+        for &(k, read) in &t.reads {
+            let prev = match read {
+                Read::Version { txn, .. } => txn,
+                _ => 0,
+            };
+            versions[k].insert(t.id, prev);
+            if t.status == Status::Committed
+                && let Some(other) = overwritten[k].insert(prev, t.id)
+            {
+                anomalies.record(Anomaly::LostUpdate, || {
+                    format!(
+                        "transactions {other} and {} both overwrote version {prev} of {}",
+                        t.id,
+                        key(k)
+                    )
+                });
+            }
+        }
+    }
+    let depths: Vec<_> = versions.iter().map(depths).collect();
+    // The deepest version of every key committed by then, by the time its
+    // transaction ended.
+    let mut committed: Vec<Vec<(u64, u64)>> = vec![Vec::new(); history.keys];
+    for t in &history.writers {
+        if t.status == Status::Committed {
+            for &(k, _) in &t.reads {
+                committed[k].push((t.end, depths[k][&t.id]));
+            }
+        }
+    }
+    for committed in &mut committed {
+        committed.sort_unstable();
+        let mut deepest = 0;
+        for (_, depth) in committed.iter_mut() {
+            deepest = deepest.max(*depth);
+            *depth = deepest;
+        }
+    }
+
+    for (t, is_reader) in Iterator::chain(
+        history.writers.iter().map(|t| (t, false)),
+        history.readers.iter().map(|t| (t, true)),
+    ) {
+        let who = match is_reader {
+            true => "a reader".to_string(),
+            false => format!("transaction {}", t.id),
+        };
+        let mut found = Vec::new();
+        let mut found_in = |anomaly, example: &dyn Fn() -> String| {
+            if !found.contains(&anomaly) {
+                found.push(anomaly);
+                anomalies.record(anomaly, example);
+            }
+        };
+        for &(k, read) in &t.reads {
+            let (txn, prev) = match read {
+                Read::Version { txn: 0, prev: 0 } => continue,
+                Read::Version { txn, prev } => (txn, prev),
+                Read::Missing | Read::Garbage => {
+                    found_in(Anomaly::GarbageRead, &|| {
+                        format!("{who} read {read:?} from {}", key(k))
+                    });
+                    continue;
+                }
+            };
+            let Some(writer) = writers.get(&txn).filter(|w| {
+                w.reads.iter().any(|&(w_k, w_read)| {
+                    let w_prev = match w_read {
+                        Read::Version { txn, .. } => txn,
+                        _ => 0,
+                    };
+                    w_k == k && w_prev == prev
+                })
+            }) else {
+                found_in(Anomaly::GarbageRead, &|| {
+                    format!(
+                        "{who} read version {txn}:{prev} of {}, which wasn't written",
+                        key(k)
+                    )
+                });
+                continue;
+            };
+            if writer.status == Status::Aborted {
+                found_in(Anomaly::AbortedRead, &|| {
+                    format!("{who} read {} from aborted transaction {txn}", key(k))
+                });
+            } else if writer.commit > t.end {
+                found_in(Anomaly::UncommittedRead, &|| {
+                    format!(
+                        "{who} read {} from transaction {txn} {}ns before it committed",
+                        key(k),
+                        writer.commit - t.end
+                    )
+                });
+            }
+        }
+        if !is_reader {
+            continue;
+        }
+        // Only readers read every key, in the same snapshot.
+        let depth_read = |k: usize| match t.reads[k].1 {
+            Read::Version { txn, .. } => depths[k].get(&txn).copied(),
+            _ => None,
+        };
+        for &(k, read) in &t.reads {
+            let Read::Version { txn, .. } = read else {
+                continue;
+            };
+            let Some(writer) = writers.get(&txn).filter(|w| w.status != Status::Aborted) else {
+                continue;
+            };
+            for &(other_k, _) in &writer.reads {
+                let (Some(depth), Some(seen)) = (depths[other_k].get(&txn), depth_read(other_k))
+                else {
+                    continue;
+                };
+                if seen < *depth {
+                    found_in(Anomaly::FracturedRead, &|| {
+                        format!(
+                            "{who} read {} from transaction {txn}, but an older version of {}",
+                            key(k),
+                            key(other_k)
+                        )
+                    });
+                }
+            }
+        }
+        for (k, committed) in committed.iter().enumerate() {
+            let before = committed.partition_point(|&(end, _)| end < t.begin);
+            let (Some(&(_, deepest)), Some(seen)) =
+                (before.checked_sub(1).map(|i| &committed[i]), depth_read(k))
+            else {
+                continue;
+            };
+            if seen < deepest {
+                found_in(Anomaly::StaleRead, &|| {
+                    format!(
+                        "{who} read an older version of {} than one committed before it began",
+                        key(k)
+                    )
+                });
+            }
+        }
+    }
+    anomalies
+}
+
+/// Runs `db-cmp isolation`: records a history on each backend of `args`,
+/// and fails if any has anomalies of snapshot isolation.
+pub fn command(args: IsolationArgs) -> Result<()> {
+    ensure!(args.keys > 0, "isolation needs keys");
+    let config = args.backend.into_config();
+    let mut results = Vec::new();
+    for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        let (db, _temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+        let history = record(
+            &*db,
+            args.keys,
+            args.writers,
+            args.readers,
+            args.txns,
+            config.seed,
+        )
+        .with_context(|| format!("failed to check {backend}"))?;
+        results.push((backend, history.transactions(), check(&history)));
+    }
+
+    print!("{:<10} {:>8}", "Backend", "Txns");
+    for anomaly in Anomaly::ALL {
+        print!(" {:>17}", format!("{anomaly}s"));
+    }
+    println!();
+    for (backend, txns, anomalies) in &results {
+        print!("{backend:<10} {txns:>8}");
+        for anomaly in Anomaly::ALL {
+            print!(" {:>17}", anomalies.count(anomaly));
+        }
+        println!();
+    }
+    for (backend, _, anomalies) in &results {
+        for example in anomalies.examples.iter().flatten() {
+            println!("{backend}: {example}");
+        }
+    }
+    let anomalous: Vec<_> = results
+        .iter()
+        .filter(|(_, _, anomalies)| !anomalies.is_snapshot_isolated())
+        .map(|(backend, _, _)| backend.as_str())
+        .collect();
+    ensure!(
+        anomalous.is_empty(),
+        "snapshot isolation anomalies found in {}",
+        anomalous.join(", ")
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(txn: u64, prev: u64) -> Read {
+        Read::Version { txn, prev }
+    }
+
+    /// A writer's transaction that read `reads`, and overwrote the keys
+    /// with versions of its own, running from `begin` to `end`.
+    fn writer(id: u64, begin: u64, end: u64, status: Status, reads: &[(usize, Read)]) -> Txn {
+        Txn {
+            id,
+            begin,
+            commit: match status {
+                Status::Aborted => u64::MAX,
+                _ => end - 1,
+            },
+            end,
+            status,
+            reads: reads.to_vec(),
+        }
+    }
+
+    /// A reader's snapshot of every key, from `begin` to `end`.
+    fn reader(begin: u64, end: u64, reads: &[Read]) -> Txn {
+        Txn {
+            id: 0,
+            begin,
+            commit: u64::MAX,
+            end,
+            status: Status::Committed,
+            reads: reads.iter().copied().enumerate().collect(),
+        }
+    }
+
+    fn check_history(writers: Vec<Txn>, readers: Vec<Txn>) -> Anomalies {
+        check(&History {
+            keys: 2,
+            writers,
+            readers,
+        })
+    }
+
+    /// Whether `anomalies` are exactly one of `anomaly`.
+    fn only(anomalies: &Anomalies, anomaly: Anomaly) -> bool {
+        Anomaly::ALL
+            .iter()
+            .all(|&a| anomalies.count(a) == (a == anomaly) as u64)
+    }
+
+    /// Transaction 1 overwrites the seeded versions of both keys.
+    fn both_keys(status: Status) -> Txn {
+        let seeded = version(0, 0);
+        writer(1, 10, 20, status, &[(0, seeded), (1, seeded)])
+    }
+
+    #[test]
+    fn serial_history_has_no_anomalies() {
+        let anomalies = check_history(
+            vec![
+                both_keys(Status::Committed),
+                writer(2, 30, 40, Status::Committed, &[(0, version(1, 0))]),
+            ],
+            vec![
+                reader(0, 5, &[version(0, 0), version(0, 0)]),
+                reader(25, 28, &[version(1, 0), version(1, 0)]),
+                reader(50, 60, &[version(2, 1), version(1, 0)]),
+            ],
+        );
+        assert!(Anomaly::ALL.iter().all(|&a| anomalies.count(a) == 0));
+        assert!(anomalies.is_snapshot_isolated());
+    }
+
+    #[test]
+    fn finds_aborted_reads() {
+        let anomalies = check_history(
+            vec![both_keys(Status::Aborted)],
+            vec![reader(30, 40, &[version(1, 0), version(1, 0)])],
+        );
+        assert!(only(&anomalies, Anomaly::AbortedRead));
+        assert!(!anomalies.is_snapshot_isolated());
+    }
+
+    #[test]
+    fn finds_uncommitted_reads() {
+        let anomalies = check_history(
+            vec![both_keys(Status::Committed)],
+            vec![reader(5, 15, &[version(1, 0), version(1, 0)])],
+        );
+        assert!(only(&anomalies, Anomaly::UncommittedRead));
+    }
+
+    #[test]
+    fn finds_fractured_reads() {
+        let anomalies = check_history(
+            vec![both_keys(Status::Committed)],
+            vec![reader(30, 40, &[version(1, 0), version(0, 0)])],
+        );
+        // Missing transaction 1 on the second key is also a stale read.
+        assert_eq!(anomalies.count(Anomaly::FracturedRead), 1);
+        assert!(!anomalies.is_snapshot_isolated());
+    }
+
+    #[test]
+    fn finds_lost_updates() {
+        let seeded = version(0, 0);
+        let anomalies = check_history(
+            vec![
+                writer(1, 10, 20, Status::Committed, &[(0, seeded)]),
+                writer(2, 12, 22, Status::Committed, &[(0, seeded)]),
+            ],
+            Vec::new(),
+        );
+        assert!(only(&anomalies, Anomaly::LostUpdate));
+    }
+
+    #[test]
+    fn failed_commits_are_not_lost_updates() {
+        let seeded = version(0, 0);
+        let anomalies = check_history(
+            vec![
+                writer(1, 10, 20, Status::Committed, &[(0, seeded)]),
+                writer(2, 12, 22, Status::Failed, &[(0, seeded)]),
+            ],
+            Vec::new(),
+        );
+        assert!(anomalies.is_snapshot_isolated());
+        assert_eq!(anomalies.count(Anomaly::LostUpdate), 0);
+    }
+
+    #[test]
+    fn stale_reads_are_snapshot_isolated() {
+        let anomalies = check_history(
+            vec![both_keys(Status::Committed)],
+            vec![reader(30, 40, &[version(0, 0), version(0, 0)])],
+        );
+        assert!(only(&anomalies, Anomaly::StaleRead));
+        assert!(anomalies.is_snapshot_isolated());
+    }
+
+    #[test]
+    fn finds_garbage_reads() {
+        for read in [Read::Missing, Read::Garbage, version(3, 0), version(1, 1)] {
+            let anomalies = check_history(
+                vec![both_keys(Status::Committed)],
+                vec![reader(30, 40, &[version(1, 0), read])],
+            );
+            assert_eq!(anomalies.count(Anomaly::GarbageRead), 1, "{read:?}");
+        }
+    }
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(Read::parse(Some(b"12:3")), version(12, 3));
+        assert_eq!(Read::parse(None), Read::Missing);
+        for garbage in [&b""[..], b"12", b"12:", b"x:3", b"1:2:3"] {
+            assert_eq!(Read::parse(Some(garbage)), Read::Garbage);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! The layout of a byodb file, read straight from the file: the shape of its
+//! B+ tree and what its pages are used for.
+//!
+//! A file starts with a 64-byte meta page, followed by pages of
+//! [`PAGE_SIZE`] bytes numbered from 0. The meta page holds the root page of
+//! the tree, the number of pages allocated so far, and where the free list
+//! starts and ends. Every node of the tree starts with its type and its
+//! number of keys, as little-endian 16-bit integers. Values are stored in
+//! the leaves, so there are no overflow pages.
+
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail, ensure};
+
+/// Size of the pages of the file, like byodb's.
+pub const PAGE_SIZE: usize = if cfg!(all(target_arch = "aarch64", target_os = "macos")) {
+    16384
+} else {
+    4096
+};
+
+/// Size of the meta page at the start of the file.
+const META_SIZE: usize = 64;
+
+/// Node types of the pages of the tree.
+const LEAF: usize = 1;
+const INTERNAL: usize = 2;
+
+/// The nodes at a level of the tree.
+#[derive(Debug, Default)]
+pub struct Level {
+    pub nodes: u64,
+    pub keys: u64,
+    /// Bytes of the pages of the nodes taken up by their headers, keys,
+    /// values and child pointers.
+    pub bytes: u64,
+}
+
+impl Level {
+    /// Average fraction of the pages of the nodes taken up.
+    pub fn fill(&self) -> f64 {
+        self.bytes as f64 / (self.nodes * PAGE_SIZE as u64) as f64
+    }
+}
+
+/// The layout of a file.
+#[derive(Debug)]
+pub struct Layout {
+    /// Number of pages allocated so far, in use or not.
+    pub pages: u64,
+    /// The levels of the tree, from the root's to the leaves'.
+    pub levels: Vec<Level>,
+    /// Number of pages the free list holds for reuse.
+    pub free_pages: u64,
+    /// Number of pages holding the free list itself.
+    pub free_list_pages: u64,
+}
+
+impl Layout {
+    pub fn tree_pages(&self) -> u64 {
+        self.levels.iter().map(|level| level.nodes).sum()
+    }
+
+    /// Number of pages neither in the tree nor in the free list: those freed
+    /// by commits but not reclaimed yet, in case readers still use them.
+    pub fn unreachable_pages(&self) -> u64 {
+        self.pages - self.tree_pages() - self.free_pages - self.free_list_pages
+    }
+
+    pub fn leaves(&self) -> &Level {
+        self.levels.last().expect("a tree has at least a leaf")
+    }
+}
+
+/// Reads the layout of the file at `path`, as of its last commit.
+pub fn read(path: &Path) -> Result<Layout> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    read_file(&file).with_context(|| format!("failed to read the layout of {}", path.display()))
+}
+
+fn read_file(file: &File) -> Result<Layout> {
+    let mut meta = [0; META_SIZE];
+    file.read_exact_at(&mut meta, 0)?;
+    let [root, pages, head_page, head_seq, tail_page, tail_seq] =
+        [0, 1, 2, 3, 4, 5].map(|i| u64_at(&meta, i * 8));
+    let read_page = |page_num: u64, page: &mut [u8]| -> Result<()> {
+        ensure!(
+            page_num < pages,
+            "page {page_num} is past the {pages} allocated ones"
+        );
+        file.read_exact_at(page, META_SIZE as u64 + page_num * PAGE_SIZE as u64)
+            .with_context(|| format!("failed to read page {page_num}"))
+    };
+
+    let mut page = vec![0; PAGE_SIZE];
+    let mut levels = Vec::new();
+    let mut frontier = vec![root];
+    while !frontier.is_empty() {
+        ensure!(
+            levels.len() < 64,
+            "the tree is deeper than it can be, so it must have a cycle"
+        );
+        let mut level = Level::default();
+        let mut children = Vec::new();
+        for page_num in frontier {
+            read_page(page_num, &mut page)?;
+            let n = u16_at(&page, 2);
+            level.nodes += 1;
+            level.keys += n as u64;
+            level.bytes += match u16_at(&page, 0) {
+                LEAF => 4 + n * 2 + offset(&page, 4, n),
+                INTERNAL => {
+                    children.extend((0..n).map(|i| u64_at(&page, 4 + i * 8)));
+                    4 + n * 10 + offset(&page, 4 + n * 8, n)
+                }
+                other => bail!("page {page_num} has unknown node type {other}"),
+            } as u64;
+        }
+        levels.push(level);
+        frontier = children;
+    }
+
+    let mut free_list_pages = 1;
+    let mut node = head_page;
+    while node != tail_page {
+        ensure!(free_list_pages <= pages, "the free list has a cycle");
+        read_page(node, &mut page)?;
+        node = u64_at(&page, 0);
+        free_list_pages += 1;
+    }
+    Ok(Layout {
+        pages,
+        levels,
+        free_pages: tail_seq - head_seq,
+        free_list_pages,
+    })
+}
+
+/// Total size of the first `n` items of a node, from its table of offsets
+/// starting at `start`, which has none for the first item.
+fn offset(page: &[u8], start: usize, n: usize) -> usize {
+    if n == 0 {
+        0
+    } else {
+        u16_at(page, start + 2 * (n - 1))
+    }
+}
+
+fn u16_at(page: &[u8], at: usize) -> usize {
+    u16::from_le_bytes(page[at..at + 2].try_into().unwrap()) as usize
+}
+
+fn u64_at(page: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(page[at..at + 8].try_into().unwrap())
+}
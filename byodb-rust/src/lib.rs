@@ -0,0 +1,56 @@
+//! The harness behind the `db-cmp` binary, also used by its Criterion
+//! benches under `benches/`. Other programs can embed it too: [`runner`]
+//! runs the workloads of a [`config::Config`] on the backends, and
+//! [`results`] reports what they measured. Every subcommand of the binary is
+//! the `command` function of its module, taking its arguments from [`cli`].
+
+pub mod access_log;
+pub mod affinity;
+#[cfg(feature = "async")]
+pub mod async_driver;
+pub mod backend;
+pub mod bench;
+pub mod bulk_load;
+pub mod cli;
+pub mod config;
+pub mod crash;
+pub mod db;
+pub mod difftest;
+pub mod distribution;
+pub mod dump;
+pub mod gate;
+pub mod generator;
+pub mod histogram;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod host;
+pub mod html;
+pub mod interrupt;
+pub mod isolation;
+pub mod layout;
+pub mod long_reader;
+pub mod metrics;
+pub mod multi_process;
+pub mod net;
+pub mod ops;
+pub mod perf;
+pub mod plot;
+pub mod process;
+pub mod profile;
+pub mod progress;
+pub mod results;
+pub mod runner;
+pub mod seed;
+pub mod seed_cache;
+pub mod seek;
+pub mod shadow;
+pub mod spans;
+pub mod startup;
+pub mod stats;
+pub mod syscalls;
+pub mod timeline;
+pub mod trace;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod workload;
+pub mod writers;
@@ -0,0 +1,123 @@
+//! The cost of a long-running reader: while a read transaction stays open,
+//! MVCC backends have to keep every version it may read, however many times
+//! writers overwrite them, so their files and memory grow, and writers may
+//! slow down.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, ensure};
+
+use crate::backend::Backend;
+use crate::cli::LongReaderArgs;
+use crate::config::Config;
+use crate::writers::Writers;
+use crate::{db, process, seed};
+
+/// What writers churning a DB for a while measured.
+pub struct Phase {
+    /// Whether a read transaction was open for the whole phase.
+    pub held: bool,
+    /// Number of transactions committed by all writers.
+    pub commits: u64,
+    pub commits_per_sec: f64,
+    /// How much the files of the DB grew, at the end of the phase.
+    pub disk_growth: i64,
+    /// How much the resident memory of the process grew, at the end of the
+    /// phase.
+    pub rss_growth: i64,
+}
+
+/// Runs `writers` that overwrite their keys of `db`, stored at `path`, for
+/// `duration`, with a read transaction opened before they start and kept
+/// open until they're measured if `hold`, which backends whose readers
+/// block writers don't support.
+pub fn measure(
+    db: &Arc<dyn Backend>,
+    path: &Path,
+    writers: usize,
+    duration: Duration,
+    hold: bool,
+) -> Result<Phase> {
+    let disk_before = db::disk_usage(path)?;
+    let rss_before = process::rss_bytes().unwrap_or(0);
+    ensure!(
+        !(hold && db.readers_block_writers()),
+        "the writers would wait for the reader until it ends"
+    );
+    let reader = if hold {
+        let reader = db.begin_ro()?;
+        // Read, so that stores that only take their snapshot on the first
+        // read, like SQLite, take it now.
+        reader.get(b"")?;
+        Some(reader)
+    } else {
+        None
+    };
+    let running = Writers::start(db, writers, None);
+    thread::sleep(duration);
+    let outcome = running.stop()?;
+    let disk_growth = db::disk_usage(path)? as i64 - disk_before as i64;
+    let rss_growth = process::rss_bytes().unwrap_or(0) as i64 - rss_before as i64;
+    drop(reader);
+    Ok(Phase {
+        held: hold,
+        commits: outcome.commits,
+        commits_per_sec: outcome.commits as f64 / outcome.elapsed.as_secs_f64(),
+        disk_growth,
+        rss_growth,
+    })
+}
+
+/// Runs `db-cmp long-reader`: measures the writers of each backend of
+/// `args` with and without a reader holding a transaction open.
+pub fn command(args: LongReaderArgs) -> Result<()> {
+    ensure!(args.writers > 0, "long-reader needs writers");
+    let config = Config {
+        items: args.items,
+        ..args.backend.into_config()
+    };
+    let mib = |bytes: i64| bytes as f64 / (1 << 20) as f64;
+    println!(
+        "{:<10} {:<7} {:>10} {:>12} {:>17} {:>19}",
+        "Backend", "Reader", "Commits", "Commits/s", "DB growth (MiB)", "Memory growth (MiB)"
+    );
+    'backends: for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        let mut phases = Vec::new();
+        // Each phase on a DB of its own, so that the second one doesn't
+        // start with what the first one left.
+        for hold in [false, true] {
+            let (db, temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+            if db.readers_block_writers() {
+                println!("{backend}: skipped, since its readers block its writers");
+                continue 'backends;
+            }
+            seed::seed_db(seed::items(&config)?, &*db)?;
+            let db: Arc<dyn Backend> = Arc::from(db);
+            let path = db::temp_path(&temp_dir);
+            let phase = measure(&db, &path, args.writers, args.duration, hold)
+                .with_context(|| format!("failed to measure {backend}"))?;
+            println!(
+                "{backend:<10} {:<7} {:>10} {:>12.0} {:>17.2} {:>19.2}",
+                if phase.held { "held" } else { "none" },
+                phase.commits,
+                phase.commits_per_sec,
+                mib(phase.disk_growth),
+                mib(phase.rss_growth),
+            );
+            phases.push(phase);
+        }
+        let [without, with] = &phases[..] else {
+            unreachable!();
+        };
+        println!(
+            "{backend:<10} the reader changed the writers' throughput by {:+.1}%, and the growth of the DB by {:+.2}MiB",
+            (with.commits_per_sec / without.commits_per_sec - 1.0) * 100.0,
+            mib(with.disk_growth - without.disk_growth),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+//! A Prometheus `/metrics` endpoint of the progress of a run, to watch long
+//! runs alongside other dashboards.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::histogram::Histogram;
+use crate::process::rss_bytes;
+use crate::timeline::{Monitor, Sample};
+
+/// Upper bounds of the buckets of the latency histograms, in seconds.
+const BUCKETS: [f64; 19] = [
+    1e-6, 2.5e-6, 5e-6, 1e-5, 2.5e-5, 5e-5, 1e-4, 2.5e-4, 5e-4, 1e-3, 2.5e-3, 5e-3, 1e-2, 2.5e-2,
+    5e-2, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// Name, help and value of a gauge of every run.
+type Gauge = (&'static str, &'static str, fn(&Sample) -> f64);
+
+const GAUGES: [Gauge; 4] = [
+    (
+        "dbcmp_elapsed_seconds",
+        "Time since the start of the measured phase.",
+        |s| s.elapsed_ms as f64 / 1e3,
+    ),
+    (
+        "dbcmp_keys",
+        "Records of op-based workloads, including the ones inserted.",
+        |s| s.n_keys as f64,
+    ),
+    (
+        "dbcmp_disk_bytes",
+        "Disk usage of the DB, if tracked.",
+        |s| s.disk_bytes as f64,
+    ),
+    (
+        "dbcmp_live_bytes",
+        "Total size of the keys and values of the records, if tracked.",
+        |s| s.live_bytes as f64,
+    ),
+];
+
+/// The latest state of every backend and workload run so far, labelled by
+/// them.
+type Runs = BTreeMap<(String, String), Run>;
+
+#[derive(Default)]
+struct Run {
+    sample: Option<Sample>,
+    latency: Histogram,
+}
+
+/// Serves the metrics of every run monitored by [`Server::monitor`] over
+/// HTTP, until the process exits.
+pub struct Server {
+    runs: Arc<Mutex<Runs>>,
+}
+
+impl Server {
+    /// Listens on `addr` in the background.
+    pub fn start(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("failed to listen for metrics on {addr}"))?;
+        let runs = Arc::new(Mutex::new(Runs::new()));
+        thread::spawn({
+            let runs = runs.clone();
+            move || {
+                for stream in listener.incoming().flatten() {
+                    // A scraper that misbehaves only misses its metrics.
+                    let _ = respond(stream, &runs);
+                }
+            }
+        });
+        Ok(Server { runs })
+    }
+
+    /// Monitors `backend` running `workload`, replacing the metrics of any
+    /// previous run of both.
+    pub fn monitor(&self, backend: &str, workload: &str) -> Box<dyn Monitor> {
+        let key = (backend.to_string(), workload.to_string());
+        self.runs
+            .lock()
+            .unwrap()
+            .insert(key.clone(), Run::default());
+        Box::new(Exporter {
+            key,
+            runs: self.runs.clone(),
+        })
+    }
+}
+
+struct Exporter {
+    key: (String, String),
+    runs: Arc<Mutex<Runs>>,
+}
+
+impl Monitor for Exporter {
+    fn update(&mut self, sample: &Sample, recent: Option<&Histogram>) -> Result<()> {
+        let mut runs = self.runs.lock().unwrap();
+        let run = runs.entry(self.key.clone()).or_default();
+        run.sample = Some(sample.clone());
+        if let Some(recent) = recent {
+            run.latency.merge(recent);
+        }
+        Ok(())
+    }
+}
+
+fn respond(stream: TcpStream, runs: &Mutex<Runs>) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers.
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    let (status, body) = match request_line.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", render(&runs.lock().unwrap())),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+    write!(
+        &stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/// Renders the metrics in the Prometheus text format.
+fn render(runs: &Runs) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# HELP dbcmp_ops_total Operations completed by each thread, or items read by scans.\n\
+         # TYPE dbcmp_ops_total counter"
+    )
+    .unwrap();
+    for ((backend, workload), run) in runs {
+        let Some(sample) = &run.sample else { continue };
+        for (thread, ops) in sample.thread_ops.iter().enumerate() {
+            writeln!(
+                out,
+                "dbcmp_ops_total{{backend=\"{backend}\",workload=\"{workload}\",thread=\"{thread}\"}} {ops}"
+            )
+            .unwrap();
+        }
+    }
+    for (name, help, value) in GAUGES {
+        writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge").unwrap();
+        for ((backend, workload), run) in runs {
+            if let Some(sample) = &run.sample {
+                writeln!(
+                    out,
+                    "{name}{{backend=\"{backend}\",workload=\"{workload}\"}} {}",
+                    value(sample)
+                )
+                .unwrap();
+            }
+        }
+    }
+    writeln!(
+        out,
+        "# HELP dbcmp_latency_seconds Latency of the operations of op-based workloads.\n\
+         # TYPE dbcmp_latency_seconds histogram"
+    )
+    .unwrap();
+    for ((backend, workload), run) in runs {
+        let labels = format!("backend=\"{backend}\",workload=\"{workload}\"");
+        let latency = &run.latency;
+        if latency.count() == 0 {
+            continue;
+        }
+        for le in BUCKETS {
+            writeln!(
+                out,
+                "dbcmp_latency_seconds_bucket{{{labels},le=\"{le}\"}} {}",
+                latency.count_at_most(Duration::from_secs_f64(le))
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "dbcmp_latency_seconds_bucket{{{labels},le=\"+Inf\"}} {count}\n\
+             dbcmp_latency_seconds_sum{{{labels}}} {}\n\
+             dbcmp_latency_seconds_count{{{labels}}} {count}",
+            latency.mean_us() * latency.count() as f64 / 1e6,
+            count = latency.count()
+        )
+        .unwrap();
+    }
+    if let Some(rss) = rss_bytes() {
+        writeln!(
+            out,
+            "# HELP dbcmp_resident_memory_bytes Resident set size of db-cmp.\n\
+             # TYPE dbcmp_resident_memory_bytes gauge\n\
+             dbcmp_resident_memory_bytes {rss}"
+        )
+        .unwrap();
+    }
+    out
+}
@@ -0,0 +1,291 @@
+//! Op-based workloads run by several processes at once, either on the same
+//! DB, for the backends that several processes can open, or on a DB each.
+//!
+//! Every process is a `process-child` of the binary, which opens its DB and
+//! reports [`OPENED`] on stdout, waits for a line on stdin so that they all
+//! start together, runs the workload on a single thread and reports a
+//! [`Report`] as a JSON line.
+
+use std::env;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{self, Backend, BackendOpts};
+use crate::cli::{ProcessChildArgs, ProcessesArgs};
+use crate::config::Config;
+use crate::histogram::Histogram;
+use crate::{db, ops, seed};
+
+/// What a child reports once it opened its DB.
+pub const OPENED: &str = "opened";
+
+/// How the processes are given their DBs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// All processes open the same DB, so their writers contend for its
+    /// lock.
+    Shared,
+    /// Every process opens a DB of its own, seeded the same.
+    Separate,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 2] = [Layout::Shared, Layout::Separate];
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Layout::Shared => "shared",
+            Layout::Separate => "separate",
+        })
+    }
+}
+
+/// What a child measured.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub ops: u64,
+    pub elapsed_us: u64,
+    /// Latencies of all its operations.
+    pub latency: Histogram,
+    /// Latencies of the commits of its read-write transactions. LMDB waits
+    /// for the writers of other processes when a transaction begins, but
+    /// SQLite may wait for them to commit too.
+    pub commit_latency: Histogram,
+}
+
+/// Runs the op-based workload of `config` on a single thread on `db`, stored
+/// at `path`, and reports what it measured.
+pub fn run_child(db: &Arc<dyn Backend>, path: &Path, config: &Config) -> Result<Report> {
+    let spec = config
+        .spec()
+        .with_context(|| format!("{} isn't an op-based workload", config.workload))?;
+    let outcome = ops::run(db, path, config, &spec, None, None, None)?;
+    let mut latency = Histogram::default();
+    for op_latency in outcome.latencies.values() {
+        latency.merge(op_latency);
+    }
+    Ok(Report {
+        ops: outcome.ops,
+        elapsed_us: outcome.elapsed.as_micros() as u64,
+        latency,
+        commit_latency: outcome.commit_latency,
+    })
+}
+
+/// What all the processes of a run measured together.
+pub struct Outcome {
+    pub ops: u64,
+    /// Wall-clock time of the slowest process.
+    pub elapsed: Duration,
+    pub latency: Histogram,
+    pub commit_latency: Histogram,
+}
+
+impl Outcome {
+    pub fn new(reports: &[Report]) -> Self {
+        let mut outcome = Outcome {
+            ops: 0,
+            elapsed: Duration::ZERO,
+            latency: Histogram::default(),
+            commit_latency: Histogram::default(),
+        };
+        for report in reports {
+            outcome.ops += report.ops;
+            outcome.elapsed = outcome
+                .elapsed
+                .max(Duration::from_micros(report.elapsed_us));
+            outcome.latency.merge(&report.latency);
+            outcome.commit_latency.merge(&report.commit_latency);
+        }
+        outcome
+    }
+
+    pub fn ops_per_sec(&self) -> f64 {
+        self.ops as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Runs `db-cmp processes`: runs the workload of `args` on each backend
+/// from every number of processes of it, re-running the current executable
+/// as its `process-child`ren.
+pub fn command(args: ProcessesArgs) -> Result<()> {
+    ensure!(
+        args.processes.iter().all(|&n| n > 0),
+        "processes needs at least one process per run"
+    );
+    let config = Config {
+        workload: args.workload,
+        items: args.items,
+        ..args.backend.into_config()
+    };
+    config.validate()?;
+    ensure!(
+        config.spec().is_some(),
+        "processes needs an op-based workload, not {}",
+        config.workload
+    );
+    println!(
+        "{:<10} {:<9} {:>6} {:>10} {:>8} {:>10} {:>12}",
+        "Backend", "DB", "Procs", "Ops/s", "Speedup", "p99 (us)", "Commit p99"
+    );
+    'backends: for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        let layouts = match backend::multi_process(backend)? {
+            true => &Layout::ALL[..],
+            false => &[Layout::Separate],
+        };
+        for &layout in layouts {
+            let mut single = None;
+            for &n in &args.processes {
+                // A DB per process, or the same one for all.
+                let dbs = match layout {
+                    Layout::Shared => 1,
+                    Layout::Separate => n,
+                };
+                let mut temp_dirs = Vec::new();
+                for _ in 0..dbs {
+                    let (db, temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+                    seed::seed_db(seed::items(&config)?, &*db)?;
+                    temp_dirs.push(temp_dir);
+                }
+                if !db::temp_path(&temp_dirs[0]).exists() {
+                    println!("{backend}: skipped, since it doesn't persist anything");
+                    continue 'backends;
+                }
+                let children: Vec<_> = (0..n)
+                    .map(|p| ProcessChild {
+                        backend,
+                        opts: &opts,
+                        path: db::temp_path(&temp_dirs[p % dbs]),
+                        config: &config,
+                        iters: args.iters,
+                        // Every process draws different operations.
+                        seed: config.seed.wrapping_add(p as u64),
+                    })
+                    .collect();
+                let reports = ProcessChild::run_all(&children)
+                    .with_context(|| format!("failed to run {n} processes of {backend}"))?;
+                let outcome = Outcome::new(&reports);
+                let ops_per_sec = outcome.ops_per_sec();
+                let single_ops_per_sec = *single.get_or_insert(ops_per_sec / n as f64);
+                println!(
+                    "{backend:<10} {layout:<9} {n:>6} {ops_per_sec:>10.0} {:>7.2}x {:>10.1} {:>12.1}",
+                    ops_per_sec / single_ops_per_sec,
+                    outcome.latency.quantile_us(0.99),
+                    outcome.commit_latency.quantile_us(0.99),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A `process-child` process of a backend.
+struct ProcessChild<'a> {
+    backend: &'a str,
+    opts: &'a BackendOpts,
+    path: PathBuf,
+    config: &'a Config,
+    iters: usize,
+    seed: u64,
+}
+
+impl ProcessChild<'_> {
+    /// Runs all the `children` at once, starting them together once they
+    /// all opened their DBs. Returns what they reported, in order.
+    fn run_all(children: &[ProcessChild]) -> Result<Vec<Report>> {
+        let mut processes = Vec::new();
+        for child in children {
+            let mut process = child.spawn()?;
+            let stdout = BufReader::new(process.stdout.take().unwrap());
+            processes.push((process, stdout));
+        }
+        let mut line = String::new();
+        for (process, stdout) in &mut processes {
+            line.clear();
+            if stdout.read_line(&mut line)? == 0 {
+                bail!("the child failed to open the DB: {}", process.wait()?);
+            }
+            ensure!(
+                line.trim_end() == OPENED,
+                "the child didn't open the DB first"
+            );
+        }
+        for (process, _) in &mut processes {
+            writeln!(process.stdin.as_mut().unwrap())?;
+        }
+        let mut reports = Vec::new();
+        for (mut process, mut stdout) in processes {
+            line.clear();
+            stdout.read_line(&mut line)?;
+            let status = process.wait()?;
+            ensure!(!line.is_empty(), "the child failed: {status}");
+            reports.push(serde_json::from_str(&line).context("invalid report of a child")?);
+        }
+        Ok(reports)
+    }
+
+    fn spawn(&self) -> Result<process::Child> {
+        let mut command = process::Command::new(env::current_exe()?);
+        command
+            .arg("process-child")
+            .arg("--backend")
+            .arg(self.backend)
+            .arg("--db-path")
+            .arg(&self.path)
+            .arg("--workload")
+            .arg(self.config.workload.to_string())
+            .arg("--items")
+            .arg(self.config.items.to_string())
+            .arg("--iters")
+            .arg(self.iters.to_string())
+            .arg("--seed")
+            .arg(self.seed.to_string());
+        for (key, val) in self.opts {
+            command.arg("--backend-opt").arg(format!("{key}={val}"));
+        }
+        Ok(command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?)
+    }
+}
+
+/// Runs `db-cmp process-child`, a process that `processes` runs.
+pub fn child_command(args: ProcessChildArgs) -> Result<()> {
+    let config = Config {
+        backends: vec![args.backend],
+        backend_opts: args.backend_opts.into_iter().collect(),
+        workload: args.workload,
+        items: args.items,
+        iters: args.iters,
+        threads: 1,
+        seed: args.seed,
+        db_path: Some(args.db_path),
+        ..Config::default()
+    };
+    let backend = config.single_backend()?;
+    let path = config.db_path.as_deref().unwrap();
+    let db: Arc<dyn Backend> = Arc::from(db::open_existing(
+        backend,
+        path,
+        &config.backend_opts_for(backend)?,
+    )?);
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", OPENED)?;
+    stdout.flush()?;
+    // Wait for the others to open their DBs too.
+    io::stdin().lines().next().transpose()?;
+    let report = run_child(&db, path, &config)?;
+    writeln!(stdout, "{}", serde_json::to_string(&report)?)?;
+    Ok(())
+}
@@ -0,0 +1,401 @@
+//! A thin TCP protocol exposing a [`Backend`] to other processes, served by
+//! the `db-cmp-server` binary and spoken by the `remote` backend, so that
+//! the stores can be compared as networked services too.
+//!
+//! Every message is a frame: its length as a little-endian 32-bit integer,
+//! followed by an opcode or status byte and its fields. Integers are
+//! little-endian, and keys and values are prefixed with their 32-bit
+//! length. Once connected, the server sends a [`HELLO`] frame saying
+//! whether the store supports reverse scans.
+//!
+//! A connection has at most one transaction open at a time. It begins with
+//! [`BEGIN_RO`] or [`BEGIN_RW`] and ends with [`COMMIT`] or [`ABORT`], which
+//! also ends read-only ones. Every request gets a response: [`OK`] and its
+//! results, or [`ERR`] and a message, e.g. if the store failed. Scans return
+//! up to a given number of records at a time, which the client pages through
+//! from the last key it got.
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::ops::Bound;
+use std::thread;
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+
+use crate::backend::{Backend, ReadTxn, WriteTxn};
+
+/// The first frame the server sends, with whether reverse scans are
+/// supported.
+pub const HELLO: u8 = 0;
+pub const BEGIN_RO: u8 = 1;
+pub const BEGIN_RW: u8 = 2;
+/// A key. Responds with whether it was found, and its value.
+pub const GET: u8 = 3;
+/// A key and a value.
+pub const PUT: u8 = 4;
+/// A key. Responds with whether it existed.
+pub const DELETE: u8 = 5;
+/// A bound, whether to scan in reverse, and the maximum number of records
+/// to respond with. Responds with the number of records, and their keys and
+/// values.
+pub const SCAN: u8 = 6;
+pub const COMMIT: u8 = 7;
+pub const ABORT: u8 = 8;
+
+/// Status of a response that succeeded.
+pub const OK: u8 = 0;
+/// Status of a response that failed, followed by the error message.
+pub const ERR: u8 = 1;
+
+/// Largest frame accepted, so that a corrupt length can't exhaust memory.
+const MAX_FRAME: usize = 1 << 30;
+
+/// A frame being written.
+#[derive(Default)]
+pub struct Frame(Vec<u8>);
+
+impl Frame {
+    pub fn new(op: u8) -> Self {
+        Frame(vec![op])
+    }
+
+    pub fn u8(mut self, n: u8) -> Self {
+        self.0.push(n);
+        self
+    }
+
+    pub fn u32(mut self, n: u32) -> Self {
+        self.0.extend(n.to_le_bytes());
+        self
+    }
+
+    pub fn bytes(self, bytes: &[u8]) -> Self {
+        let mut frame = self.u32(bytes.len() as u32);
+        frame.0.extend(bytes);
+        frame
+    }
+
+    pub fn bound(self, bound: Bound<&[u8]>) -> Self {
+        match bound {
+            Bound::Unbounded => self.u8(0),
+            Bound::Included(key) => self.u8(1).bytes(key),
+            Bound::Excluded(key) => self.u8(2).bytes(key),
+        }
+    }
+}
+
+/// A frame being read, from its first byte.
+pub struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    pub fn u8(&mut self) -> Result<u8> {
+        let (&n, rest) = self.0.split_first().context("truncated frame")?;
+        self.0 = rest;
+        Ok(n)
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        ensure!(self.0.len() >= 4, "truncated frame");
+        let (n, rest) = self.0.split_at(4);
+        self.0 = rest;
+        Ok(u32::from_le_bytes(n.try_into().unwrap()))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        ensure!(self.0.len() >= len, "truncated frame");
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    pub fn bound(&mut self) -> Result<Bound<&'a [u8]>> {
+        Ok(match self.u8()? {
+            0 => Bound::Unbounded,
+            1 => Bound::Included(self.bytes()?),
+            2 => Bound::Excluded(self.bytes()?),
+            other => bail!("unknown bound {other}"),
+        })
+    }
+}
+
+/// A buffered connection, sending and receiving frames.
+pub struct Conn {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    frame: Vec<u8>,
+}
+
+impl Conn {
+    pub fn new(stream: TcpStream) -> Result<Self> {
+        // Every request waits for its response, so don't delay small frames.
+        stream.set_nodelay(true)?;
+        Ok(Conn {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            frame: Vec::new(),
+        })
+    }
+
+    pub fn send(&mut self, frame: Frame) -> Result<()> {
+        self.writer
+            .write_all(&(frame.0.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&frame.0)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Receives the next frame, or `None` if the peer closed the connection
+    /// in between frames.
+    pub fn receive(&mut self) -> Result<Option<Fields<'_>>> {
+        let mut len = [0; 4];
+        match self.reader.read_exact(&mut len) {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        ensure!(len <= MAX_FRAME, "frame of {len} bytes is too big");
+        self.frame.resize(len, 0);
+        self.reader.read_exact(&mut self.frame)?;
+        Ok(Some(Fields(&self.frame)))
+    }
+
+    /// Sends `request` and receives the fields of its response, or the error
+    /// of the server.
+    pub fn call(&mut self, request: Frame) -> Result<Fields<'_>> {
+        self.send(request)?;
+        let mut response = self
+            .receive()?
+            .context("the server closed the connection")?;
+        match response.u8()? {
+            OK => Ok(response),
+            ERR => bail!("{}", String::from_utf8_lossy(response.bytes()?)),
+            other => bail!("unknown status {other}"),
+        }
+    }
+}
+
+/// Serves `db` to every connection to `listener`, each on a thread of its
+/// own, until accepting one fails.
+pub fn serve(db: &dyn Backend, listener: TcpListener) -> Result<()> {
+    thread::scope(|s| -> Result<()> {
+        loop {
+            let (stream, peer) = listener.accept()?;
+            s.spawn(move || {
+                if let Err(err) = serve_conn(db, stream) {
+                    eprintln!("connection from {peer} failed: {err:#}");
+                }
+            });
+        }
+    })
+}
+
+/// An open transaction of a connection.
+enum Txn<'d> {
+    Ro(Box<dyn ReadTxn + 'd>),
+    Rw(Box<dyn WriteTxn + 'd>),
+}
+
+impl Txn<'_> {
+    fn read(&self) -> &dyn ReadTxn {
+        match self {
+            Txn::Ro(t) => &**t,
+            Txn::Rw(t) => &**t,
+        }
+    }
+}
+
+fn serve_conn(db: &dyn Backend, stream: TcpStream) -> Result<()> {
+    let mut conn = Conn::new(stream)?;
+    conn.send(Frame::new(HELLO).u8(db.reverse_scans() as u8))?;
+    let mut txn = None;
+    loop {
+        let Some(mut request) = conn.receive()? else {
+            return Ok(());
+        };
+        let op = request.u8()?;
+        let response = handle(db, &mut txn, op, &mut request)
+            .unwrap_or_else(|err| Frame::new(ERR).bytes(format!("{err:#}").as_bytes()));
+        conn.send(response)?;
+    }
+}
+
+/// Performs the request `op` with the fields of `request` on `db`, in the
+/// open transaction `txn`, if any. Returns the response.
+fn handle<'d>(
+    db: &'d dyn Backend,
+    txn: &mut Option<Txn<'d>>,
+    op: u8,
+    request: &mut Fields,
+) -> Result<Frame> {
+    let no_txn = || anyhow!("no transaction is open");
+    match op {
+        BEGIN_RO | BEGIN_RW => {
+            ensure!(txn.is_none(), "a transaction is already open");
+            *txn = Some(match op {
+                BEGIN_RO => Txn::Ro(db.begin_ro()?),
+                _ => Txn::Rw(db.begin_rw()?),
+            });
+            Ok(Frame::new(OK))
+        }
+        GET => {
+            let t = txn.as_ref().ok_or_else(no_txn)?;
+            Ok(match t.read().get(request.bytes()?)? {
+                Some(v) => Frame::new(OK).u8(1).bytes(&v),
+                None => Frame::new(OK).u8(0),
+            })
+        }
+        SCAN => {
+            let t = txn.as_ref().ok_or_else(no_txn)?;
+            let bound = request.bound()?;
+            let reverse = request.u8()? != 0;
+            let limit = request.u32()?;
+            let mut n = 0;
+            let mut records = Frame::default();
+            let mut visit = |k: &[u8], v: &[u8]| {
+                records = mem::take(&mut records).bytes(k).bytes(v);
+                n += 1;
+                n < limit
+            };
+            if limit > 0 {
+                match reverse {
+                    false => t.read().scan(bound, &mut visit)?,
+                    true => t.read().scan_rev(bound, &mut visit)?,
+                }
+            }
+            let mut response = Frame::new(OK).u32(n);
+            response.0.extend(records.0);
+            Ok(response)
+        }
+        PUT | DELETE => {
+            let Some(Txn::Rw(t)) = txn else {
+                bail!("no read-write transaction is open");
+            };
+            let key = request.bytes()?;
+            Ok(match op {
+                PUT => {
+                    t.put(key, request.bytes()?)?;
+                    Frame::new(OK)
+                }
+                _ => Frame::new(OK).u8(t.delete(key)? as u8),
+            })
+        }
+        COMMIT | ABORT => {
+            match (txn.take().ok_or_else(no_txn)?, op) {
+                (Txn::Ro(_), _) => {}
+                (Txn::Rw(t), COMMIT) => t.commit()?,
+                (Txn::Rw(t), _) => t.abort()?,
+            }
+            Ok(Frame::new(OK))
+        }
+        other => bail!("unknown opcode {other}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::backend::{self, BackendOpts};
+
+    /// A frame of every type of field.
+    fn frame() -> Frame {
+        Frame::new(PUT)
+            .u8(7)
+            .u32(0xdead_beef)
+            .bytes(b"key")
+            .bytes(b"")
+            .bound(Bound::Unbounded)
+            .bound(Bound::Included(b"from"))
+            .bound(Bound::Excluded(b"to"))
+    }
+
+    #[test]
+    fn frames_round_trip() -> Result<()> {
+        let frame = frame();
+        let mut fields = Fields(&frame.0);
+        assert_eq!(fields.u8()?, PUT);
+        assert_eq!(fields.u8()?, 7);
+        assert_eq!(fields.u32()?, 0xdead_beef);
+        assert_eq!(fields.bytes()?, b"key");
+        assert_eq!(fields.bytes()?, b"");
+        assert_eq!(fields.bound()?, Bound::Unbounded);
+        assert_eq!(fields.bound()?, Bound::Included(&b"from"[..]));
+        assert_eq!(fields.bound()?, Bound::Excluded(&b"to"[..]));
+        assert!(fields.u8().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_frames_fail() {
+        let frame = frame();
+        for len in 0..frame.0.len() {
+            let mut fields = Fields(&frame.0[..len]);
+            let read = (|| -> Result<()> {
+                fields.u8()?;
+                fields.u8()?;
+                fields.u32()?;
+                fields.bytes()?;
+                fields.bytes()?;
+                for _ in 0..3 {
+                    fields.bound()?;
+                }
+                Ok(())
+            })();
+            assert!(read.is_err(), "frame truncated to {len} bytes");
+        }
+    }
+
+    #[test]
+    fn unknown_bounds_fail() {
+        let frame = Frame::new(SCAN).u8(3);
+        let mut fields = Fields(&frame.0[1..]);
+        assert!(fields.bound().is_err());
+    }
+
+    /// Performs the request `frame` with [`handle`], returning the fields
+    /// of the response after its status.
+    fn request<'d>(
+        db: &'d dyn Backend,
+        txn: &mut Option<Txn<'d>>,
+        frame: Frame,
+    ) -> Result<Vec<u8>> {
+        let mut fields = Fields(&frame.0);
+        let op = fields.u8()?;
+        let response = handle(db, txn, op, &mut fields)?;
+        let mut fields = Fields(&response.0);
+        assert_eq!(fields.u8()?, OK);
+        Ok(fields.0.to_vec())
+    }
+
+    #[test]
+    fn requests_are_handled() -> Result<()> {
+        let db = backend::open(backend::BASELINE, Path::new(""), &BackendOpts::new())?;
+        let db = &*db;
+        let mut txn = None;
+        assert!(request(db, &mut txn, Frame::new(GET).bytes(b"a")).is_err());
+        request(db, &mut txn, Frame::new(BEGIN_RW))?;
+        assert!(request(db, &mut txn, Frame::new(BEGIN_RO)).is_err());
+        for key in [b"a", b"b", b"c"] {
+            request(db, &mut txn, Frame::new(PUT).bytes(key).bytes(b"v"))?;
+        }
+        let deleted = request(db, &mut txn, Frame::new(DELETE).bytes(b"c"))?;
+        assert_eq!(deleted, [1]);
+        request(db, &mut txn, Frame::new(COMMIT))?;
+
+        request(db, &mut txn, Frame::new(BEGIN_RO))?;
+        assert!(request(db, &mut txn, Frame::new(PUT).bytes(b"d").bytes(b"v")).is_err());
+        let found = request(db, &mut txn, Frame::new(GET).bytes(b"a"))?;
+        assert_eq!(found, Frame::default().u8(1).bytes(b"v").0);
+        let missing = request(db, &mut txn, Frame::new(GET).bytes(b"c"))?;
+        assert_eq!(missing, [0]);
+        let scan = Frame::new(SCAN).bound(Bound::Unbounded).u8(0).u32(1);
+        let page = request(db, &mut txn, scan)?;
+        assert_eq!(page, Frame::default().u32(1).bytes(b"a").bytes(b"v").0);
+        request(db, &mut txn, Frame::new(ABORT))?;
+        assert!(request(db, &mut txn, Frame::new(COMMIT)).is_err());
+        Ok(())
+    }
+}
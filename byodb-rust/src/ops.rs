@@ -0,0 +1,1230 @@
+use std::cell::Cell;
+use std::fmt;
+use std::hint::black_box;
+use std::ops::Bound;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::vec;
+
+use anyhow::{Context, Error, Result, anyhow, ensure};
+use clap::ValueEnum;
+use rand::{
+    SeedableRng,
+    distr::{Alphanumeric, SampleString},
+    prelude::*,
+};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use tracing::Span;
+
+use byodb_rust::consts;
+
+use crate::affinity;
+use crate::backend::{Backend, ReadTxn, WriteTxn};
+use crate::config::{Budget, Config};
+use crate::db;
+use crate::distribution::{Distribution, Length, fnv1a};
+use crate::generator::{self, Generator, Keys};
+use crate::histogram::{Histogram, Latencies};
+use crate::perf::{Counters, PerfCounts};
+use crate::process::{self, CpuTime, Io};
+use crate::profile::Profiler;
+use crate::seed;
+use crate::shadow::{Pending, Shadow};
+use crate::timeline::{self, Counter, Monitor, Sample};
+use crate::trace::{Step, Trace, TracedOp};
+use crate::writers::{self, Retries, WriterOutcome, Writers};
+
+/// Default size of the values, like the 10 fields of 100 bytes of a YCSB
+/// record.
+pub const VALUE_SIZE: usize = 1000;
+
+/// Size of the part of a value rewritten by a read-modify-write, like a
+/// single YCSB field.
+pub const FIELD_SIZE: usize = 100;
+
+/// An op-based workload: every thread performs `config.iters` operations,
+/// each in its own transaction.
+#[derive(Debug, Clone, Copy)]
+pub struct Spec {
+    pub mix: Mix,
+    pub distribution: Distribution,
+    /// Number of items each scan reads, starting from a chosen key.
+    pub scan_len: Length,
+    /// Size of the values of the records.
+    pub value_size: Length,
+    /// Whether keys are in the order they were inserted, like timestamps,
+    /// rather than scattered over the keyspace.
+    pub ordered_keys: bool,
+    /// What the keys look like, unless they have prefixes.
+    pub key_type: KeyType,
+    /// Fraction of the reads that look up keys no record has.
+    pub miss_fraction: f64,
+    /// Number of consecutive records grouped under each `user:{id}:` prefix,
+    /// with keys like `user:{id}:{field}`, if any. Scans then read every
+    /// record under the prefix of the chosen one.
+    pub prefix_size: Option<u64>,
+    /// Number of tenants the records are dealt to, with keys like
+    /// `tenant:{tenant}:{timestamp}:{sequence}`, in order of insertion
+    /// within each tenant, if any. Scans then stop at the end of the tenant
+    /// of the chosen record.
+    pub tenants: Option<u64>,
+    /// The type of operation whose latencies are charted in the summary, if
+    /// any.
+    pub charted_op: Option<Op>,
+    /// Whether deletes remove the oldest remaining record, rather than a
+    /// chosen one, so that the records form a sliding window.
+    pub delete_oldest: bool,
+    /// Whether to sample the disk usage of the DB during the run.
+    pub track_space: bool,
+    /// Whether to summarize the throughput over time.
+    pub track_throughput: bool,
+}
+
+/// The relative weights of each type of operation, written as
+/// comma-separated `<op>=<weight>` pairs, e.g. `read=70,update=30`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Mix {
+    pub read: f64,
+    pub update: f64,
+    pub insert: f64,
+    pub delete: f64,
+    pub scan: f64,
+    /// Read-modify-write.
+    pub rmw: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Read,
+    Update,
+    Insert,
+    Delete,
+    Scan,
+    Rmw,
+}
+
+impl Op {
+    pub const ALL: [Op; 6] = [
+        Op::Read,
+        Op::Update,
+        Op::Insert,
+        Op::Delete,
+        Op::Scan,
+        Op::Rmw,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Read => "read",
+            Op::Update => "update",
+            Op::Insert => "insert",
+            Op::Delete => "delete",
+            Op::Scan => "scan",
+            Op::Rmw => "rmw",
+        }
+    }
+}
+
+/// Name of the latencies of reads that found nothing, which are kept apart
+/// from those of the reads of existing keys.
+pub const READ_MISS: &str = "read-miss";
+
+/// Suffix of the keys that reads meant to miss look up: the key of an
+/// existing record followed by it, which no record has, and which sorts
+/// right after that record.
+pub const MISS_SUFFIX: &str = "!";
+
+/// Histograms of the latencies of each type of operation, indexed like
+/// [`Op::ALL`], followed by the one of [`READ_MISS`].
+type OpLatencies = [Histogram; Op::ALL.len() + 1];
+
+/// The names of the [`OpLatencies`], in order.
+fn latency_names() -> impl Iterator<Item = &'static str> {
+    Op::ALL.into_iter().map(Op::name).chain([READ_MISS])
+}
+
+impl Mix {
+    fn ops(&self) -> [(Op, f64); 6] {
+        [
+            (Op::Read, self.read),
+            (Op::Update, self.update),
+            (Op::Insert, self.insert),
+            (Op::Delete, self.delete),
+            (Op::Scan, self.scan),
+            (Op::Rmw, self.rmw),
+        ]
+    }
+
+    fn weight_mut(&mut self, op: &str) -> Option<&mut f64> {
+        match op {
+            "read" => Some(&mut self.read),
+            "update" => Some(&mut self.update),
+            "insert" => Some(&mut self.insert),
+            "delete" => Some(&mut self.delete),
+            "scan" => Some(&mut self.scan),
+            "rmw" => Some(&mut self.rmw),
+            _ => None,
+        }
+    }
+
+    fn total(&self) -> f64 {
+        self.ops().iter().map(|(_, w)| w).sum()
+    }
+
+    /// The fraction of operations that are inserts.
+    fn insert_fraction(&self) -> f64 {
+        self.insert / self.total()
+    }
+
+    /// Draws a type of operation, following the weights.
+    pub fn choose(&self, rng: &mut impl Rng) -> Op {
+        let mut x = rng.random::<f64>() * self.total();
+        let mut chosen = Op::Read;
+        for (op, w) in self.ops() {
+            if w > 0.0 {
+                chosen = op;
+                if x < w {
+                    break;
+                }
+                x -= w;
+            }
+        }
+        chosen
+    }
+}
+
+impl FromStr for Mix {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut mix = Mix::default();
+        for pair in s.split(',') {
+            let (op, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("{pair} is not of the form <op>=<weight>"))?;
+            let w = mix.weight_mut(op).ok_or_else(|| {
+                anyhow!("unknown op {op}, expected one of read, update, insert, delete, scan, rmw")
+            })?;
+            *w = weight
+                .parse()
+                .with_context(|| format!("invalid weight {weight} for op {op}"))?;
+            ensure!(*w >= 0.0, "weight of op {op} must not be negative");
+        }
+        ensure!(mix.total() > 0.0, "op mix {s} has no positive weight");
+        Ok(mix)
+    }
+}
+
+impl TryFrom<String> for Mix {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Mix> for String {
+    fn from(mix: Mix) -> Self {
+        mix.to_string()
+    }
+}
+
+impl fmt::Display for Mix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<_> = self
+            .ops()
+            .into_iter()
+            .filter(|(_, w)| *w > 0.0)
+            .map(|(op, w)| format!("{}={w}", op.name()))
+            .collect();
+        f.write_str(&pairs.join(","))
+    }
+}
+
+/// The key of the record numbered `n`. Like in YCSB, the number is hashed
+/// so that consecutive inserts aren't in key order, unless `ordered`.
+pub fn key(n: u64, ordered: bool) -> String {
+    if ordered {
+        format!("user{n:020}")
+    } else {
+        format!("user{}", fnv1a(n))
+    }
+}
+
+/// What the keys of the records look like.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyType {
+    /// Names like `user6284781860667377211`, from the hash of the record
+    /// number, so scattered over the keyspace, or in order for the
+    /// time-series workload.
+    #[default]
+    Random,
+    /// The record number as a big-endian 64-bit integer, so every insert
+    /// goes after all the existing keys. Keys are strings, so it's written
+    /// as 16 hex digits, most significant first, which sort the same.
+    U64Seq,
+    /// Random version 4 UUIDs, like `67e55044-10b1-426f-9247-bb680e5fe0c8`,
+    /// scattered over the keyspace.
+    #[value(alias = "uuid")]
+    #[serde(alias = "uuid")]
+    UuidV4,
+    /// Time-ordered version 7 UUIDs, which start with a millisecond
+    /// timestamp, 16 records apart, followed by random bits. Inserts land in a narrow window after the existing keys.
+    UuidV7,
+}
+
+/// Number of records whose version 7 UUIDs have the same timestamp.
+pub const UUID_V7_PER_MS: u64 = 16;
+
+/// Timestamp of the first version 7 UUID, in milliseconds since the Unix
+/// epoch.
+const UUID_V7_EPOCH_MS: u64 = 1_700_000_000_000;
+
+impl KeyType {
+    /// The key of the record numbered `n`, which is also the `n`th seeded
+    /// item of the scan workload, or `None` if it's random like the values.
+    /// The random bits of UUIDs are drawn from `n`, so that the same record
+    /// always has the same key.
+    pub fn seeded(self, n: u64) -> Option<String> {
+        match self {
+            KeyType::Random => None,
+            KeyType::U64Seq => Some(format!("{n:016x}")),
+            KeyType::UuidV4 => Some(
+                uuid::Builder::from_random_bytes(ChaCha8Rng::seed_from_u64(n).random())
+                    .into_uuid()
+                    .to_string(),
+            ),
+            KeyType::UuidV7 => {
+                let ms = UUID_V7_EPOCH_MS + n / UUID_V7_PER_MS;
+                let uuid = uuid::Builder::from_unix_timestamp_millis(
+                    ms,
+                    &ChaCha8Rng::seed_from_u64(n).random(),
+                );
+                Some(uuid.into_uuid().to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no key type is skipped");
+        f.write_str(value.get_name())
+    }
+}
+
+/// A random alphanumeric value with a length drawn from `size`, clamped to
+/// the sizes byodb supports.
+pub fn value(rng: &mut impl Rng, size: Length) -> String {
+    let size = size.sample(rng).clamp(1, consts::MAX_VALUE_SIZE);
+    Alphanumeric.sample_string(rng, size)
+}
+
+impl Spec {
+    /// The key of the record numbered `n`.
+    pub fn key(&self, n: u64) -> String {
+        match (self.prefix_size, self.tenants, self.key_type) {
+            (Some(size), _, _) => format!("{}{:04}", self.prefix(n), n % size),
+            (None, Some(_), _) => format!(
+                "{}{:013}:{:04}",
+                self.prefix(n),
+                TENANT_EPOCH_MS + n / EVENTS_PER_MS,
+                n % EVENTS_PER_MS
+            ),
+            (None, None, KeyType::Random) => key(n, self.ordered_keys),
+            (None, None, key_type) => key_type.seeded(n).expect("only random keys are drawn"),
+        }
+    }
+
+    /// The prefix of the key of the record numbered `n`, if keys have
+    /// prefixes: its user's, or its tenant's.
+    pub fn prefix(&self, n: u64) -> String {
+        if let Some(tenants) = self.tenants {
+            return format!("tenant:{:08}:", fnv1a(n) % tenants);
+        }
+        let id = n / self.prefix_size.unwrap_or(1);
+        if self.ordered_keys {
+            format!("user:{id:020}:")
+        } else {
+            format!("user:{}:", fnv1a(id))
+        }
+    }
+}
+
+/// Number of records of the multi-tenant workload with the same timestamp,
+/// told apart by their sequence numbers.
+const EVENTS_PER_MS: u64 = 4;
+
+/// Timestamp of the first record of the multi-tenant workload, in
+/// milliseconds since the Unix epoch.
+const TENANT_EPOCH_MS: u64 = 1_700_000_000_000;
+
+/// The RNG of thread `t` of a run with `seed`: stream `t + 1` of it, 0 being
+/// the one of the [`Records`], so that every thread draws the same numbers
+/// across runs and backends, however the threads are scheduled.
+pub fn stream(seed: u64, t: u64) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(t + 1);
+    rng
+}
+
+/// Deterministically generates the `n` records that op-based workloads
+/// start with, numbered from 0.
+pub struct Records {
+    next: u64,
+    n: u64,
+    spec: Spec,
+    rng: ChaCha8Rng,
+}
+
+impl Records {
+    pub fn new(n: usize, seed: u64, spec: &Spec) -> Self {
+        Records {
+            next: 0,
+            n: n as u64,
+            spec: *spec,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Iterator for Records {
+    type Item = (String, String);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.n {
+            return None;
+        }
+        self.next += 1;
+        Some((
+            self.spec.key(self.next - 1),
+            value(&mut self.rng, self.spec.value_size),
+        ))
+    }
+}
+
+/// What was measured by [`run`], or nothing by default, for runs that skip
+/// the run phase.
+#[derive(Default)]
+pub struct Outcome {
+    /// Number of operations completed by all threads, or items for the scan
+    /// workload.
+    pub ops: u64,
+    /// Wall-clock time of all operations.
+    pub elapsed: Duration,
+    /// Latencies of the operations of all threads.
+    pub latencies: Latencies,
+    /// Progress of the run over time.
+    pub timeline: Vec<Sample>,
+    /// What each thread did, in order.
+    pub threads: Vec<ThreadOutcome>,
+    /// What the `config.writers` measured, if any.
+    pub writers: Option<WriterOutcome>,
+    /// Latencies of committing the read-write transactions of all threads,
+    /// which are also part of the latencies of their operations.
+    pub commit_latency: Histogram,
+    /// Total size of the keys and values put by all threads and writers.
+    pub written_bytes: u64,
+    /// The read-write transactions of all threads and writers, and how many
+    /// were retried after a conflict.
+    pub retries: Retries,
+    /// The I/O of the process during the run, if known.
+    pub io: Option<Io>,
+    /// The CPU time of the process during the run.
+    pub cpu: CpuTime,
+    /// The hardware events counted during the run, if `config.perf_counters`.
+    pub perf: Option<PerfCounts>,
+    /// The flamegraph SVG of the run, if `config.profile`.
+    pub flamegraph: Option<Vec<u8>>,
+    /// The steps of every thread, if `config.record`.
+    pub trace: Option<Trace>,
+}
+
+/// What a single thread of a run measured.
+pub struct ThreadOutcome {
+    /// Number of operations completed by the thread, or items for the scan
+    /// workload.
+    pub ops: u64,
+    /// Wall-clock time of its operations.
+    pub elapsed: Duration,
+    /// Latencies of all its operations.
+    pub latency: Histogram,
+}
+
+/// Runs `config.threads` threads that each perform `config.iters`
+/// operations of `spec` on `db`, which holds the initial [`Records`] and is
+/// stored at `path`, or rather the steps of `replay`, if any. The commits of
+/// the run are applied to `shadow`, if any.
+/// Every sample of the run is also passed to `monitor`, if any.
+pub fn run(
+    db: &Arc<dyn Backend>,
+    path: &Path,
+    config: &Config,
+    spec: &Spec,
+    shadow: Option<&Arc<Shadow>>,
+    replay: Option<&Trace>,
+    mut monitor: Option<&mut dyn Monitor>,
+) -> Result<Outcome> {
+    ensure!(
+        config.items > 0 || replay.is_some(),
+        "the {} workload needs items",
+        config.workload
+    );
+    // The number of operations of a timed run isn't known in advance, so
+    // assume as many as there are items.
+    let n_ops = match config.budget() {
+        Budget::Iters(iters) => config.threads * iters,
+        Budget::Time(_) => config.items,
+    };
+    let space = keyspace(config, spec, n_ops);
+    let n_keys = Arc::new(AtomicU64::new(config.items as u64));
+    let n_deleted = Arc::new(AtomicU64::new(0));
+    let live_bytes = Arc::new(AtomicU64::new(0));
+    if spec.track_space {
+        live_bytes.store(seed::live_bytes(&**db)?, Ordering::Relaxed);
+    }
+    let keys = Keys {
+        spec: *spec,
+        n_keys: n_keys.clone(),
+        n_deleted: n_deleted.clone(),
+        space,
+    };
+    let cpus = affinity::worker_cpus(config)?;
+    let workers = (0..config.threads)
+        .map(|t| -> Result<Worker> {
+            Ok(Worker {
+                db: db.clone(),
+                spec: *spec,
+                generator: generator::generator(config, &keys, t)?,
+                stream: stream(config.seed, t as u64),
+                span: Span::current(),
+                live_bytes: live_bytes.clone(),
+                live_delta: Cell::new(0),
+                untimed: Cell::new(Duration::ZERO),
+                latencies: OpLatencies::default(),
+                commit_latency: Histogram::default(),
+                written_bytes: Cell::new(0),
+                retries: Retries::default(),
+                shadow: shadow.cloned(),
+                pending: Pending::default(),
+                batch: config.batch,
+                recent: monitor
+                    .is_some()
+                    .then(|| Arc::new(Mutex::new(Histogram::default()))),
+                // Each thread takes an equal share of the target rate, with
+                // their arrivals interleaved.
+                schedule: config.target_ops.map(|target_ops| {
+                    let interval = Duration::from_secs_f64(config.threads as f64 / target_ops);
+                    Schedule {
+                        offset: interval.mul_f64(t as f64 / config.threads as f64),
+                        interval,
+                    }
+                }),
+                intended: None,
+                bucket: config.rate_per_thread.map(TokenBucket::new),
+                replay: replay.map(|trace| trace.threads[t].clone().into_iter()),
+                recording: config.record.is_some().then(Vec::new),
+                cpu: cpus.as_ref().map(|cpus| cpus[t % cpus.len()]),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let workers = match config.warmup {
+        Some(budget) => warm_up(workers, budget)?,
+        None => workers,
+    };
+
+    let counters: Arc<Vec<Counter>> =
+        Arc::new((0..config.threads).map(|_| Counter::default()).collect());
+    let recent: Vec<_> = workers.iter().filter_map(|w| w.recent.clone()).collect();
+    let start_time = Instant::now();
+    let sample = || -> Result<Sample> {
+        let thread_ops: Vec<_> = counters.iter().map(Counter::get).collect();
+        let sample = Sample {
+            elapsed_ms: start_time.elapsed().as_millis() as u64,
+            ops: thread_ops.iter().sum(),
+            thread_ops,
+            n_keys: n_keys.load(Ordering::Relaxed) - n_deleted.load(Ordering::Relaxed),
+            disk_bytes: if spec.track_space {
+                db::disk_usage(path)?
+            } else {
+                0
+            },
+            live_bytes: live_bytes.load(Ordering::Relaxed),
+            rss_bytes: process::rss_bytes().unwrap_or(0),
+            mapped_bytes: process::mapped_bytes().unwrap_or(0),
+        };
+        if let Some(monitor) = &mut monitor {
+            let mut latency = Histogram::default();
+            for worker_recent in &recent {
+                let mut worker_recent = worker_recent.lock().unwrap();
+                latency.merge(&worker_recent);
+                worker_recent.reset();
+            }
+            monitor.update(&sample, Some(&latency))?;
+        }
+        Ok(sample)
+    };
+    // A replay performs the steps of the trace, however many there are.
+    let budget = match replay {
+        Some(_) => Budget::Iters(usize::MAX),
+        None => config.budget(),
+    };
+    let io = Io::now();
+    let perf = config.perf_counters.then(Counters::start).transpose()?;
+    let profiler = config.profile.map(Profiler::start).transpose()?;
+    let writers = (config.writers > 0).then(|| Writers::start(db, config.writers, shadow));
+    let (results, timeline) = timeline::sampled(config.sample_interval(), sample, || {
+        // The wall-clock and CPU time of the workers alone, over the same
+        // interval, not counting starting and stopping the writers.
+        let measured_time = Instant::now();
+        let cpu = CpuTime::now();
+        let threads: Vec<_> = workers
+            .into_iter()
+            .enumerate()
+            .map(|(t, worker)| {
+                let counters = counters.clone();
+                thread::spawn(move || worker.run(budget, &counters[t]))
+            })
+            .collect();
+        let results: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        (measured_time.elapsed(), CpuTime::now().since(&cpu), results)
+    });
+    let (elapsed, cpu, results) = results;
+    let writers = writers.map(Writers::stop).transpose()?;
+    let io = Option::zip(Io::now(), io).map(|(now, before)| now.since(&before));
+    let perf = perf.map(Counters::stop).transpose()?;
+    let flamegraph = profiler.map(Profiler::finish).transpose()?;
+    let mut latencies = OpLatencies::default();
+    let mut commit_latency = Histogram::default();
+    let mut written_bytes = writers.as_ref().map_or(0, |w| w.written_bytes);
+    let mut retries = writers
+        .as_ref()
+        .map_or_else(Retries::default, |w| w.retries);
+    let mut thread_outcomes = Vec::new();
+    let mut recorded = Vec::new();
+    for (result, counter) in results.into_iter().zip(counters.iter()) {
+        let (worker, worker_elapsed) = result?;
+        let mut thread_latency = Histogram::default();
+        for (total, latency) in latencies.iter_mut().zip(&worker.latencies) {
+            total.merge(latency);
+            thread_latency.merge(latency);
+        }
+        commit_latency.merge(&worker.commit_latency);
+        written_bytes += worker.written_bytes.get();
+        retries.merge(&worker.retries);
+        recorded.extend(worker.recording);
+        thread_outcomes.push(ThreadOutcome {
+            ops: counter.get(),
+            elapsed: worker_elapsed,
+            latency: thread_latency,
+        });
+    }
+    let timeline = timeline?;
+    let trace = config.record.is_some().then(|| Trace {
+        config: config.clone(),
+        threads: recorded,
+        items: None,
+    });
+    Ok(Outcome {
+        ops: counters.iter().map(Counter::get).sum(),
+        elapsed,
+        latencies: latency_names()
+            .zip(latencies)
+            .filter(|(_, latency)| latency.count() > 0)
+            .map(|(name, latency)| (name.to_string(), latency))
+            .collect(),
+        timeline,
+        threads: thread_outcomes,
+        writers,
+        commit_latency,
+        written_bytes,
+        retries,
+        io,
+        cpu,
+        perf,
+        flamegraph,
+        trace,
+    })
+}
+
+/// The number of keys chosen from for `n_ops` operations of `spec`: like
+/// YCSB, the initial records, plus room for twice the expected inserts.
+fn keyspace(config: &Config, spec: &Spec, n_ops: usize) -> u64 {
+    config.items as u64 + (n_ops as f64 * spec.mix.insert_fraction() * 2.0) as u64
+}
+
+/// Performs the operations of `spec` a step at a time, for drivers other
+/// than the threads of [`run`], like the async one. The clients made
+/// together share the number of records, like the threads of a run.
+pub struct Client(Worker);
+
+/// Makes `n` clients that perform `n_ops` operations of `spec` between them
+/// on `db`, which holds the initial [`Records`]. Each draws from its own
+/// stream of `config.seed`.
+pub fn clients(
+    db: &Arc<dyn Backend>,
+    config: &Config,
+    spec: &Spec,
+    n: usize,
+    n_ops: usize,
+) -> Result<Vec<Client>> {
+    let keys = Keys {
+        spec: *spec,
+        n_keys: Arc::new(AtomicU64::new(config.items as u64)),
+        n_deleted: Arc::new(AtomicU64::new(0)),
+        space: keyspace(config, spec, n_ops),
+    };
+    (0..n)
+        .map(|c| {
+            Ok(Client(Worker {
+                db: db.clone(),
+                spec: *spec,
+                generator: generator::generator(config, &keys, c)?,
+                stream: stream(config.seed, c as u64),
+                span: Span::current(),
+                live_bytes: Arc::new(AtomicU64::new(0)),
+                live_delta: Cell::new(0),
+                untimed: Cell::new(Duration::ZERO),
+                latencies: OpLatencies::default(),
+                commit_latency: Histogram::default(),
+                written_bytes: Cell::new(0),
+                retries: Retries::default(),
+                shadow: None,
+                pending: Pending::default(),
+                batch: config.batch,
+                recent: None,
+                schedule: None,
+                intended: None,
+                bucket: None,
+                replay: None,
+                recording: None,
+                cpu: None,
+            }))
+        })
+        .collect()
+}
+
+impl Client {
+    /// Performs the next `config.batch` operations, in a transaction of
+    /// their own unless it's a batch of several. Returns how many there
+    /// were.
+    pub fn step(&mut self) -> Result<usize> {
+        Ok(self.0.step()?.expect("only replays run out of steps"))
+    }
+
+    /// Latencies of the operations performed so far, measured around the
+    /// calls to the backend alone.
+    pub fn latency(&self) -> Histogram {
+        let mut latency = Histogram::default();
+        for op_latency in &self.0.latencies {
+            latency.merge(op_latency);
+        }
+        latency
+    }
+}
+
+/// Runs the `workers` in parallel for `budget`, then forgets what they
+/// measured.
+fn warm_up(workers: Vec<Worker>, budget: Budget) -> Result<Vec<Worker>> {
+    let threads: Vec<_> = workers
+        .into_iter()
+        .map(|mut worker| {
+            thread::spawn(move || -> Result<Worker> {
+                if let Some(cpu) = worker.cpu {
+                    affinity::pin(cpu)?;
+                }
+                let start_time = Instant::now();
+                let mut iters = 0;
+                while !budget.is_spent(iters, start_time) {
+                    let Some(n) = worker.step()? else {
+                        break;
+                    };
+                    iters += n;
+                    if let Some(bucket) = &mut worker.bucket {
+                        bucket.take(n);
+                    }
+                }
+                worker.latencies = OpLatencies::default();
+                worker.commit_latency.reset();
+                worker.written_bytes.set(0);
+                worker.retries = Retries::default();
+                if let Some(recent) = &worker.recent {
+                    recent.lock().unwrap().reset();
+                }
+                Ok(worker)
+            })
+        })
+        .collect();
+    threads.into_iter().map(|t| t.join().unwrap()).collect()
+}
+
+struct Worker {
+    db: Arc<dyn Backend>,
+    spec: Spec,
+    generator: Box<dyn Generator>,
+    /// The stream of the thread, seeding the RNG of each operation.
+    stream: ChaCha8Rng,
+    /// The span of the run, the parent of those of the operations.
+    span: Span,
+    /// Total size of the keys and values of the records.
+    live_bytes: Arc<AtomicU64>,
+    /// How the current transaction changes `live_bytes` once committed.
+    live_delta: Cell<i64>,
+    /// Time spent looking up the sizes of the records the current operation
+    /// overwrites or deletes, which its latency doesn't count.
+    untimed: Cell<Duration>,
+    /// Latencies of each type of operation, and of reads that missed.
+    latencies: OpLatencies,
+    /// Latencies of the commits of the read-write transactions.
+    commit_latency: Histogram,
+    /// Total size of the keys and values put.
+    written_bytes: Cell<u64>,
+    /// The read-write transactions, and their retries.
+    retries: Retries,
+    /// The model to apply the commits to, if any.
+    shadow: Option<Arc<Shadow>>,
+    /// The writes of the current transaction, if there's a model.
+    pending: Pending,
+    /// Number of operations per transaction.
+    batch: usize,
+    /// Latencies of all operations since the last sample, if monitored.
+    recent: Option<Arc<Mutex<Histogram>>>,
+    /// When to start the operations of an open-loop run.
+    schedule: Option<Schedule>,
+    /// When the current operation was scheduled to start.
+    intended: Option<Instant>,
+    /// What throttles the thread, if anything.
+    bucket: Option<TokenBucket>,
+    /// The steps left to replay, if replaying a trace.
+    replay: Option<vec::IntoIter<Step>>,
+    /// The steps performed, if recording a trace.
+    recording: Option<Vec<Step>>,
+    /// The CPU the thread running the worker is pinned to, if any.
+    cpu: Option<usize>,
+}
+
+/// The arrival times of the operations of a thread in an open-loop run:
+/// `offset`, then every `interval` after that, regardless of how long the
+/// operations take.
+struct Schedule {
+    offset: Duration,
+    interval: Duration,
+}
+
+/// Reads every record whose key starts with `prefix` in `t`.
+fn scan_prefix(t: &dyn ReadTxn, prefix: &[u8]) -> Result<()> {
+    t.scan(Bound::Included(prefix), &mut |k, _| k.starts_with(prefix))
+}
+
+/// Reads up to `len` records from `from` in `t`, or none at all if `len` is
+/// 0, which a registered generator may draw.
+fn scan_from(t: &dyn ReadTxn, from: &[u8], len: u64) -> Result<()> {
+    scan_within(t, b"", from, len)
+}
+
+/// Reads up to `len` records from `from` in `t`, as long as their keys start
+/// with `prefix`.
+fn scan_within(t: &dyn ReadTxn, prefix: &[u8], from: &[u8], len: u64) -> Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    let mut left = len;
+    t.scan(Bound::Included(from), &mut |k, _| {
+        left -= 1;
+        left > 0 && k.starts_with(prefix)
+    })
+}
+
+/// Throttles a thread to `rate` operations per second. The bucket holds a
+/// single token, so a thread that fell behind, e.g. after a slow operation,
+/// doesn't catch up with a burst of operations.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        TokenBucket {
+            rate,
+            tokens: 1.0,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Takes a token for each of the `n` operations just performed, waiting
+    /// until they're all refilled.
+    fn take(&mut self, n: usize) {
+        let now = Instant::now();
+        let refill = (now - self.refilled).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(1.0) - n as f64;
+        self.refilled = now;
+        if self.tokens < 0.0 {
+            self.refilled += Duration::from_secs_f64(-self.tokens / self.rate);
+            self.tokens = 0.0;
+            wait_until(self.refilled);
+        }
+    }
+}
+
+/// Waits until `deadline`, yielding for the last stretch since sleeps are
+/// too coarse for the short intervals of high target rates. Unlike spinning,
+/// yielding lets other threads run even if there are more than cores.
+fn wait_until(deadline: Instant) {
+    const SPIN: Duration = Duration::from_millis(1);
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        if deadline - now > SPIN {
+            thread::sleep(deadline - now - SPIN);
+        } else {
+            thread::yield_now();
+        }
+    }
+}
+
+impl Worker {
+    /// Returns the worker with the latencies it measured, and how long its
+    /// operations took in all.
+    fn run(mut self, budget: Budget, counter: &Counter) -> Result<(Self, Duration)> {
+        if let Some(cpu) = self.cpu {
+            affinity::pin(cpu)?;
+        }
+        let start_time = Instant::now();
+        let mut i = 0;
+        while !budget.is_spent(i, start_time) {
+            if let Some(schedule) = &self.schedule {
+                let intended = start_time + schedule.offset + schedule.interval.mul_f64(i as f64);
+                wait_until(intended);
+                self.intended = Some(intended);
+            }
+            let Some(n) = self.step()? else {
+                break;
+            };
+            // Batches count as all their operations, even if it overshoots
+            // the iterations.
+            counter.add(n as u64);
+            i += n;
+            if let Some(bucket) = &mut self.bucket {
+                bucket.take(n);
+            }
+        }
+        let elapsed = start_time.elapsed();
+        Ok((self, elapsed))
+    }
+
+    /// Records the latency of an operation of type `op`, or of a read that
+    /// found nothing if `missed`, that started at `start_time`, or rather
+    /// was scheduled to start at the intended time of an open-loop run, so
+    /// that any queueing behind slow operations counts too.
+    fn record(&mut self, op: Op, missed: bool, start_time: Instant) {
+        let latency = self.latency_since(start_time);
+        self.record_latency(op, missed, latency);
+    }
+
+    /// The latency of an operation that started at `start_time`, or was
+    /// scheduled to, as of now, less the time spent tracking the size of the
+    /// records since.
+    fn latency_since(&self, start_time: Instant) -> Duration {
+        let latency = self.intended.unwrap_or(start_time).elapsed();
+        latency.saturating_sub(self.untimed.take())
+    }
+
+    fn record_latency(&mut self, op: Op, missed: bool, latency: Duration) {
+        let i = if missed { Op::ALL.len() } else { op as usize };
+        self.latencies[i].record(latency);
+        if let Some(recent) = &self.recent {
+            recent.lock().unwrap().record(latency);
+        }
+    }
+
+    /// Puts `v` under `k` in `t`, keeping track of the size of the records
+    /// if `spec.track_space`.
+    fn put(&self, t: &mut dyn WriteTxn, k: &[u8], v: &[u8]) -> Result<()> {
+        self.written_bytes
+            .set(self.written_bytes.get() + (k.len() + v.len()) as u64);
+        if self.shadow.is_some() {
+            self.pending.put(k, v);
+        }
+        if self.spec.track_space {
+            self.forget_size(t, k)?;
+            self.live_delta
+                .set(self.live_delta.get() + (k.len() + v.len()) as i64);
+        }
+        t.put(k, v)
+    }
+
+    /// Deletes `k` in `t`, keeping track of the size of the records if
+    /// `spec.track_space`.
+    fn delete(&self, t: &mut dyn WriteTxn, k: &[u8]) -> Result<()> {
+        if self.spec.track_space {
+            self.forget_size(t, k)?;
+        }
+        if self.shadow.is_some() {
+            self.pending.delete(k);
+        }
+        t.delete(k)?;
+        Ok(())
+    }
+
+    /// Subtracts the size of the record at `k`, if any, from the live bytes.
+    /// The lookup isn't part of the operation, so it's left out of its
+    /// latency.
+    fn forget_size(&self, t: &dyn WriteTxn, k: &[u8]) -> Result<()> {
+        let start_time = Instant::now();
+        if let Some(v) = t.get(k)? {
+            self.live_delta
+                .set(self.live_delta.get() - (k.len() + v.len()) as i64);
+        }
+        self.untimed.set(self.untimed.get() + start_time.elapsed());
+        Ok(())
+    }
+
+    /// Performs `ops` in a read-write transaction and commits it, recording
+    /// how long the commit took, and applies its writes to the model, if
+    /// any. Returns what `ops` did. Transactions that fail on a conflict are
+    /// retried from the start, forgetting what they wrote, once backed off,
+    /// up to [`writers::MAX_ATTEMPTS`] attempts.
+    fn write<T>(
+        &mut self,
+        mut ops: impl FnMut(&mut Self, &mut dyn WriteTxn) -> Result<T>,
+    ) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let _span = tracing::info_span!("txn", attempt).entered();
+            let written_bytes = self.written_bytes.get();
+            let start_time = Instant::now();
+            let result = self.try_write(&mut ops);
+            if let Some(result) = self.retries.count(result, start_time.elapsed(), attempt) {
+                return result;
+            }
+            writers::back_off(attempt);
+            self.written_bytes.set(written_bytes);
+            self.live_delta.set(0);
+            self.pending.clear();
+        }
+    }
+
+    /// A single attempt of [`Worker::write`].
+    fn try_write<T>(
+        &mut self,
+        ops: &mut impl FnMut(&mut Self, &mut dyn WriteTxn) -> Result<T>,
+    ) -> Result<T> {
+        let db = self.db.clone();
+        let shadow = self.shadow.clone();
+        let lock = shadow.as_deref().map(Shadow::lock);
+        let mut t = db.begin_rw()?;
+        let done = ops(self, &mut *t)?;
+        let start_time = Instant::now();
+        tracing::info_span!("commit").in_scope(|| match lock {
+            Some(lock) => lock.commit(t, &self.pending),
+            None => t.commit(),
+        })?;
+        self.commit_latency.record(start_time.elapsed());
+        let delta = self.live_delta.take();
+        if delta >= 0 {
+            self.live_bytes.fetch_add(delta as u64, Ordering::Relaxed);
+        } else {
+            self.live_bytes.fetch_sub(-delta as u64, Ordering::Relaxed);
+        }
+        Ok(done)
+    }
+
+    /// Reads the record `k` and rewrites the field numbered `pick`, modulo
+    /// the number of fields of its value, with `field` in `t`.
+    fn rmw(&self, t: &mut dyn WriteTxn, k: &[u8], pick: u64, field: &[u8]) -> Result<()> {
+        // Deleted records, and all of the null backend's, are recreated;
+        // cheaply, so as not to skew the harness overhead.
+        let mut v = match t.get(k)? {
+            Some(v) => v.into_owned(),
+            None => vec![b'0'; VALUE_SIZE],
+        };
+        // Like YCSB, rewrite a single field of the value.
+        let n = pick as usize % v.len().div_ceil(FIELD_SIZE);
+        for (b, new) in v.iter_mut().skip(n * FIELD_SIZE).zip(field) {
+            *b = *new;
+        }
+        self.put(t, k, &v)
+    }
+
+    /// Draws the next operation of the generator, if it doesn't skip it.
+    /// Each operation draws from an RNG of its own, seeded from the stream
+    /// of the thread, so that however many numbers one takes, e.g. to pick
+    /// a key that exists by now, the ones after it stay the same.
+    fn draw(&mut self) -> Option<TracedOp> {
+        let mut rng = ChaCha8Rng::from_seed(self.stream.random());
+        self.generator.next_op(&mut rng)
+    }
+
+    /// Performs `op` in `t`. Returns whether it was a read that found
+    /// nothing.
+    fn apply(&self, t: &mut dyn WriteTxn, op: &TracedOp) -> Result<bool> {
+        match op {
+            TracedOp::Read(k) => return Ok(black_box(t.get(k)?).is_none()),
+            TracedOp::Update(k, v) | TracedOp::Insert(k, v) => self.put(t, k, v)?,
+            TracedOp::Delete(k) => self.delete(t, k)?,
+            TracedOp::Scan(k, len) => scan_from(t, k, *len)?,
+            TracedOp::PrefixScan(prefix) => scan_prefix(t, prefix)?,
+            TracedOp::ScanWithin { prefix, from, len } => scan_within(t, prefix, from, *len)?,
+            TracedOp::Rmw { key, pick, field } => self.rmw(t, key, *pick, field)?,
+        }
+        Ok(false)
+    }
+
+    /// Performs the next step: the next one of the trace when replaying
+    /// one, or else `batch` operations drawn from the mix, recording them if
+    /// recording. Returns the number of operations it counts as, or `None`
+    /// once the trace is done.
+    fn step(&mut self) -> Result<Option<usize>> {
+        let Some(replay) = &mut self.replay else {
+            let step = Step {
+                batch: self.batch > 1,
+                ops: (0..self.batch).filter_map(|_| self.draw()).collect(),
+            };
+            self.perform(&step)?;
+            if let Some(recording) = &mut self.recording {
+                recording.push(step);
+            }
+            // Batches count as all their operations, even if some weren't
+            // drawn.
+            return Ok(Some(self.batch));
+        };
+        let Some(step) = replay.next() else {
+            return Ok(None);
+        };
+        self.perform(&step)?;
+        Ok(Some(step.ops.len().max(1)))
+    }
+
+    /// The span of `op`, within that of the run, or of the batch it's in.
+    fn op_span(&self, op: &TracedOp) -> Span {
+        tracing::info_span!(
+            parent: Span::current().id().or(self.span.id()),
+            "op",
+            kind = op.kind().name(),
+            key = %String::from_utf8_lossy(op.key()),
+        )
+    }
+
+    /// Performs a single operation in its own transaction, or a batch in a
+    /// single read-write transaction, each operation timed on its own.
+    fn perform(&mut self, step: &Step) -> Result<()> {
+        let op = match step.ops.as_slice() {
+            [op] if !step.batch => op,
+            [] if !step.batch => return Ok(()),
+            ops => {
+                // Only the latencies of the attempt that committed count.
+                let _span =
+                    tracing::info_span!(parent: &self.span, "batch", ops = ops.len()).entered();
+                let timed = self.write(|w, t| {
+                    let mut timed = Vec::with_capacity(ops.len());
+                    for op in ops {
+                        let _span = w.op_span(op).entered();
+                        w.untimed.set(Duration::ZERO);
+                        let start_time = Instant::now();
+                        let missed = w.apply(t, op)?;
+                        timed.push((op.kind(), missed, w.latency_since(start_time)));
+                    }
+                    Ok(timed)
+                })?;
+                for (op, missed, latency) in timed {
+                    self.record_latency(op, missed, latency);
+                }
+                return Ok(());
+            }
+        };
+        let _span = self.op_span(op).entered();
+        self.untimed.set(Duration::ZERO);
+        let start_time = Instant::now();
+        let mut missed = false;
+        match op {
+            TracedOp::Read(k) => {
+                missed = black_box(self.db.begin_ro()?.get(k)?).is_none();
+            }
+            TracedOp::Scan(k, len) => scan_from(&*self.db.begin_ro()?, k, *len)?,
+            TracedOp::PrefixScan(prefix) => scan_prefix(&*self.db.begin_ro()?, prefix)?,
+            TracedOp::ScanWithin { prefix, from, len } => {
+                scan_within(&*self.db.begin_ro()?, prefix, from, *len)?
+            }
+            // The writer lock, if any, is held for about this long.
+            _ => self.write(|w, t| w.apply(t, op).map(drop))?,
+        }
+        self.record(op.kind(), missed, start_time);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn parse_mix() {
+        let mix: Mix = "read=70,update=30".parse().unwrap();
+        assert_eq!(
+            mix,
+            Mix {
+                read: 70.0,
+                update: 30.0,
+                ..Mix::default()
+            }
+        );
+        let mix: Mix = "scan=0.95,insert=0.05,rmw=0".parse().unwrap();
+        assert_eq!(
+            mix,
+            Mix {
+                scan: 0.95,
+                insert: 0.05,
+                ..Mix::default()
+            }
+        );
+    }
+
+    #[test]
+    fn mix_round_trips() {
+        let mix: Mix = "read=50,delete=25,rmw=25".parse().unwrap();
+        assert_eq!(mix.to_string(), "read=50,delete=25,rmw=25");
+        assert_eq!(mix.to_string().parse::<Mix>().unwrap(), mix);
+    }
+
+    #[test]
+    fn parse_malformed_mixes() {
+        for malformed in [
+            "",
+            "read",
+            "read=",
+            "read=x",
+            "get=1",
+            "read=-1,update=2",
+            "read=0",
+            "read=1;update=1",
+        ] {
+            assert!(malformed.parse::<Mix>().is_err(), "{malformed:?} parsed");
+        }
+    }
+
+    #[test]
+    fn mix_chooses_only_weighted_ops() {
+        let mix: Mix = "update=1,scan=3".parse().unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let scans = (0..10_000)
+            .map(|_| mix.choose(&mut rng))
+            .inspect(|op| assert!(matches!(op, Op::Update | Op::Scan), "drew {op:?}"))
+            .filter(|op| *op == Op::Scan)
+            .count();
+        assert!((7000..8000).contains(&scans), "{scans} scans of 10000");
+    }
+}
@@ -0,0 +1,145 @@
+//! Hardware performance counters of the measured phase of a run, from the
+//! Linux `perf_event_open` interface.
+
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::FromRawFd;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Counts of hardware events in user space, by all threads started after
+/// [`Counters::start`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PerfCounts {
+    pub cycles: u64,
+    pub instructions: u64,
+    /// Misses of the last-level cache.
+    pub llc_misses: u64,
+    pub branch_misses: u64,
+}
+
+impl PerfCounts {
+    /// Instructions per cycle.
+    pub fn ipc(&self) -> f64 {
+        self.instructions as f64 / self.cycles.max(1) as f64
+    }
+}
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+
+const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+
+/// Count the events of the threads the calling thread starts, too.
+const FLAG_INHERIT: u64 = 1 << 1;
+/// Unprivileged users can only count events in user space by default.
+const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+/// The first version of `struct perf_event_attr`, which every kernel since
+/// 2.6.31 accepts.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+/// Counters of the events of [`PerfCounts`], counting from when they're
+/// started.
+pub struct Counters {
+    cycles: File,
+    instructions: File,
+    llc_misses: File,
+    branch_misses: File,
+}
+
+impl Counters {
+    pub fn start() -> Result<Self> {
+        Ok(Counters {
+            cycles: open(PERF_COUNT_HW_CPU_CYCLES)?,
+            instructions: open(PERF_COUNT_HW_INSTRUCTIONS)?,
+            llc_misses: open(PERF_COUNT_HW_CACHE_MISSES)?,
+            branch_misses: open(PERF_COUNT_HW_BRANCH_MISSES)?,
+        })
+    }
+
+    /// Reads the counts so far, including those of the threads started since
+    /// that have exited.
+    pub fn stop(self) -> Result<PerfCounts> {
+        Ok(PerfCounts {
+            cycles: read(self.cycles)?,
+            instructions: read(self.instructions)?,
+            llc_misses: read(self.llc_misses)?,
+            branch_misses: read(self.branch_misses)?,
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open(_config: u64) -> Result<File> {
+    anyhow::bail!("hardware performance counters are only supported on Linux");
+}
+
+#[cfg(target_os = "linux")]
+fn open(config: u64) -> Result<File> {
+    let attr = PerfEventAttr {
+        type_: PERF_TYPE_HARDWARE,
+        size: size_of::<PerfEventAttr>() as u32,
+        config,
+        read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+        flags: FLAG_INHERIT | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+        ..PerfEventAttr::default()
+    };
+    // SAFETY: `attr` outlives the call, and its `size` is its own. The
+    // counter follows this thread (pid 0) on any CPU (-1), on its own
+    // (group -1).
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_perf_event_open,
+            &attr as *const PerfEventAttr,
+            0,
+            -1,
+            -1,
+            PERF_FLAG_FD_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error()).context(
+            "failed to open a hardware performance counter; is it supported, \
+             and is /proc/sys/kernel/perf_event_paranoid at most 2?",
+        );
+    }
+    // SAFETY: the fd was just opened, and is owned by nothing else.
+    Ok(unsafe { File::from_raw_fd(fd as i32) })
+}
+
+/// Reads the count of `counter`, scaled up if the kernel only counted for
+/// part of the time, to share the hardware counters with others.
+fn read(mut counter: File) -> Result<u64> {
+    let mut buf = [0; 24];
+    counter
+        .read_exact(&mut buf)
+        .context("failed to read a hardware performance counter")?;
+    let [value, enabled, running] =
+        [0, 1, 2].map(|i| u64::from_ne_bytes(buf[i * 8..i * 8 + 8].try_into().unwrap()));
+    if running == 0 {
+        return Ok(0);
+    }
+    Ok((value as f64 * enabled as f64 / running as f64) as u64)
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::results::RunResult;
+
+/// The highest latency percentile plotted, as a number of nines: 99.99%.
+const NINES: u32 = 4;
+
+/// Saves gnuplot data files and the scripts that plot them to `dir`:
+///
+/// - `latency.dat` and `latency.gp`: the latency CDF of every type of
+///   operation of every result, by percentile.
+/// - `scaling.dat` and `scaling.gp`: the throughput of every backend and
+///   workload by number of threads, across results, e.g. loaded from the
+///   files of several runs.
+///
+/// Running `gnuplot latency.gp scaling.gp` in `dir` renders them as SVG.
+pub fn save(dir: &Path, results: &[RunResult]) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    save_latency(dir, results)?;
+    save_scaling(dir, results)?;
+    save_size(dir, results)
+}
+
+fn save_latency(dir: &Path, results: &[RunResult]) -> Result<()> {
+    // Every curve is a data block, separated by two blank lines, so that
+    // gnuplot can pick it by index.
+    let mut curves = Vec::new();
+    let mut dat = String::new();
+    for result in results {
+        for (op, latency) in &result.latencies {
+            if latency.count() == 0 {
+                continue;
+            }
+            let title = format!("{} {} {op}", result.backend, result.config.workload);
+            writeln!(dat, "# {title}\n# percentile_x latency_us").unwrap();
+            for (x, us) in latency.percentile_curve(NINES, 20) {
+                writeln!(dat, "{} {us}", 10f64.powf(x)).unwrap();
+            }
+            dat += "\n\n";
+            curves.push(title);
+        }
+    }
+    if curves.is_empty() {
+        return Ok(());
+    }
+    let xtics: Vec<_> = (0..=NINES)
+        .map(|nines| {
+            let percent = 100.0 * (1.0 - 10f64.powi(-(nines as i32)));
+            let percent = (percent * 100.0).round() / 100.0;
+            format!("\"{percent}%\" {}", 10u64.pow(nines))
+        })
+        .collect();
+    let gp = gnuplot_script(
+        "latency",
+        "Latency by percentile",
+        &[
+            "set logscale x".to_string(),
+            format!("set xtics ({})", xtics.join(", ")),
+            "set xlabel \"percentile\"".to_string(),
+            "set ylabel \"latency (us)\"".to_string(),
+        ],
+        &curves,
+        "lines",
+    );
+    write(dir, "latency.dat", &dat)?;
+    write(dir, "latency.gp", &gp)
+}
+
+fn save_scaling(dir: &Path, results: &[RunResult]) -> Result<()> {
+    // Like the latency curves, every backend and workload is a data block.
+    let mut scaling: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for result in results {
+        scaling
+            .entry(format!("{} {}", result.backend, result.config.workload))
+            .or_default()
+            .push((result.config.threads, result.throughput()));
+    }
+    let mut dat = String::new();
+    for (title, points) in &mut scaling {
+        points.sort_by_key(|&(threads, _)| threads);
+        writeln!(dat, "# {title}\n# threads throughput").unwrap();
+        for (threads, throughput) in points {
+            writeln!(dat, "{threads} {throughput}").unwrap();
+        }
+        dat += "\n\n";
+    }
+    let curves: Vec<_> = scaling.into_keys().collect();
+    let gp = gnuplot_script(
+        "scaling",
+        "Throughput by number of threads",
+        &[
+            "set xlabel \"threads\"".to_string(),
+            "set ylabel \"operations or items per second\"".to_string(),
+            "set yrange [0:*]".to_string(),
+        ],
+        &curves,
+        "linespoints",
+    );
+    write(dir, "scaling.dat", &dat)?;
+    write(dir, "scaling.gp", &gp)
+}
+
+fn save_size(dir: &Path, results: &[RunResult]) -> Result<()> {
+    // A data block per backend, workload and kind of operation.
+    let mut curves: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for result in results {
+        let title = format!("{} {}", result.backend, result.config.workload);
+        let (reads, writes) = result.read_write_latencies();
+        for (kind, latency) in [("reads", reads), ("writes", writes)] {
+            if latency.count() > 0 {
+                curves
+                    .entry(format!("{title} {kind}"))
+                    .or_default()
+                    .push((result.n_items, latency.mean_us()));
+            }
+        }
+    }
+    let mut dat = String::new();
+    for (title, points) in &mut curves {
+        points.sort_by_key(|&(items, _)| items);
+        writeln!(dat, "# {title}\n# items mean_latency_us").unwrap();
+        for (items, us) in points {
+            writeln!(dat, "{items} {us}").unwrap();
+        }
+        dat += "\n\n";
+    }
+    let curves: Vec<_> = curves.into_keys().collect();
+    let gp = gnuplot_script(
+        "size",
+        "Latency by size of the DB",
+        &[
+            "set logscale x".to_string(),
+            "set xlabel \"seeded items\"".to_string(),
+            "set ylabel \"mean latency (us)\"".to_string(),
+            "set yrange [0:*]".to_string(),
+        ],
+        &curves,
+        "linespoints",
+    );
+    write(dir, "size.dat", &dat)?;
+    write(dir, "size.gp", &gp)
+}
+
+/// Generates a gnuplot script that plots the `curves`, the data blocks of
+/// `<name>.dat` in order, to `<name>.svg`.
+fn gnuplot_script(
+    name: &str,
+    title: &str,
+    settings: &[String],
+    curves: &[String],
+    style: &str,
+) -> String {
+    let mut gp = String::new();
+    writeln!(gp, "set terminal svg size 800,500 dynamic").unwrap();
+    writeln!(gp, "set output \"{name}.svg\"").unwrap();
+    writeln!(gp, "set title \"{title}\"").unwrap();
+    writeln!(gp, "set key top left").unwrap();
+    writeln!(gp, "set grid").unwrap();
+    for setting in settings {
+        writeln!(gp, "{setting}").unwrap();
+    }
+    let plots: Vec<_> = curves
+        .iter()
+        .enumerate()
+        .map(|(i, title)| {
+            format!("\"{name}.dat\" index {i} using 1:2 with {style} title \"{title}\"")
+        })
+        .collect();
+    writeln!(gp, "plot {}", plots.join(", \\\n     ")).unwrap();
+    gp
+}
+
+fn write(dir: &Path, file: &str, contents: &str) -> Result<()> {
+    let path = dir.join(file);
+    fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
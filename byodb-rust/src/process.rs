@@ -0,0 +1,107 @@
+//! Resource usage of this process.
+
+use std::fs;
+use std::time::Duration;
+
+use crate::syscalls::Syncs;
+
+/// The I/O done by this process so far, from `/proc/self/io`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Io {
+    /// Bytes that the process caused to be written to storage, which
+    /// includes dirtying the page cache.
+    pub write_bytes: u64,
+    /// Number of write syscalls, of any file.
+    pub write_syscalls: u64,
+    /// The syncs issued, if they're counted.
+    pub syncs: Option<Syncs>,
+}
+
+impl Io {
+    /// The I/O done so far, on Linux.
+    pub fn now() -> Option<Io> {
+        let io = fs::read_to_string("/proc/self/io").ok()?;
+        let field = |name: &str| -> Option<u64> {
+            let line = io.lines().find(|line| line.starts_with(name))?;
+            line[name.len()..]
+                .trim_start_matches(':')
+                .trim()
+                .parse()
+                .ok()
+        };
+        Some(Io {
+            write_bytes: field("write_bytes")?,
+            write_syscalls: field("syscw")?,
+            syncs: Syncs::now(),
+        })
+    }
+
+    /// The I/O done since `earlier`.
+    pub fn since(&self, earlier: &Io) -> Io {
+        Io {
+            write_bytes: self.write_bytes.saturating_sub(earlier.write_bytes),
+            write_syscalls: self.write_syscalls - earlier.write_syscalls,
+            syncs: Option::zip(self.syncs, earlier.syncs).map(|(now, earlier)| now.since(&earlier)),
+        }
+    }
+}
+
+/// The CPU time consumed by all threads of this process so far.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuTime {
+    pub user: Duration,
+    pub system: Duration,
+}
+
+impl CpuTime {
+    pub fn now() -> CpuTime {
+        // SAFETY: getrusage only writes to `usage`, which is zeroable.
+        let usage = unsafe {
+            let mut usage: libc::rusage = std::mem::zeroed();
+            libc::getrusage(libc::RUSAGE_SELF, &mut usage);
+            usage
+        };
+        let duration =
+            |tv: libc::timeval| Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000);
+        CpuTime {
+            user: duration(usage.ru_utime),
+            system: duration(usage.ru_stime),
+        }
+    }
+
+    /// The CPU time consumed since `earlier`.
+    pub fn since(&self, earlier: &CpuTime) -> CpuTime {
+        CpuTime {
+            user: self.user.saturating_sub(earlier.user),
+            system: self.system.saturating_sub(earlier.system),
+        }
+    }
+}
+
+/// The resident set size of this process, on Linux.
+pub fn rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// The total size of the file-backed memory mappings of this process, on
+/// Linux: those of mmap'd DB files, but also of the executable and its
+/// libraries.
+pub fn mapped_bytes() -> Option<u64> {
+    let maps = fs::read_to_string("/proc/self/maps").ok()?;
+    let mut bytes = 0;
+    for line in maps.lines() {
+        // <start>-<end> <perms> <offset> <dev> <inode> [<path>]
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        if fields.nth(3)? == "0" {
+            continue;
+        }
+        let start = u64::from_str_radix(start, 16).ok()?;
+        let end = u64::from_str_radix(end, 16).ok()?;
+        bytes += end - start;
+    }
+    Some(bytes)
+}
@@ -0,0 +1,57 @@
+//! Profiles of the measured phase of a run.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// What to profile the measured phase of a run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// Samples the stacks of all threads and draws them as a flamegraph SVG.
+    Flamegraph,
+}
+
+/// Number of stacks sampled per second of CPU time.
+#[cfg(feature = "flamegraph")]
+const FREQUENCY: i32 = 999;
+
+/// A profiler running until [`Profiler::finish`].
+pub struct Profiler {
+    #[cfg(feature = "flamegraph")]
+    guard: pprof::ProfilerGuard<'static>,
+}
+
+impl Profiler {
+    #[cfg(feature = "flamegraph")]
+    pub fn start(profile: Profile) -> Result<Self> {
+        match profile {
+            Profile::Flamegraph => Ok(Profiler {
+                guard: pprof::ProfilerGuardBuilder::default()
+                    .frequency(FREQUENCY)
+                    .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+                    .build()?,
+            }),
+        }
+    }
+
+    #[cfg(not(feature = "flamegraph"))]
+    pub fn start(profile: Profile) -> Result<Self> {
+        let Profile::Flamegraph = profile;
+        anyhow::bail!("db-cmp was built without profiling; rebuild it with `--features flamegraph`")
+    }
+
+    /// Stops profiling, and returns the flamegraph SVG of the samples.
+    #[cfg(feature = "flamegraph")]
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let report = self.guard.report().build()?;
+        let mut svg = Vec::new();
+        report.flamegraph(&mut svg)?;
+        Ok(svg)
+    }
+
+    #[cfg(not(feature = "flamegraph"))]
+    pub fn finish(self) -> Result<Vec<u8>> {
+        unreachable!("profilers can't be started without the flamegraph feature")
+    }
+}
@@ -0,0 +1,72 @@
+//! Progress bars of the seed and measured phases of a run, on stderr.
+
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressIterator, ProgressStyle};
+
+use crate::config::{Budget, Config};
+use crate::histogram::Histogram;
+use crate::timeline::{Monitor, Sample};
+
+/// Shows the progress of seeding the `n` `items`, as they're inserted.
+pub fn seeding<I: Iterator>(items: I, n: usize) -> impl Iterator<Item = I::Item> {
+    let bar = ProgressBar::new(n as u64)
+        .with_style(style("{pos}/{len} items"))
+        .with_prefix("Seeding");
+    items.progress_with(bar)
+}
+
+/// Shows the progress of the measured phase of a run, towards the end of
+/// its duration or of the iterations of every thread.
+pub struct Measuring {
+    bar: ProgressBar,
+    budget: Budget,
+    unit: &'static str,
+}
+
+impl Measuring {
+    /// Creates the bar of `backend` running `config` on a DB of `n_items`.
+    pub fn new(backend: &str, config: &Config, n_items: usize) -> Self {
+        let budget = config.budget();
+        let unit = config.workload.unit();
+        let bar = match budget {
+            Budget::Time(duration) => {
+                ProgressBar::new(duration.as_millis() as u64).with_style(style("{msg}"))
+            }
+            Budget::Iters(iters) => {
+                // Every iteration of a scan reads all items.
+                let ops_per_iter = match config.spec() {
+                    Some(_) => 1,
+                    None => n_items,
+                };
+                let len = (config.threads * iters * ops_per_iter) as u64;
+                ProgressBar::new(len).with_style(style(&format!("{{pos}}/{{len}} {unit}s")))
+            }
+        };
+        Measuring {
+            bar: bar.with_prefix(backend.to_string()),
+            budget,
+            unit,
+        }
+    }
+}
+
+impl Monitor for Measuring {
+    fn update(&mut self, sample: &Sample, _recent: Option<&Histogram>) -> Result<()> {
+        match self.budget {
+            Budget::Time(_) => {
+                self.bar.set_position(sample.elapsed_ms);
+                self.bar
+                    .set_message(format!("{} {}s", sample.ops, self.unit));
+            }
+            Budget::Iters(_) => self.bar.set_position(sample.ops),
+        }
+        Ok(())
+    }
+}
+
+fn style(counts: &str) -> ProgressStyle {
+    ProgressStyle::with_template(&format!(
+        "{{prefix:>8}} [{{elapsed_precise}}] {{wide_bar}} {counts} (ETA {{eta}})"
+    ))
+    .expect("the progress bar templates are valid")
+}
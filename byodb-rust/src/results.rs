@@ -0,0 +1,1604 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::backend;
+use crate::config::{Budget, Config};
+use crate::db;
+use crate::histogram::{Histogram, Latencies};
+use crate::host;
+use crate::html;
+use crate::ops::{KeyType, Op, Outcome, READ_MISS, ThreadOutcome};
+use crate::perf::PerfCounts;
+use crate::plot;
+use crate::process::CpuTime;
+use crate::stats;
+use crate::timeline::Sample;
+use crate::workload::Workload;
+use crate::writers::{self, Retries, WriterOutcome};
+
+/// Number of samples of the timeline printed by
+/// [`RunResult::print_summary`].
+const MAX_SAMPLES: usize = 10;
+
+/// The latency percentiles reported by [`RunResult::print_summary`].
+const QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+const MIB: f64 = (1 << 20) as f64;
+
+/// How results are output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Human-readable summaries, printed to stdout.
+    Text,
+    /// All results, with the environment they were measured in, as JSON.
+    Json,
+    /// One row per backend, workload, operation and metric, for
+    /// spreadsheets and dataframes.
+    Csv,
+    /// A Markdown table comparing the throughput and latencies of every
+    /// backend and workload, for issues and pull requests.
+    Markdown,
+    /// A self-contained HTML page with charts of the latencies and
+    /// throughput of every result.
+    Html,
+}
+
+/// All the results of a `db-cmp run`, as saved by `--out-file` or printed by
+/// `--output json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Document {
+    /// Where the results were measured.
+    pub environment: Environment,
+    /// The results of every backend, in the order they were run.
+    pub results: Vec<RunResult>,
+    /// The backends that were skipped since they don't support a workload.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<Skipped>,
+}
+
+impl Document {
+    pub fn new(results: Vec<RunResult>, skipped: Vec<Skipped>) -> Self {
+        Document {
+            environment: Environment::current(),
+            results,
+            skipped,
+        }
+    }
+}
+
+/// A backend that couldn't run a workload, reported as N/A.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Skipped {
+    pub backend: String,
+    pub workload: Workload,
+    /// Why it couldn't.
+    pub reason: String,
+}
+
+/// The machine and build that results were measured on, or nothing for
+/// results saved before it was recorded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Environment {
+    /// Version of db-cmp.
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    /// Number of CPUs available to the benchmark.
+    pub cpus: usize,
+    /// Whether db-cmp was built with debug assertions, which makes every
+    /// backend slower.
+    pub debug: bool,
+    /// When the results were saved, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The git commit db-cmp was built from, with a `-dirty` suffix if it
+    /// had changes that weren't committed, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// Version of the rustc that built db-cmp, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rustc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_model: Option<String>,
+    /// Release of the kernel, e.g. `6.8.0-45-generic`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+}
+
+impl Environment {
+    fn current() -> Self {
+        Environment {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: thread::available_parallelism().map_or(1, |n| n.get()),
+            debug: cfg!(debug_assertions),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            git_commit: Some(env!("DB_CMP_GIT_COMMIT"))
+                .filter(|commit| !commit.is_empty())
+                .map(str::to_string),
+            rustc: Some(env!("DB_CMP_RUSTC_VERSION"))
+                .filter(|version| !version.is_empty())
+                .map(str::to_string),
+            hostname: host::hostname(),
+            cpu_model: host::cpu_model(),
+            kernel: host::kernel(),
+        }
+    }
+}
+
+/// Summary statistics of the latencies of one type of operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpMetrics {
+    pub count: u64,
+    pub ops_per_sec: f64,
+    pub mean_us: f64,
+    pub p50_us: f64,
+    pub p90_us: f64,
+    pub p99_us: f64,
+    pub p999_us: f64,
+    pub max_us: f64,
+}
+
+impl OpMetrics {
+    fn new(latency: &Histogram, elapsed_us: u64) -> Self {
+        let [p50_us, p90_us, p99_us, p999_us] = QUANTILES.map(|q| latency.quantile_us(q));
+        OpMetrics {
+            count: latency.count(),
+            ops_per_sec: latency.count() as f64 / (elapsed_us as f64 / 1e6),
+            mean_us: latency.mean_us(),
+            p50_us,
+            p90_us,
+            p99_us,
+            p999_us,
+            max_us: latency.max_us(),
+        }
+    }
+}
+
+/// What a single thread of a run did, to spot threads starved by others,
+/// e.g. by an unfair scheduler or lock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMetrics {
+    /// Number of operations completed by the thread, or items for the scan
+    /// workload.
+    pub ops: u64,
+    /// Wall-clock time of its operations.
+    pub elapsed_us: u64,
+    pub ops_per_sec: f64,
+    pub mean_us: f64,
+    pub p50_us: f64,
+    pub p99_us: f64,
+    pub max_us: f64,
+    /// Latencies of all its operations, or full scans.
+    pub latency: Histogram,
+}
+
+impl ThreadMetrics {
+    fn new(outcome: ThreadOutcome) -> Self {
+        let elapsed_us = outcome.elapsed.as_micros() as u64;
+        let latency = outcome.latency;
+        let [p50_us, p99_us] = [0.5, 0.99].map(|q| latency.quantile_us(q));
+        ThreadMetrics {
+            ops: outcome.ops,
+            elapsed_us,
+            ops_per_sec: outcome.ops as f64 / (elapsed_us.max(1) as f64 / 1e6),
+            mean_us: latency.mean_us(),
+            p50_us,
+            p99_us,
+            max_us: latency.max_us(),
+            latency,
+        }
+    }
+}
+
+/// What the writer threads that ran alongside the workload did.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WriterMetrics {
+    /// Number of writer threads.
+    pub threads: usize,
+    /// Number of transactions committed by all writers.
+    pub commits: u64,
+    pub commits_per_sec: f64,
+    /// Latencies of waiting to begin a write transaction (`lock-wait`) and
+    /// of committing it (`commit`).
+    pub latencies: Latencies,
+    /// Summary statistics of [`WriterMetrics::latencies`].
+    pub metrics: BTreeMap<String, OpMetrics>,
+}
+
+impl WriterMetrics {
+    fn new(outcome: WriterOutcome) -> Self {
+        let elapsed_us = outcome.elapsed.as_micros() as u64;
+        WriterMetrics {
+            threads: outcome.threads,
+            commits: outcome.commits,
+            commits_per_sec: outcome.commits as f64 / (elapsed_us.max(1) as f64 / 1e6),
+            metrics: outcome
+                .latencies
+                .iter()
+                .map(|(op, latency)| (op.clone(), OpMetrics::new(latency, elapsed_us)))
+                .collect(),
+            latencies: outcome.latencies,
+        }
+    }
+}
+
+/// The read-write transactions of the threads and writers of a run that
+/// failed on a conflict with a concurrent one, and were retried.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictMetrics {
+    /// Number of transactions committed.
+    pub commits: u64,
+    /// Number of attempts aborted on a conflict.
+    pub aborts: u64,
+    /// The share of the attempts that were aborted, from 0 to 1.
+    pub conflict_rate: f64,
+    /// The share of the time spent in read-write transactions that went
+    /// into the aborted attempts, from 0 to 1.
+    pub wasted_work: f64,
+    /// Most attempts a single transaction took, of the
+    /// [`writers::MAX_ATTEMPTS`] after which the run fails.
+    #[serde(default)]
+    pub max_attempts: u32,
+}
+
+impl ConflictMetrics {
+    /// Only if there were conflicts, or several threads wrote, so that
+    /// there could have been.
+    fn new(retries: &Retries, config: &Config) -> Option<Self> {
+        let concurrent = config.threads + config.writers > 1 && retries.commits > 0;
+        if retries.aborts == 0 && !concurrent {
+            return None;
+        }
+        let attempts = retries.commits + retries.aborts;
+        Some(ConflictMetrics {
+            commits: retries.commits,
+            aborts: retries.aborts,
+            conflict_rate: retries.aborts as f64 / attempts as f64,
+            wasted_work: retries.wasted.as_secs_f64() / retries.busy.as_secs_f64().max(1e-9),
+            max_attempts: retries.max_attempts,
+        })
+    }
+}
+
+/// Memory usage of the process during the measured phase of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryMetrics {
+    /// Largest resident set size sampled.
+    pub peak_rss_bytes: u64,
+    /// Mean resident set size over the second half of the run, once caches
+    /// have filled up.
+    pub steady_rss_bytes: u64,
+    /// Largest size of the file-backed memory mappings sampled, e.g. of an
+    /// mmap'd DB file.
+    pub peak_mapped_bytes: u64,
+    /// Mean size of the file-backed memory mappings over the second half of
+    /// the run.
+    pub steady_mapped_bytes: u64,
+}
+
+impl MemoryMetrics {
+    /// Summarizes the memory usage sampled in `timeline`, if it was.
+    fn new(timeline: &[Sample]) -> Option<Self> {
+        let samples: Vec<_> = timeline.iter().filter(|s| s.rss_bytes > 0).collect();
+        let steady = &samples[samples.len() / 2..];
+        let mean = |bytes: fn(&Sample) -> u64| {
+            steady.iter().map(|&s| bytes(s)).sum::<u64>() / steady.len().max(1) as u64
+        };
+        Some(MemoryMetrics {
+            peak_rss_bytes: samples.iter().map(|s| s.rss_bytes).max()?,
+            steady_rss_bytes: mean(|s| s.rss_bytes),
+            peak_mapped_bytes: samples.iter().map(|s| s.mapped_bytes).max()?,
+            steady_mapped_bytes: mean(|s| s.mapped_bytes),
+        })
+    }
+}
+
+/// The size of the DB at the end of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpaceMetrics {
+    /// Total size of the files of the DB.
+    pub disk_bytes: u64,
+    /// Total size of the keys and values of its records.
+    pub live_bytes: u64,
+    /// `disk_bytes / live_bytes`, unless the DB holds no records.
+    pub amplification: Option<f64>,
+}
+
+impl SpaceMetrics {
+    /// The size of a DB that persists `disk_bytes`, if any, to store
+    /// `live_bytes` of records.
+    pub fn new(disk_bytes: u64, live_bytes: u64) -> Option<Self> {
+        // Backends that don't persist anything have no files.
+        (disk_bytes > 0).then(|| SpaceMetrics {
+            disk_bytes,
+            live_bytes,
+            amplification: (live_bytes > 0).then(|| disk_bytes as f64 / live_bytes as f64),
+        })
+    }
+}
+
+/// The bytes written during the measured phase of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteMetrics {
+    /// Total size of the keys and values put by the workload and writers.
+    pub logical_bytes: u64,
+    /// Bytes the process wrote to storage, as accounted by the OS.
+    pub disk_bytes: u64,
+    /// `disk_bytes / logical_bytes`.
+    pub amplification: f64,
+}
+
+impl WriteMetrics {
+    fn new(outcome: &Outcome) -> Option<Self> {
+        let disk_bytes = outcome.io?.write_bytes;
+        // Backends that don't persist anything write nothing.
+        (outcome.written_bytes > 0 && disk_bytes > 0).then(|| WriteMetrics {
+            logical_bytes: outcome.written_bytes,
+            disk_bytes,
+            amplification: disk_bytes as f64 / outcome.written_bytes as f64,
+        })
+    }
+}
+
+/// The syscalls issued during the measured phase of a run, if the syncs
+/// are counted (see [`crate::syscalls`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyscallMetrics {
+    /// Write syscalls, of any file.
+    pub writes: u64,
+    pub fsyncs: u64,
+    pub fdatasyncs: u64,
+    pub msyncs: u64,
+    /// Syncs of any kind per operation.
+    pub syncs_per_op: f64,
+}
+
+impl SyscallMetrics {
+    fn new(outcome: &Outcome) -> Option<Self> {
+        let io = outcome.io?;
+        let syncs = io.syncs?;
+        Some(SyscallMetrics {
+            writes: io.write_syscalls,
+            fsyncs: syncs.fsyncs,
+            fdatasyncs: syncs.fdatasyncs,
+            msyncs: syncs.msyncs,
+            syncs_per_op: syncs.total() as f64 / outcome.ops.max(1) as f64,
+        })
+    }
+}
+
+/// The CPU time consumed by a phase of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpuMetrics {
+    pub user_us: u64,
+    pub system_us: u64,
+    /// Average number of cores kept busy: the CPU time over the wall-clock
+    /// time.
+    pub cores: f64,
+    /// CPU time per operation, or per item seeded.
+    pub us_per_op: f64,
+}
+
+impl CpuMetrics {
+    fn new(cpu: CpuTime, elapsed: Duration, ops: u64) -> Self {
+        let total = cpu.user + cpu.system;
+        CpuMetrics {
+            user_us: cpu.user.as_micros() as u64,
+            system_us: cpu.system.as_micros() as u64,
+            cores: total.as_secs_f64() / elapsed.as_secs_f64().max(1e-6),
+            us_per_op: total.as_secs_f64() * 1e6 / ops.max(1) as f64,
+        }
+    }
+
+    /// Describes the CPU time per `unit`.
+    fn describe(&self, unit: &str) -> String {
+        format!(
+            "{:.3}s user, {:.3}s system, {:.2} cores busy, {:.3}us per {unit}",
+            self.user_us as f64 / 1e6,
+            self.system_us as f64 / 1e6,
+            self.cores,
+            self.us_per_op
+        )
+    }
+}
+
+/// The results of benchmarking a single backend, as saved by
+/// `db-cmp run --out-file`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunResult {
+    /// Name of the benchmarked backend.
+    pub backend: String,
+    /// The effective scenario that was run.
+    pub config: Config,
+    /// Number of items in the DB at the start of the run.
+    pub n_items: usize,
+    /// Number of operations performed by all threads, counting every item
+    /// read as one for the scan workload.
+    pub ops: u64,
+    /// Wall-clock time of seeding the DB (the load phase), unless an
+    /// existing DB was used.
+    pub load_us: Option<u64>,
+    /// Wall-clock time of the measured phase.
+    pub elapsed_us: u64,
+    /// Latencies of each type of operation, or of the full scans of the
+    /// scan workload.
+    #[serde(default)]
+    pub latencies: Latencies,
+    /// Summary statistics of [`RunResult::latencies`], by type of operation.
+    #[serde(default)]
+    pub metrics: BTreeMap<String, OpMetrics>,
+    /// Progress of the run over time.
+    #[serde(default)]
+    pub timeline: Vec<Sample>,
+    /// Which of the `config.repeat` runs of the backend this is, from 0.
+    #[serde(default)]
+    pub repetition: usize,
+    /// Whether the run was interrupted before the end of its budget.
+    #[serde(default)]
+    pub interrupted: bool,
+    /// What each thread did, in order.
+    #[serde(default)]
+    pub threads: Vec<ThreadMetrics>,
+    /// What the `config.writers` did, if any.
+    #[serde(default)]
+    pub writers: Option<WriterMetrics>,
+    /// Latencies of committing the read-write transactions of the workload,
+    /// e.g. of fsyncing, apart from the operations they're part of, if any.
+    #[serde(default)]
+    pub commit_latency: Option<Histogram>,
+    /// Summary statistics of [`RunResult::commit_latency`].
+    #[serde(default)]
+    pub commits: Option<OpMetrics>,
+    /// The read-write transactions that were retried after a conflict, if
+    /// several threads wrote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflicts: Option<ConflictMetrics>,
+    /// Memory usage of the process during the run, which includes any that
+    /// the backends run before didn't give back.
+    #[serde(default)]
+    pub memory: Option<MemoryMetrics>,
+    /// The size of the DB at the end of the run, if it persists anything.
+    #[serde(default)]
+    pub space: Option<SpaceMetrics>,
+    /// The bytes written during the run, if it put anything.
+    #[serde(default)]
+    pub writes: Option<WriteMetrics>,
+    /// The syscalls issued during the run, if known.
+    #[serde(default)]
+    pub syscalls: Option<SyscallMetrics>,
+    /// The CPU time consumed by the measured phase.
+    #[serde(default)]
+    pub cpu: Option<CpuMetrics>,
+    /// The CPU time consumed by the load phase, if any.
+    #[serde(default)]
+    pub load_cpu: Option<CpuMetrics>,
+    /// The hardware events counted during the run, if
+    /// `config.perf_counters`.
+    #[serde(default)]
+    pub perf: Option<PerfCounts>,
+    /// The type of the filesystem holding the DB, if it persists anything
+    /// and it's known.
+    #[serde(default)]
+    pub filesystem: Option<String>,
+    /// The options the filesystem holding the DB is mounted with, if it
+    /// persists anything and they're known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount_options: Option<String>,
+}
+
+impl RunResult {
+    pub fn new(
+        backend: &str,
+        config: Config,
+        n_items: usize,
+        load_elapsed: Option<Duration>,
+        load_cpu: Option<CpuTime>,
+        outcome: Outcome,
+        space: Option<SpaceMetrics>,
+    ) -> Self {
+        let elapsed_us = outcome.elapsed.as_micros() as u64;
+        let writes = WriteMetrics::new(&outcome);
+        let syscalls = SyscallMetrics::new(&outcome);
+        let cpu = CpuMetrics::new(outcome.cpu, outcome.elapsed, outcome.ops);
+        let conflicts = ConflictMetrics::new(&outcome.retries, &config);
+        RunResult {
+            backend: backend.to_string(),
+            config,
+            n_items,
+            ops: outcome.ops,
+            load_us: load_elapsed.map(|d| d.as_micros() as u64),
+            elapsed_us,
+            metrics: outcome
+                .latencies
+                .iter()
+                .map(|(op, latency)| (op.clone(), OpMetrics::new(latency, elapsed_us)))
+                .collect(),
+            latencies: outcome.latencies,
+            memory: MemoryMetrics::new(&outcome.timeline),
+            space,
+            writes,
+            syscalls,
+            cpu: Some(cpu),
+            perf: outcome.perf,
+            filesystem: None,
+            mount_options: None,
+            load_cpu: Option::zip(load_cpu, load_elapsed).map(|(load_cpu, load_elapsed)| {
+                CpuMetrics::new(load_cpu, load_elapsed, n_items as u64)
+            }),
+            timeline: outcome.timeline,
+            repetition: 0,
+            interrupted: false,
+            threads: outcome
+                .threads
+                .into_iter()
+                .map(ThreadMetrics::new)
+                .collect(),
+            writers: outcome.writers.map(WriterMetrics::new),
+            commits: (outcome.commit_latency.count() > 0)
+                .then(|| OpMetrics::new(&outcome.commit_latency, elapsed_us)),
+            commit_latency: (outcome.commit_latency.count() > 0).then_some(outcome.commit_latency),
+            conflicts,
+        }
+    }
+
+    /// Average latency of one operation, as seen by one of the threads, or
+    /// `None` if there was none.
+    pub fn avg_latency_us(&self) -> Option<f64> {
+        if self.config.target_ops.is_some() || self.config.rate_per_thread.is_some() {
+            // The wall-clock time of open-loop and throttled runs is set by
+            // the target rate, so only the recorded latencies tell.
+            let (sum, count) = self
+                .latencies
+                .values()
+                .fold((0.0, 0), |(sum, count), latency| {
+                    (
+                        sum + latency.mean_us() * latency.count() as f64,
+                        count + latency.count(),
+                    )
+                });
+            return (count > 0).then(|| sum / count as f64);
+        }
+        (self.ops > 0)
+            .then(|| (self.elapsed_us * self.config.threads as u64) as f64 / self.ops as f64)
+    }
+
+    /// The latencies of all types of operations together.
+    pub fn total_latency(&self) -> Histogram {
+        let mut latency = Histogram::default();
+        for op_latency in self.latencies.values() {
+            latency.merge(op_latency);
+        }
+        latency
+    }
+
+    /// The throughput and latencies of all types of operations together, as
+    /// `all`, followed by those of each type and of the commits of their
+    /// transactions, if there are several.
+    pub fn breakdown(&self) -> Vec<(String, f64, Histogram)> {
+        let mut rows = vec![("all".to_string(), self.throughput(), self.total_latency())];
+        let commit = self
+            .commit_latency
+            .as_ref()
+            .map(|latency| ("commit".to_string(), latency));
+        let ops: Vec<_> = self
+            .latencies
+            .iter()
+            .map(|(op, latency)| (op.clone(), latency))
+            .chain(commit)
+            .filter(|(_, latency)| latency.count() > 0)
+            .collect();
+        if ops.len() > 1 {
+            let secs = self.elapsed_us as f64 / 1e6;
+            rows.extend(
+                ops.into_iter()
+                    .map(|(op, latency)| (op, latency.count() as f64 / secs, latency.clone())),
+            );
+        }
+        rows
+    }
+
+    /// The latencies of the operations that only read, and of the ones that
+    /// write, each all together.
+    pub fn read_write_latencies(&self) -> (Histogram, Histogram) {
+        let writes = [Op::Update, Op::Insert, Op::Delete, Op::Rmw].map(Op::name);
+        let (mut reads, mut written) = (Histogram::default(), Histogram::default());
+        for (op, latency) in &self.latencies {
+            if writes.contains(&op.as_str()) {
+                written.merge(latency);
+            } else {
+                reads.merge(latency);
+            }
+        }
+        (reads, written)
+    }
+
+    /// Operations per second across all threads.
+    pub fn throughput(&self) -> f64 {
+        self.ops as f64 / (self.elapsed_us.max(1) as f64 / 1e6)
+    }
+
+    /// The throughput of the slowest thread relative to the fastest one,
+    /// from 0 if one starved to 1 if all were equally fast, if there are
+    /// several.
+    pub fn fairness(&self) -> Option<f64> {
+        if self.threads.len() < 2 {
+            return None;
+        }
+        let rates = self.threads.iter().map(|t| t.ops_per_sec);
+        let min = rates.clone().fold(f64::INFINITY, f64::min);
+        let max = rates.fold(0.0, f64::max);
+        Some(if max > 0.0 { min / max } else { 1.0 })
+    }
+
+    fn print_space(&self) {
+        if let Some(space) = &self.space {
+            let amplification = match space.amplification {
+                Some(amplification) => format!("{amplification:.2}x"),
+                None => "N/A".to_string(),
+            };
+            println!(
+                "Space: {:.2}MiB on disk, {:.2}MiB live ({amplification} space amplification)",
+                space.disk_bytes as f64 / MIB,
+                space.live_bytes as f64 / MIB,
+            );
+        }
+    }
+
+    /// Prints the result, relative to the baseline and harness overhead
+    /// among the results of the same `session`, if any.
+    pub fn print_summary(&self, session: &[RunResult]) {
+        let Config {
+            threads: n_threads,
+            writer: bkgd_writer,
+            workload,
+            ..
+        } = self.config;
+        let unit = workload.unit();
+        let budget = match self.config.budget() {
+            Budget::Iters(n_iters) => format!("n_iters: {n_iters}"),
+            Budget::Time(duration) => format!("duration: {duration:?}"),
+        };
+        let repetition = match self.config.repeat {
+            1 => String::new(),
+            n => format!(", repetition: {}/{n}", self.repetition + 1),
+        };
+        let interrupted = if self.interrupted {
+            " (interrupted)"
+        } else {
+            ""
+        };
+        let writers = match self.config.writers {
+            0 => format!("bkgd_writer: {bkgd_writer}"),
+            n => format!("writers: {n}"),
+        };
+        let batch = match self.config.batch {
+            1 => String::new(),
+            n => format!(", batch: {n}"),
+        };
+        let durability = match self.config.durability {
+            Some(durability) => format!(", durability: {durability}"),
+            None => String::new(),
+        };
+        let keys = match self.config.key_type {
+            KeyType::Random => String::new(),
+            key_type => format!(", keys: {key_type}"),
+        };
+        let pinning = match (self.config.pin_threads, self.config.numa_node) {
+            (false, None) => String::new(),
+            (true, None) => ", pinned".to_string(),
+            (false, Some(node)) => format!(", numa node: {node}"),
+            (true, Some(node)) => format!(", pinned, numa node: {node}"),
+        };
+        let cold = if self.config.cold { ", cold" } else { "" };
+        println!(
+            "backend: {}, workload: {workload}, n_items: {}, seed: {}, n_threads: {n_threads}, {budget}, {writers}{batch}{durability}{keys}{pinning}{cold}, elapsed: {}us{repetition}{interrupted}",
+            self.backend, self.n_items, self.config.seed, self.elapsed_us
+        );
+        match &self.filesystem {
+            Some(fs) if db::is_in_memory(fs) => println!(
+                "Filesystem: {fs}, which is in memory, so I/O costs nothing (see --db-dir)"
+            ),
+            Some(fs) => println!("Filesystem: {fs}"),
+            None => {}
+        }
+        if let Some(options) = &self.mount_options {
+            println!("Mounted with: {options}");
+        }
+        if let Some(load_us) = self.load_us {
+            println!(
+                "Load phase: {} items in {load_us}us ({:.0} items/s)",
+                self.n_items,
+                self.n_items as f64 / (load_us.max(1) as f64 / 1e6)
+            );
+        }
+        if let Some(cpu) = &self.load_cpu {
+            println!("Load CPU: {}", cpu.describe("item"));
+        }
+        if self.config.load_only {
+            println!("Run phase: skipped (--load-only)");
+            self.print_space();
+            return;
+        }
+        if self.config.duration_ms.is_some() || self.interrupted {
+            println!("Completed: {} {unit}s", self.ops);
+        }
+        match self.avg_latency_us() {
+            Some(avg_latency_us) => self.print_avg_latency(session, avg_latency_us, unit),
+            None => println!("Avg latency per {unit}: N/A, with no {unit}s completed"),
+        }
+        match self.config.target_ops {
+            Some(target_ops) => println!(
+                "Throughput: {:.0} {unit}s/s (target: {target_ops:.0} {unit}s/s)",
+                self.throughput()
+            ),
+            None => match self.config.rate_per_thread {
+                Some(rate) => println!(
+                    "Throughput: {:.0} {unit}s/s (limit: {rate:.0} {unit}s/s per thread)",
+                    self.throughput()
+                ),
+                None => println!("Throughput: {:.0} {unit}s/s", self.throughput()),
+            },
+        }
+        if let Some(c) = &self.conflicts {
+            println!(
+                "Conflicts: {} of {} write transactions aborted and retried ({:.2}% conflict rate, {:.2}% of their time wasted, up to {} of {} attempts)",
+                c.aborts,
+                c.commits + c.aborts,
+                c.conflict_rate * 100.0,
+                c.wasted_work * 100.0,
+                c.max_attempts,
+                writers::MAX_ATTEMPTS
+            );
+        }
+        // Results saved by older versions only have the histograms.
+        let metrics = self
+            .latencies
+            .iter()
+            .map(|(op, latency)| (op.clone(), OpMetrics::new(latency, self.elapsed_us)))
+            .collect();
+        print_latencies("Op", &metrics);
+        if let Some(commits) = &self.commits {
+            print_latencies(
+                "Phase",
+                &BTreeMap::from([("commit".to_string(), commits.clone())]),
+            );
+        }
+        if let Some(writers) = &self.writers {
+            println!(
+                "Writers: {} threads, {} commits, {:.0} commits/s",
+                writers.threads, writers.commits, writers.commits_per_sec
+            );
+            print_latencies("Writer", &writers.metrics);
+        }
+        self.print_space();
+        if let Some(writes) = &self.writes {
+            println!(
+                "Writes: {:.2}MiB put, {:.2}MiB written to disk ({:.2}x write amplification)",
+                writes.logical_bytes as f64 / MIB,
+                writes.disk_bytes as f64 / MIB,
+                writes.amplification
+            );
+        }
+        if let Some(cpu) = &self.cpu {
+            println!("CPU: {}", cpu.describe(unit));
+        }
+        if let Some(perf) = &self.perf {
+            let per_op = |count: u64| count as f64 / self.ops.max(1) as f64;
+            println!(
+                "Perf counters: {} cycles, {} instructions ({:.2} IPC), {} LLC misses, {} branch misses",
+                perf.cycles,
+                perf.instructions,
+                perf.ipc(),
+                perf.llc_misses,
+                perf.branch_misses
+            );
+            println!(
+                "  per {unit}: {:.0} cycles, {:.0} instructions, {:.2} LLC misses, {:.2} branch misses",
+                per_op(perf.cycles),
+                per_op(perf.instructions),
+                per_op(perf.llc_misses),
+                per_op(perf.branch_misses)
+            );
+        }
+        if let Some(s) = &self.syscalls {
+            println!(
+                "Syscalls: {} writes, {} fsyncs, {} fdatasyncs, {} msyncs ({:.3} syncs per {unit})",
+                s.writes, s.fsyncs, s.fdatasyncs, s.msyncs, s.syncs_per_op
+            );
+        }
+        if let Some(m) = &self.memory {
+            println!(
+                "Memory: {:.1}MiB resident at peak, {:.1}MiB steady; {:.1}MiB mapped at peak, {:.1}MiB steady",
+                m.peak_rss_bytes as f64 / MIB,
+                m.steady_rss_bytes as f64 / MIB,
+                m.peak_mapped_bytes as f64 / MIB,
+                m.steady_mapped_bytes as f64 / MIB
+            );
+        }
+        if let Some(fairness) = self.fairness() {
+            println!(
+                "{:<10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                "Thread", "Count", "Ops/s", "Mean(us)", "p50", "p99", "Max"
+            );
+            for (i, t) in self.threads.iter().enumerate() {
+                println!(
+                    "{i:<10} {:>10} {:>10.0} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+                    t.ops, t.ops_per_sec, t.mean_us, t.p50_us, t.p99_us, t.max_us
+                );
+            }
+            println!("Fairness: {fairness:.3} (slowest / fastest thread throughput)");
+        }
+        let charted_op = self.config.spec().and_then(|spec| spec.charted_op);
+        if let Some(op) = charted_op {
+            // Reads that missed are charted apart from the ones that hit.
+            let misses = (op == Op::Read).then_some(READ_MISS);
+            for name in [Some(op.name()), misses].into_iter().flatten() {
+                if let Some(latency) = self.latencies.get(name) {
+                    println!("Latency of {name} ops:");
+                    latency.print();
+                }
+            }
+        }
+        let Some(spec) = self.config.spec() else {
+            return;
+        };
+        // Nothing to report for backends that don't persist anything.
+        if spec.track_space && self.timeline.iter().any(|sample| sample.disk_bytes > 0) {
+            println!("Disk usage vs live data:");
+            for sample in evenly_spaced(&self.timeline, MAX_SAMPLES) {
+                println!(
+                    "  {:>8}ms: {:>10.2}MiB on disk, {:>10.2}MiB live ({:.2}x)",
+                    sample.elapsed_ms,
+                    sample.disk_bytes as f64 / MIB,
+                    sample.live_bytes as f64 / MIB,
+                    sample.disk_bytes as f64 / sample.live_bytes.max(1) as f64
+                );
+            }
+        }
+        if spec.track_throughput && self.timeline.len() > 1 {
+            println!("Throughput over time:");
+            let samples = evenly_spaced(&self.timeline, MAX_SAMPLES + 1);
+            for pair in samples.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                let secs = (to.elapsed_ms - from.elapsed_ms).max(1) as f64 / 1e3;
+                let span = format!("{}..{}ms", from.elapsed_ms, to.elapsed_ms);
+                println!(
+                    "  {span:>18}: {:>10.0} ops/s, {} items at the end",
+                    (to.ops - from.ops) as f64 / secs,
+                    to.n_keys
+                );
+            }
+        }
+    }
+
+    /// Prints the average latency of an operation, compared to the runs of
+    /// the baseline and null backends, and with random keys, in `session`.
+    fn print_avg_latency(&self, session: &[RunResult], avg_latency_us: f64, unit: &str) {
+        let mut notes = Vec::new();
+        if let Some(baseline) = find(session, backend::BASELINE, &self.config)
+            && baseline.backend != self.backend
+            && let Some(baseline_us) = baseline.avg_latency_us()
+        {
+            notes.push(format!(
+                "{:.2}x {}",
+                avg_latency_us / baseline_us,
+                baseline.backend
+            ));
+        }
+        let random_keys = Config {
+            key_type: KeyType::Random,
+            ..self.config.clone()
+        };
+        if self.config.key_type != KeyType::Random
+            && let Some(random) = find(session, &self.backend, &random_keys)
+            && let Some(random_us) = random.avg_latency_us()
+        {
+            notes.push(format!("{:.2}x random keys", avg_latency_us / random_us));
+        }
+        if let Some(null) = find(session, backend::NULL, &self.config)
+            && null.backend != self.backend
+            && let Some(null_us) = null.avg_latency_us()
+        {
+            notes.push(format!(
+                "{:.3}us excluding harness overhead",
+                avg_latency_us - null_us
+            ));
+        }
+        if self.backend == backend::NULL {
+            println!("Harness overhead per {unit}: {avg_latency_us:.3}us");
+        } else if notes.is_empty() {
+            println!("Avg latency per {unit}: {avg_latency_us:.3}us");
+        } else {
+            println!(
+                "Avg latency per {unit}: {avg_latency_us:.3}us ({})",
+                notes.join(", ")
+            );
+        }
+    }
+}
+
+/// Whether `a` and `b` are results of the same backend running the same
+/// scenario, as told by [`Config::scenario`].
+fn same_scenario(a: &RunResult, b: &RunResult) -> bool {
+    a.backend == b.backend
+        && match (a.config.scenario(), b.config.scenario()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+}
+
+/// The result of the same backend and scenario as `result` among
+/// `baseline`, if any: of the same repetition, if `baseline` has it, or
+/// else of the first.
+pub fn counterpart<'b>(result: &RunResult, baseline: &'b [RunResult]) -> Option<&'b RunResult> {
+    let counterparts: Vec<_> = baseline
+        .iter()
+        .filter(|b| same_scenario(b, result))
+        .collect();
+    counterparts
+        .iter()
+        .find(|b| b.repetition == result.repetition)
+        .or(counterparts.first())
+        .copied()
+}
+
+/// The throughput and p50 latency of the operations of type `op` of
+/// `result`, or of its commits if `op` is `commit`, if it performed any.
+fn op_summary(result: &RunResult, op: &str) -> Option<(f64, f64)> {
+    let latency = match op {
+        "commit" => result.commit_latency.as_ref(),
+        _ => result.latencies.get(op),
+    }?;
+    (latency.count() > 0).then(|| {
+        (
+            OpMetrics::new(latency, result.elapsed_us).ops_per_sec,
+            latency.quantile_us(0.5),
+        )
+    })
+}
+
+/// Compares the latencies of every type of operation of `results`, and of
+/// their commits, to those of the same backend, scenario and operation
+/// among `baseline`, and describes which are significantly faster or
+/// slower. The samples compared are the p50 latencies of the repetitions of
+/// each scenario, so that telling a difference takes a few of them.
+pub fn comparison(results: &[RunResult], baseline: &[RunResult]) -> String {
+    let mut out = format!(
+        "{:<10} {:<10} {:>7} {:>12} {:>8} {:>10} {:>8} {:>10}  Verdict\n",
+        "Backend", "Op", "Runs", "Ops/s", "Change", "p50(us)", "Change", "p-value"
+    );
+    // The fields that tell the scenarios of the same backend apart.
+    let scenarios: Vec<_> = results
+        .iter()
+        .filter_map(|result| result.config.scenario().ok())
+        .collect();
+    let varying: Vec<_> = scenarios
+        .first()
+        .into_iter()
+        .flat_map(|first| first.keys())
+        .filter(|field| {
+            scenarios
+                .iter()
+                .any(|s| s.get(*field) != scenarios[0].get(*field))
+        })
+        .collect();
+    for (i, result) in results.iter().enumerate() {
+        // Every scenario once, with all its repetitions.
+        if results[..i].iter().any(|r| same_scenario(r, result)) {
+            continue;
+        }
+        let runs: Vec<_> = results
+            .iter()
+            .filter(|r| same_scenario(r, result))
+            .collect();
+        let base_runs: Vec<_> = baseline
+            .iter()
+            .filter(|b| same_scenario(b, result))
+            .collect();
+        if base_runs.is_empty() {
+            continue;
+        }
+        if !varying.is_empty() {
+            let values: Vec<_> = varying
+                .iter()
+                .map(|field| match result.config.field(field) {
+                    Some(toml::Value::String(s)) => format!("{field} = {s}"),
+                    Some(value) => format!("{field} = {value}"),
+                    None => format!("{field} unset"),
+                })
+                .collect();
+            out += &format!("{}:\n", values.join(", "));
+        }
+        let commit = result.commit_latency.as_ref().map(|_| "commit");
+        let ops = result.latencies.keys().map(String::as_str).chain(commit);
+        for op in ops {
+            let [summaries, base_summaries] = [&runs, &base_runs].map(|runs| {
+                runs.iter()
+                    .filter_map(|r| op_summary(r, op))
+                    .collect::<Vec<_>>()
+            });
+            let p50s: Vec<_> = summaries.iter().map(|&(_, p50_us)| p50_us).collect();
+            let base_p50s: Vec<_> = base_summaries.iter().map(|&(_, p50_us)| p50_us).collect();
+            let Some(test) = stats::mann_whitney(&p50s, &base_p50s) else {
+                continue;
+            };
+            let mean = |summaries: &[(f64, f64)], f: fn(&(f64, f64)) -> f64| {
+                summaries.iter().map(f).sum::<f64>() / summaries.len() as f64
+            };
+            let ops_per_sec = mean(&summaries, |s| s.0);
+            let base_ops_per_sec = mean(&base_summaries, |s| s.0);
+            let p50_us = mean(&summaries, |s| s.1);
+            let base_p50_us = mean(&base_summaries, |s| s.1);
+            let verdict = match test.effect {
+                _ if stats::min_p_value(p50s.len(), base_p50s.len()) >= stats::ALPHA => {
+                    "too few repetitions to tell"
+                }
+                _ if test.p_value >= stats::ALPHA => "no significant difference",
+                effect if effect > 0.5 => "slower",
+                _ => "faster",
+            };
+            out += &format!(
+                "{:<10} {op:<10} {:>7} {ops_per_sec:>12.0} {:>+7.1}% {p50_us:>10.3} {:>+7.1}% {:>10.4}  {verdict}\n",
+                result.backend,
+                format!("{}/{}", p50s.len(), base_p50s.len()),
+                (ops_per_sec / base_ops_per_sec - 1.0) * 100.0,
+                (p50_us / base_p50_us - 1.0) * 100.0,
+                test.p_value
+            );
+        }
+    }
+    out
+}
+
+/// Prints a table of the throughput and latencies of every result of a
+/// sweep, by backend and value of each of the `swept` fields.
+pub fn print_sweep(results: &[RunResult], swept: &[String]) {
+    let width = |field: &str| field.len().max(8);
+    print!("{:<10}", "Backend");
+    for field in swept {
+        print!(" {field:>w$}", w = width(field));
+    }
+    println!(
+        " {:>12} {:>10} {:>10} {:>12} {:>16} {:>16}",
+        "Ops/s", "p50(us)", "p99(us)", "Commits/s", "Commit p50(us)", "Commit p99(us)"
+    );
+    for result in results {
+        print!("{:<10}", result.backend);
+        for field in swept {
+            let value = match result.config.field(field) {
+                Some(toml::Value::String(s)) => s,
+                Some(value) => value.to_string(),
+                None => "-".to_string(),
+            };
+            print!(" {value:>w$}", w = width(field));
+        }
+        let latency = result.total_latency();
+        let (commits_per_sec, commit_p50_us, commit_p99_us) = match &result.commits {
+            Some(m) => (m.ops_per_sec, m.p50_us, m.p99_us),
+            None => (0.0, 0.0, 0.0),
+        };
+        println!(
+            " {:>12.0} {:>10.3} {:>10.3} {commits_per_sec:>12.0} {commit_p50_us:>16.3} {commit_p99_us:>16.3}",
+            result.throughput(),
+            latency.quantile_us(0.5),
+            latency.quantile_us(0.99),
+        );
+    }
+}
+
+/// Prints a table of how the throughput of every backend and workload scales
+/// with the number of threads: its speedup over the result with a single
+/// thread and otherwise the same config, and its parallel efficiency, the
+/// speedup per thread.
+pub fn print_thread_scaling(results: &[RunResult]) {
+    println!(
+        "{:<10} {:<12} {:>8} {:>12} {:>10} {:>10}",
+        "Backend", "Workload", "Threads", "Ops/s", "Speedup", "Efficiency"
+    );
+    for result in results {
+        let single = Config {
+            threads: 1,
+            ..result.config.clone()
+        };
+        let (speedup, efficiency) = match find(results, &result.backend, &single) {
+            Some(single) => {
+                let speedup = result.throughput() / single.throughput();
+                (
+                    format!("{speedup:.2}x"),
+                    format!("{:.1}%", speedup / result.config.threads as f64 * 100.0),
+                )
+            }
+            None => ("-".to_string(), "-".to_string()),
+        };
+        println!(
+            "{:<10} {:<12} {:>8} {:>12.0} {speedup:>10} {efficiency:>10}",
+            result.backend,
+            result.config.workload.to_string(),
+            result.config.threads,
+            result.throughput(),
+        );
+    }
+}
+
+/// Prints a table of how the latencies of reads and writes scale with the
+/// size of the DB, by backend, workload and number of seeded items.
+pub fn print_size_scaling(results: &[RunResult]) {
+    let mut results: Vec<_> = results.iter().collect();
+    results.sort_by_key(|r| (r.backend.clone(), r.config.workload.to_string(), r.n_items));
+    println!(
+        "{:<10} {:<12} {:>10} {:>12} {:>10} {:>10} {:>12} {:>10} {:>10}",
+        "Backend", "Workload", "Items", "Read (us)", "p50", "p99", "Write (us)", "p50", "p99"
+    );
+    let stats = |latency: &Histogram| match latency.count() {
+        0 => format!("{:>12} {:>10} {:>10}", "-", "-", "-"),
+        _ => format!(
+            "{:>12.3} {:>10.3} {:>10.3}",
+            latency.mean_us(),
+            latency.quantile_us(0.5),
+            latency.quantile_us(0.99)
+        ),
+    };
+    for result in results {
+        let (reads, writes) = result.read_write_latencies();
+        println!(
+            "{:<10} {:<12} {:>10} {} {}",
+            result.backend,
+            result.config.workload.to_string(),
+            result.n_items,
+            stats(&reads),
+            stats(&writes),
+        );
+    }
+}
+
+/// Prints a table of the throughput and space and write amplifications of
+/// every result, by backend, workload and size of the values.
+pub fn print_value_sizes(results: &[RunResult]) {
+    println!(
+        "{:<10} {:<12} {:>12} {:>12} {:>12} {:>12} {:>10} {:>10}",
+        "Backend",
+        "Workload",
+        "Value size",
+        "Ops/s",
+        "Disk (MiB)",
+        "Live (MiB)",
+        "Space amp",
+        "Write amp"
+    );
+    for result in results {
+        let value_size = match result.config.value_size {
+            Some(size) => size.to_string(),
+            None => "-".to_string(),
+        };
+        let write_amplification = match &result.writes {
+            Some(writes) => format!("{:.2}", writes.amplification),
+            None => "-".to_string(),
+        };
+        let space = match &result.space {
+            Some(s) => format!(
+                "{:>12.2} {:>12.2} {:>10}",
+                s.disk_bytes as f64 / MIB,
+                s.live_bytes as f64 / MIB,
+                match s.amplification {
+                    Some(amplification) => format!("{amplification:.2}"),
+                    None => "N/A".to_string(),
+                }
+            ),
+            None => format!("{:>12} {:>12} {:>10}", "-", "-", "-"),
+        };
+        println!(
+            "{:<10} {:<12} {value_size:>12} {:>12.0} {space} {write_amplification:>10}",
+            result.backend,
+            result.config.workload.to_string(),
+            result.throughput(),
+        );
+    }
+}
+
+/// Prints the mean, standard deviation and 95% confidence interval of the
+/// metrics of `repetitions`, the results of repeated runs of one backend.
+pub fn print_repetitions(repetitions: &[RunResult]) {
+    let mut metrics: Vec<(String, Vec<f64>)> = vec![
+        (
+            "Ops/s".to_string(),
+            repetitions.iter().map(RunResult::throughput).collect(),
+        ),
+        (
+            "Avg latency(us)".to_string(),
+            repetitions
+                .iter()
+                .filter_map(RunResult::avg_latency_us)
+                .collect(),
+        ),
+    ];
+    for op in repetitions[0].latencies.keys() {
+        for (name, q) in [("p50", 0.5), ("p99", 0.99)] {
+            let values: Vec<_> = repetitions
+                .iter()
+                .filter_map(|r| r.latencies.get(op))
+                .filter(|latency| latency.count() > 0)
+                .map(|latency| latency.quantile_us(q))
+                .collect();
+            if values.len() == repetitions.len() {
+                metrics.push((format!("{op} {name}(us)"), values));
+            }
+        }
+    }
+    println!(
+        "Across {} repetitions of {}:",
+        repetitions.len(),
+        repetitions[0].backend
+    );
+    println!(
+        "  {:<22} {:>14} {:>12} {:>30}",
+        "Metric", "Mean", "Stddev", "95% CI"
+    );
+    for (name, values) in metrics {
+        let summary = stats::summarize(&values);
+        let ci = format!(
+            "[{:.3}, {:.3}]",
+            summary.mean - summary.ci95,
+            summary.mean + summary.ci95
+        );
+        println!(
+            "  {name:<22} {:>14.3} {:>12.3} {ci:>30}",
+            summary.mean, summary.stddev
+        );
+    }
+}
+
+/// Prints a table row of the latency `metrics` of every type of operation,
+/// under a header whose first column is `what`.
+fn print_latencies(what: &str, metrics: &BTreeMap<String, OpMetrics>) {
+    if metrics.is_empty() {
+        return;
+    }
+    println!(
+        "{what:<10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "Count", "Ops/s", "Mean(us)", "p50", "p90", "p99", "p99.9", "Max"
+    );
+    for (op, m) in metrics {
+        let mut line = format!("{op:<10} {:>10} {:>10.0}", m.count, m.ops_per_sec);
+        for us in [m.mean_us, m.p50_us, m.p90_us, m.p99_us, m.p999_us, m.max_us] {
+            line += &format!(" {us:>10.3}");
+        }
+        println!("{line}");
+    }
+}
+
+/// Picks at most `n` evenly spaced samples, including the first and the
+/// last.
+fn evenly_spaced(timeline: &[Sample], n: usize) -> Vec<&Sample> {
+    let n = timeline.len().min(n);
+    (0..n)
+        .map(|i| &timeline[i * (timeline.len() - 1) / (n - 1).max(1)])
+        .collect()
+}
+
+/// Finds the result of `backend` running the same scenario as `config`,
+/// among those of a sweep.
+fn find<'r>(results: &'r [RunResult], backend: &str, config: &Config) -> Option<&'r RunResult> {
+    let scenario = toml::Table::try_from(config).ok();
+    results
+        .iter()
+        .find(|r| r.backend == backend && toml::Table::try_from(&r.config).ok() == scenario)
+}
+
+/// Formats `document` as `output`, which mustn't be [`Output::Text`].
+pub fn format(document: &Document, output: Output) -> Result<String> {
+    match output {
+        Output::Text => unreachable!("text output is printed as it goes"),
+        Output::Json => Ok(serde_json::to_string_pretty(document)?),
+        Output::Csv => Ok(to_csv(document)),
+        Output::Markdown => Ok(to_markdown(document)),
+        Output::Html => Ok(html::render(document)),
+    }
+}
+
+/// Formats `document` as a Markdown table, with rows per backend grouped by
+/// workload: one of all types of operations together, followed by one per
+/// type if there are several, as in [`RunResult::breakdown`].
+fn to_markdown(document: &Document) -> String {
+    let mut results: Vec<_> = document.results.iter().collect();
+    results.sort_by_key(|r| r.config.workload.to_string());
+    let mut md = "| Workload | Backend | Op | Throughput | p50 (us) | p99 (us) |\n".to_string();
+    md += "|---|---|---|--:|--:|--:|\n";
+    for result in results {
+        for (op, throughput, latency) in result.breakdown() {
+            let [p50_us, p99_us] = [0.5, 0.99].map(|q| match latency.count() {
+                0 => "-".to_string(),
+                _ => format!("{:.3}", latency.quantile_us(q)),
+            });
+            let unit = match op.as_str() {
+                "all" => result.config.workload.unit(),
+                _ => "op",
+            };
+            md += &format!(
+                "| {} | {} | {op} | {throughput:.0} {unit}s/s | {p50_us} | {p99_us} |\n",
+                result.config.workload, result.backend,
+            );
+        }
+    }
+    for skipped in &document.skipped {
+        md += &format!(
+            "| {} | {} | all | N/A | - | - |\n",
+            skipped.workload, skipped.backend
+        );
+    }
+    md
+}
+
+/// Formats the metrics of `document` as CSV, in long form. The metrics of
+/// the whole run are under the `all` op.
+fn to_csv(document: &Document) -> String {
+    let mut csv = "backend,workload,op,metric,value\n".to_string();
+    for result in &document.results {
+        let mut row = |op: &str, metric: &str, value: f64| {
+            csv += &format!(
+                "{},{},{op},{metric},{value}\n",
+                result.backend, result.config.workload
+            );
+        };
+        row("all", "ops", result.ops as f64);
+        row("all", "elapsed_us", result.elapsed_us as f64);
+        row("all", "ops_per_sec", result.throughput());
+        if let Some(avg_latency_us) = result.avg_latency_us() {
+            row("all", "avg_latency_us", avg_latency_us);
+        }
+        if let Some(load_us) = result.load_us {
+            row("all", "load_us", load_us as f64);
+            row(
+                "all",
+                "load_items_per_sec",
+                result.n_items as f64 / (load_us.max(1) as f64 / 1e6),
+            );
+        }
+        if let Some(fairness) = result.fairness() {
+            row("all", "fairness", fairness);
+        }
+        if let Some(space) = &result.space {
+            row("space", "disk_bytes", space.disk_bytes as f64);
+            row("space", "live_bytes", space.live_bytes as f64);
+            if let Some(amplification) = space.amplification {
+                row("space", "amplification", amplification);
+            }
+        }
+        if let Some(writes) = &result.writes {
+            row("writes", "logical_bytes", writes.logical_bytes as f64);
+            row("writes", "disk_bytes", writes.disk_bytes as f64);
+            row("writes", "amplification", writes.amplification);
+        }
+        for (phase, cpu) in [("cpu", &result.cpu), ("load-cpu", &result.load_cpu)] {
+            if let Some(cpu) = cpu {
+                row(phase, "user_us", cpu.user_us as f64);
+                row(phase, "system_us", cpu.system_us as f64);
+                row(phase, "cores", cpu.cores);
+                row(phase, "us_per_op", cpu.us_per_op);
+            }
+        }
+        if let Some(perf) = &result.perf {
+            row("perf", "cycles", perf.cycles as f64);
+            row("perf", "instructions", perf.instructions as f64);
+            row("perf", "ipc", perf.ipc());
+            row("perf", "llc_misses", perf.llc_misses as f64);
+            row("perf", "branch_misses", perf.branch_misses as f64);
+        }
+        if let Some(s) = &result.syscalls {
+            row("syscalls", "writes", s.writes as f64);
+            row("syscalls", "fsyncs", s.fsyncs as f64);
+            row("syscalls", "fdatasyncs", s.fdatasyncs as f64);
+            row("syscalls", "msyncs", s.msyncs as f64);
+            row("syscalls", "syncs_per_op", s.syncs_per_op);
+        }
+        if let Some(m) = &result.memory {
+            row("memory", "peak_rss_bytes", m.peak_rss_bytes as f64);
+            row("memory", "steady_rss_bytes", m.steady_rss_bytes as f64);
+            row("memory", "peak_mapped_bytes", m.peak_mapped_bytes as f64);
+            row(
+                "memory",
+                "steady_mapped_bytes",
+                m.steady_mapped_bytes as f64,
+            );
+        }
+        if let Some(m) = &result.commits {
+            row("commit", "count", m.count as f64);
+            row("commit", "ops_per_sec", m.ops_per_sec);
+            row("commit", "mean_us", m.mean_us);
+            row("commit", "p50_us", m.p50_us);
+            row("commit", "p90_us", m.p90_us);
+            row("commit", "p99_us", m.p99_us);
+            row("commit", "p999_us", m.p999_us);
+            row("commit", "max_us", m.max_us);
+        }
+        if let Some(c) = &result.conflicts {
+            row("conflicts", "commits", c.commits as f64);
+            row("conflicts", "aborts", c.aborts as f64);
+            row("conflicts", "conflict_rate", c.conflict_rate);
+            row("conflicts", "wasted_work", c.wasted_work);
+            row("conflicts", "max_attempts", c.max_attempts as f64);
+        }
+        if let Some(writers) = &result.writers {
+            row("writers", "threads", writers.threads as f64);
+            row("writers", "commits", writers.commits as f64);
+            row("writers", "commits_per_sec", writers.commits_per_sec);
+            for (op, m) in &writers.metrics {
+                let op = format!("writer-{op}");
+                row(&op, "mean_us", m.mean_us);
+                row(&op, "p50_us", m.p50_us);
+                row(&op, "p99_us", m.p99_us);
+                row(&op, "max_us", m.max_us);
+            }
+        }
+        for (op, m) in &result.metrics {
+            row(op, "count", m.count as f64);
+            row(op, "ops_per_sec", m.ops_per_sec);
+            row(op, "mean_us", m.mean_us);
+            row(op, "p50_us", m.p50_us);
+            row(op, "p90_us", m.p90_us);
+            row(op, "p99_us", m.p99_us);
+            row(op, "p999_us", m.p999_us);
+            row(op, "max_us", m.max_us);
+        }
+        for (i, t) in result.threads.iter().enumerate() {
+            let thread = format!("thread-{i}");
+            row(&thread, "ops", t.ops as f64);
+            row(&thread, "ops_per_sec", t.ops_per_sec);
+            row(&thread, "mean_us", t.mean_us);
+            row(&thread, "p50_us", t.p50_us);
+            row(&thread, "p99_us", t.p99_us);
+            row(&thread, "max_us", t.max_us);
+        }
+    }
+    csv
+}
+
+/// Saves `document` as `output` to `path`.
+pub fn save(path: &Path, document: &Document, output: Output) -> Result<()> {
+    fs::write(path, format(document, output)?)
+        .with_context(|| format!("failed to write results {}", path.display()))
+}
+
+/// Loads results saved by [`save`] as JSON, or by older versions as a bare
+/// list of results.
+pub fn load(path: &Path) -> Result<Document> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Saved {
+        Document(Box<Document>),
+        Results(Vec<RunResult>),
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read results {}", path.display()))?;
+    let saved = serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse results {}", path.display()))?;
+    Ok(match saved {
+        Saved::Document(document) => *document,
+        Saved::Results(results) => Document {
+            environment: Environment::default(),
+            results,
+            skipped: Vec::new(),
+        },
+    })
+}
+
+/// Runs `db-cmp report`: renders the results saved in `files` as `output`,
+/// and plots them to `plot_dir`, if any.
+pub fn report(files: &[PathBuf], output: Output, plot_dir: Option<&Path>) -> Result<()> {
+    if let Some(plot_dir) = plot_dir {
+        let mut results = Vec::new();
+        for file in files {
+            results.extend(load(file)?.results);
+        }
+        plot::save(plot_dir, &results)?;
+    }
+    if output == Output::Text {
+        for file in files {
+            println!("{}:", file.display());
+            let results = load(file)?.results;
+            for result in &results {
+                result.print_summary(&results);
+            }
+        }
+        return Ok(());
+    }
+    // All the results together, in the environment of the first file.
+    let mut documents = files.iter().map(|file| load(file));
+    let mut document = documents.next().unwrap()?;
+    for other in documents {
+        document.results.extend(other?.results);
+    }
+    println!("{}", format(&document, output)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner;
+
+    /// The results of a short ycsb-a run on btreemap, which reads and
+    /// updates, and of the reverse-scan workload skipped on byodb.
+    fn document() -> Document {
+        let config = Config {
+            backends: vec!["btreemap".to_string()],
+            workload: Workload::YcsbA,
+            items: 100,
+            iters: 200,
+            baseline: false,
+            calibrate: false,
+            ..Config::default()
+        };
+        let skipped = Skipped {
+            backend: "byodb".to_string(),
+            workload: Workload::ReverseScan,
+            reason: "no reverse scans".to_string(),
+        };
+        Document::new(runner::run(&config).unwrap(), vec![skipped])
+    }
+
+    #[test]
+    fn markdown_has_a_row_per_op() {
+        let md = format(&document(), Output::Markdown).unwrap();
+        let lines: Vec<_> = md.lines().collect();
+        assert_eq!(
+            lines[..2],
+            [
+                "| Workload | Backend | Op | Throughput | p50 (us) | p99 (us) |",
+                "|---|---|---|--:|--:|--:|"
+            ]
+        );
+        for op in ["all", "read", "update", "commit"] {
+            let row = format!("| ycsb-a | btreemap | {op} | ");
+            assert!(lines.iter().any(|l| l.starts_with(&row)), "{op}:\n{md}");
+        }
+        assert_eq!(
+            lines.last(),
+            Some(&"| reverse-scan | byodb | all | N/A | - | - |")
+        );
+    }
+
+    #[test]
+    fn csv_is_in_long_form() {
+        let csv = format(&document(), Output::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("backend,workload,op,metric,value"));
+        let rows: Vec<Vec<_>> = lines.map(|l| l.split(',').collect()).collect();
+        assert!(rows.iter().all(|row| row.len() == 5), "{csv}");
+        assert!(rows.iter().all(|row| row[..2] == ["btreemap", "ycsb-a"]));
+        assert!(rows.contains(&vec!["btreemap", "ycsb-a", "all", "ops", "200"]));
+        assert!(
+            rows.iter()
+                .all(|row| row[4].parse::<f64>().is_ok_and(f64::is_finite)),
+            "{csv}"
+        );
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let json = format(&document(), Output::Json).unwrap();
+        let document: Document = serde_json::from_str(&json).unwrap();
+        let [result] = &document.results[..] else {
+            panic!("expected a single result");
+        };
+        assert_eq!(result.backend, "btreemap");
+        assert_eq!(result.ops, 200);
+        assert_eq!(document.skipped[0].workload, Workload::ReverseScan);
+        assert_eq!(
+            result.metrics["read"].count + result.metrics["update"].count,
+            200
+        );
+    }
+}
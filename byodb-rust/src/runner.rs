@@ -0,0 +1,652 @@
+//! Runs the workloads of a [`Config`] on the backends, the way `db-cmp run`
+//! does, for other programs to embed the harness, e.g. in their own
+//! integration tests or benches.
+
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::backend::{self, Backend};
+use crate::cli::{OutputArgs, RunArgs};
+use crate::config::Config;
+#[cfg(feature = "history")]
+use crate::history;
+use crate::process::CpuTime;
+use crate::results::{self, Document, Output, RunResult, Skipped, SpaceMetrics};
+use crate::shadow::Shadow;
+use crate::timeline::Monitor;
+use crate::trace::Trace;
+#[cfg(feature = "tui")]
+use crate::tui::Dashboard;
+use crate::workload::Workload;
+use crate::{
+    access_log, affinity, bench, db, gate, interrupt, metrics, ops, plot, progress, seed,
+    seed_cache, spans, stats, timeline,
+};
+
+/// Runs every scenario of `config` on its backends, after the null and
+/// baseline ones if enabled, quietly and without monitors, replaying the
+/// trace or access log of `config`, if any. Returns the results, without
+/// the scenarios a backend doesn't support, and only those so far if
+/// interrupted.
+pub fn run(config: &Config) -> Result<Vec<RunResult>> {
+    let mut config = config.clone();
+    config.validate()?;
+    let replay = load_replay(&mut config)?;
+    let options = Options {
+        quiet: true,
+        ..Options::default()
+    };
+    Ok(run_with(&config, replay.as_ref(), options)?.results)
+}
+
+/// What a run does besides measuring, which `db-cmp run` sets from its
+/// command line, and [`run`] leaves out.
+#[derive(Default)]
+pub struct Options<'a> {
+    /// Hides the progress of the load phases, and unless printing
+    /// summaries, what is skipped.
+    pub quiet: bool,
+    /// Prints the summary of every result as it comes, of the repetitions
+    /// of every scenario, and of the sweeps.
+    pub print_summaries: bool,
+    /// Where to save the flamegraphs of profiled runs (default: the current
+    /// directory).
+    pub profile_dir: Option<&'a Path>,
+    pub monitors: Option<&'a mut Monitors<'a>>,
+}
+
+/// Makes the monitors of the measured phase of a backend running a scenario
+/// on a DB of the given number of items.
+pub type Monitors<'a> = dyn FnMut(&str, &Config, usize) -> Vec<Box<dyn Monitor>> + 'a;
+
+/// The results of a run, and the backends it skipped.
+pub struct Runs {
+    pub results: Vec<RunResult>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// Runs every scenario of `config`, validated first, on its backends, after
+/// the null and baseline ones if enabled, and every repetition of each.
+/// Performs the steps of `replay` instead, if any, as loaded by
+/// [`load_replay`]. Swept durability levels a backend doesn't support, and
+/// workloads it doesn't support, are skipped. Stops early, with the
+/// results so far, if interrupted.
+pub fn run_with(config: &Config, replay: Option<&Trace>, options: Options) -> Result<Runs> {
+    config.validate()?;
+    let Options {
+        quiet,
+        print_summaries,
+        profile_dir,
+        mut monitors,
+    } = options;
+    let scenarios = config.scenarios()?;
+    let swept = config.swept_fields();
+    let mut results: Vec<RunResult> = Vec::new();
+    let mut skipped = Vec::new();
+    'backends: for backend in &backends(config) {
+        for config in &scenarios {
+            // Swept durability levels are skipped where they don't apply,
+            // unlike one set explicitly.
+            if let Some(durability) = config.durability
+                && swept.iter().any(|field| field == "durability")
+                && !backend::supports(backend, durability)?
+            {
+                if print_summaries || !quiet {
+                    eprintln!(
+                        "Skipping {backend} with durability {durability}, which it doesn't support"
+                    );
+                }
+                continue;
+            }
+            let first = results.len();
+            for repetition in 0..config.repeat {
+                let _span = tracing::info_span!(
+                    "run",
+                    backend = backend.as_str(),
+                    workload = %config.workload,
+                    repetition
+                )
+                .entered();
+                let result = run_backend(
+                    backend,
+                    config,
+                    replay,
+                    quiet,
+                    profile_dir,
+                    &mut |n_items| match &mut monitors {
+                        Some(monitors) => monitors(backend, config, n_items),
+                        None => Vec::new(),
+                    },
+                )
+                .with_context(|| format!("failed to benchmark {backend}"))?;
+                let mut result = match result {
+                    Ran::Done(result) => *result,
+                    Ran::Interrupted => break 'backends,
+                    Ran::Unsupported(reason) => {
+                        if print_summaries || !quiet {
+                            eprintln!("Skipping {backend}: {reason}");
+                        }
+                        skipped.push(Skipped {
+                            backend: backend.clone(),
+                            workload: config.workload,
+                            reason,
+                        });
+                        break;
+                    }
+                };
+                result.repetition = repetition;
+                result.interrupted = interrupt::is_interrupted();
+                if print_summaries {
+                    result.print_summary(&results);
+                }
+                results.push(result);
+                if interrupt::is_interrupted() {
+                    break 'backends;
+                }
+            }
+            // None if the backend was skipped.
+            if print_summaries && config.repeat > 1 && results.len() > first {
+                results::print_repetitions(&results[first..]);
+            }
+        }
+    }
+    if print_summaries && !swept.is_empty() {
+        results::print_sweep(&results, &swept);
+        if swept.iter().any(|field| field == "threads") {
+            results::print_thread_scaling(&results);
+        }
+        if swept.iter().any(|field| field == "items") {
+            results::print_size_scaling(&results);
+        }
+        if swept.iter().any(|field| field == "value_size") {
+            results::print_value_sizes(&results);
+        }
+    }
+    Ok(Runs { results, skipped })
+}
+
+/// Loads the trace or access log that `config` replays, if any, and makes
+/// `config` run the workload it was recorded with.
+pub fn load_replay(config: &mut Config) -> Result<Option<Trace>> {
+    let trace = match (&config.replay, &config.access_log) {
+        (Some(file), _) => Trace::load(file)?,
+        (None, Some(file)) => access_log::load(file, config)?,
+        (None, None) => return Ok(None),
+    };
+    trace.apply(config);
+    ensure!(
+        config.spec().is_some(),
+        "the trace isn't of an op-based workload"
+    );
+    ensure!(
+        config.sweep.is_empty() && !config.batch_sweep,
+        "traces can't be replayed with a sweep of the workload"
+    );
+    ensure!(
+        config.warmup.is_none(),
+        "traces can't be replayed after a warmup"
+    );
+    Ok(Some(trace))
+}
+
+/// The backends of `config`, after the null and baseline ones if enabled,
+/// in the order they're run.
+pub fn backends(config: &Config) -> Vec<String> {
+    let mut backends = config.backends.clone();
+    if config.baseline && !backends.iter().any(|b| b == backend::BASELINE) {
+        backends.insert(0, backend::BASELINE.to_string());
+    }
+    if config.calibrate && !backends.iter().any(|b| b == backend::NULL) {
+        backends.insert(0, backend::NULL.to_string());
+    }
+    backends
+}
+
+/// How a run of a backend ended.
+pub enum Ran {
+    Done(Box<RunResult>),
+    /// Interrupted before measuring anything.
+    Interrupted,
+    /// The backend doesn't support the workload, for this reason.
+    Unsupported(String),
+}
+
+/// Runs the workload of `config` on `backend`, on the DB at `config.db_path`
+/// if set, or else on a temporary one it loads.
+/// Performs the steps of `replay` instead, if any, and shows the progress
+/// of the load phase unless `quiet`. The measured phase shows its progress
+/// on the monitors made by `monitors` for the number of items of the DB,
+/// and saves its flamegraph, if profiled, to `profile_dir` (default: the
+/// current directory).
+pub fn run_backend(
+    backend: &str,
+    config: &Config,
+    replay: Option<&Trace>,
+    quiet: bool,
+    profile_dir: Option<&Path>,
+    monitors: &mut dyn FnMut(usize) -> Vec<Box<dyn Monitor>>,
+) -> Result<Ran> {
+    // Before the DB is opened, so that everything it allocates is on the
+    // node, and the threads started from here on inherit the binding.
+    if let Some(node) = config.numa_node {
+        affinity::bind_to_node(node)?;
+    }
+    // Setup, timing the load phase if the DB is seeded here.
+    let opts = config.backend_opts_for(backend)?;
+    // On an empty DB of its own, so that the backend isn't loaded only to
+    // be skipped.
+    if config.workload == Workload::ReverseScan {
+        let (db, _temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+        if !db.reverse_scans() {
+            return Ok(Ran::Unsupported(format!(
+                "{backend} doesn't support reverse scans"
+            )));
+        }
+    }
+    // The baseline and null backends load temporary DBs of their own.
+    let load_into_path = config.load_only && config.db_path.is_some();
+    let (db, temp_dir, load_elapsed, load_cpu) = match &config.db_path {
+        Some(path) if load_into_path && backend == config.single_backend()? => {
+            ensure!(!path.exists(), "DB {} already exists", path.display());
+            let db = backend::open(backend, path, &opts)?;
+            let (elapsed, cpu) = load(&*db, config, replay, quiet)?;
+            (db, None, Some(elapsed), Some(cpu))
+        }
+        // The baseline and null backends can't open another backend's DB, so
+        // they get a copy.
+        Some(path) if !load_into_path && backend != config.single_backend()? => {
+            let src_backend = config.single_backend()?;
+            let src = db::open_existing(src_backend, path, &config.backend_opts_for(src_backend)?)?;
+            let (db, temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+            seed::copy_items(&*src, &*db)?;
+            (db, Some(temp_dir), None, None)
+        }
+        Some(path) if !load_into_path => {
+            (db::open_existing(backend, path, &opts)?, None, None, None)
+        }
+        _ => {
+            // The items of traces aren't cached.
+            let cache_entry = match &config.seed_cache {
+                Some(dir) if replay.is_none_or(|trace| trace.items.is_none()) => {
+                    Some(seed_cache::entry(dir, backend, config)?)
+                }
+                _ => None,
+            };
+            match cache_entry {
+                Some(entry) if entry.exists() => {
+                    let temp_dir = db::temp_dir(config.db_dir.as_deref())?;
+                    let path = db::temp_path(&temp_dir);
+                    db::copy(&entry, &path)?;
+                    (
+                        db::open_existing(backend, &path, &opts)?,
+                        Some(temp_dir),
+                        None,
+                        None,
+                    )
+                }
+                _ => {
+                    let (db, temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+                    let (elapsed, cpu) = load(&*db, config, replay, quiet)?;
+                    let path = db::temp_path(&temp_dir);
+                    let db = match cache_entry {
+                        // Backends that don't persist anything have nothing
+                        // to cache.
+                        Some(entry) if path.exists() => {
+                            drop(db);
+                            seed_cache::store(&path, &entry)?;
+                            backend::open(backend, &path, &opts)?
+                        }
+                        _ => db,
+                    };
+                    (db, Some(temp_dir), Some(elapsed), Some(cpu))
+                }
+            }
+        }
+    };
+    let path = match (&temp_dir, &config.db_path) {
+        (Some(temp_dir), _) => db::temp_path(temp_dir),
+        (None, path) => path.clone().unwrap(),
+    };
+    let db: Arc<dyn Backend> = Arc::from(db);
+    let n_items = seed::count_items(&*db)?;
+    // Interrupted while seeding, before measuring anything.
+    if interrupt::is_interrupted() {
+        return Ok(Ran::Interrupted);
+    }
+    if config.load_only {
+        let live_bytes = seed::live_bytes(&*db)?;
+        drop(db);
+        let space = SpaceMetrics::new(db::disk_usage(&path)?, live_bytes);
+        let mut result = RunResult::new(
+            backend,
+            config.clone(),
+            n_items,
+            load_elapsed,
+            load_cpu,
+            ops::Outcome::default(),
+            space,
+        );
+        result.cpu = None;
+        if result.space.is_some() {
+            result.filesystem = db::filesystem(&path);
+            result.mount_options = db::mount_options(&path);
+        }
+        return Ok(Ran::Done(Box::new(result)));
+    }
+    let db = if config.cold {
+        reopen_cold(backend, db, &path, config)?
+    } else {
+        db
+    };
+
+    // Dropped before the summary is printed, e.g. to give the terminal back.
+    let mut monitors = monitors(n_items);
+    let monitor = (!monitors.is_empty()).then_some(&mut monitors as &mut dyn Monitor);
+    // The null backend drops everything it's given.
+    let shadow = (config.shadow && backend != backend::NULL)
+        .then(|| Shadow::load(&*db))
+        .transpose()?
+        .map(Arc::new);
+    let mut outcome = match config.spec() {
+        None => bench::bench_readers(&db, config, shadow.as_ref(), monitor)?,
+        Some(spec) => ops::run(&db, &path, config, &spec, shadow.as_ref(), replay, monitor)?,
+    };
+    if let Some(shadow) = &shadow {
+        shadow.verify(&*db)?;
+    }
+    // Only the first backend's run is recorded, without the calibration
+    // ones.
+    if let Some(trace) = outcome.trace.take()
+        && let Some(file) = &config.record
+        && backend == config.backends[0]
+    {
+        trace.save(file)?;
+    }
+    if let Some(svg) = outcome.flamegraph.take() {
+        let dir = profile_dir.unwrap_or(Path::new("."));
+        let file = dir.join(format!("{backend}-{}.svg", config.workload));
+        fs::create_dir_all(dir)
+            .and_then(|()| fs::write(&file, svg))
+            .with_context(|| format!("failed to save flamegraph {}", file.display()))?;
+    }
+    // The files are measured once the DB is closed, so that the writes it
+    // buffered count.
+    let live_bytes = seed::live_bytes(&*db)?;
+    drop(db);
+    let space = SpaceMetrics::new(db::disk_usage(&path)?, live_bytes);
+    let mut result = RunResult::new(
+        backend,
+        config.clone(),
+        n_items,
+        load_elapsed,
+        load_cpu,
+        outcome,
+        space,
+    );
+    // Only backends that persist something have files.
+    if result.space.is_some() {
+        result.filesystem = db::filesystem(&path);
+        result.mount_options = db::mount_options(&path);
+    }
+    Ok(Ran::Done(Box::new(result)))
+}
+
+/// Seeds the empty `db` with the items of `config`, or of `replay` if it has
+/// any, showing their progress unless `quiet`. Returns how long it took, in
+/// wall-clock and CPU time.
+fn load(
+    db: &dyn Backend,
+    config: &Config,
+    replay: Option<&Trace>,
+    quiet: bool,
+) -> Result<(Duration, CpuTime)> {
+    let start_time = Instant::now();
+    let cpu = CpuTime::now();
+    let items = match replay.and_then(|trace| trace.items.clone()) {
+        Some(items) => Box::new(items.into_iter()),
+        None => items(config, quiet)?,
+    };
+    seed::seed_db_parallel(items, db, config.seed_threads, config.seed_batch)?;
+    Ok((start_time.elapsed(), CpuTime::now().since(&cpu)))
+}
+
+/// Closes `db`, evicts its files from the OS page cache and reopens it,
+/// unless it doesn't persist anything.
+fn reopen_cold(
+    backend: &str,
+    db: Arc<dyn Backend>,
+    path: &Path,
+    config: &Config,
+) -> Result<Arc<dyn Backend>> {
+    if db::disk_usage(path)? == 0 {
+        return Ok(db);
+    }
+    drop(db);
+    db::evict_from_cache(path)?;
+    Ok(Arc::from(db::open_existing(
+        backend,
+        path,
+        &config.backend_opts_for(backend)?,
+    )?))
+}
+
+/// The items to seed the DB with, showing their progress unless `quiet`.
+pub fn items(config: &Config, quiet: bool) -> Result<Box<dyn Iterator<Item = (String, String)>>> {
+    let items = seed::items(config)?;
+    if quiet {
+        return Ok(items);
+    }
+    // Dumps know how many items they have.
+    let n = items.size_hint().1.unwrap_or(config.items);
+    Ok(Box::new(progress::seeding(items, n)))
+}
+
+/// Runs `db-cmp run`: [`run_with`] the config of `args`, then reports the
+/// results as its outputs ask, and fails if they missed any target.
+pub fn command(mut args: RunArgs, quiet: bool) -> Result<()> {
+    let mut outputs = mem::take(&mut args.outputs);
+    let mut config = args.into_config()?;
+    config.validate()?;
+    let replay = load_replay(&mut config)?;
+    let output = outputs.output();
+    let out_file = outputs.out_file.take();
+    if output == Output::Text && out_file.is_some() {
+        bail!("text output can only be printed, not saved to --out-file");
+    }
+    // Fail early if the baseline can't be loaded.
+    let baseline = match outputs.baseline_file.take() {
+        Some(file) => {
+            let baseline = results::load(&file)?;
+            Some((file, baseline))
+        }
+        None => None,
+    };
+    let metrics = match outputs.metrics_addr {
+        Some(addr) => Some(metrics::Server::start(addr)?),
+        None => None,
+    };
+    // Summaries would get mixed up with a document printed to stdout.
+    let print_summaries = output == Output::Text || out_file.is_some();
+    let targets = outputs.targets();
+    // Only once the config is known to be valid.
+    if let Some(file) = &outputs.manifest {
+        let manifest = config.manifest()?;
+        match file.to_str() {
+            Some("-") if print_summaries => println!("{manifest}"),
+            Some("-") => eprintln!("{manifest}"),
+            _ => fs::write(file, manifest)
+                .with_context(|| format!("failed to write manifest {}", file.display()))?,
+        }
+    }
+    // Flushed once the run is done, even if it failed.
+    let _spans = match &outputs.trace_output {
+        Some(file) => Some(spans::Output::install(file)?),
+        None => None,
+    };
+    interrupt::install_handler()?;
+    let mut monitors = |backend: &str, config: &Config, n_items| {
+        monitors(&outputs, quiet, metrics.as_ref(), backend, config, n_items)
+    };
+    let Runs { results, skipped } = run_with(
+        &config,
+        replay.as_ref(),
+        Options {
+            quiet,
+            print_summaries,
+            profile_dir: outputs.profile_dir.as_deref(),
+            monitors: Some(&mut monitors),
+        },
+    )?;
+    if let Some(timeline_file) = outputs.timeline_file {
+        timeline::save(&timeline_file, &results)?;
+    }
+    if let Some(plot_dir) = outputs.plot_dir {
+        plot::save(&plot_dir, &results)?;
+    }
+    let mut violations = Vec::new();
+    if let Some((baseline_file, baseline)) = baseline {
+        if let Some(condition) = &outputs.fail_if {
+            violations = condition.check(&results, &baseline.results)?;
+        }
+        let comparison = results::comparison(&results, &baseline.results);
+        let header = format!(
+            "Compared to {} (Mann-Whitney U test of the p50 latencies of the repetitions, significant below p = {}):",
+            baseline_file.display(),
+            stats::ALPHA
+        );
+        if print_summaries {
+            print!("{header}\n{comparison}");
+        } else {
+            eprint!("{header}\n{comparison}");
+        }
+    }
+    // The backends added for reference aren't held to the targets.
+    let misses = gate::missed_targets(
+        &targets,
+        results
+            .iter()
+            .filter(|result| config.backends.contains(&result.backend)),
+    );
+    let document = Document::new(results, skipped);
+    #[cfg(feature = "history")]
+    if let Some(history) = outputs.history {
+        history::record(&history, &document)?;
+    }
+    if output != Output::Text {
+        match out_file {
+            Some(out_file) => results::save(&out_file, &document, output)?,
+            None => println!("{}", results::format(&document, output)?),
+        }
+    }
+    for violation in &violations {
+        eprintln!("{violation}");
+    }
+    for miss in &misses {
+        eprintln!("{miss}");
+    }
+    let mut failures = Vec::new();
+    if !violations.is_empty() {
+        failures.push(format!(
+            "{} of the results failed --fail-if {}",
+            violations.len(),
+            outputs.fail_if.unwrap()
+        ));
+    }
+    if !misses.is_empty() {
+        failures.push(format!("{} --assert-* targets were missed", misses.len()));
+    }
+    ensure!(failures.is_empty(), "{}", failures.join(", and "));
+    Ok(())
+}
+
+/// Creates what shows or exports the progress of the measured phase of
+/// `backend` running on a DB of `n_items`.
+#[cfg_attr(not(feature = "tui"), allow(unused_variables))]
+fn monitors(
+    outputs: &OutputArgs,
+    quiet: bool,
+    metrics: Option<&metrics::Server>,
+    backend: &str,
+    config: &Config,
+    n_items: usize,
+) -> Vec<Box<dyn Monitor>> {
+    let mut monitors = Vec::new();
+    if let Some(metrics) = metrics {
+        monitors.push(metrics.monitor(backend, &config.workload.to_string()));
+    }
+    #[cfg(feature = "tui")]
+    if outputs.tui {
+        monitors.push(Box::new(Dashboard::new(backend, config)));
+        return monitors;
+    }
+    if !quiet {
+        monitors.push(Box::new(progress::Measuring::new(backend, config, n_items)));
+    }
+    monitors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn db_path_runs_without_loading() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = Config {
+            backends: vec!["byodb".to_string()],
+            workload: Workload::YcsbC,
+            items: 100,
+            iters: 100,
+            baseline: false,
+            calibrate: false,
+            db_path: Some(dir.path().join("db")),
+            load_only: true,
+            ..Config::default()
+        };
+        let [loaded] = &run(&config)?[..] else {
+            panic!("expected a single result");
+        };
+        assert!(loaded.load_us.is_some());
+        assert_eq!(loaded.ops, 0);
+
+        let config = Config {
+            load_only: false,
+            ..config
+        };
+        let [ran] = &run(&config)?[..] else {
+            panic!("expected a single result");
+        };
+        assert_eq!(ran.load_us, None);
+        assert_eq!(ran.n_items, 100);
+        assert!(ran.ops > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_repetitions_are_skipped() -> Result<()> {
+        let config = Config {
+            backends: vec!["byodb".to_string()],
+            workload: Workload::ReverseScan,
+            items: 100,
+            iters: 100,
+            repeat: 2,
+            baseline: false,
+            calibrate: false,
+            ..Config::default()
+        };
+        let options = Options {
+            quiet: true,
+            print_summaries: true,
+            ..Options::default()
+        };
+        let runs = run_with(&config, None, options)?;
+        assert!(runs.results.is_empty());
+        assert_eq!(runs.skipped.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+use std::mem;
+use std::ops::Bound;
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::{Context, Result, bail, ensure};
+use rand::{
+    SeedableRng,
+    distr::{Alphabetic, SampleString},
+};
+use rand_chacha::ChaCha8Rng;
+
+use byodb_rust::consts;
+
+use crate::backend::{self, Backend};
+use crate::config::Config;
+use crate::distribution::Length;
+use crate::ops::{KeyType, Records};
+use crate::{db, dump, runner};
+
+/// Deterministically generates `n` random key-value pairs from a seed.
+/// Their sizes are clamped to the ones byodb supports.
+pub struct Seeder {
+    n: usize,
+    next: u64,
+    key_type: KeyType,
+    key_size: Length,
+    value_size: Length,
+    rng: ChaCha8Rng,
+}
+
+impl Seeder {
+    pub fn new(
+        n: usize,
+        seed: u64,
+        key_type: KeyType,
+        key_size: Length,
+        value_size: Length,
+    ) -> Self {
+        Seeder {
+            n,
+            next: 0,
+            key_type,
+            key_size,
+            value_size,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Iterator for Seeder {
+    type Item = (String, String);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let key_len = self
+            .key_size
+            .sample(&mut self.rng)
+            .clamp(1, consts::MAX_KEY_SIZE);
+        let val_len = self
+            .value_size
+            .sample(&mut self.rng)
+            .clamp(1, consts::MAX_VALUE_SIZE);
+        let key = match self.key_type.seeded(self.next) {
+            Some(key) => key,
+            None => Alphabetic.sample_string(&mut self.rng, key_len),
+        };
+        self.next += 1;
+        let val: String = Alphabetic.sample_string(&mut self.rng, val_len);
+        Some((key, val))
+    }
+}
+
+/// The items the DB is seeded with for `config.workload`.
+pub fn items(config: &Config) -> Result<Box<dyn Iterator<Item = (String, String)>>> {
+    if let Some(path) = &config.seed_from {
+        ensure!(
+            config.spec().is_none(),
+            "the keys of the {} workload are numbered records, which can't be loaded from a dump",
+            config.workload
+        );
+        return Ok(Box::new(dump::load(path)?.into_iter()));
+    }
+    match config.spec() {
+        None if config.key_type != KeyType::Random && config.key_size.is_some() => {
+            bail!("the size of {} keys can't be set", config.key_type)
+        }
+        None => Ok(Box::new(Seeder::new(
+            config.items,
+            config.seed,
+            config.key_type,
+            config.key_size.unwrap_or(Length::Uniform {
+                min: 1,
+                max: consts::MAX_KEY_SIZE,
+            }),
+            config.value_size.unwrap_or(Length::Uniform {
+                min: 1,
+                max: consts::MAX_VALUE_SIZE,
+            }),
+        ))),
+        Some(spec) => {
+            if (spec.prefix_size.is_some() || spec.tenants.is_some())
+                && spec.key_type != KeyType::Random
+            {
+                bail!(
+                    "the keys of the {} workload have prefixes, so they can't be {}",
+                    config.workload,
+                    spec.key_type
+                );
+            }
+            if config.key_size.is_some() {
+                bail!(
+                    "the keys of the {} workload are numbered records, whose size can't be set",
+                    config.workload
+                );
+            }
+            Ok(Box::new(Records::new(config.items, config.seed, &spec)))
+        }
+    }
+}
+
+/// Inserts `items` into `db`. A duplicate key overwrites the value of the
+/// previous one.
+pub fn seed_db(items: impl Iterator<Item = (String, String)>, db: &dyn Backend) -> Result<()> {
+    let mut t = db.begin_rw()?;
+    for (i, (k, v)) in items.enumerate() {
+        t.put(k.as_bytes(), v.as_bytes())
+            .with_context(|| format!("failed to insert {i}th ({k}, {v})"))?;
+    }
+    t.commit()
+}
+
+/// Inserts `items` into `db` like [`seed_db`], but from `threads` threads,
+/// each committing a transaction of every `batch` items dealt to it, or of
+/// all of them. Items are dealt by the hash of their key, so that a
+/// duplicate key still overwrites the value of the previous one. Backends
+/// with a single writer commit the transactions in sequence, while the next
+/// items are generated.
+pub fn seed_db_parallel(
+    items: impl Iterator<Item = (String, String)>,
+    db: &dyn Backend,
+    threads: usize,
+    batch: Option<usize>,
+) -> Result<()> {
+    ensure!(threads > 0, "seeding needs threads");
+    ensure!(batch != Some(0), "the seed batch must be positive");
+    if threads == 1 && batch.is_none() {
+        return seed_db(items, db);
+    }
+    let batch = batch.unwrap_or(usize::MAX);
+    thread::scope(|s| {
+        let (senders, handles): (Vec<_>, Vec<_>) = (0..threads)
+            .map(|_| {
+                let (sender, receiver) = mpsc::sync_channel::<Vec<(String, String)>>(1);
+                let handle = s.spawn(move || -> Result<()> {
+                    for chunk in receiver {
+                        seed_db(chunk.into_iter(), db)?;
+                    }
+                    Ok(())
+                });
+                (sender, handle)
+            })
+            .unzip();
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+        let mut chunks = vec![Vec::new(); threads];
+        // A thread only stops receiving when it failed, which joining it
+        // reports.
+        let mut failed = false;
+        for (k, v) in items {
+            let i = (hasher.hash_one(&k) % threads as u64) as usize;
+            chunks[i].push((k, v));
+            if chunks[i].len() >= batch && senders[i].send(mem::take(&mut chunks[i])).is_err() {
+                failed = true;
+                break;
+            }
+        }
+        if !failed {
+            for (sender, chunk) in senders.iter().zip(chunks) {
+                if !chunk.is_empty() {
+                    let _ = sender.send(chunk);
+                }
+            }
+        }
+        drop(senders);
+        handles.into_iter().try_for_each(|h| h.join().unwrap())
+    })
+}
+
+/// Checks that `db` contains exactly the items that [`seed_db`] would have
+/// inserted. Returns the number of items checked.
+pub fn verify_db(items: impl Iterator<Item = (String, String)>, db: &dyn Backend) -> Result<usize> {
+    let want: HashMap<String, String> = items.collect();
+    let t = db.begin_ro()?;
+    for (k, v) in &want {
+        match t
+            .get(k.as_bytes())
+            .with_context(|| format!("failed to get key {k}"))?
+        {
+            Some(got) if *got == *v.as_bytes() => {}
+            Some(_) => bail!("key {k} has an unexpected value"),
+            None => bail!("key {k} is missing"),
+        }
+    }
+    let n_items = count_items(db)?;
+    if n_items != want.len() {
+        bail!(
+            "DB has {n_items} items, but only {} were seeded",
+            want.len()
+        );
+    }
+    Ok(n_items)
+}
+
+/// Totals the sizes of the keys and values of the items in `db`, i.e. the
+/// logical size of the data, by iterating over all of them.
+pub fn live_bytes(db: &dyn Backend) -> Result<u64> {
+    let mut bytes = 0;
+    db.begin_ro()?.scan(Bound::Unbounded, &mut |k, v| {
+        bytes += (k.len() + v.len()) as u64;
+        true
+    })?;
+    Ok(bytes)
+}
+
+/// Counts the items in `db` by iterating over all of them.
+pub fn count_items(db: &dyn Backend) -> Result<usize> {
+    let mut n_items = 0;
+    db.begin_ro()?.scan(Bound::Unbounded, &mut |_, _| {
+        n_items += 1;
+        true
+    })?;
+    Ok(n_items)
+}
+
+/// Copies all items of `from` into `to` in a single transaction.
+pub fn copy_items(from: &dyn Backend, to: &dyn Backend) -> Result<()> {
+    let mut txn = to.begin_rw()?;
+    let mut result = Ok(());
+    from.begin_ro()?.scan(Bound::Unbounded, &mut |k, v| {
+        result = txn.put(k, v);
+        result.is_ok()
+    })?;
+    result?;
+    txn.commit()
+}
+
+/// Runs `db-cmp seed`: creates a DB at `config.db_path`, seeded with the
+/// items of `config`, showing their progress unless `quiet`.
+pub fn create(config: &Config, quiet: bool) -> Result<()> {
+    let path = config
+        .db_path
+        .as_deref()
+        .context("seed requires --db-path")?;
+    if path.exists() {
+        bail!("DB {} already exists", path.display());
+    }
+    let backend = config.single_backend()?;
+    let db = backend::open(backend, path, &config.backend_opts_for(backend)?)?;
+    seed_db_parallel(
+        runner::items(config, quiet)?,
+        &*db,
+        config.seed_threads,
+        config.seed_batch,
+    )?;
+    match &config.seed_from {
+        Some(dump) => println!("Seeded {} with {}", path.display(), dump.display()),
+        None => println!("Seeded {} with {} items", path.display(), config.items),
+    }
+    Ok(())
+}
+
+/// Runs `db-cmp verify`: checks that the DB at `config.db_path` holds
+/// exactly the items of `config`.
+pub fn verify(config: &Config) -> Result<()> {
+    let path = config
+        .db_path
+        .as_deref()
+        .context("verify requires --db-path")?;
+    let backend = config.single_backend()?;
+    let db = db::open_existing(backend, path, &config.backend_opts_for(backend)?)?;
+    let n_items = verify_db(items(config)?, &*db)?;
+    println!("Verified {} items in {}", n_items, path.display());
+    Ok(())
+}
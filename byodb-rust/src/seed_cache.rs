@@ -0,0 +1,64 @@
+//! Cached copies of seeded DBs: a run seeds its DB once, and copies it,
+//! closed, into the cache directory, from which later runs seeding the same
+//! items into the same backend copy it instead of seeding their own.
+
+use std::fs;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+use crate::db;
+
+/// Where a DB of `backend` seeded as `config` says is cached in `dir`: named
+/// after the backend and the number of items, and a hash of everything else
+/// that determines the DB.
+pub fn entry(dir: &Path, backend: &str, config: &Config) -> Result<PathBuf> {
+    let dump = match &config.seed_from {
+        Some(path) => {
+            let metadata = fs::metadata(path)
+                .with_context(|| format!("failed to stat dump {}", path.display()))?;
+            Some((path, metadata.len(), metadata.modified()?))
+        }
+        None => None,
+    };
+    let key = format!(
+        "{:?}",
+        (
+            config.backend_opts_for(backend)?,
+            config.spec().map(|spec| {
+                (
+                    spec.ordered_keys,
+                    spec.prefix_size,
+                    spec.tenants,
+                    spec.value_size,
+                )
+            }),
+            config.key_type,
+            config.seed,
+            config.key_size,
+            config.value_size,
+            dump,
+        )
+    );
+    let hash = BuildHasherDefault::<DefaultHasher>::default().hash_one(key);
+    Ok(dir.join(format!("{backend}-{}-{hash:016x}", config.items)))
+}
+
+/// Caches a copy of the closed DB at `path` as `entry`.
+pub fn store(path: &Path, entry: &Path) -> Result<()> {
+    let dir = entry.parent().expect("entries are in the cache directory");
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create seed cache {}", dir.display()))?;
+    // Copied under another name first, so that concurrent runs never see a
+    // partial copy.
+    let partial = entry.with_extension(format!("partial-{}", process::id()));
+    db::copy(path, &partial)?;
+    if fs::rename(&partial, entry).is_err() {
+        // Another run cached the same DB first.
+        db::remove(&partial)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Seeks apart from iteration: positioning a scan at a key descends the
+//! tree, while every next record after it mostly stays in the same leaf, so
+//! the two scale very differently with the size of the DB.
+
+use std::ops::Bound;
+use std::time::Instant;
+
+use anyhow::{Context, Result, ensure};
+use rand::distr::{Alphabetic, SampleString};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::backend::Backend;
+use crate::cli::SeekArgs;
+use crate::config::Config;
+use crate::histogram::Histogram;
+use crate::{db, seed};
+
+/// Maximum size of the keys seeked to.
+const MAX_TARGET_SIZE: usize = 16;
+
+/// What scans of a DB measured.
+#[derive(Default)]
+pub struct Latencies {
+    /// From the start of each scan to its first record.
+    pub seek: Histogram,
+    /// From each record to the next one of the same scan.
+    pub next: Histogram,
+}
+
+/// Performs `iters` scans of `db`, each in a read transaction of its own,
+/// from a random key of letters, like the seeded ones of the scan workload,
+/// to up to `nexts` records after the first one. Scans that start past the
+/// last record measure nothing.
+///
+/// Every record is timed with a clock read of its own, which is included in
+/// the next latencies.
+pub fn measure(db: &dyn Backend, iters: usize, nexts: usize, seed: u64) -> Result<Latencies> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut latencies = Latencies::default();
+    for _ in 0..iters {
+        let len = rng.random_range(1..=MAX_TARGET_SIZE);
+        let target = Alphabetic.sample_string(&mut rng, len);
+        let t = db.begin_ro()?;
+        let mut visited = 0;
+        let start = Instant::now();
+        let mut last = start;
+        t.scan(Bound::Included(target.as_bytes()), &mut |_, _| {
+            let now = Instant::now();
+            if visited == 0 {
+                latencies.seek.record(now - start);
+            } else {
+                latencies.next.record(now - last);
+            }
+            last = now;
+            visited += 1;
+            visited <= nexts
+        })?;
+    }
+    Ok(latencies)
+}
+
+/// Runs `db-cmp seek`: measures seeks and nexts on each backend of `args`,
+/// at every DB size of it.
+pub fn command(args: SeekArgs) -> Result<()> {
+    ensure!(args.iters > 0, "seek needs iterations");
+    println!(
+        "{:<10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "Backend", "Items", "Seek (us)", "p50", "p99", "Next (us)", "p50", "p99"
+    );
+    let config = args.backend.into_config();
+    for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        for &items in &args.items {
+            let config = Config {
+                items,
+                ..config.clone()
+            };
+            let (db, _temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+            seed::seed_db(seed::items(&config)?, &*db)?;
+            let latencies = measure(&*db, args.iters, args.nexts, config.seed)
+                .with_context(|| format!("failed to measure {backend}"))?;
+            let (seek, next) = (&latencies.seek, &latencies.next);
+            println!(
+                "{backend:<10} {items:>10} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+                seek.mean_us(),
+                seek.quantile_us(0.5),
+                seek.quantile_us(0.99),
+                next.mean_us(),
+                next.quantile_us(0.5),
+                next.quantile_us(0.99),
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//! A model of what a DB should hold, kept alongside it during a run by
+//! applying every write that's committed to a [`BTreeMap`], to check that
+//! the store didn't lose or corrupt any of it.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::{Result, bail};
+
+use crate::backend::{Backend, WriteTxn};
+
+/// Number of diverging keys shown by [`Shadow::verify`].
+const EXAMPLES: usize = 5;
+
+/// The records, by key.
+type Map = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// The contents the DB should have.
+pub struct Shadow {
+    map: Mutex<Map>,
+}
+
+/// The model, locked for the whole of a read-write transaction, so that
+/// commits are applied in the order they happen. Taking it before the
+/// transaction begins, rather than to commit, never makes a writer wait for
+/// it while holding the DB's own locks.
+pub struct Lock<'a> {
+    map: MutexGuard<'a, Map>,
+}
+
+/// A key with the value put under it, or none if it's deleted.
+type Write = (Vec<u8>, Option<Vec<u8>>);
+
+/// The writes of a transaction, applied to the [`Shadow`] once committed.
+#[derive(Default)]
+pub struct Pending {
+    writes: RefCell<Vec<Write>>,
+}
+
+impl Pending {
+    pub fn put(&self, k: &[u8], v: &[u8]) {
+        self.writes
+            .borrow_mut()
+            .push((k.to_vec(), Some(v.to_vec())));
+    }
+
+    pub fn delete(&self, k: &[u8]) {
+        self.writes.borrow_mut().push((k.to_vec(), None));
+    }
+
+    /// Forgets the writes of a transaction that was aborted.
+    pub fn clear(&self) {
+        self.writes.borrow_mut().clear();
+    }
+}
+
+impl Shadow {
+    /// Starts from the current contents of `db`.
+    pub fn load(db: &dyn Backend) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        db.begin_ro()?.scan(Bound::Unbounded, &mut |k, v| {
+            map.insert(k.to_vec(), v.to_vec());
+            true
+        })?;
+        Ok(Shadow {
+            map: Mutex::new(map),
+        })
+    }
+
+    /// Locks the model, to begin a read-write transaction.
+    pub fn lock(&self) -> Lock<'_> {
+        Lock {
+            map: self.map.lock().unwrap(),
+        }
+    }
+
+    /// Fails unless `db` holds exactly the contents of the model.
+    pub fn verify(&self, db: &dyn Backend) -> Result<()> {
+        let map = self.map.lock().unwrap();
+        let mut want = map.iter().peekable();
+        let (mut missing, mut extra, mut different) = (0, 0, 0);
+        let mut examples = Vec::new();
+        let mut diverge = |what: &str, k: &[u8], count: &mut usize| {
+            *count += 1;
+            if examples.len() < EXAMPLES {
+                examples.push(format!("{what} {}", String::from_utf8_lossy(k)));
+            }
+        };
+        db.begin_ro()?.scan(Bound::Unbounded, &mut |k, v| {
+            while let Some((want_k, _)) = want.next_if(|(want_k, _)| want_k.as_slice() < k) {
+                diverge("missing", want_k, &mut missing);
+            }
+            match want.next_if(|(want_k, _)| want_k.as_slice() == k) {
+                Some((_, want_v)) if want_v.as_slice() == v => {}
+                Some(_) => diverge("different value of", k, &mut different),
+                None => diverge("unexpected", k, &mut extra),
+            }
+            true
+        })?;
+        for (want_k, _) in want {
+            diverge("missing", want_k, &mut missing);
+        }
+        if missing + extra + different > 0 {
+            bail!(
+                "the DB diverged from what was committed: {missing} keys missing, {extra} unexpected, {different} with different values, e.g. {}",
+                examples.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Lock<'_> {
+    /// Commits `t` and applies its `pending` writes.
+    pub fn commit(mut self, t: Box<dyn WriteTxn + '_>, pending: &Pending) -> Result<()> {
+        let writes = pending.writes.take();
+        t.commit()?;
+        for (k, v) in writes {
+            match v {
+                Some(v) => self.map.insert(k, v),
+                None => self.map.remove(&k),
+            };
+        }
+        Ok(())
+    }
+}
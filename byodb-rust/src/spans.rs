@@ -0,0 +1,58 @@
+//! Writes the spans of the transactions and operations of a run to a file
+//! with `--trace-output`, a line for each once it closes, with how long it
+//! was busy, so that the slow outliers of a run can be told apart.
+//!
+//! Every operation of an op-based workload has an `op` span, within the
+//! `run` span of its backend, and every attempt at a read-write
+//! transaction a `txn` span within it, which includes its `commit` span.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// The file the spans are written to, flushed once dropped.
+pub struct Output(Arc<Mutex<BufWriter<File>>>);
+
+impl Output {
+    /// Creates `path` and writes the spans of every thread to it from now
+    /// on.
+    pub fn install(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let output = Output(Arc::new(Mutex::new(BufWriter::new(file))));
+        let file = output.0.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_thread_ids(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(move || Writer(file.clone()))
+            .finish();
+        tracing::subscriber::set_global_default(subscriber)
+            .context("failed to install the span subscriber")?;
+        Ok(output)
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        // Nothing to report a failure to by now.
+        let _ = self.0.lock().unwrap().flush();
+    }
+}
+
+/// Writes a line of the subscriber to the shared file.
+struct Writer(Arc<Mutex<BufWriter<File>>>);
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
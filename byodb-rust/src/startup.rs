@@ -0,0 +1,97 @@
+//! How long backends take to open an existing DB: reading their meta pages
+//! or manifests, replaying their logs, and whatever else they do before the
+//! first transaction, which can grow with the size of the DB.
+
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::{Context, Result, ensure};
+
+use crate::backend::BackendOpts;
+use crate::cli::OpenArgs;
+use crate::config::Config;
+use crate::histogram::Histogram;
+use crate::{db, seed};
+
+/// What opening a DB measured.
+#[derive(Default)]
+pub struct Opens {
+    /// With the files of the DB evicted from the page cache first.
+    pub cold: Histogram,
+    /// With the files of the DB in the page cache.
+    pub warm: Histogram,
+}
+
+/// Opens the closed DB of `backend` at `path` `iters` times cold, then
+/// `iters` times warm, after an untimed open to read it back into the page
+/// cache. Each open is timed until the backend returns, and the DB is
+/// closed again after every one, untimed.
+pub fn measure(backend: &str, path: &Path, opts: &BackendOpts, iters: usize) -> Result<Opens> {
+    let mut opens = Opens::default();
+    for _ in 0..iters {
+        db::evict_from_cache(path)?;
+        let start = Instant::now();
+        let db = db::open_existing(backend, path, opts)?;
+        opens.cold.record(start.elapsed());
+        drop(db);
+    }
+    drop(db::open_existing(backend, path, opts)?);
+    for _ in 0..iters {
+        let start = Instant::now();
+        let db = db::open_existing(backend, path, opts)?;
+        opens.warm.record(start.elapsed());
+        drop(db);
+    }
+    Ok(opens)
+}
+
+/// Runs `db-cmp open`: measures how long each backend of `args` takes to
+/// open a DB, at every DB size of it or on the DB at its path.
+pub fn command(args: OpenArgs) -> Result<()> {
+    ensure!(args.iters > 0, "open needs iterations");
+    let config = Config {
+        db_path: args.db_path,
+        ..args.backend.into_config()
+    };
+    println!(
+        "{:<10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "Backend", "Items", "Size (MiB)", "Cold (ms)", "max", "Warm (ms)", "max"
+    );
+    let print = |backend: &str, items: &str, path: &Path, iters: usize| -> Result<()> {
+        let opts = config.backend_opts_for(backend)?;
+        let opens = measure(backend, path, &opts, iters)
+            .with_context(|| format!("failed to measure {backend}"))?;
+        let ms = |us: f64| us / 1000.0;
+        println!(
+            "{backend:<10} {items:>10} {:>10.2} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+            db::disk_usage(path)? as f64 / (1 << 20) as f64,
+            ms(opens.cold.mean_us()),
+            ms(opens.cold.max_us()),
+            ms(opens.warm.mean_us()),
+            ms(opens.warm.max_us()),
+        );
+        Ok(())
+    };
+    if let Some(path) = &config.db_path {
+        return print(config.single_backend()?, "-", path, args.iters);
+    }
+    for backend in &config.backends {
+        let opts = config.backend_opts_for(backend)?;
+        for &items in &args.items {
+            let config = Config {
+                items,
+                ..config.clone()
+            };
+            let (db, temp_dir) = db::open_temp(backend, &opts, config.db_dir.as_deref())?;
+            seed::seed_db(seed::items(&config)?, &*db)?;
+            drop(db);
+            let path = db::temp_path(&temp_dir);
+            if !path.exists() {
+                println!("{backend}: skipped, since it doesn't persist anything");
+                break;
+            }
+            print(backend, &items.to_string(), &path, args.iters)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,277 @@
+//! Statistical tests, to tell real differences between results from noise.
+
+/// The p-value below which differences are deemed significant.
+pub const ALPHA: f64 = 0.05;
+
+/// Two-sided 95% critical values of Student's t distribution, by degrees of
+/// freedom from 1.
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// The mean of a sample of repeated measurements, and how much they vary.
+pub struct Summary {
+    pub mean: f64,
+    /// The sample standard deviation, or 0 for a single measurement.
+    pub stddev: f64,
+    /// Half the width of the 95% confidence interval of the mean, which is
+    /// infinite for a single measurement.
+    pub ci95: f64,
+}
+
+/// Summarizes the non-empty sample `values`.
+pub fn summarize(values: &[f64]) -> Summary {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return Summary {
+            mean,
+            stddev: 0.0,
+            ci95: f64::INFINITY,
+        };
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let stddev = variance.sqrt();
+    // Beyond the table, the t distribution is close enough to the normal one.
+    let t = T_95.get(values.len() - 2).copied().unwrap_or(1.96);
+    Summary {
+        mean,
+        stddev,
+        ci95: t * stddev / n.sqrt(),
+    }
+}
+
+/// The result of a two-sided Mann-Whitney U test of whether the values of
+/// one sample tend to be larger or smaller than those of another.
+pub struct MannWhitney {
+    /// Probability that the `a` values are larger than the `b` values, plus
+    /// half the probability that they're equal: 0.5 if neither tends to be
+    /// larger.
+    pub effect: f64,
+    /// Probability of a difference at least this large if both samples came
+    /// from the same distribution.
+    pub p_value: f64,
+}
+
+/// Largest number of values of both samples for which [`mann_whitney`]
+/// computes the exact p-value, rather than its normal approximation.
+const EXACT_MAX: usize = 40;
+
+/// Runs a Mann-Whitney U test on the samples `a` and `b`, such as the
+/// summaries of the repetitions of two runs. The p-value is exact for
+/// samples of a few repetitions without ties, and otherwise uses the normal
+/// approximation with a correction for ties. Returns `None` if either is
+/// empty.
+pub fn mann_whitney(a: &[f64], b: &[f64]) -> Option<MannWhitney> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+    // All values in order, with whether they are `a` ones.
+    let mut values: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&value| (value, true))
+        .chain(b.iter().map(|&value| (value, false)))
+        .collect();
+    values.sort_by(|x, y| x.0.total_cmp(&y.0));
+    // Sum of the ranks of the `a` values, where tied values all get the
+    // average of their ranks.
+    let mut rank_sum_a = 0.0;
+    let mut ties = 0.0;
+    let mut below = 0.0;
+    for tied_values in values.chunk_by(|x, y| x.0 == y.0) {
+        let tied = tied_values.len() as f64;
+        let count_a = tied_values.iter().filter(|(_, is_a)| *is_a).count() as f64;
+        rank_sum_a += count_a * (below + (tied + 1.0) / 2.0);
+        ties += tied.powi(3) - tied;
+        below += tied;
+    }
+    let n = n_a + n_b;
+    let u_a = rank_sum_a - n_a * (n_a + 1.0) / 2.0;
+    let p_value = if ties == 0.0 && values.len() <= EXACT_MAX {
+        exact_p_value(u_a as usize, a.len(), b.len())
+    } else {
+        let mean = n_a * n_b / 2.0;
+        let variance = n_a * n_b / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+        if variance > 0.0 {
+            erfc((u_a - mean).abs() / variance.sqrt() / std::f64::consts::SQRT_2)
+        } else {
+            // All values are equal.
+            1.0
+        }
+    };
+    Some(MannWhitney {
+        effect: u_a / (n_a * n_b),
+        p_value: p_value.min(1.0),
+    })
+}
+
+/// The smallest p-value a two-sided [`mann_whitney`] test of samples of
+/// `n_a` and `n_b` distinct values can reach: one above [`ALPHA`] means
+/// they are too small to tell any difference.
+pub fn min_p_value(n_a: usize, n_b: usize) -> f64 {
+    // 2 / (n_a + n_b choose n_a), the two orderings that separate them.
+    let orderings = (1..=n_a).fold(1.0, |c, i| c * (n_b + i) as f64 / i as f64);
+    (2.0 / orderings).min(1.0)
+}
+
+/// The two-sided p-value of the U statistic `u` of `n_a` values against
+/// `n_b` ones, all distinct.
+fn exact_p_value(u: usize, n_a: usize, n_b: usize) -> f64 {
+    let counts = u_counts(n_a, n_b);
+    let total: f64 = counts.iter().sum();
+    // The distribution is symmetric, so the smaller tail is the one on the
+    // side of `u`.
+    let tail: f64 = counts[..=u.min(n_a * n_b - u)].iter().sum();
+    2.0 * tail / total
+}
+
+/// The number of orderings of `n_a` and `n_b` distinct values, by the U
+/// statistic of the `a` ones, from 0 to `n_a * n_b`.
+fn u_counts(n_a: usize, n_b: usize) -> Vec<f64> {
+    // By number of `b` values, for no `a` value so far.
+    let mut counts = vec![vec![1.0]; n_b + 1];
+    for m in 1..=n_a {
+        let mut next: Vec<Vec<f64>> = Vec::with_capacity(n_b + 1);
+        for n in 0..=n_b {
+            let mut count = vec![0.0; m * n + 1];
+            // The largest value is an `a` one, above all `n` `b` ones...
+            for (u, &k) in counts[n].iter().enumerate() {
+                count[u + n] += k;
+            }
+            // ...or a `b` one.
+            if n > 0 {
+                for (u, &k) in next[n - 1].iter().enumerate() {
+                    count[u] += k;
+                }
+            }
+            next.push(count);
+        }
+        counts = next;
+    }
+    counts.pop().unwrap()
+}
+
+/// The complementary error function, to within 1.2e-7 (Numerical Recipes'
+/// `erfcc`).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let r = t * poly.exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} isn't within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn summarize_single_value() {
+        let summary = summarize(&[3.0]);
+        assert_eq!(summary.mean, 3.0);
+        assert_eq!(summary.stddev, 0.0);
+        assert_eq!(summary.ci95, f64::INFINITY);
+    }
+
+    #[test]
+    fn summarize_uses_t_quantiles() {
+        let summary = summarize(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(summary.mean, 3.0);
+        assert_close(summary.stddev, 2.5f64.sqrt(), 1e-12);
+        // 4 degrees of freedom.
+        assert_close(summary.ci95, 2.776 * 2.5f64.sqrt() / 5f64.sqrt(), 1e-12);
+        // 1 degree of freedom, with a standard deviation of sqrt(2).
+        let summary = summarize(&[1.0, 3.0]);
+        assert_close(summary.ci95, 12.706, 1e-12);
+    }
+
+    #[test]
+    fn summarize_beyond_the_t_table() {
+        // 30 degrees of freedom, the last in the table.
+        let values: Vec<_> = (0..31).map(f64::from).collect();
+        let summary = summarize(&values);
+        assert_close(summary.ci95, 2.042 * summary.stddev / 31f64.sqrt(), 1e-12);
+        // 40 degrees of freedom, approximated by the normal distribution.
+        let values: Vec<_> = (0..41).map(f64::from).collect();
+        let summary = summarize(&values);
+        assert_close(summary.ci95, 1.96 * summary.stddev / 41f64.sqrt(), 1e-12);
+    }
+
+    #[test]
+    fn mann_whitney_of_separate_samples() {
+        let (low, high) = ([1.0, 2.0, 3.0, 4.0], [5.0, 6.0, 7.0, 8.0]);
+        let test = mann_whitney(&low, &high).unwrap();
+        assert_eq!(test.effect, 0.0);
+        // Exact: 2 of the 70 orderings are as separate.
+        assert_close(test.p_value, 2.0 / 70.0, 1e-12);
+        let test = mann_whitney(&high, &low).unwrap();
+        assert_eq!(test.effect, 1.0);
+        assert_close(test.p_value, 2.0 / 70.0, 1e-12);
+    }
+
+    #[test]
+    fn mann_whitney_of_interleaved_samples() {
+        let test = mann_whitney(&[1.0, 4.0, 5.0, 8.0], &[2.0, 3.0, 6.0, 7.0]).unwrap();
+        assert_eq!(test.effect, 0.5);
+        assert_close(test.p_value, 1.0, 1e-12);
+    }
+
+    #[test]
+    fn mann_whitney_with_ties() {
+        let test = mann_whitney(&[1.0, 2.0, 2.0, 3.0], &[2.0, 3.0, 4.0, 5.0]).unwrap();
+        // U counts the ties as half: 0 + 0.5 + 0.5 + 1.5 of 16 pairs.
+        assert_eq!(test.effect, 2.5 / 16.0);
+        // Normal approximation, with the variance corrected for a tie of
+        // three values and one of two.
+        assert_close(test.p_value, 0.1015915, 1e-6);
+    }
+
+    #[test]
+    fn mann_whitney_of_equal_values() {
+        let test = mann_whitney(&[1.0, 1.0], &[1.0, 1.0]).unwrap();
+        assert_eq!(test.effect, 0.5);
+        assert_eq!(test.p_value, 1.0);
+    }
+
+    #[test]
+    fn mann_whitney_of_empty_sample() {
+        assert!(mann_whitney(&[], &[1.0]).is_none());
+        assert!(mann_whitney(&[1.0], &[]).is_none());
+    }
+
+    #[test]
+    fn min_p_value_of_small_samples() {
+        assert_eq!(min_p_value(1, 1), 1.0);
+        assert_close(min_p_value(3, 3), 0.1, 1e-12);
+        assert_close(min_p_value(4, 4), 2.0 / 70.0, 1e-12);
+        assert!(min_p_value(3, 4) > ALPHA);
+        assert!(min_p_value(4, 4) < ALPHA);
+    }
+
+    #[test]
+    fn erfc_values() {
+        assert_close(erfc(0.0), 1.0, 1.2e-7);
+        assert_close(erfc(0.5), 0.4795001221869535, 1.2e-7);
+        assert_close(erfc(1.0), 0.15729920705028513, 1.2e-7);
+        assert_close(erfc(-1.0), 1.842700792949715, 1.2e-7);
+        assert_close(erfc(2.0), 0.004677734981047265, 1.2e-7);
+        assert_close(erfc(10.0), 0.0, 1.2e-7);
+    }
+}
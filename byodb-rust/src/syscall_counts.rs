@@ -0,0 +1,37 @@
+//! Counts the syncs of every backend (see [`db_cmp::syscalls`]): this
+//! binary defines `fsync`, `fdatasync` and `msync` itself, in place of the C
+//! library's, and each counts the call and issues the syscall directly.
+
+use std::ffi::{c_int, c_void};
+
+use db_cmp::syscalls::{self, SyncCall};
+
+/// Makes the results report the syncs counted.
+pub fn install() {
+    syscalls::enable();
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fsync(fd: c_int) -> c_int {
+    syscalls::count(SyncCall::Fsync);
+    // SAFETY: fsync takes a single file descriptor, whatever its value. Like
+    // the C library's wrapper, it returns -1 and sets `errno` on failure.
+    unsafe { libc::syscall(libc::SYS_fsync, fd) as c_int }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fdatasync(fd: c_int) -> c_int {
+    syscalls::count(SyncCall::Fdatasync);
+    // SAFETY: fdatasync takes a single file descriptor, whatever its value.
+    unsafe { libc::syscall(libc::SYS_fdatasync, fd) as c_int }
+}
+
+/// # Safety
+///
+/// Same as the C library's `msync`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn msync(addr: *mut c_void, len: libc::size_t, flags: c_int) -> c_int {
+    syscalls::count(SyncCall::Msync);
+    // SAFETY: the kernel checks the range, and the caller owns the mapping.
+    unsafe { libc::syscall(libc::SYS_msync, addr, len, flags) as c_int }
+}
@@ -0,0 +1,72 @@
+//! Counts the syscalls that make writes durable, whichever backend issues
+//! them, without tracing the process.
+//!
+//! Counting them takes defining `fsync`, `fdatasync` and `msync` in place of
+//! the C library's, so that the calls of the Rust standard library and of
+//! the C libraries linked in (e.g. LMDB and SQLite) reach them too. That
+//! replaces them for the whole program, so the library leaves it to the
+//! program: the `db-cmp` binary defines them with its `syscall-counts`
+//! feature, on by default, [`count`]s every call before issuing it, and
+//! [`enable`]s the counts. Other programs embedding the harness have none.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FSYNCS: AtomicU64 = AtomicU64::new(0);
+static FDATASYNCS: AtomicU64 = AtomicU64::new(0);
+static MSYNCS: AtomicU64 = AtomicU64::new(0);
+
+/// A kind of syscall that syncs writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncCall {
+    Fsync,
+    Fdatasync,
+    Msync,
+}
+
+/// Makes [`Syncs::now`] report the counts, once every call is counted.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Counts a call about to be issued.
+pub fn count(call: SyncCall) {
+    let counter = match call {
+        SyncCall::Fsync => &FSYNCS,
+        SyncCall::Fdatasync => &FDATASYNCS,
+        SyncCall::Msync => &MSYNCS,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Numbers of syncs issued by this process so far.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Syncs {
+    pub fsyncs: u64,
+    pub fdatasyncs: u64,
+    pub msyncs: u64,
+}
+
+impl Syncs {
+    /// The syncs issued so far, if they're counted.
+    pub fn now() -> Option<Syncs> {
+        ENABLED.load(Ordering::Relaxed).then(|| Syncs {
+            fsyncs: FSYNCS.load(Ordering::Relaxed),
+            fdatasyncs: FDATASYNCS.load(Ordering::Relaxed),
+            msyncs: MSYNCS.load(Ordering::Relaxed),
+        })
+    }
+
+    /// The syncs issued since `earlier`.
+    pub fn since(&self, earlier: &Syncs) -> Syncs {
+        Syncs {
+            fsyncs: self.fsyncs - earlier.fsyncs,
+            fdatasyncs: self.fdatasyncs - earlier.fdatasyncs,
+            msyncs: self.msyncs - earlier.msyncs,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.fsyncs + self.fdatasyncs + self.msyncs
+    }
+}
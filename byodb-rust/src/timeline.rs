@@ -0,0 +1,153 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::histogram::Histogram;
+use crate::results::RunResult;
+
+/// The state of a run at some point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    /// Time since the start of the run.
+    pub elapsed_ms: u64,
+    /// Number of operations completed by all threads, counting every item
+    /// read as one for the scan workload.
+    pub ops: u64,
+    /// Number of operations completed by each thread.
+    pub thread_ops: Vec<u64>,
+    /// Number of records of op-based workloads, including the ones inserted
+    /// during the run.
+    pub n_keys: u64,
+    /// Disk usage of the DB, if `spec.track_space`.
+    pub disk_bytes: u64,
+    /// Total size of the keys and values of the records, if
+    /// `spec.track_space`.
+    pub live_bytes: u64,
+    /// Resident set size of the process, or 0 if unknown.
+    #[serde(default)]
+    pub rss_bytes: u64,
+    /// Size of the file-backed memory mappings of the process, or 0 if
+    /// unknown.
+    #[serde(default)]
+    pub mapped_bytes: u64,
+}
+
+/// Counts the operations completed by a single thread, so that another
+/// thread can sample it. Counters of different threads are padded to
+/// separate cache lines, so that they don't slow each other down.
+#[derive(Default)]
+#[repr(align(128))]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    /// Counts one more operation. Must only be called by the owning thread.
+    pub fn incr(&self) {
+        // Cheaper than an atomic increment, as there's a single writer.
+        self.0
+            .store(self.0.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+    }
+
+    /// Counts `n` more operations. Must only be called by the owning thread.
+    pub fn add(&self, n: u64) {
+        self.0
+            .store(self.0.load(Ordering::Relaxed) + n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Watches the progress of a run as it's sampled, e.g. to display it live.
+pub trait Monitor: Send {
+    /// Called with every `sample` of the run, and the latencies of the
+    /// operations completed since the previous one, if measured.
+    fn update(&mut self, sample: &Sample, recent: Option<&Histogram>) -> Result<()>;
+}
+
+/// Updates every monitor in turn.
+impl Monitor for Vec<Box<dyn Monitor>> {
+    fn update(&mut self, sample: &Sample, recent: Option<&Histogram>) -> Result<()> {
+        for monitor in self {
+            monitor.update(sample, recent)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f` while another thread calls `sample` every `interval`, starting
+/// right away and once more when `f` returns.
+pub fn sampled<R, S: Send>(
+    interval: Duration,
+    mut sample: impl FnMut() -> Result<S> + Send,
+    f: impl FnOnce() -> R,
+) -> (R, Result<Vec<S>>) {
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        let sampler = s.spawn(|| -> Result<Vec<S>> {
+            let mut samples = vec![sample()?];
+            while !done.load(Ordering::Relaxed) {
+                // Woken up early once done.
+                thread::park_timeout(interval);
+                samples.push(sample()?);
+            }
+            Ok(samples)
+        });
+        let result = f();
+        done.store(true, Ordering::Relaxed);
+        sampler.thread().unpark();
+        (result, sampler.join().unwrap())
+    })
+}
+
+/// The throughput of a single thread between two samples of a timeline.
+#[derive(Serialize)]
+struct Row<'r> {
+    backend: &'r str,
+    elapsed_ms: u64,
+    thread: usize,
+    ops: u64,
+    ops_per_sec: f64,
+}
+
+/// Saves the throughput timelines of all threads of all backends to `path`,
+/// as CSV if it ends in `.csv`, or JSON otherwise.
+pub fn save(path: &Path, results: &[RunResult]) -> Result<()> {
+    let mut rows = Vec::new();
+    for result in results {
+        for pair in result.timeline.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            let secs = (to.elapsed_ms - from.elapsed_ms).max(1) as f64 / 1e3;
+            for (thread, (from_ops, to_ops)) in
+                from.thread_ops.iter().zip(&to.thread_ops).enumerate()
+            {
+                rows.push(Row {
+                    backend: &result.backend,
+                    elapsed_ms: to.elapsed_ms,
+                    thread,
+                    ops: to_ops - from_ops,
+                    ops_per_sec: (to_ops - from_ops) as f64 / secs,
+                });
+            }
+        }
+    }
+    let contents = if path.extension().is_some_and(|ext| ext == "csv") {
+        let mut csv = "backend,elapsed_ms,thread,ops,ops_per_sec\n".to_string();
+        for row in &rows {
+            csv += &format!(
+                "{},{},{},{},{:.0}\n",
+                row.backend, row.elapsed_ms, row.thread, row.ops, row.ops_per_sec
+            );
+        }
+        csv
+    } else {
+        serde_json::to_string_pretty(&rows)?
+    };
+    fs::write(path, contents)
+        .with_context(|| format!("failed to write timeline {}", path.display()))
+}
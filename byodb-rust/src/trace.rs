@@ -0,0 +1,356 @@
+//! Traces of the operations of op-based workloads: `--record` saves the
+//! steps every thread performed, with their keys and values, and `--replay`
+//! performs exactly the same ones again, against any backend.
+//!
+//! A trace file starts with [`MAGIC`] and the TOML of the recorded
+//! [`Config`], followed by the steps of every thread. Integers are
+//! little-endian `u64`s, and byte strings are prefixed with their length.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::config::Config;
+use crate::ops::Op;
+
+/// The start of a trace file, with the version of its format.
+const MAGIC: &[u8; 8] = b"dbcmptr1";
+
+/// An operation with everything random about it drawn.
+#[derive(Debug, Clone)]
+pub enum TracedOp {
+    Read(Vec<u8>),
+    Update(Vec<u8>, Vec<u8>),
+    Insert(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+    /// Visits up to this many records from the key.
+    Scan(Vec<u8>, u64),
+    /// Visits every record whose key starts with this prefix.
+    PrefixScan(Vec<u8>),
+    /// Visits up to `len` records from `from`, as long as their keys start
+    /// with `prefix`.
+    ScanWithin {
+        prefix: Vec<u8>,
+        from: Vec<u8>,
+        len: u64,
+    },
+    /// Rewrites the field of the value of the key numbered `pick` modulo
+    /// the number of fields of the value, with `field`.
+    Rmw {
+        key: Vec<u8>,
+        pick: u64,
+        field: Vec<u8>,
+    },
+}
+
+impl TracedOp {
+    pub fn kind(&self) -> Op {
+        match self {
+            TracedOp::Read(_) => Op::Read,
+            TracedOp::Update(..) => Op::Update,
+            TracedOp::Insert(..) => Op::Insert,
+            TracedOp::Delete(_) => Op::Delete,
+            TracedOp::Scan(..) | TracedOp::PrefixScan(_) | TracedOp::ScanWithin { .. } => Op::Scan,
+            TracedOp::Rmw { .. } => Op::Rmw,
+        }
+    }
+
+    /// The key it accesses, or the first one for scans.
+    pub fn key(&self) -> &[u8] {
+        match self {
+            TracedOp::Read(k)
+            | TracedOp::Update(k, _)
+            | TracedOp::Insert(k, _)
+            | TracedOp::Delete(k)
+            | TracedOp::Scan(k, _)
+            | TracedOp::PrefixScan(k) => k,
+            TracedOp::ScanWithin { from, .. } => from,
+            TracedOp::Rmw { key, .. } => key,
+        }
+    }
+}
+
+/// What a thread performs in a transaction: a single operation, in a
+/// read-only transaction if it's a read or a scan, or a batch of them in a
+/// read-write transaction.
+#[derive(Debug, Clone, Default)]
+pub struct Step {
+    pub batch: bool,
+    pub ops: Vec<TracedOp>,
+}
+
+/// The steps of every thread of a run, in order.
+pub struct Trace {
+    /// The config of the recorded run.
+    pub config: Config,
+    pub threads: Vec<Vec<Step>>,
+    /// The records to seed the DB with instead of the config's, for traces
+    /// of access logs. Not saved.
+    pub items: Option<Vec<(String, String)>>,
+}
+
+impl Trace {
+    /// Sets the fields of `config` that determine the seeded items and the
+    /// operations to the recorded ones.
+    pub fn apply(&self, config: &mut Config) {
+        let recorded = self.config.clone();
+        config.workload = recorded.workload;
+        config.mix = recorded.mix;
+        config.scan_len = recorded.scan_len;
+        config.prefix_size = recorded.prefix_size;
+        config.tenants = recorded.tenants;
+        config.rmw_fraction = recorded.rmw_fraction;
+        config.miss_fraction = recorded.miss_fraction;
+        config.distribution = recorded.distribution;
+        config.theta = recorded.theta;
+        config.hotspot = recorded.hotspot;
+        config.items = recorded.items;
+        config.key_type = recorded.key_type;
+        config.key_size = recorded.key_size;
+        config.value_size = recorded.value_size;
+        config.threads = self.threads.len();
+        config.iters = recorded.iters;
+        config.duration_ms = recorded.duration_ms;
+        config.batch = recorded.batch;
+        config.target_ops = recorded.target_ops;
+        config.rate_per_thread = recorded.rate_per_thread;
+        config.seed = recorded.seed;
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create trace {}", path.display()))?;
+        let mut w = BufWriter::new(file);
+        self.write(&mut w)
+            .and_then(|()| Ok(w.flush()?))
+            .with_context(|| format!("failed to save trace {}", path.display()))
+    }
+
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(MAGIC)?;
+        write_bytes(w, toml::to_string(&self.config)?.as_bytes())?;
+        write_u64(w, self.threads.len() as u64)?;
+        for steps in &self.threads {
+            write_u64(w, steps.len() as u64)?;
+            for step in steps {
+                write_u64(w, step.batch as u64)?;
+                write_u64(w, step.ops.len() as u64)?;
+                for op in &step.ops {
+                    write_op(w, op)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open trace {}", path.display()))?;
+        Self::read(&mut BufReader::new(file))
+            .with_context(|| format!("failed to load trace {}", path.display()))
+    }
+
+    fn read(r: &mut impl Read) -> Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        r.read_exact(&mut magic)?;
+        ensure!(&magic == MAGIC, "not a trace, or of another version");
+        let config = toml::from_str(&String::from_utf8(read_bytes(r)?)?)?;
+        let mut threads = Vec::new();
+        for _ in 0..read_u64(r)? {
+            let mut steps = Vec::new();
+            for _ in 0..read_u64(r)? {
+                let batch = read_u64(r)? != 0;
+                let ops = (0..read_u64(r)?)
+                    .map(|_| read_op(r))
+                    .collect::<Result<_>>()?;
+                steps.push(Step { batch, ops });
+            }
+            threads.push(steps);
+        }
+        Ok(Trace {
+            config,
+            threads,
+            items: None,
+        })
+    }
+}
+
+fn write_u64(w: &mut impl Write, n: u64) -> Result<()> {
+    w.write_all(&n.to_le_bytes())?;
+    Ok(())
+}
+
+fn write_bytes(w: &mut impl Write, bytes: &[u8]) -> Result<()> {
+    write_u64(w, bytes.len() as u64)?;
+    w.write_all(bytes)?;
+    Ok(())
+}
+
+/// The tags of prefix scans and of scans within a prefix, which follow
+/// those of the [`Op`]s of the other operations.
+const PREFIX_SCAN: u64 = Op::ALL.len() as u64;
+const SCAN_WITHIN: u64 = PREFIX_SCAN + 1;
+
+fn write_op(w: &mut impl Write, op: &TracedOp) -> Result<()> {
+    match op {
+        TracedOp::PrefixScan(_) => write_u64(w, PREFIX_SCAN)?,
+        TracedOp::ScanWithin { .. } => write_u64(w, SCAN_WITHIN)?,
+        _ => write_u64(w, op.kind() as u64)?,
+    }
+    match op {
+        TracedOp::Read(k) | TracedOp::Delete(k) | TracedOp::PrefixScan(k) => write_bytes(w, k),
+        TracedOp::Update(k, v) | TracedOp::Insert(k, v) => {
+            write_bytes(w, k)?;
+            write_bytes(w, v)
+        }
+        TracedOp::Scan(k, len) => {
+            write_bytes(w, k)?;
+            write_u64(w, *len)
+        }
+        TracedOp::ScanWithin { prefix, from, len } => {
+            write_bytes(w, prefix)?;
+            write_bytes(w, from)?;
+            write_u64(w, *len)
+        }
+        TracedOp::Rmw { key, pick, field } => {
+            write_bytes(w, key)?;
+            write_u64(w, *pick)?;
+            write_bytes(w, field)
+        }
+    }
+}
+
+fn read_u64(r: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_bytes(r: &mut impl Read) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; read_u64(r)? as usize];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_op(r: &mut impl Read) -> Result<TracedOp> {
+    let kind = read_u64(r)?;
+    if kind == PREFIX_SCAN {
+        return Ok(TracedOp::PrefixScan(read_bytes(r)?));
+    }
+    if kind == SCAN_WITHIN {
+        return Ok(TracedOp::ScanWithin {
+            prefix: read_bytes(r)?,
+            from: read_bytes(r)?,
+            len: read_u64(r)?,
+        });
+    }
+    let Some(&kind) = Op::ALL.get(kind as usize) else {
+        bail!("unknown operation {kind}");
+    };
+    Ok(match kind {
+        Op::Read => TracedOp::Read(read_bytes(r)?),
+        Op::Update => TracedOp::Update(read_bytes(r)?, read_bytes(r)?),
+        Op::Insert => TracedOp::Insert(read_bytes(r)?, read_bytes(r)?),
+        Op::Delete => TracedOp::Delete(read_bytes(r)?),
+        Op::Scan => TracedOp::Scan(read_bytes(r)?, read_u64(r)?),
+        Op::Rmw => TracedOp::Rmw {
+            key: read_bytes(r)?,
+            pick: read_u64(r)?,
+            field: read_bytes(r)?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload::Workload;
+
+    /// A trace of two threads, with every type of operation.
+    fn trace() -> Trace {
+        let op = |ops: Vec<TracedOp>| Step {
+            batch: ops.len() > 1,
+            ops,
+        };
+        Trace {
+            config: Config {
+                workload: Workload::YcsbA,
+                seed: 7,
+                ..Config::default()
+            },
+            threads: vec![
+                vec![
+                    op(vec![TracedOp::Read(b"a".to_vec())]),
+                    op(vec![
+                        TracedOp::Update(b"a".to_vec(), b"1".to_vec()),
+                        TracedOp::Insert(b"b".to_vec(), b"2".to_vec()),
+                        TracedOp::Delete(b"c".to_vec()),
+                    ]),
+                    op(Vec::new()),
+                ],
+                vec![
+                    op(vec![TracedOp::Scan(b"a".to_vec(), 10)]),
+                    op(vec![TracedOp::PrefixScan(b"user:".to_vec())]),
+                    op(vec![TracedOp::ScanWithin {
+                        prefix: b"tenant:".to_vec(),
+                        from: b"tenant:1".to_vec(),
+                        len: 5,
+                    }]),
+                    op(vec![TracedOp::Rmw {
+                        key: b"a".to_vec(),
+                        pick: u64::MAX,
+                        field: b"field".to_vec(),
+                    }]),
+                ],
+            ],
+            items: None,
+        }
+    }
+
+    fn written(trace: &Trace) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        trace.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn traces_round_trip() {
+        let trace = trace();
+        let bytes = written(&trace);
+        let read = Trace::read(&mut &bytes[..]).unwrap();
+        assert_eq!(read.config.workload, Workload::YcsbA);
+        assert_eq!(read.config.seed, 7);
+        assert_eq!(
+            format!("{:?}", read.threads),
+            format!("{:?}", trace.threads)
+        );
+        assert_eq!(written(&read), bytes);
+    }
+
+    #[test]
+    fn truncated_traces_fail() {
+        let bytes = written(&trace());
+        for len in [0, 4, MAGIC.len() + 4, bytes.len() / 2, bytes.len() - 1] {
+            assert!(Trace::read(&mut &bytes[..len]).is_err(), "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn malformed_traces_fail() {
+        let mut bytes = written(&trace());
+        bytes[0] = b'x';
+        assert!(Trace::read(&mut &bytes[..]).is_err());
+
+        let mut w = MAGIC.to_vec();
+        write_bytes(&mut w, b"").unwrap();
+        write_u64(&mut w, 1).unwrap();
+        write_u64(&mut w, 1).unwrap();
+        write_u64(&mut w, 0).unwrap();
+        write_u64(&mut w, 1).unwrap();
+        write_u64(&mut w, SCAN_WITHIN + 1).unwrap();
+        let err = Trace::read(&mut &w[..]).err().unwrap();
+        assert!(err.to_string().contains("unknown operation"), "{err}");
+    }
+}
@@ -0,0 +1,193 @@
+//! A live terminal dashboard of the progress of a run.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::text::Line;
+use ratatui::widgets::{Bar, BarChart, Block, Paragraph, Sparkline};
+
+use crate::config::{Budget, Config};
+use crate::histogram::Histogram;
+use crate::interrupt;
+use crate::process::rss_bytes;
+use crate::timeline::{Monitor, Sample};
+
+/// Number of samples of the total throughput kept for its sparkline.
+const HISTORY: usize = 512;
+
+/// Shows the throughput of every thread, the recent p99 latency, the memory
+/// usage and the elapsed and remaining time of the measured phase of a run,
+/// as it's sampled. Takes over the terminal from the first sample until
+/// dropped.
+pub struct Dashboard {
+    title: String,
+    unit: &'static str,
+    budget: Budget,
+    /// Total number of operations of the run, if known in advance.
+    total_ops: Option<u64>,
+    terminal: Option<DefaultTerminal>,
+    previous: Option<Sample>,
+    recent_p99_us: Option<f64>,
+    /// Total throughput between consecutive samples, oldest first.
+    throughput: Vec<u64>,
+}
+
+impl Dashboard {
+    pub fn new(backend: &str, config: &Config) -> Self {
+        let budget = config.budget();
+        // Scans count items, so their total depends on the size of the DB.
+        let total_ops = match budget {
+            Budget::Iters(iters) if config.spec().is_some() => {
+                Some((config.threads * iters) as u64)
+            }
+            _ => None,
+        };
+        Dashboard {
+            title: format!(" {backend}: {} ", config.workload),
+            unit: config.workload.unit(),
+            budget,
+            total_ops,
+            terminal: None,
+            previous: None,
+            recent_p99_us: None,
+            throughput: Vec::new(),
+        }
+    }
+
+    /// Interrupts the run on `q` or Ctrl-C, which doesn't raise SIGINT in raw
+    /// mode, and exits on the second time.
+    fn handle_events(&mut self) -> Result<()> {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if key.code == KeyCode::Char('q') || ctrl_c {
+                if interrupt::is_interrupted() {
+                    ratatui::restore();
+                }
+                interrupt::interrupt();
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, sample: &Sample) -> Result<()> {
+        let elapsed = Duration::from_millis(sample.elapsed_ms);
+        let secs = (sample.elapsed_ms.max(1)) as f64 / 1e3;
+        let remaining = match (self.budget, self.total_ops) {
+            (Budget::Time(duration), _) => {
+                format!("{:.1?}", duration.saturating_sub(elapsed))
+            }
+            (Budget::Iters(_), Some(total_ops)) if sample.ops > 0 => {
+                let left = total_ops.saturating_sub(sample.ops) as f64;
+                format!(
+                    "~{:.1?}",
+                    Duration::from_secs_f64(left * secs / sample.ops as f64)
+                )
+            }
+            _ => "unknown".to_string(),
+        };
+        let p99 = match self.recent_p99_us {
+            Some(us) => format!("{us:.3}us"),
+            None => "-".to_string(),
+        };
+        let rss = match rss_bytes() {
+            Some(bytes) => format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64),
+            None => "-".to_string(),
+        };
+        let unit = self.unit;
+        let stats = Paragraph::new(vec![
+            Line::from(format!("Elapsed:   {elapsed:.1?} (remaining: {remaining})")),
+            Line::from(format!(
+                "Completed: {} {unit}s, {:.0} {unit}s/s overall, {} {unit}s/s now",
+                sample.ops,
+                sample.ops as f64 / secs,
+                self.throughput.last().copied().unwrap_or(0)
+            )),
+            Line::from(format!("p99:       {p99} (since the last sample)")),
+            Line::from(format!("Memory:    {rss} resident")),
+            Line::from("Press q to stop, twice to quit"),
+        ])
+        .block(Block::bordered().title(self.title.as_str()));
+
+        let thread_ops_per_sec = self.thread_ops_per_sec(sample);
+        let bars: Vec<_> = thread_ops_per_sec
+            .iter()
+            .enumerate()
+            .map(|(thread, &ops_per_sec)| {
+                Bar::with_label(format!("{thread:>3}"), ops_per_sec)
+                    .text_value(format!("{ops_per_sec} {unit}s/s"))
+            })
+            .collect();
+        let threads = BarChart::horizontal(bars)
+            .bar_width(1)
+            .bar_gap(0)
+            .block(Block::bordered().title(format!(" Throughput by thread ({unit}s/s) ")));
+
+        let n_threads = thread_ops_per_sec.len() as u16;
+        let history = &self.throughput;
+        let terminal = self.terminal.get_or_insert_with(ratatui::init);
+        terminal.draw(|frame| {
+            let [stats_area, threads_area, throughput_area] = Layout::vertical([
+                Constraint::Length(7),
+                Constraint::Max(n_threads + 2),
+                Constraint::Min(5),
+            ])
+            .areas(frame.area());
+            frame.render_widget(stats, stats_area);
+            frame.render_widget(threads, threads_area);
+            // The most recent samples that fit, inside the borders.
+            let width = throughput_area.width.saturating_sub(2) as usize;
+            let throughput = Sparkline::default()
+                .data(&history[history.len().saturating_sub(width)..])
+                .block(Block::bordered().title(format!(" Throughput over time ({unit}s/s) ")));
+            frame.render_widget(throughput, throughput_area);
+        })?;
+        Ok(())
+    }
+
+    /// The throughput of every thread since the previous sample.
+    fn thread_ops_per_sec(&self, sample: &Sample) -> Vec<u64> {
+        let Some(previous) = &self.previous else {
+            return vec![0; sample.thread_ops.len()];
+        };
+        let secs = (sample.elapsed_ms.saturating_sub(previous.elapsed_ms)).max(1) as f64 / 1e3;
+        sample
+            .thread_ops
+            .iter()
+            .zip(&previous.thread_ops)
+            .map(|(ops, prev)| (ops.saturating_sub(*prev) as f64 / secs) as u64)
+            .collect()
+    }
+}
+
+impl Monitor for Dashboard {
+    fn update(&mut self, sample: &Sample, recent: Option<&Histogram>) -> Result<()> {
+        if let Some(recent) = recent.filter(|recent| recent.count() > 0) {
+            self.recent_p99_us = Some(recent.quantile_us(0.99));
+        }
+        if self.previous.is_some() {
+            let ops_per_sec: u64 = self.thread_ops_per_sec(sample).iter().sum();
+            if self.throughput.len() == HISTORY {
+                self.throughput.remove(0);
+            }
+            self.throughput.push(ops_per_sec);
+        }
+        self.draw(sample)?;
+        self.previous = Some(sample.clone());
+        self.handle_events()
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        if self.terminal.take().is_some() {
+            ratatui::restore();
+        }
+    }
+}
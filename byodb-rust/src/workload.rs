@@ -0,0 +1,213 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::distribution::{Distribution, Length};
+use crate::ops::{Mix, Op, Spec, VALUE_SIZE};
+
+/// What the benchmark threads do, which also determines the seeded items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Workload {
+    /// Full in-order scans of randomly sized items, optionally alongside a
+    /// background writer.
+    Scan,
+    /// YCSB A, update heavy: 50% reads, 50% updates, Zipfian.
+    YcsbA,
+    /// YCSB B, read mostly: 95% reads, 5% updates, Zipfian.
+    YcsbB,
+    /// YCSB C, read only: 100% reads, Zipfian.
+    YcsbC,
+    /// YCSB D, read latest: 95% reads, 5% inserts, latest.
+    YcsbD,
+    /// YCSB E, short ranges: 95% scans of up to 100 items, 5% inserts, Zipfian.
+    YcsbE,
+    /// YCSB F, read-modify-write: 50% reads, 50% read-modify-writes, Zipfian.
+    YcsbF,
+    /// Time series: 10% appends of increasing keys, 90% reads of the latest
+    /// ones.
+    TimeSeries,
+    /// Churn: 50% inserts of new records, 50% deletes of the oldest ones,
+    /// tracking the disk usage of the DB as its logical size stays put.
+    Churn,
+    /// Inserts of new records only, tracking the throughput over time as the
+    /// DB grows.
+    InsertOnly,
+    /// Point lookups of uniformly random existing keys, each timed on its own.
+    PointGet,
+    /// Read-modify-writes of uniformly random keys, each in a single
+    /// transaction and timed on its own, and reads for the rest (see
+    /// `--rmw-fraction`).
+    Rmw,
+    /// Range scans: seeks to a uniformly random key and reads `--scan-len`
+    /// items from there.
+    RangeScan,
+    /// Prefix scans: reads every record under the prefix of a uniformly
+    /// random key, with keys like `user:{id}:{field}` that group
+    /// `--prefix-size` records under each prefix.
+    PrefixScan,
+    /// Multi-tenant index: 95% scans of up to `--scan-len` records of the
+    /// tenant of a uniformly random key, from that key on, and 5% inserts,
+    /// with composite keys like `tenant:{tenant}:{timestamp}:{sequence}` of
+    /// `--tenants` tenants.
+    MultiTenant,
+    /// The operations given by `--mix`, on uniformly distributed keys.
+    Custom,
+    /// Full scans in descending key order, like the scan workload, on the
+    /// backends that support them.
+    ReverseScan,
+}
+
+impl Workload {
+    /// The operations of an op-based workload, or `None` for
+    /// [`Workload::Scan`] and [`Workload::ReverseScan`].
+    /// Parameters of the workload, like `--rmw-fraction`, are taken from
+    /// `config`.
+    pub fn spec(self, config: &Config) -> Option<Spec> {
+        let (mix, distribution) = match self {
+            Workload::Scan | Workload::ReverseScan => return None,
+            Workload::YcsbA => (
+                Mix {
+                    read: 0.5,
+                    update: 0.5,
+                    ..Mix::default()
+                },
+                Distribution::Zipfian,
+            ),
+            Workload::YcsbB => (
+                Mix {
+                    read: 0.95,
+                    update: 0.05,
+                    ..Mix::default()
+                },
+                Distribution::Zipfian,
+            ),
+            Workload::YcsbC => (
+                Mix {
+                    read: 1.0,
+                    ..Mix::default()
+                },
+                Distribution::Zipfian,
+            ),
+            Workload::YcsbD => (
+                Mix {
+                    read: 0.95,
+                    insert: 0.05,
+                    ..Mix::default()
+                },
+                Distribution::Latest,
+            ),
+            Workload::YcsbE => (
+                Mix {
+                    scan: 0.95,
+                    insert: 0.05,
+                    ..Mix::default()
+                },
+                Distribution::Zipfian,
+            ),
+            Workload::YcsbF => (
+                Mix {
+                    read: 0.5,
+                    rmw: 0.5,
+                    ..Mix::default()
+                },
+                Distribution::Zipfian,
+            ),
+            Workload::TimeSeries => (
+                Mix {
+                    read: 0.9,
+                    insert: 0.1,
+                    ..Mix::default()
+                },
+                Distribution::Latest,
+            ),
+            Workload::Churn => (
+                Mix {
+                    insert: 0.5,
+                    delete: 0.5,
+                    ..Mix::default()
+                },
+                Distribution::Uniform,
+            ),
+            Workload::InsertOnly => (
+                Mix {
+                    insert: 1.0,
+                    ..Mix::default()
+                },
+                Distribution::Uniform,
+            ),
+            Workload::PointGet => (
+                Mix {
+                    read: 1.0,
+                    ..Mix::default()
+                },
+                Distribution::Uniform,
+            ),
+            Workload::Rmw => (
+                Mix {
+                    read: 1.0 - config.rmw_fraction,
+                    rmw: config.rmw_fraction,
+                    ..Mix::default()
+                },
+                Distribution::Uniform,
+            ),
+            Workload::RangeScan | Workload::PrefixScan => (
+                Mix {
+                    scan: 1.0,
+                    ..Mix::default()
+                },
+                Distribution::Uniform,
+            ),
+            Workload::MultiTenant => (
+                Mix {
+                    scan: 0.95,
+                    insert: 0.05,
+                    ..Mix::default()
+                },
+                Distribution::Uniform,
+            ),
+            Workload::Custom => (
+                Mix {
+                    read: 1.0,
+                    ..Mix::default()
+                },
+                Distribution::Uniform,
+            ),
+        };
+        Some(Spec {
+            mix,
+            distribution,
+            scan_len: Length::Uniform { min: 1, max: 100 },
+            value_size: Length::Fixed(VALUE_SIZE),
+            ordered_keys: self == Workload::TimeSeries,
+            key_type: config.key_type,
+            miss_fraction: config.miss_fraction,
+            prefix_size: (self == Workload::PrefixScan).then_some(config.prefix_size as u64),
+            tenants: (self == Workload::MultiTenant).then_some(config.tenants as u64),
+            charted_op: match self {
+                Workload::PointGet => Some(Op::Read),
+                Workload::Rmw => Some(Op::Rmw),
+                _ => None,
+            },
+            delete_oldest: self == Workload::Churn,
+            track_space: self == Workload::Churn,
+            track_throughput: self == Workload::InsertOnly,
+        })
+    }
+
+    /// What a single measured operation is, for reporting latencies.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Workload::Scan | Workload::ReverseScan => "item",
+            _ => "op",
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_possible_value().unwrap().get_name())
+    }
+}
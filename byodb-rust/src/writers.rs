@@ -0,0 +1,211 @@
+//! Writer threads that commit independent transactions while a workload
+//! runs, to measure write contention and how it slows the readers down.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::backend::{self, Backend};
+use crate::histogram::{Histogram, Latencies};
+use crate::shadow::{Pending, Shadow};
+
+/// Number of distinct keys each writer overwrites in turn, so that the DB
+/// doesn't grow for as long as they run.
+const KEYS_PER_WRITER: u64 = 1024;
+
+/// Size of the values written.
+const VALUE_SIZE: usize = 100;
+
+/// Number of attempts at a transaction that may fail on a conflict before
+/// it is given up on, failing the run.
+pub const MAX_ATTEMPTS: u32 = 100;
+
+/// What the writers measured.
+pub struct WriterOutcome {
+    /// Number of writer threads.
+    pub threads: usize,
+    /// Number of transactions committed by all writers.
+    pub commits: u64,
+    /// Total size of the keys and values put by all writers.
+    pub written_bytes: u64,
+    /// Wall-clock time the writers ran for.
+    pub elapsed: Duration,
+    /// Latencies of beginning a write transaction, i.e. of waiting for the
+    /// write lock of single-writer backends (`lock-wait`), and of
+    /// committing one (`commit`).
+    pub latencies: Latencies,
+    /// The transactions of all writers that were retried.
+    pub retries: Retries,
+}
+
+/// The attempts at read-write transactions, including those that failed
+/// with a [`backend::Conflict`] and were retried.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Retries {
+    /// Number of attempts that committed.
+    pub commits: u64,
+    /// Number of attempts that were aborted on a conflict.
+    pub aborts: u64,
+    /// Time spent in all attempts.
+    pub busy: Duration,
+    /// Time spent in the attempts that were aborted.
+    pub wasted: Duration,
+    /// Most attempts a single transaction took.
+    pub max_attempts: u32,
+}
+
+impl Retries {
+    /// Counts the `attempt`th attempt at a transaction, which took `elapsed`
+    /// and ended with `result`. Returns `None` if it failed on a conflict,
+    /// and should be retried once [`back_off`], or else its result: an
+    /// error if it failed otherwise, or on a conflict at the
+    /// [`MAX_ATTEMPTS`]th attempt.
+    pub fn count<T>(
+        &mut self,
+        result: Result<T>,
+        elapsed: Duration,
+        attempt: u32,
+    ) -> Option<Result<T>> {
+        self.busy += elapsed;
+        self.max_attempts = self.max_attempts.max(attempt);
+        match result {
+            Ok(done) => {
+                self.commits += 1;
+                Some(Ok(done))
+            }
+            Err(err) if backend::is_conflict(&err) => {
+                self.aborts += 1;
+                self.wasted += elapsed;
+                (attempt >= MAX_ATTEMPTS).then(|| {
+                    Err(err.context(format!(
+                        "gave up on a transaction after {attempt} attempts failed on a conflict"
+                    )))
+                })
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    pub fn merge(&mut self, other: &Retries) {
+        self.commits += other.commits;
+        self.aborts += other.aborts;
+        self.busy += other.busy;
+        self.wasted += other.wasted;
+        self.max_attempts = self.max_attempts.max(other.max_attempts);
+    }
+}
+
+/// Waits before retrying a transaction whose `attempt`th attempt failed on
+/// a conflict: for a random time up to twice as long as after the previous
+/// one, from 2us up to about 1ms, so that the transactions it conflicted
+/// with don't keep retrying in lockstep.
+pub fn back_off(attempt: u32) {
+    let max = Duration::from_micros(1 << attempt.min(10));
+    thread::sleep(max.mul_f64(rand::random()));
+}
+
+/// What a single writer did.
+#[derive(Default)]
+struct Tally {
+    commits: u64,
+    written_bytes: u64,
+    lock_wait: Histogram,
+    commit: Histogram,
+    retries: Retries,
+}
+
+/// Writer threads running in the background until [`Writers::stop`].
+pub struct Writers {
+    stop: Arc<AtomicBool>,
+    start_time: Instant,
+    threads: Vec<JoinHandle<Result<Tally>>>,
+}
+
+impl Writers {
+    /// Starts `n` writers that each put a single key per transaction in `db`,
+    /// as fast as they can, and apply it to `shadow`, if any.
+    pub fn start(db: &Arc<dyn Backend>, n: usize, shadow: Option<&Arc<Shadow>>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let threads = (0..n)
+            .map(|w| {
+                let db = db.clone();
+                let stop = stop.clone();
+                let shadow = shadow.cloned();
+                thread::spawn(move || {
+                    let mut tally = Tally::default();
+                    let val = [b'w'; VALUE_SIZE];
+                    let mut attempts = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        let key = format!("writer{w}-{:04}", tally.commits % KEYS_PER_WRITER);
+                        let mut attempt = || -> Result<()> {
+                            let start_time = Instant::now();
+                            let lock = shadow.as_deref().map(Shadow::lock);
+                            let mut t = db.begin_rw()?;
+                            tally.lock_wait.record(start_time.elapsed());
+                            t.put(key.as_bytes(), &val)?;
+                            let start_time = Instant::now();
+                            match lock {
+                                Some(lock) => {
+                                    let pending = Pending::default();
+                                    pending.put(key.as_bytes(), &val);
+                                    lock.commit(t, &pending)?;
+                                }
+                                None => t.commit()?,
+                            }
+                            tally.commit.record(start_time.elapsed());
+                            Ok(())
+                        };
+                        attempts += 1;
+                        let start_time = Instant::now();
+                        let result = attempt();
+                        let Some(result) =
+                            tally.retries.count(result, start_time.elapsed(), attempts)
+                        else {
+                            back_off(attempts);
+                            continue;
+                        };
+                        result?;
+                        attempts = 0;
+                        tally.commits += 1;
+                        tally.written_bytes += (key.len() + val.len()) as u64;
+                    }
+                    Ok(tally)
+                })
+            })
+            .collect();
+        Writers {
+            stop,
+            start_time: Instant::now(),
+            threads,
+        }
+    }
+
+    /// Stops the writers once their current transaction is committed.
+    pub fn stop(self) -> Result<WriterOutcome> {
+        self.stop.store(true, Ordering::Relaxed);
+        let mut total = Tally::default();
+        let threads = self.threads.len();
+        for thread in self.threads {
+            let tally = thread.join().unwrap()?;
+            total.commits += tally.commits;
+            total.written_bytes += tally.written_bytes;
+            total.lock_wait.merge(&tally.lock_wait);
+            total.commit.merge(&tally.commit);
+            total.retries.merge(&tally.retries);
+        }
+        Ok(WriterOutcome {
+            threads,
+            commits: total.commits,
+            written_bytes: total.written_bytes,
+            elapsed: self.start_time.elapsed(),
+            latencies: Latencies::from([
+                ("lock-wait".to_string(), total.lock_wait),
+                ("commit".to_string(), total.commit),
+            ]),
+            retries: total.retries,
+        })
+    }
+}